@@ -0,0 +1,121 @@
+//! Compares the lookup tables of `normal_shanten` with the recursive search
+//! it replaced, on random hands, on chinitsu hands where the search is the
+//! slowest, and on the ukeire of every discard as `State` calculates it.
+//!
+//! Run with `cargo bench --bench shanten`.
+
+mod search;
+#[path = "../../src/shanten.rs"]
+#[allow(dead_code)]
+mod shanten;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use search::ShantenHelper;
+use shanten::{normal_shanten, Tiles};
+
+/// Draws 14 pais from a wall of the kinds in `kinds`, four of each.
+fn random_hand(rng: &mut fastrand::Rng, kinds: std::ops::Range<usize>) -> Tiles {
+    let mut wall: Vec<_> = kinds.flat_map(|i| [i; 4]).collect();
+    rng.shuffle(&mut wall);
+    let mut tiles = [0; 34];
+    for &i in &wall[..14] {
+        tiles[i] += 1;
+    }
+    tiles
+}
+
+fn hands(kinds: std::ops::Range<usize>) -> Vec<Tiles> {
+    let mut rng = fastrand::Rng::with_seed(0);
+    (0..1000)
+        .map(|_| random_hand(&mut rng, kinds.clone()))
+        .collect()
+}
+
+/// Shanten after every possible discard and then every possible draw.
+fn ukeire(tiles: &Tiles, shanten: impl Fn(&Tiles) -> i8) -> usize {
+    let mut tiles = *tiles;
+    let mut improving = 0;
+    for discard in 0..34 {
+        if tiles[discard] == 0 {
+            continue;
+        }
+        tiles[discard] -= 1;
+        let base = shanten(&tiles);
+        for draw in 0..34 {
+            if tiles[draw] < 4 {
+                tiles[draw] += 1;
+                improving += (shanten(&tiles) < base) as usize;
+                tiles[draw] -= 1;
+            }
+        }
+        tiles[discard] += 1;
+    }
+    improving
+}
+
+fn bench(c: &mut Criterion) {
+    // builds the tables outside of the measurement
+    normal_shanten(&[0; 34], 0);
+
+    for (name, kinds) in [("random", 0..34), ("chinitsu", 0..9)] {
+        let hands = hands(kinds);
+        for hand in &hands {
+            let search = ShantenHelper::new(hand, 0).normal();
+            let table = normal_shanten(hand, 0);
+            assert!(
+                table >= search,
+                "the tables give {} but the search {} for {:?}",
+                table,
+                search,
+                hand,
+            );
+        }
+
+        let mut group = c.benchmark_group(name);
+        group.bench_function("search", |b| {
+            b.iter_batched_ref(
+                || hands.clone(),
+                |hands| {
+                    for hand in hands.iter() {
+                        black_box(ShantenHelper::new(hand, 0).normal());
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function("table", |b| {
+            b.iter_batched_ref(
+                || hands.clone(),
+                |hands| {
+                    for hand in hands.iter() {
+                        black_box(normal_shanten(hand, 0));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.finish();
+    }
+
+    let hands = &hands(0..34)[..20];
+    let mut group = c.benchmark_group("ukeire");
+    group.sample_size(10);
+    group.bench_function("search", |b| {
+        b.iter(|| {
+            for hand in hands {
+                black_box(ukeire(hand, |t| ShantenHelper::new(t, 0).normal()));
+            }
+        })
+    });
+    group.bench_function("table", |b| {
+        b.iter(|| {
+            for hand in hands {
+                black_box(ukeire(hand, |t| normal_shanten(t, 0)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+use super::shanten::Tiles;
+
+/// The recursive search `normal_shanten` replaced, kept here to compare
+/// with.
+///
+/// `fuuro_count` mentsu are considered already completed by naki or ankan, so
+/// the search only needs to look for the rest of them in `tiles`.
+pub struct ShantenHelper {
+    tiles: Tiles,
+    fuuro_count: i8,
+
+    mentsu: i8,
+    taatsu: i8,
+    has_jantou: bool,
+    min_shanten: i8,
+}
+
+impl ShantenHelper {
+    pub fn new(tiles: &Tiles, fuuro_count: u8) -> Self {
+        Self {
+            tiles: *tiles,
+            fuuro_count: fuuro_count as i8,
+            mentsu: 0,
+            taatsu: 0,
+            has_jantou: false,
+            min_shanten: 8,
+        }
+    }
+
+    pub fn normal(mut self) -> i8 {
+        // without jantou
+        self.search_mentsu(0);
+
+        // with each possible jantou
+        for i in 0..34 {
+            if self.tiles[i] >= 2 {
+                self.tiles[i] -= 2;
+                self.has_jantou = true;
+                self.search_mentsu(0);
+                self.has_jantou = false;
+                self.tiles[i] += 2;
+            }
+        }
+
+        self.min_shanten
+    }
+
+    fn search_mentsu(&mut self, start: usize) {
+        let i = match (start..34).find(|&i| self.tiles[i] > 0) {
+            Some(i) => i,
+            None => {
+                self.search_taatsu(0);
+                return;
+            }
+        };
+
+        // koutsu
+        if self.tiles[i] >= 3 {
+            self.tiles[i] -= 3;
+            self.mentsu += 1;
+            self.search_mentsu(i);
+            self.mentsu -= 1;
+            self.tiles[i] += 3;
+        }
+
+        // shuntsu
+        if is_shuntsu_head(i) && self.tiles[i + 1] > 0 && self.tiles[i + 2] > 0 {
+            self.tiles[i] -= 1;
+            self.tiles[i + 1] -= 1;
+            self.tiles[i + 2] -= 1;
+            self.mentsu += 1;
+            self.search_mentsu(i);
+            self.mentsu -= 1;
+            self.tiles[i] += 1;
+            self.tiles[i + 1] += 1;
+            self.tiles[i + 2] += 1;
+        }
+
+        // leave the pais at i for taatsu
+        self.search_mentsu(i + 1);
+    }
+
+    fn search_taatsu(&mut self, start: usize) {
+        self.update_min_shanten();
+        if self.mentsu + self.fuuro_count + self.taatsu >= 4 {
+            return;
+        }
+
+        for i in start..34 {
+            if self.tiles[i] == 0 {
+                continue;
+            }
+
+            // toitsu
+            if self.tiles[i] >= 2 {
+                self.tiles[i] -= 2;
+                self.taatsu += 1;
+                self.search_taatsu(i);
+                self.taatsu -= 1;
+                self.tiles[i] += 2;
+            }
+
+            if i < 27 {
+                // ryanmen or penchan
+                if i % 9 <= 7 && self.tiles[i + 1] > 0 {
+                    self.tiles[i] -= 1;
+                    self.tiles[i + 1] -= 1;
+                    self.taatsu += 1;
+                    self.search_taatsu(i);
+                    self.taatsu -= 1;
+                    self.tiles[i] += 1;
+                    self.tiles[i + 1] += 1;
+                }
+
+                // kanchan
+                if i % 9 <= 6 && self.tiles[i + 2] > 0 {
+                    self.tiles[i] -= 1;
+                    self.tiles[i + 2] -= 1;
+                    self.taatsu += 1;
+                    self.search_taatsu(i);
+                    self.taatsu -= 1;
+                    self.tiles[i] += 1;
+                    self.tiles[i + 2] += 1;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn update_min_shanten(&mut self) {
+        let mentsu = self.mentsu + self.fuuro_count;
+        let taatsu = self.taatsu.min(4 - mentsu);
+        let shanten = 8 - mentsu * 2 - taatsu - self.has_jantou as i8;
+        self.min_shanten = self.min_shanten.min(shanten);
+    }
+}
+
+#[inline]
+fn is_shuntsu_head(i: usize) -> bool {
+    i < 27 && i % 9 <= 6
+}
@@ -1,17 +1,68 @@
-use std::io;
+use std::env;
+use std::io::{self, prelude::*};
 
-use convlog::tenhou;
+use convlog::{mjai, stream, tenhou};
 use serde_json as json;
 
 fn main() {
     let stdin = io::stdin();
 
-    let tenhou_log_raw: tenhou::RawLog =
-        json::from_reader(stdin).expect("failed to parse tenhou log");
-    let tenhou_log = tenhou::Log::from(tenhou_log_raw);
+    // with --reverse, read mjai events line by line and write a tenhou.net/6
+    // log instead
+    if env::args().nth(1).as_deref() == Some("--reverse") {
+        let events: Vec<mjai::Event> = stdin
+            .lock()
+            .lines()
+            .map(|line| line.expect("failed to read stdin"))
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| json::from_str(&line).expect("failed to parse mjai event"))
+            .collect();
+        let tenhou_log = convlog::mjai_to_tenhou(&events).expect("failed to transform mjai log");
 
-    convlog::tenhou_to_mjai(&tenhou_log)
-        .expect("failed to transform tenhou log")
-        .iter()
-        .for_each(|event| println!("{}", json::to_string(event).unwrap()));
+        println!(
+            "{}",
+            json::to_string(&tenhou::RawLog::from(tenhou_log)).unwrap()
+        );
+        return;
+    }
+
+    // read any number of logs and write each kyoku as soon as it is converted
+    let mut reader = stream::Reader::new(stdin.lock());
+    loop {
+        // kyokus converted before the header is known, as start_game must
+        // come first, `None` once it is written
+        let mut pending = Some(vec![]);
+        let raw_log = reader
+            .next_log_parts(|part| {
+                match part {
+                    stream::LogPart::Header(header) => {
+                        let log = tenhou::Log::from(header.clone());
+                        print_event(&convlog::tenhou_start_game(&log));
+                        pending
+                            .take()
+                            .into_iter()
+                            .flatten()
+                            .for_each(|e| print_event(&e));
+                    }
+                    stream::LogPart::Kyoku(kyoku) => {
+                        let events = convlog::tenhou_kyoku_to_mjai(&kyoku)?;
+                        match &mut pending {
+                            Some(pending) => pending.extend(events),
+                            None => events.iter().for_each(print_event),
+                        }
+                    }
+                }
+                Ok::<_, convlog::ConvertError>(())
+            })
+            .expect("failed to transform tenhou log");
+        if raw_log.is_none() {
+            break;
+        }
+
+        print_event(&mjai::Event::EndGame);
+    }
+}
+
+fn print_event(event: &mjai::Event) {
+    println!("{}", json::to_string(event).unwrap());
 }
@@ -58,14 +58,10 @@ struct BackTrack {
 
 /// Transform a tenhou.net/6 format log into mjai format.
 pub fn tenhou_to_mjai(log: &tenhou::Log) -> Result<Vec<mjai::Event>> {
-    let mut events = vec![mjai::Event::StartGame {
-        kyoku_first: log.game_length as u8,
-        aka_flag: log.has_aka,
-        names: log.names.clone(),
-    }];
+    let mut events = vec![tenhou_start_game(log)];
 
     for kyoku in &log.kyokus {
-        let kyoku_events = tenhou_kyoku_to_mjai_events(kyoku)?;
+        let kyoku_events = tenhou_kyoku_to_mjai(kyoku)?;
         events.extend(kyoku_events);
     }
 
@@ -73,7 +69,19 @@ pub fn tenhou_to_mjai(log: &tenhou::Log) -> Result<Vec<mjai::Event>> {
     Ok(events)
 }
 
-fn tenhou_kyoku_to_mjai_events(kyoku: &tenhou::Kyoku) -> Result<Vec<mjai::Event>> {
+/// The start_game event of a tenhou.net/6 format log, which does not depend
+/// on its kyokus.
+pub fn tenhou_start_game(log: &tenhou::Log) -> mjai::Event {
+    mjai::Event::StartGame {
+        kyoku_first: log.game_length as u8,
+        aka_flag: log.has_aka,
+        names: log.names.clone(),
+    }
+}
+
+/// Transform one kyoku of a tenhou.net/6 format log into mjai events, from
+/// start_kyoku to end_kyoku.
+pub fn tenhou_kyoku_to_mjai(kyoku: &tenhou::Kyoku) -> Result<Vec<mjai::Event>> {
     // First of all, transform all takes and discards to events.
     let (take_events, discard_events): (Vec<_>, Vec<_>) = (0..4)
         .map(|a| {
@@ -151,7 +159,7 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &tenhou::Kyoku) -> Result<Vec<mjai::Event>
         loop {
             // Start to process a take event.
             let take =
-                &*take_events[actor]
+                take_events[actor]
                     .get(take_i[actor])
                     .ok_or(ConvertError::InsufficientTakes {
                         kyoku: kyoku.meta.kyoku_num,
@@ -299,6 +307,16 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &tenhou::Kyoku) -> Result<Vec<mjai::Event>
             // Here it simply checks if there is no more take for every single
             // actor.
             if (0..4).all(|a| take_i[a] >= take_events[a].len()) {
+                // The riichi dahai has passed unless it is ronned, which is
+                // the case of suuchariichi, where the 4th riichi stick is
+                // still put down.
+                if let tenhou::kyoku::EndStatus::Ryukyoku { reason, .. } = kyoku.end_status {
+                    if let Some(actor) = reach_flag.take() {
+                        if reason != Some(mjai::RyukyokuReason::Sanchaho) {
+                            events.push(mjai::Event::ReachAccepted { actor: actor as u8 });
+                        }
+                    }
+                }
                 end_kyoku(&mut events, kyoku);
                 break;
             }
@@ -402,7 +420,7 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &tenhou::Kyoku) -> Result<Vec<mjai::Event>
                         }
                         Entry::Occupied(mut o) => {
                             // This is where the backtrack happens.
-                            let mut bc = o.get_mut();
+                            let bc = o.get_mut();
                             if bc.use_the_first_branch {
                                 // When this branch is reached, it is likely the
                                 // first branch has failed, that is, the real naki
@@ -766,12 +784,21 @@ fn end_kyoku(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Kyoku) {
                 actor: detail.who,
                 target: detail.target,
                 deltas: Some(detail.score_deltas),
+                ura_markers: if kyoku.ura_indicators.is_empty() {
+                    None
+                } else {
+                    Some(kyoku.ura_indicators.clone())
+                },
             }));
         }
 
-        tenhou::kyoku::EndStatus::Ryukyoku { score_deltas } => {
+        tenhou::kyoku::EndStatus::Ryukyoku {
+            score_deltas,
+            reason,
+        } => {
             events.push(mjai::Event::Ryukyoku {
                 deltas: Some(*score_deltas),
+                reason: *reason,
             });
         }
     };
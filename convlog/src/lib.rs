@@ -1,13 +1,20 @@
-//! Provides methods to transform mahjong logs from tenhou.net/6 format into
+//! Provides methods to transform mahjong logs between tenhou.net/6 format and
 //! mjai format.
 
 mod conv;
 mod kyoku_filter;
 pub mod mjai;
 pub mod pai;
+mod reverse;
+pub mod stream;
 pub mod tenhou;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use conv::tenhou_to_mjai;
 pub use conv::ConvertError;
+pub use conv::{tenhou_kyoku_to_mjai, tenhou_start_game};
 pub use kyoku_filter::KyokuFilter;
 pub use pai::Pai;
+pub use reverse::mjai_to_tenhou;
+pub use reverse::ReverseConvertError;
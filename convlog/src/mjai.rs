@@ -99,10 +99,15 @@ pub enum Event {
         // record the field.
         #[serde(skip_serializing_if = "Option::is_none")]
         deltas: Option<[i32; 4]>,
+        #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ura_markers: Option<Vec<Pai>>,
     },
     Ryukyoku {
         #[serde(skip_serializing_if = "Option::is_none")]
         deltas: Option<[i32; 4]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<RyukyokuReason>,
     },
 
     EndKyoku,
@@ -111,6 +116,34 @@ pub enum Event {
 
 impl Eq for Event {}
 
+/// Why a kyoku ends without any hora.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RyukyokuReason {
+    /// Exhaustive draw.
+    Fanpai,
+    Nagashimangan,
+    /// Nine different terminals and honors in the first draw.
+    Kyushukyuhai,
+    /// Everyone has declared riichi.
+    Suuchariichi,
+    /// Four kans by more than one player.
+    Suukaikan,
+    /// The same wind discarded by everyone in the first go-around.
+    Suufonrenda,
+    /// Three players ron the same discard.
+    Sanchaho,
+}
+
+impl RyukyokuReason {
+    /// Whether the kyoku is aborted rather than drawn out, in which case
+    /// there is no tenpai payment.
+    #[inline]
+    pub const fn is_abortive(self) -> bool {
+        !matches!(self, Self::Fanpai | Self::Nagashimangan)
+    }
+}
+
 // ["5sr", "3p", "6m", ...] => [Pai::AkaSou5, Pai::Pin3, Pai::Man6, ...]
 macro_rules! make_pai_array_from_string_array {
     ($array:ident, $($index:expr),*) => {
@@ -131,6 +131,48 @@ impl Pai {
         self as usize
     }
 
+    /// Returns the index of the pai within 0~33, in the order of manzu,
+    /// pinzu, souzu and jihai. Aka pais share the index with their normal
+    /// variants. Returns `None` for `Pai::Unknown`.
+    #[inline]
+    pub fn as_index(self) -> Option<usize> {
+        match self {
+            Self::Unknown => None,
+            Self::AkaMan5 => Some(4),
+            Self::AkaPin5 => Some(9 + 4),
+            Self::AkaSou5 => Some(18 + 4),
+            _ => {
+                let id = self.as_usize();
+                Some((id / 10 - 1) * 9 + id % 10 - 1)
+            }
+        }
+    }
+
+    /// The inverse of `as_index`. Never returns an aka pai.
+    #[inline]
+    pub fn from_index(index: usize) -> Option<Self> {
+        if index >= 34 {
+            return None;
+        }
+        Self::try_from(((index / 9 + 1) * 10 + index % 9 + 1) as u8).ok()
+    }
+
+    /// Returns the normal variant of an aka pai, or itself if it is not aka.
+    #[inline]
+    pub const fn deaka(self) -> Self {
+        match self {
+            Self::AkaMan5 => Self::Man5,
+            Self::AkaPin5 => Self::Pin5,
+            Self::AkaSou5 => Self::Sou5,
+            _ => self,
+        }
+    }
+
+    #[inline]
+    pub const fn is_aka(self) -> bool {
+        matches!(self, Self::AkaMan5 | Self::AkaPin5 | Self::AkaSou5)
+    }
+
     #[inline]
     pub fn as_ord(self) -> impl Ord {
         match self {
@@ -0,0 +1,330 @@
+use crate::mjai;
+use crate::tenhou::{self, kyoku, ActionItem, ActionTable, GameLength};
+use crate::Pai;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReverseConvertError {
+    #[error("the log does not begin with start_game")]
+    MissingStartGame,
+
+    #[error("unexpected event outside of a kyoku: {0:?}")]
+    UnexpectedEvent(mjai::Event),
+
+    #[error("kyoku ends without hora or ryukyoku: at kyoku {kyoku} honba {honba}")]
+    MissingEndStatus { kyoku: u8, honba: u8 },
+
+    #[error("kakan without a previous pon: {0:?}")]
+    KakanWithoutPon(mjai::Event),
+}
+
+pub type Result<T> = std::result::Result<T, ReverseConvertError>;
+
+/// Transform a mjai format log into tenhou.net/6 format, which is the inverse
+/// of `tenhou_to_mjai`.
+///
+/// mjai does not record yakus, fu or han, so the hora details in the result
+/// only contain who wins from whom and the score deltas.
+pub fn mjai_to_tenhou(events: &[mjai::Event]) -> Result<tenhou::Log> {
+    let mut iter = events.iter();
+    let (game_length, has_aka, names) = match iter.next() {
+        Some(mjai::Event::StartGame {
+            kyoku_first,
+            aka_flag,
+            names,
+        }) => {
+            let game_length = if *kyoku_first == GameLength::Tonpuu as u8 {
+                GameLength::Tonpuu
+            } else {
+                GameLength::Hanchan
+            };
+            (game_length, *aka_flag, names.clone())
+        }
+        _ => return Err(ReverseConvertError::MissingStartGame),
+    };
+
+    let mut kyokus = vec![];
+    let mut builder: Option<KyokuBuilder> = None;
+    for event in iter {
+        match event {
+            mjai::Event::StartKyoku { .. } => {
+                builder = Some(KyokuBuilder::new(event));
+            }
+
+            mjai::Event::EndKyoku => {
+                let kyoku = builder
+                    .take()
+                    .ok_or_else(|| ReverseConvertError::UnexpectedEvent(event.clone()))?
+                    .build()?;
+                kyokus.push(kyoku);
+            }
+
+            mjai::Event::EndGame => break,
+
+            _ => builder
+                .as_mut()
+                .ok_or_else(|| ReverseConvertError::UnexpectedEvent(event.clone()))?
+                .push(event)?,
+        }
+    }
+
+    Ok(tenhou::Log {
+        names,
+        game_length,
+        has_aka,
+        kyokus,
+    })
+}
+
+struct KyokuBuilder {
+    meta: kyoku::Meta,
+    scoreboard: [i32; 4],
+    dora_indicators: Vec<Pai>,
+    ura_indicators: Vec<Pai>,
+    action_tables: [ActionTable; 4],
+    end_status: Option<kyoku::EndStatus>,
+
+    reach_declared: [bool; 4],
+    /// Pais and their targets of pons, for kakan.
+    pons: [Vec<(Pai, u8)>; 4],
+}
+
+impl KyokuBuilder {
+    fn new(start_kyoku: &mjai::Event) -> Self {
+        let (meta, scoreboard, dora_marker, tehais) = match *start_kyoku {
+            mjai::Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                scores,
+                tehais,
+                ..
+            } => {
+                let meta = kyoku::Meta {
+                    kyoku_num: (bakaze.as_u8() - Pai::East.as_u8()) * 4 + kyoku - 1,
+                    honba,
+                    kyotaku,
+                };
+                (meta, scores, dora_marker, tehais)
+            }
+            _ => unreachable!(),
+        };
+
+        let table = |haipai| ActionTable {
+            haipai,
+            takes: vec![],
+            discards: vec![],
+        };
+
+        Self {
+            meta,
+            scoreboard,
+            dora_indicators: vec![dora_marker],
+            ura_indicators: vec![],
+            action_tables: [
+                table(tehais[0]),
+                table(tehais[1]),
+                table(tehais[2]),
+                table(tehais[3]),
+            ],
+            end_status: None,
+            reach_declared: [false; 4],
+            pons: Default::default(),
+        }
+    }
+
+    fn push(&mut self, event: &mjai::Event) -> Result<()> {
+        match *event {
+            mjai::Event::Tsumo { actor, pai } => {
+                self.takes(actor).push(ActionItem::Pai(pai));
+            }
+
+            mjai::Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let item = if self.reach_declared[actor as usize] {
+                    self.reach_declared[actor as usize] = false;
+                    if tsumogiri {
+                        ActionItem::Naki("r60".to_owned())
+                    } else {
+                        ActionItem::Naki(format!("r{}", pai.as_u8()))
+                    }
+                } else if tsumogiri {
+                    ActionItem::Tsumogiri(60)
+                } else {
+                    ActionItem::Pai(pai)
+                };
+                self.discards(actor).push(item);
+            }
+
+            mjai::Event::Reach { actor } => {
+                self.reach_declared[actor as usize] = true;
+            }
+
+            mjai::Event::Chi {
+                actor,
+                pai,
+                consumed,
+                ..
+            } => {
+                // e.g. "c275226" => chi 7p with 06p from kamicha
+                let [a, b] = consumed.as_array();
+                let naki = format!("c{}{}{}", pai.as_u8(), a.as_u8(), b.as_u8());
+                self.takes(actor).push(ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let [a, b] = consumed.as_array();
+                let (a, b, p) = (a.as_u8(), b.as_u8(), pai.as_u8());
+                let naki = match (target + 4 - actor) % 4 {
+                    // from kamicha, e.g. "p252525"
+                    3 => format!("p{}{}{}", p, a, b),
+                    // from toimen, e.g. "12p1212"
+                    2 => format!("{}p{}{}", a, p, b),
+                    // from shimocha, e.g. "3737p37"
+                    _ => format!("{}{}p{}", a, b, p),
+                };
+                self.takes(actor).push(ActionItem::Naki(naki));
+                self.pons[actor as usize].push((pai.deaka(), target));
+            }
+
+            mjai::Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let [a, b, c] = consumed.as_array();
+                let (a, b, c, p) = (a.as_u8(), b.as_u8(), c.as_u8(), pai.as_u8());
+                let naki = match (target + 4 - actor) % 4 {
+                    // from kamicha, e.g. "m39393939"
+                    3 => format!("m{}{}{}{}", p, a, b, c),
+                    // from toimen, e.g. "26m262626"
+                    2 => format!("{}m{}{}{}", a, p, b, c),
+                    // from shimocha, e.g. "131313m13"
+                    _ => format!("{}{}{}m{}", a, b, c, p),
+                };
+                self.takes(actor).push(ActionItem::Naki(naki));
+                // tenhou.net/6 puts a 0 in the discards for daiminkan
+                self.discards(actor).push(ActionItem::Pai(Pai::Unknown));
+            }
+
+            mjai::Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                let target = self.pons[actor as usize]
+                    .iter()
+                    .find(|(p, _)| *p == pai.deaka())
+                    .map(|&(_, target)| target)
+                    .ok_or_else(|| ReverseConvertError::KakanWithoutPon(event.clone()))?;
+                let [a, b, c] = consumed.as_array();
+                let (a, b, c, p) = (a.as_u8(), b.as_u8(), c.as_u8(), pai.as_u8());
+                let naki = match (target + 4 - actor) % 4 {
+                    // from kamicha, e.g. "k16161616"
+                    3 => format!("k{}{}{}{}", p, a, b, c),
+                    // from toimen, e.g. "41k414141"
+                    2 => format!("{}k{}{}{}", a, p, b, c),
+                    // from shimocha, e.g. "4646k4646"
+                    _ => format!("{}{}k{}{}", a, b, p, c),
+                };
+                self.discards(actor).push(ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Ankan { actor, consumed } => {
+                // e.g. "424242a42"
+                let [a, b, c, d] = consumed.as_array();
+                let naki = format!("{}{}{}a{}", a.as_u8(), b.as_u8(), c.as_u8(), d.as_u8());
+                self.discards(actor).push(ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Dora { dora_marker } => {
+                self.dora_indicators.push(dora_marker);
+            }
+
+            mjai::Event::Hora {
+                actor,
+                target,
+                deltas,
+                ref ura_markers,
+            } => {
+                if let Some(ura) = ura_markers {
+                    if self.ura_indicators.is_empty() {
+                        self.ura_indicators = ura.clone();
+                    }
+                }
+
+                let detail = kyoku::HoraDetail {
+                    who: actor,
+                    target,
+                    score_deltas: deltas.unwrap_or_default(),
+                };
+                match &mut self.end_status {
+                    Some(kyoku::EndStatus::Hora { details }) => details.push(detail),
+                    _ => {
+                        self.end_status = Some(kyoku::EndStatus::Hora {
+                            details: vec![detail],
+                        })
+                    }
+                }
+            }
+
+            mjai::Event::Ryukyoku { deltas, reason } => {
+                self.end_status = Some(kyoku::EndStatus::Ryukyoku {
+                    score_deltas: deltas.unwrap_or_default(),
+                    reason,
+                });
+            }
+
+            mjai::Event::ReachAccepted { .. } | mjai::Event::None => (),
+
+            mjai::Event::StartGame { .. }
+            | mjai::Event::StartKyoku { .. }
+            | mjai::Event::EndKyoku
+            | mjai::Event::EndGame => {
+                return Err(ReverseConvertError::UnexpectedEvent(event.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn takes(&mut self, actor: u8) -> &mut Vec<ActionItem> {
+        &mut self.action_tables[actor as usize].takes
+    }
+
+    #[inline]
+    fn discards(&mut self, actor: u8) -> &mut Vec<ActionItem> {
+        &mut self.action_tables[actor as usize].discards
+    }
+
+    fn build(self) -> Result<tenhou::Kyoku> {
+        let end_status = self
+            .end_status
+            .ok_or(ReverseConvertError::MissingEndStatus {
+                kyoku: self.meta.kyoku_num,
+                honba: self.meta.honba,
+            })?;
+
+        Ok(tenhou::Kyoku {
+            meta: self.meta,
+            scoreboard: self.scoreboard,
+            dora_indicators: self.dora_indicators,
+            ura_indicators: self.ura_indicators,
+            action_tables: self.action_tables,
+            end_status,
+        })
+    }
+}
@@ -0,0 +1,197 @@
+//! Reads tenhou.net/6 logs from a JSON stream one kyoku at a time, so that
+//! neither a whole log nor a dump of many logs has to be kept in memory.
+
+use crate::tenhou::{Kyoku, RawKyoku, RawLog};
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json as json;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StreamError<E: StdError + 'static> {
+    #[error("failed to parse tenhou.net/6 log: {0}")]
+    Json(#[from] json::Error),
+
+    /// Returned by the kyoku handler, which stops the reading.
+    #[error(transparent)]
+    Kyoku(E),
+}
+
+/// Reads tenhou.net/6 logs one after another from `R`, which holds any
+/// number of them separated by whitespace, like a dump with one log per line.
+pub struct Reader<R: io::Read> {
+    de: json::Deserializer<json::de::IoRead<R>>,
+}
+
+impl<R: io::Read> Reader<R> {
+    #[inline]
+    pub fn new(reader: R) -> Self {
+        Self {
+            de: json::Deserializer::from_reader(reader),
+        }
+    }
+
+    /// Read the next log, calling `on_kyoku` with each of its kyokus in order
+    /// as soon as it is parsed.
+    ///
+    /// Returns the rest of the log, with no kyoku in it, or `None` at the end
+    /// of the stream. Tenhou puts `name` and `rule` after `log`, so they are
+    /// usually known only after all the kyokus have been handled, see
+    /// `next_log_parts` to learn them as soon as they are parsed.
+    pub fn next_log<F, E>(&mut self, mut on_kyoku: F) -> Result<Option<RawLog>, StreamError<E>>
+    where
+        F: FnMut(Kyoku) -> Result<(), E>,
+        E: StdError + 'static,
+    {
+        self.next_log_parts(|part| match part {
+            LogPart::Header(_) => Ok(()),
+            LogPart::Kyoku(kyoku) => on_kyoku(*kyoku),
+        })
+    }
+
+    /// Like `next_log`, but also calls `on_part` with the header of the log
+    /// as soon as its `name` and `rule` have been parsed, which is before the
+    /// kyokus if they come first in the log, and after them otherwise.
+    pub fn next_log_parts<F, E>(&mut self, on_part: F) -> Result<Option<RawLog>, StreamError<E>>
+    where
+        F: FnMut(LogPart<'_>) -> Result<(), E>,
+        E: StdError + 'static,
+    {
+        if self.de.end().is_ok() {
+            return Ok(None);
+        }
+
+        let mut failure = None;
+        let seed = LogSeed {
+            on_part,
+            failure: &mut failure,
+        };
+        match seed.deserialize(&mut self.de) {
+            Ok(raw_log) => Ok(Some(raw_log)),
+            Err(err) => Err(failure.map_or(StreamError::Json(err), StreamError::Kyoku)),
+        }
+    }
+}
+
+/// What `Reader::next_log_parts` hands over while reading a log.
+pub enum LogPart<'a> {
+    /// Everything but the kyokus, handed over once.
+    Header(&'a RawLog),
+    Kyoku(Box<Kyoku>),
+}
+
+/// Deserializes a log, passing its header and kyokus to `on_part` instead of
+/// keeping the kyokus.
+struct LogSeed<'a, F, E> {
+    on_part: F,
+    failure: &'a mut Option<E>,
+}
+
+impl<'de, F, E> DeserializeSeed<'de> for LogSeed<'_, F, E>
+where
+    F: FnMut(LogPart<'_>) -> Result<(), E>,
+{
+    type Value = RawLog;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F, E> Visitor<'de> for LogSeed<'_, F, E>
+where
+    F: FnMut(LogPart<'_>) -> Result<(), E>,
+{
+    type Value = RawLog;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a tenhou.net/6 log")
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut rest = Map::new();
+        let mut has_header = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "log" {
+                map.next_value_seed(KyokusSeed {
+                    on_part: &mut self.on_part,
+                    failure: &mut *self.failure,
+                })?;
+                rest.insert(key, Value::Array(vec![]));
+            } else {
+                let value = map.next_value()?;
+                rest.insert(key, value);
+            }
+
+            if !has_header && rest.contains_key("name") && rest.contains_key("rule") {
+                has_header = true;
+                let mut header = rest.clone();
+                header.insert("log".to_owned(), Value::Array(vec![]));
+                let header =
+                    RawLog::deserialize(Value::Object(header)).map_err(de::Error::custom)?;
+                if let Err(err) = (self.on_part)(LogPart::Header(&header)) {
+                    *self.failure = Some(err);
+                    return Err(de::Error::custom("stopped by the header handler"));
+                }
+            }
+        }
+
+        RawLog::deserialize(Value::Object(rest)).map_err(de::Error::custom)
+    }
+}
+
+struct KyokusSeed<'a, F, E> {
+    on_part: &'a mut F,
+    failure: &'a mut Option<E>,
+}
+
+impl<'de, F, E> DeserializeSeed<'de> for KyokusSeed<'_, F, E>
+where
+    F: FnMut(LogPart<'_>) -> Result<(), E>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F, E> Visitor<'de> for KyokusSeed<'_, F, E>
+where
+    F: FnMut(LogPart<'_>) -> Result<(), E>,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array of kyokus")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while let Some(raw_kyoku) = seq.next_element::<RawKyoku>()? {
+            if let Err(err) = (self.on_part)(LogPart::Kyoku(Box::new(Kyoku::from(raw_kyoku)))) {
+                *self.failure = Some(err);
+                return Err(de::Error::custom("stopped by the kyoku handler"));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mjai::RyukyokuReason;
 use crate::{KyokuFilter, Pai};
 
 use std::fmt;
@@ -55,8 +56,14 @@ pub mod kyoku {
 
     #[derive(Debug, Clone)]
     pub enum EndStatus {
-        Hora { details: Vec<HoraDetail> },
-        Ryukyoku { score_deltas: [i32; 4] },
+        Hora {
+            details: Vec<HoraDetail>,
+        },
+        Ryukyoku {
+            score_deltas: [i32; 4],
+            /// `None` if the status text is not known.
+            reason: Option<RyukyokuReason>,
+        },
     }
 
     #[derive(Debug, Clone, Default)]
@@ -69,6 +76,35 @@ pub mod kyoku {
 
 pub use kyoku::Kyoku;
 
+impl RyukyokuReason {
+    /// Parses the status text of a ryukyoku in tenhou.net/6 format, which is
+    /// the same for Mahjong Soul logs converted by tensoul.
+    pub fn from_tenhou_status(status: &str) -> Option<Self> {
+        match status {
+            "流局" | "全員聴牌" | "全員不聴" => Some(Self::Fanpai),
+            "流し満貫" => Some(Self::Nagashimangan),
+            "九種九牌" => Some(Self::Kyushukyuhai),
+            "四家立直" => Some(Self::Suuchariichi),
+            "四槓散了" => Some(Self::Suukaikan),
+            "四風連打" => Some(Self::Suufonrenda),
+            "三家和了" => Some(Self::Sanchaho),
+            _ => None,
+        }
+    }
+
+    pub const fn tenhou_status(self) -> &'static str {
+        match self {
+            Self::Fanpai => "流局",
+            Self::Nagashimangan => "流し満貫",
+            Self::Kyushukyuhai => "九種九牌",
+            Self::Suuchariichi => "四家立直",
+            Self::Suukaikan => "四槓散了",
+            Self::Suufonrenda => "四風連打",
+            Self::Sanchaho => "三家和了",
+        }
+    }
+}
+
 /// A group of "配牌", "取" and "出", describing a player's
 /// gaming status and actions throughout a kyoku.
 #[derive(Debug, Clone)]
@@ -99,7 +135,7 @@ mod json_scheme {
     }
 
     #[derive(Debug, Clone, SerializeTuple, DeserializeTuple)]
-    pub(super) struct Kyoku {
+    pub(crate) struct Kyoku {
         pub(super) meta: kyoku::Meta,
         pub(super) scoreboard: [i32; 4],
         pub(super) dora_indicators: Vec<Pai>,
@@ -152,6 +188,10 @@ mod json_scheme {
         pub(super) rate: Option<Vec<f64>>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub(super) sx: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) sc: Option<Vec<f64>>,
+        #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+        pub(super) log_ref: Option<String>,
     }
 
     #[derive(Debug, Serialize)]
@@ -164,9 +204,57 @@ mod json_scheme {
     }
 }
 
+pub(crate) use json_scheme::Kyoku as RawKyoku;
 pub use json_scheme::{Log as RawLog, PartialLog as RawPartialLog};
 
 impl RawLog {
+    #[inline]
+    pub fn names(&self) -> &[String; 4] {
+        &self.names
+    }
+
+    /// The log ID recorded in the log, which Tenhou logs have.
+    #[inline]
+    pub fn log_ref(&self) -> Option<&str> {
+        self.log_ref.as_deref()
+    }
+
+    /// Room and rule as displayed, like "鳳南喰赤".
+    #[inline]
+    pub fn rule_disp(&self) -> &str {
+        &self.rule.disp
+    }
+
+    /// The lobby on Tenhou, 0 for the public one.
+    #[inline]
+    pub fn lobby(&self) -> Option<i32> {
+        self.lobby
+    }
+
+    #[inline]
+    pub fn dans(&self) -> Option<&[String]> {
+        self.dan.as_deref()
+    }
+
+    #[inline]
+    pub fn rates(&self) -> Option<&[f64]> {
+        self.rate.as_deref()
+    }
+
+    /// Scores at the end of the game, from `sc`, which has the score and the
+    /// pt of each player in turn.
+    pub fn final_scores(&self) -> Option<[i32; 4]> {
+        let sc = self.sc.as_ref()?;
+        if sc.len() < 8 {
+            return None;
+        }
+        let mut scores = [0; 4];
+        for (s, pair) in scores.iter_mut().zip(sc.chunks(2)) {
+            *s = pair[0] as i32;
+        }
+        Some(scores)
+    }
+
     #[inline]
     pub fn hide_names(&mut self) {
         self.names
@@ -179,6 +267,12 @@ impl RawLog {
             });
     }
 
+    /// The kyoku number and honba of each kyoku, in order.
+    #[inline]
+    pub fn kyoku_metas(&self) -> impl Iterator<Item = &kyoku::Meta> {
+        self.logs.iter().map(|l| &l.meta)
+    }
+
     #[inline]
     pub fn filter_kyokus(&mut self, kyoku_filter: &KyokuFilter) {
         self.logs
@@ -212,6 +306,53 @@ impl RawLog {
     }
 }
 
+/// A kyoku removed from a log by `remove_invalid_kyokus`.
+#[derive(Debug, Clone)]
+pub struct InvalidKyoku {
+    /// Position in the log, counting from 0.
+    pub index: usize,
+    /// `None` if even the meta of the kyoku is malformed.
+    pub meta: Option<kyoku::Meta>,
+    pub reason: String,
+}
+
+/// Remove the kyokus that fail to parse or to convert into mjai format from
+/// `log`, a tenhou.net/6 log in JSON, so that the rest of it can still be
+/// parsed and converted. Returns the removed ones in order.
+///
+/// Scraped and hand-edited logs often have one broken kyoku, while the
+/// kyokus are converted independently of each other.
+pub fn remove_invalid_kyokus(log: &mut Value) -> Vec<InvalidKyoku> {
+    let kyokus = match log.get_mut("log").and_then(Value::as_array_mut) {
+        Some(kyokus) => kyokus,
+        None => return vec![],
+    };
+
+    let mut invalid = vec![];
+    let mut index = 0;
+    kyokus.retain(|kyoku| {
+        let checked = RawKyoku::deserialize(kyoku)
+            .map_err(|err| err.to_string())
+            .and_then(|raw_kyoku| {
+                crate::tenhou_kyoku_to_mjai(&Kyoku::from(raw_kyoku)).map_err(|err| err.to_string())
+            });
+        index += 1;
+        match checked {
+            Ok(_) => true,
+            Err(reason) => {
+                invalid.push(InvalidKyoku {
+                    index: index - 1,
+                    meta: kyoku.get(0).and_then(|m| kyoku::Meta::deserialize(m).ok()),
+                    reason,
+                });
+                false
+            }
+        }
+    });
+
+    invalid
+}
+
 impl From<RawPartialLog<'_>> for RawLog {
     fn from(partial_log: RawPartialLog) -> Self {
         RawLog {
@@ -234,6 +375,115 @@ impl Log {
         self.kyokus
             .retain(|l| kyoku_filter.test(l.meta.kyoku_num, l.meta.honba))
     }
+
+    /// Override the rule recorded in the log, for games played elsewhere
+    /// under a rule the log does not tell. `None` keeps the recorded one.
+    #[inline]
+    pub fn override_rule(&mut self, game_length: Option<GameLength>, has_aka: Option<bool>) {
+        if let Some(game_length) = game_length {
+            self.game_length = game_length;
+        }
+        if let Some(has_aka) = has_aka {
+            self.has_aka = has_aka;
+        }
+    }
+}
+
+impl From<Log> for RawLog {
+    /// The inverse of `Log::from(RawLog)`, mainly for logs converted from mjai
+    /// format.
+    ///
+    /// Only who and from whom are kept in hora details, as `Log` does not
+    /// record the rest.
+    fn from(log: Log) -> Self {
+        let Log {
+            names,
+            game_length,
+            has_aka,
+            kyokus,
+        } = log;
+
+        let logs = kyokus
+            .into_iter()
+            .map(|kyoku| {
+                let results = match kyoku.end_status {
+                    kyoku::EndStatus::Hora { details } => {
+                        let mut results = vec![json_scheme::ResultItem::Status("和了".to_owned())];
+                        for detail in details {
+                            results.push(json_scheme::ResultItem::ScoreDeltas(detail.score_deltas));
+                            results.push(json_scheme::ResultItem::HoraDetail(vec![
+                                detail.who.into(),
+                                detail.target.into(),
+                                detail.who.into(),
+                            ]));
+                        }
+                        results
+                    }
+                    kyoku::EndStatus::Ryukyoku {
+                        score_deltas,
+                        reason,
+                    } => vec![
+                        json_scheme::ResultItem::Status(
+                            reason
+                                .unwrap_or(RyukyokuReason::Fanpai)
+                                .tenhou_status()
+                                .to_owned(),
+                        ),
+                        json_scheme::ResultItem::ScoreDeltas(score_deltas),
+                    ],
+                };
+
+                let [t0, t1, t2, t3] = kyoku.action_tables;
+                json_scheme::Kyoku {
+                    meta: kyoku.meta,
+                    scoreboard: kyoku.scoreboard,
+                    dora_indicators: kyoku.dora_indicators,
+                    ura_indicators: kyoku.ura_indicators,
+
+                    haipai_0: t0.haipai,
+                    takes_0: t0.takes,
+                    discards_0: t0.discards,
+
+                    haipai_1: t1.haipai,
+                    takes_1: t1.takes,
+                    discards_1: t1.discards,
+
+                    haipai_2: t2.haipai,
+                    takes_2: t2.takes,
+                    discards_2: t2.discards,
+
+                    haipai_3: t3.haipai,
+                    takes_3: t3.takes,
+                    discards_3: t3.discards,
+
+                    results,
+                }
+            })
+            .collect();
+
+        let disp = match game_length {
+            GameLength::Hanchan => "般南喰赤",
+            GameLength::Tonpuu => "般東喰赤",
+        };
+        let rule = json_scheme::Rule {
+            disp: disp.to_owned(),
+            aka: has_aka as u8,
+            ..Default::default()
+        };
+
+        RawLog {
+            logs,
+            names,
+            rule,
+            ratingc: None,
+            lobby: None,
+            dan: None,
+            rate: None,
+            sx: None,
+            sc: None,
+            log_ref: None,
+        }
+    }
 }
 
 impl From<RawLog> for Log {
@@ -249,83 +499,7 @@ impl From<RawLog> for Log {
         };
         let has_aka = rule.aka + rule.aka51 + rule.aka52 + rule.aka53 > 0;
 
-        let kyokus = logs
-            .into_iter()
-            .map(|log| {
-                let mut item = Kyoku {
-                    meta: log.meta,
-                    scoreboard: log.scoreboard,
-                    dora_indicators: log.dora_indicators,
-                    ura_indicators: log.ura_indicators,
-                    action_tables: [
-                        ActionTable {
-                            haipai: log.haipai_0,
-                            takes: log.takes_0,
-                            discards: log.discards_0,
-                        },
-                        ActionTable {
-                            haipai: log.haipai_1,
-                            takes: log.takes_1,
-                            discards: log.discards_1,
-                        },
-                        ActionTable {
-                            haipai: log.haipai_2,
-                            takes: log.takes_2,
-                            discards: log.discards_2,
-                        },
-                        ActionTable {
-                            haipai: log.haipai_3,
-                            takes: log.takes_3,
-                            discards: log.discards_3,
-                        },
-                    ],
-                    end_status: kyoku::EndStatus::Ryukyoku {
-                        score_deltas: [0; 4], // default
-                    },
-                };
-
-                if let Some(json_scheme::ResultItem::Status(status_text)) = log.results.get(0) {
-                    if status_text == "和了" {
-                        let hora_details = log.results[1..]
-                            .chunks_exact(2)
-                            .filter_map(|detail_tuple| {
-                                if let (
-                                    json_scheme::ResultItem::ScoreDeltas(score_deltas),
-                                    json_scheme::ResultItem::HoraDetail(who_target_tuple),
-                                ) = (&detail_tuple[0], &detail_tuple[1])
-                                {
-                                    // TODO: it can actually fail, maybe impl TryFrom instead
-                                    let hora_detail = kyoku::HoraDetail {
-                                        score_deltas: *score_deltas,
-                                        who: who_target_tuple[0].as_u64().unwrap_or(0) as u8,
-                                        target: who_target_tuple[1].as_u64().unwrap_or(0) as u8,
-                                    };
-                                    Some(hora_detail)
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect();
-
-                        item.end_status = kyoku::EndStatus::Hora {
-                            details: hora_details,
-                        };
-                    } else {
-                        let score_deltas = if let Some(json_scheme::ResultItem::ScoreDeltas(dts)) =
-                            log.results.get(1)
-                        {
-                            *dts
-                        } else {
-                            [0; 4]
-                        };
-
-                        item.end_status = kyoku::EndStatus::Ryukyoku { score_deltas };
-                    }
-                }
-
-                item
-            })
-            .collect();
+        let kyokus = logs.into_iter().map(Kyoku::from).collect();
 
         Log {
             names,
@@ -335,3 +509,83 @@ impl From<RawLog> for Log {
         }
     }
 }
+
+impl From<RawKyoku> for Kyoku {
+    fn from(log: RawKyoku) -> Self {
+        let mut item = Kyoku {
+            meta: log.meta,
+            scoreboard: log.scoreboard,
+            dora_indicators: log.dora_indicators,
+            ura_indicators: log.ura_indicators,
+            action_tables: [
+                ActionTable {
+                    haipai: log.haipai_0,
+                    takes: log.takes_0,
+                    discards: log.discards_0,
+                },
+                ActionTable {
+                    haipai: log.haipai_1,
+                    takes: log.takes_1,
+                    discards: log.discards_1,
+                },
+                ActionTable {
+                    haipai: log.haipai_2,
+                    takes: log.takes_2,
+                    discards: log.discards_2,
+                },
+                ActionTable {
+                    haipai: log.haipai_3,
+                    takes: log.takes_3,
+                    discards: log.discards_3,
+                },
+            ],
+            end_status: kyoku::EndStatus::Ryukyoku {
+                score_deltas: [0; 4], // default
+                reason: None,
+            },
+        };
+
+        if let Some(json_scheme::ResultItem::Status(status_text)) = log.results.first() {
+            if status_text == "和了" {
+                let hora_details = log.results[1..]
+                    .chunks_exact(2)
+                    .filter_map(|detail_tuple| {
+                        if let (
+                            json_scheme::ResultItem::ScoreDeltas(score_deltas),
+                            json_scheme::ResultItem::HoraDetail(who_target_tuple),
+                        ) = (&detail_tuple[0], &detail_tuple[1])
+                        {
+                            // TODO: it can actually fail, maybe impl TryFrom instead
+                            let hora_detail = kyoku::HoraDetail {
+                                score_deltas: *score_deltas,
+                                who: who_target_tuple[0].as_u64().unwrap_or(0) as u8,
+                                target: who_target_tuple[1].as_u64().unwrap_or(0) as u8,
+                            };
+                            Some(hora_detail)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                item.end_status = kyoku::EndStatus::Hora {
+                    details: hora_details,
+                };
+            } else {
+                let score_deltas =
+                    if let Some(json_scheme::ResultItem::ScoreDeltas(dts)) = log.results.get(1) {
+                        *dts
+                    } else {
+                        [0; 4]
+                    };
+
+                item.end_status = kyoku::EndStatus::Ryukyoku {
+                    score_deltas,
+                    reason: RyukyokuReason::from_tenhou_status(status_text),
+                };
+            }
+        }
+
+        item
+    }
+}
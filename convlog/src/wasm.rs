@@ -0,0 +1,58 @@
+//! JavaScript bindings, enabled by feature `wasm`.
+//!
+//! Build from the convlog directory, as `--features` cannot pick features of
+//! another package from the workspace root, with
+//! `cd convlog && cargo build --target wasm32-unknown-unknown --no-default-features --features wasm`
+//! and generate the JS glue with `wasm-bindgen`.
+
+use crate::{mjai, tenhou};
+
+use serde_json as json;
+use wasm_bindgen::prelude::*;
+
+/// Transform a tenhou.net/6 format log in JSON string into mjai format.
+///
+/// The return value is a string of mjai events, one JSON event per line.
+#[wasm_bindgen(js_name = tenhouToMjai)]
+pub fn tenhou_to_mjai(tenhou_log: &str) -> Result<String, JsValue> {
+    let log = tenhou::Log::from_json_str(tenhou_log)
+        .map_err(|err| JsValue::from_str(&format!("failed to parse tenhou.net/6 log: {}", err)))?;
+    let events = crate::tenhou_to_mjai(&log).map_err(|err| {
+        JsValue::from_str(&format!("failed to convert tenhou.net/6 log: {}", err))
+    })?;
+
+    let mut ret = String::new();
+    for event in &events {
+        let line = json::to_string(event).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        ret.push_str(&line);
+        ret.push('\n');
+    }
+
+    Ok(ret)
+}
+
+/// Transform a mjai format log, one JSON event per line, into tenhou.net/6
+/// format in JSON string.
+#[wasm_bindgen(js_name = mjaiToTenhou)]
+pub fn mjai_to_tenhou(mjai_log: &str) -> Result<String, JsValue> {
+    let events = mjai_log
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(json::from_str)
+        .collect::<json::Result<Vec<mjai::Event>>>()
+        .map_err(|err| JsValue::from_str(&format!("failed to parse mjai log: {}", err)))?;
+    let log = crate::mjai_to_tenhou(&events)
+        .map_err(|err| JsValue::from_str(&format!("failed to convert mjai log: {}", err)))?;
+
+    json::to_string(&tenhou::RawLog::from(log)).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Split a tenhou.net/6 format log in JSON string into a JSON array of logs,
+/// each of which contains only one kyoku.
+#[wasm_bindgen(js_name = splitByKyoku)]
+pub fn split_by_kyoku(tenhou_log: &str) -> Result<String, JsValue> {
+    let raw_log: tenhou::RawLog = json::from_str(tenhou_log)
+        .map_err(|err| JsValue::from_str(&format!("failed to parse tenhou.net/6 log: {}", err)))?;
+
+    json::to_string(&raw_log.split_by_kyoku()).map_err(|err| JsValue::from_str(&err.to_string()))
+}
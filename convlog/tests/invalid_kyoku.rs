@@ -0,0 +1,45 @@
+mod testdata;
+
+use convlog::*;
+use testdata::{TestCase, TESTDATA};
+
+use serde_json as json;
+
+#[test]
+fn test_remove_invalid_kyokus() {
+    let TestCase { description, data } = TESTDATA
+        .iter()
+        .find(|c| c.description == "ranked_game")
+        .unwrap();
+    let mut value: json::Value = json::from_str(data).unwrap();
+    let kyoku_count = value["log"].as_array().unwrap().len();
+    assert!(kyoku_count >= 3, "case: {}", description);
+
+    // a conversion error in the 2nd kyoku and a parse error in the 3rd
+    value["log"][1][5].as_array_mut().unwrap().truncate(2);
+    value["log"][2] = json::json!(["garbage"]);
+
+    let invalid = tenhou::remove_invalid_kyokus(&mut value);
+    assert_eq!(invalid.len(), 2);
+    assert_eq!(invalid[0].index, 1);
+    assert!(matches!(
+        &invalid[0].meta,
+        Some(tenhou::kyoku::Meta { kyoku_num: 1, .. })
+    ));
+    assert_eq!(invalid[1].index, 2);
+    assert!(invalid[1].meta.is_none());
+
+    let raw_log: tenhou::RawLog = json::from_value(value).unwrap();
+    assert_eq!(raw_log.len(), kyoku_count - 2);
+    let log = tenhou::Log::from(raw_log);
+    tenhou_to_mjai(&log).unwrap();
+}
+
+#[test]
+fn test_remove_invalid_kyokus_keeps_valid_logs() {
+    TESTDATA.iter().for_each(|TestCase { description, data }| {
+        let mut value: json::Value = json::from_str(data).unwrap();
+        let invalid = tenhou::remove_invalid_kyokus(&mut value);
+        assert!(invalid.is_empty(), "case: {}", description);
+    });
+}
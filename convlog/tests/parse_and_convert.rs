@@ -6,15 +6,44 @@ use testdata::{TestCase, TESTDATA};
 #[test]
 fn test_parse_and_convert() {
     TESTDATA.iter().for_each(|TestCase { description, data }| {
-        let tenhou_log = tenhou::Log::from_json_str(data).expect(&*format!(
-            "failed to parse tenhou log (case: {})",
-            description
-        ));
-        let mjai_log = tenhou_to_mjai(&tenhou_log).expect(&*format!(
-            "failed to transform tenhou log (case: {})",
-            description
-        ));
+        let tenhou_log = tenhou::Log::from_json_str(data).unwrap_or_else(|err| {
+            panic!(
+                "failed to parse tenhou log (case: {}): {}",
+                description, err
+            )
+        });
+        let mjai_log = tenhou_to_mjai(&tenhou_log).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform tenhou log (case: {}): {}",
+                description, err
+            )
+        });
 
         assert!(mjai_log.len() >= 4);
     });
 }
+
+#[test]
+fn test_override_rule() {
+    let TestCase { description, data } = &TESTDATA[0];
+    let mut tenhou_log = tenhou::Log::from_json_str(data).unwrap_or_else(|err| {
+        panic!(
+            "failed to parse tenhou log (case: {}): {}",
+            description, err
+        )
+    });
+    tenhou_log.override_rule(Some(tenhou::GameLength::Tonpuu), Some(false));
+
+    let mjai_log = tenhou_to_mjai(&tenhou_log).unwrap();
+    match &mjai_log[0] {
+        mjai::Event::StartGame {
+            kyoku_first,
+            aka_flag,
+            ..
+        } => {
+            assert_eq!(*kyoku_first, tenhou::GameLength::Tonpuu as u8);
+            assert!(!aka_flag);
+        }
+        ev => panic!("expected start_game, got {:?}", ev),
+    }
+}
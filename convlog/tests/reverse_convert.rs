@@ -0,0 +1,47 @@
+mod testdata;
+
+use convlog::*;
+use testdata::{TestCase, TESTDATA};
+
+use serde_json as json;
+
+#[test]
+fn test_mjai_to_tenhou_roundtrip() {
+    TESTDATA.iter().for_each(|TestCase { description, data }| {
+        let log = tenhou::Log::from_json_str(data).unwrap_or_else(|err| {
+            panic!(
+                "failed to parse tenhou log (case: {}): {}",
+                description, err
+            )
+        });
+        let mjai_log = tenhou_to_mjai(&log).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform tenhou log (case: {}): {}",
+                description, err
+            )
+        });
+
+        let reversed = mjai_to_tenhou(&mjai_log).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform mjai log (case: {}): {}",
+                description, err
+            )
+        });
+        // go through the JSON form to make sure it is also parsable
+        let reversed_json = json::to_string(&tenhou::RawLog::from(reversed)).unwrap();
+        let reparsed = tenhou::Log::from_json_str(&reversed_json).unwrap_or_else(|err| {
+            panic!(
+                "failed to parse reversed tenhou log (case: {}): {}",
+                description, err
+            )
+        });
+        let mjai_log_again = tenhou_to_mjai(&reparsed).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform reversed tenhou log (case: {}): {}",
+                description, err
+            )
+        });
+
+        assert_eq!(mjai_log, mjai_log_again, "case: {}", description);
+    });
+}
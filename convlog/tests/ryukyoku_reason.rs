@@ -0,0 +1,61 @@
+mod testdata;
+
+use convlog::mjai::{Event, RyukyokuReason};
+use convlog::*;
+use testdata::{TestCase, TESTDATA};
+
+fn convert(description: &str) -> Vec<Event> {
+    let TestCase { data, .. } = TESTDATA
+        .iter()
+        .find(|c| c.description == description)
+        .unwrap();
+    let log = tenhou::Log::from_json_str(data).unwrap();
+    tenhou_to_mjai(&log).unwrap()
+}
+
+/// The events right before end_kyoku of the first kyoku.
+fn ending(events: &[Event]) -> &[Event] {
+    let end = events.iter().position(|ev| *ev == Event::EndKyoku).unwrap();
+    &events[end - 2..end]
+}
+
+#[test]
+fn test_ryukyoku_reasons() {
+    let cases = [
+        ("ryukyoku", RyukyokuReason::Fanpai),
+        ("kyushukyuhai", RyukyokuReason::Kyushukyuhai),
+        ("four_reach", RyukyokuReason::Suuchariichi),
+        ("suufonrenda", RyukyokuReason::Suufonrenda),
+        ("sanchaho", RyukyokuReason::Sanchaho),
+    ];
+
+    for &(description, expected) in &cases {
+        let events = convert(description);
+        let reason = events.iter().rev().find_map(|ev| match *ev {
+            Event::Ryukyoku { reason, .. } => Some(reason),
+            _ => None,
+        });
+        assert_eq!(reason, Some(Some(expected)), "case: {}", description);
+    }
+}
+
+#[test]
+fn test_abortive_draw_reach_sticks() {
+    // the 4th riichi stick is put down before the kyoku is aborted
+    let events = convert("four_reach");
+    let last_kyoku = events
+        .iter()
+        .rposition(|ev| matches!(ev, Event::StartKyoku { .. }))
+        .unwrap();
+    assert!(matches!(
+        ending(&events[last_kyoku..]),
+        [Event::ReachAccepted { .. }, Event::Ryukyoku { .. }]
+    ));
+
+    // while a ronned riichi dahai is not accepted
+    let events = convert("sanchaho");
+    assert!(matches!(
+        ending(&events),
+        [Event::Dahai { .. }, Event::Ryukyoku { .. }]
+    ));
+}
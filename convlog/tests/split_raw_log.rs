@@ -8,10 +8,12 @@ use serde_json as json;
 #[test]
 fn test_split_by_kyoku() {
     TESTDATA.iter().for_each(|TestCase { description, data }| {
-        let raw_log: tenhou::RawLog = json::from_str(data).expect(&*format!(
-            "failed to parse tenhou log (case: {})",
-            description
-        ));
+        let raw_log: tenhou::RawLog = json::from_str(data).unwrap_or_else(|err| {
+            panic!(
+                "failed to parse tenhou log (case: {}): {}",
+                description, err
+            )
+        });
         let splited_raw_logs = raw_log.split_by_kyoku();
 
         let log = tenhou::Log::from(raw_log.clone());
@@ -26,14 +28,18 @@ fn test_split_by_kyoku() {
             ..log.clone()
         };
 
-        let mjai_log = tenhou_to_mjai(&log).expect(&*format!(
-            "failed to transform tenhou (case: {})",
-            description
-        ));
-        let mjai_log_joined = tenhou_to_mjai(&joined_logs).expect(&*format!(
-            "failed to transform tenhou (case: {})",
-            description
-        ));
+        let mjai_log = tenhou_to_mjai(&log).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform tenhou (case: {}): {}",
+                description, err
+            )
+        });
+        let mjai_log_joined = tenhou_to_mjai(&joined_logs).unwrap_or_else(|err| {
+            panic!(
+                "failed to transform tenhou (case: {}): {}",
+                description, err
+            )
+        });
 
         assert_eq!(mjai_log, mjai_log_joined);
     });
@@ -0,0 +1,103 @@
+mod testdata;
+
+use convlog::*;
+use testdata::{TestCase, TESTDATA};
+
+#[test]
+fn test_stream_many_logs() {
+    let dump = TESTDATA
+        .iter()
+        .map(|TestCase { data, .. }| data.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut reader = stream::Reader::new(dump.as_bytes());
+
+    TESTDATA.iter().for_each(|TestCase { description, data }| {
+        let mut kyoku_events = vec![];
+        let raw_log = reader
+            .next_log(|kyoku| {
+                kyoku_events.extend(tenhou_kyoku_to_mjai(&kyoku)?);
+                Ok::<_, ConvertError>(())
+            })
+            .unwrap_or_else(|err| {
+                panic!(
+                    "failed to stream tenhou log (case: {}): {}",
+                    description, err
+                )
+            })
+            .unwrap_or_else(|| panic!("unexpected end of stream (case: {})", description));
+
+        let log = tenhou::Log::from(raw_log);
+        assert!(log.kyokus.is_empty());
+        let mut mjai_log_streamed = vec![tenhou_start_game(&log)];
+        mjai_log_streamed.extend(kyoku_events);
+        mjai_log_streamed.push(mjai::Event::EndGame);
+
+        let mjai_log = tenhou_to_mjai(&tenhou::Log::from_json_str(data).unwrap()).unwrap();
+        assert_eq!(mjai_log, mjai_log_streamed, "case: {}", description);
+    });
+
+    assert!(reader
+        .next_log(|_| Ok::<_, ConvertError>(()))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_stream_stops_on_handler_error() {
+    let TestCase { data, .. } = &TESTDATA[0];
+    let mut reader = stream::Reader::new(data.as_bytes());
+
+    let mut count = 0;
+    let err = reader
+        .next_log(|_| {
+            count += 1;
+            Err(ConvertError::UnexpectedTsumogiri)
+        })
+        .unwrap_err();
+
+    assert_eq!(count, 1);
+    assert!(matches!(
+        err,
+        stream::StreamError::Kyoku(ConvertError::UnexpectedTsumogiri)
+    ));
+}
+
+/// The order of the parts handed over by `next_log_parts` for `data`, "h" for
+/// the header and "k" for a kyoku.
+fn part_order(data: &str) -> String {
+    let mut reader = stream::Reader::new(data.as_bytes());
+    let mut order = String::new();
+    reader
+        .next_log_parts(|part| {
+            order.push(match part {
+                stream::LogPart::Header(_) => 'h',
+                stream::LogPart::Kyoku(_) => 'k',
+            });
+            Ok::<_, ConvertError>(())
+        })
+        .unwrap()
+        .unwrap();
+    order
+}
+
+#[test]
+fn test_stream_header() {
+    let TestCase { data, .. } = &TESTDATA[0];
+    let value: serde_json::Value = serde_json::from_str(data).unwrap();
+    let kyokus = value["log"].as_array().unwrap().len();
+
+    // header first
+    let reordered = format!(
+        r#"{{"name":{},"rule":{},"log":{}}}"#,
+        value["name"], value["rule"], value["log"],
+    );
+    assert_eq!(part_order(&reordered), format!("h{}", "k".repeat(kyokus)));
+
+    // Tenhou puts it last
+    let reordered = format!(
+        r#"{{"log":{},"rule":{},"name":{}}}"#,
+        value["log"], value["rule"], value["name"],
+    );
+    assert_eq!(part_order(&reordered), format!("{}h", "k".repeat(kyokus)));
+}
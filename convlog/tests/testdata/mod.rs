@@ -72,6 +72,10 @@ pub const TESTDATA: &[TestCase] = &[
         description: "ryukyoku",
         data: include_str!("ryukyoku.json"),
     },
+    TestCase {
+        description: "sanchaho",
+        data: include_str!("sanchaho.json"),
+    },
     TestCase {
         description: "suukantsu_0",
         data: include_str!("suukantsu_0.json"),
@@ -80,4 +84,8 @@ pub const TESTDATA: &[TestCase] = &[
         description: "suukantsu_1",
         data: include_str!("suukantsu_1.json"),
     },
+    TestCase {
+        description: "suufonrenda",
+        data: include_str!("suufonrenda.json"),
+    },
 ];
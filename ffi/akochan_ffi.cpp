@@ -0,0 +1,294 @@
+// Implements akochan_ffi.h by running akochan's own main in a thread, with its
+// std::cin and std::cout redirected to the events fed and the responses
+// returned. akochan's main must be renamed to akochan_main and akochan must be
+// compiled with akochan_ffi_io.h force-included, which gives each instance
+// streams of its own, e.g. from the root of akochan:
+//
+//   g++ -std=c++11 -O2 -shared -fPIC -pthread -Dmain=akochan_main \
+//       -include /path/to/akochan_ffi_io.h \
+//       -o libakochan_ffi.so /path/to/akochan_ffi.cpp main.cpp -L. -lai
+//
+// An event has been answered once akochan comes back for the next line of
+// std::cin, so whatever it wrote to std::cout in between is the response.
+//
+// akochan reads its params relative to the working directory. On Linux the
+// thread of each instance gets a working directory of its own. Elsewhere the
+// working directory of the process is changed to akochan_dir only while
+// akochan loads its params, which it does before reading the first event.
+
+// the force-included redirection is for akochan only
+#undef cin
+#undef cout
+
+#include "akochan_ffi.h"
+
+#include <chrono>
+#include <condition_variable>
+#include <cstdlib>
+#include <cstring>
+#include <iostream>
+#include <mutex>
+#include <streambuf>
+#include <string>
+#include <thread>
+#include <vector>
+
+#ifdef _WIN32
+#include <direct.h>
+#define chdir _chdir
+#define getcwd _getcwd
+#else
+#include <unistd.h>
+#endif
+#ifdef __linux__
+#include <sched.h>
+#endif
+
+int akochan_main(int argc, char *argv[]);
+
+namespace {
+
+struct Akochan;
+
+// the instance run by the current thread, if any
+thread_local Akochan *current = nullptr;
+
+#ifndef __linux__
+// held while the working directory of the process is borrowed
+std::mutex cwd_mutex;
+#endif
+
+// Hands the fed events to std::cin, blocking until there is one.
+class InBuf : public std::streambuf {
+public:
+    explicit InBuf(Akochan &akochan) : akochan_(akochan) {}
+
+protected:
+    int_type underflow() override;
+
+private:
+    Akochan &akochan_;
+    std::string line_;
+};
+
+// Collects what is written to std::cout.
+class OutBuf : public std::streambuf {
+public:
+    explicit OutBuf(Akochan &akochan) : akochan_(akochan) {}
+
+protected:
+    int_type overflow(int_type ch) override;
+    std::streamsize xsputn(const char *s, std::streamsize n) override;
+
+private:
+    Akochan &akochan_;
+};
+
+struct Akochan {
+    std::mutex mutex;
+    std::condition_variable cond;
+    // the next line for std::cin
+    std::string pending;
+    bool has_pending = false;
+    // akochan is blocked on std::cin
+    bool waiting = false;
+    // akochan_main has returned
+    bool finished = false;
+    bool closing = false;
+    // freed while busy, so the thread frees it once akochan_main returns
+    bool orphaned = false;
+    std::string output;
+
+    InBuf in_buf{*this};
+    OutBuf out_buf{*this};
+    std::istream in{&in_buf};
+    std::ostream out{&out_buf};
+    std::thread thread;
+
+    // Waits until akochan is done with what it has been fed, for at most
+    // `timeout_ms` if it is not negative.
+    int wait_idle(std::unique_lock<std::mutex> &lock, long timeout_ms) {
+        auto idle = [this] { return waiting || finished; };
+        if (timeout_ms < 0) {
+            cond.wait(lock, idle);
+        } else if (!cond.wait_for(lock, std::chrono::milliseconds(timeout_ms),
+                                  idle)) {
+            return AKOCHAN_TIMED_OUT;
+        }
+        return finished ? AKOCHAN_FAILED : AKOCHAN_OK;
+    }
+};
+
+InBuf::int_type InBuf::underflow() {
+    std::unique_lock<std::mutex> lock(akochan_.mutex);
+    akochan_.waiting = true;
+    akochan_.cond.notify_all();
+    akochan_.cond.wait(lock, [this] {
+        return akochan_.has_pending || akochan_.closing;
+    });
+    if (!akochan_.has_pending) {
+        return traits_type::eof();
+    }
+
+    line_ = std::move(akochan_.pending);
+    akochan_.has_pending = false;
+    akochan_.waiting = false;
+    char *begin = &line_[0];
+    setg(begin, begin, begin + line_.size());
+    return traits_type::to_int_type(*begin);
+}
+
+OutBuf::int_type OutBuf::overflow(int_type ch) {
+    if (!traits_type::eq_int_type(ch, traits_type::eof())) {
+        std::lock_guard<std::mutex> lock(akochan_.mutex);
+        akochan_.output.push_back(traits_type::to_char_type(ch));
+    }
+    return traits_type::not_eof(ch);
+}
+
+std::streamsize OutBuf::xsputn(const char *s, std::streamsize n) {
+    std::lock_guard<std::mutex> lock(akochan_.mutex);
+    akochan_.output.append(s, static_cast<size_t>(n));
+    return n;
+}
+
+char *copy_string(const std::string &s) {
+    char *ret = static_cast<char *>(std::malloc(s.size() + 1));
+    if (ret != nullptr) {
+        std::memcpy(ret, s.c_str(), s.size() + 1);
+    }
+    return ret;
+}
+
+// Makes akochan_main return at its next read of std::cin. A busy akochan,
+// e.g. one that timed out, cannot be stopped, so its thread is left to free
+// the instance by itself.
+void destroy(Akochan *akochan) {
+    {
+        std::lock_guard<std::mutex> lock(akochan->mutex);
+        akochan->closing = true;
+        if (!akochan->waiting && !akochan->finished &&
+            akochan->thread.joinable()) {
+            akochan->orphaned = true;
+            akochan->thread.detach();
+            return;
+        }
+    }
+    akochan->cond.notify_all();
+    if (akochan->thread.joinable()) {
+        akochan->thread.join();
+    }
+    delete akochan;
+}
+
+void run(Akochan *akochan, const std::string &akochan_dir,
+         std::vector<std::string> args) {
+    current = akochan;
+#ifdef __linux__
+    bool entered = unshare(CLONE_FS) == 0 && chdir(akochan_dir.c_str()) == 0;
+#else
+    (void)akochan_dir;
+    bool entered = true;
+#endif
+    if (entered) {
+        std::vector<char *> argv;
+        for (std::string &arg : args) {
+            argv.push_back(&arg[0]);
+        }
+        argv.push_back(nullptr);
+        akochan_main(static_cast<int>(args.size()), argv.data());
+    }
+
+    std::unique_lock<std::mutex> lock(akochan->mutex);
+    akochan->finished = true;
+    akochan->cond.notify_all();
+    if (akochan->orphaned) {
+        lock.unlock();
+        delete akochan;
+    }
+}
+
+} // namespace
+
+namespace std {
+
+istream &akochan_ffi_cin() { return current != nullptr ? current->in : cin; }
+
+ostream &akochan_ffi_cout() {
+    return current != nullptr ? current->out : cout;
+}
+
+} // namespace std
+
+extern "C" void *akochan_new(const char *akochan_dir,
+                             const char *tactics_config, int target_actor) {
+#ifndef __linux__
+    std::lock_guard<std::mutex> cwd_lock(cwd_mutex);
+    char orig_dir[4096];
+    if (getcwd(orig_dir, sizeof(orig_dir)) == nullptr ||
+        chdir(akochan_dir) != 0) {
+        return nullptr;
+    }
+#endif
+
+    Akochan *akochan = new Akochan;
+    std::vector<std::string> args = {"system.exe", "pipe_detailed",
+                                     tactics_config,
+                                     std::to_string(target_actor)};
+    akochan->thread = std::thread(run, akochan, std::string(akochan_dir),
+                                  std::move(args));
+
+    // akochan loads its params before reading the first event
+    int status;
+    {
+        std::unique_lock<std::mutex> lock(akochan->mutex);
+        status = akochan->wait_idle(lock, -1);
+        akochan->output.clear();
+    }
+#ifndef __linux__
+    if (chdir(orig_dir) != 0) {
+        status = AKOCHAN_FAILED;
+    }
+#endif
+    if (status != AKOCHAN_OK) {
+        destroy(akochan);
+        return nullptr;
+    }
+
+    return akochan;
+}
+
+extern "C" int akochan_feed(void *ptr, const char *event, long timeout_ms,
+                            char **response) {
+    Akochan *akochan = static_cast<Akochan *>(ptr);
+    std::unique_lock<std::mutex> lock(akochan->mutex);
+    int status = akochan->wait_idle(lock, timeout_ms);
+    if (status != AKOCHAN_OK) {
+        return status;
+    }
+
+    akochan->output.clear();
+    akochan->pending = std::string(event) + "\n";
+    akochan->has_pending = true;
+    akochan->waiting = false;
+    akochan->cond.notify_all();
+    status = akochan->wait_idle(lock, timeout_ms);
+    if (status != AKOCHAN_OK) {
+        return status;
+    }
+
+    std::string output = std::move(akochan->output);
+    akochan->output.clear();
+    while (!output.empty() &&
+           (output.back() == '\n' || output.back() == '\r')) {
+        output.pop_back();
+    }
+    *response = copy_string(output);
+    return *response != nullptr ? AKOCHAN_OK : AKOCHAN_FAILED;
+}
+
+extern "C" void akochan_free_string(char *s) { std::free(s); }
+
+extern "C" void akochan_free(void *ptr) {
+    destroy(static_cast<Akochan *>(ptr));
+}
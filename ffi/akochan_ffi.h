@@ -0,0 +1,40 @@
+/* The C API that akochan-reviewer loads with --akochan-lib, see src/ffi.rs.
+ * akochan_ffi.cpp implements it on top of akochan's own main. */
+
+#ifndef AKOCHAN_FFI_H
+#define AKOCHAN_FFI_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Statuses returned by akochan_feed. */
+#define AKOCHAN_OK 0
+#define AKOCHAN_FAILED 1
+#define AKOCHAN_TIMED_OUT 2
+
+/* Starts akochan in pipe_detailed mode. Returns NULL on failure. Several
+ * instances may run at the same time. `akochan_dir` is where akochan looks
+ * for its params, i.e. the working directory of system.exe. */
+void *akochan_new(const char *akochan_dir, const char *tactics_config,
+                  int target_actor);
+
+/* Feeds one mjai event as a line of JSON, waiting at most `timeout_ms` for
+ * akochan to be done with it, or forever if it is negative. On AKOCHAN_OK,
+ * `*response` is what system.exe would write to stdout, an empty string if
+ * there is none, and must be freed with akochan_free_string. An instance
+ * that has failed or timed out can only be freed. */
+int akochan_feed(void *akochan, const char *event, long timeout_ms,
+                 char **response);
+
+void akochan_free_string(char *s);
+
+/* Frees the instance. One that is still busy, e.g. after a timeout, is freed
+ * by its own thread once akochan is done. */
+void akochan_free(void *akochan);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
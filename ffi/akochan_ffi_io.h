@@ -0,0 +1,19 @@
+/* Force-included (-include) when compiling akochan for akochan_ffi.cpp, so
+ * that every instance of akochan running in the process reads and writes
+ * streams of its own instead of the process-wide std::cin and std::cout.
+ * Threads that are not running akochan_main still get the real ones. */
+
+#ifndef AKOCHAN_FFI_IO_H
+#define AKOCHAN_FFI_IO_H
+
+#include <iostream>
+
+namespace std {
+istream &akochan_ffi_cin();
+ostream &akochan_ffi_cout();
+} // namespace std
+
+#define cin akochan_ffi_cin()
+#define cout akochan_ffi_cout()
+
+#endif
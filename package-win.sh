@@ -29,9 +29,10 @@ cp -rt "$OUT_DIR"/akochan \
 popd
 
 pushd "$REVIEWER_DIR"
-cargo build --release
+cargo build --release --features gui
 cp -rt "$OUT_DIR" \
     target/release/akochan-reviewer.exe \
+    target/release/akochan-reviewer-gui.exe \
     tactics.json \
     README.md \
     LICENSE
@@ -0,0 +1,445 @@
+use crate::board::BoardState;
+use crate::heatmap::Heatmap;
+use crate::i18n::{self, Language, Terms};
+use crate::intent::{self, IntentArgs};
+use crate::log;
+use crate::post_mortem;
+use crate::review::{dahai_pai, Acceptance, Entry, KyokuReview};
+use crate::ryukyoku;
+use crate::score_audit::{ScoreAuditor, ScoreMismatch};
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use anyhow::{ensure, Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+/// Makes a registered analyzer anew for each review.
+type Factory = Box<dyn Fn() -> Box<dyn Analyzer> + Send>;
+
+static REGISTERED: Lazy<Mutex<Vec<Factory>>> = Lazy::new(Default::default);
+
+/// Looks over a finished review and adds to the report.
+///
+/// Besides the built-in ones, analyzers come from `--script`, `--analyzer`
+/// and, for a binary of another crate that runs [`crate::cli::main`],
+/// [`register`].
+///
+/// An analyzer first sees every kyoku in order, each event of it through
+/// `on_event` along with the board after it, and each reviewed decision, with
+/// akochan's candidates in `Entry::details`, through `on_entry`. `finish` is
+/// called once at the end.
+pub trait Analyzer {
+    /// Key of the output in the report data, e.g. in the `--json` output.
+    fn name(&self) -> &str;
+
+    fn on_event(&mut self, _kyoku: &KyokuReview, _board: &BoardState, _event: &Event) {}
+
+    /// Returns a note to show with the decision, if any.
+    fn on_entry(&mut self, _kyoku: &KyokuReview, _entry: &Entry) -> Option<String> {
+        None
+    }
+
+    /// Returns `None` if there is nothing to add to the report. Analyzers
+    /// that need the whole game first may annotate the entries here.
+    fn finish(&mut self, kyokus: &mut [KyokuReview], target_actor: u8) -> Result<Option<Output>>;
+}
+
+/// What an analyzer adds to the report.
+pub enum Output {
+    /// Data for a section the report template already knows, under the name
+    /// of the analyzer.
+    Data(json::Value),
+    /// A section of its own, rendered as a table.
+    Section(Section),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Section {
+    pub title: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Name of the analyzer.
+    pub analyzer: String,
+    pub text: String,
+}
+
+/// Everything the analyzers added, to be attached to the report.
+#[derive(Debug, Default)]
+pub struct Analyses {
+    pub data: BTreeMap<String, json::Value>,
+    pub sections: Vec<Section>,
+}
+
+/// The analyzers behind the built-in report sections. `kuitan` is the rule
+/// the horas are scored under, and notes are written in `lang` with `terms`.
+pub fn builtin(kuitan: bool, lang: Language, terms: Terms) -> Vec<Box<dyn Analyzer>> {
+    vec![
+        Box::new(HeatmapAnalyzer),
+        Box::new(RyukyokuAnalyzer),
+        Box::new(PostMortemAnalyzer),
+        Box::new(ScoreAuditAnalyzer::new(kuitan)),
+        Box::new(IntentAnalyzer::new(kuitan, lang, terms)),
+    ]
+}
+
+/// Adds the analyzer made by `factory` to every review, after the built-in
+/// ones.
+pub fn register<F>(factory: F)
+where
+    F: Fn() -> Box<dyn Analyzer> + Send + 'static,
+{
+    REGISTERED.lock().unwrap().push(Box::new(factory));
+}
+
+/// The built-in analyzers followed by the ones from [`register`].
+pub fn all(kuitan: bool, lang: Language, terms: Terms) -> Vec<Box<dyn Analyzer>> {
+    let mut analyzers = builtin(kuitan, lang, terms);
+    analyzers.extend(REGISTERED.lock().unwrap().iter().map(|factory| factory()));
+    analyzers
+}
+
+/// Runs `analyzers` over `kyokus` in order, attaching their annotations to
+/// the entries.
+pub fn run(
+    analyzers: &mut [Box<dyn Analyzer>],
+    kyokus: &mut [KyokuReview],
+    target_actor: u8,
+) -> Result<Analyses> {
+    let mut board = BoardState::new();
+    for kyoku in kyokus.iter_mut() {
+        for event in &kyoku.events {
+            board.update(event).context("failed to update board")?;
+            for analyzer in analyzers.iter_mut() {
+                analyzer.on_event(kyoku, &board, event);
+            }
+        }
+
+        let mut annotations = vec![vec![]; kyoku.entries.len()];
+        for analyzer in analyzers.iter_mut() {
+            for (entry, notes) in kyoku.entries.iter().zip(&mut annotations) {
+                if let Some(text) = analyzer.on_entry(kyoku, entry) {
+                    notes.push(Annotation {
+                        analyzer: analyzer.name().to_owned(),
+                        text,
+                    });
+                }
+            }
+        }
+        for (entry, notes) in kyoku.entries.iter_mut().zip(annotations) {
+            entry.annotations.extend(notes);
+        }
+    }
+
+    let mut analyses = Analyses::default();
+    for analyzer in analyzers.iter_mut() {
+        let output = analyzer
+            .finish(kyokus, target_actor)
+            .with_context(|| format!("failed to run analyzer {}", analyzer.name()))?;
+        match output {
+            Some(Output::Data(data)) => {
+                analyses.data.insert(analyzer.name().to_owned(), data);
+            }
+            Some(Output::Section(section)) => analyses.sections.push(section),
+            None => (),
+        }
+    }
+
+    Ok(analyses)
+}
+
+struct HeatmapAnalyzer;
+
+impl Analyzer for HeatmapAnalyzer {
+    fn name(&self) -> &str {
+        "heatmap"
+    }
+
+    fn finish(&mut self, kyokus: &mut [KyokuReview], _target_actor: u8) -> Result<Option<Output>> {
+        Heatmap::new(kyokus)
+            .map(|h| json::to_value(h).map(Output::Data))
+            .transpose()
+            .map_err(Into::into)
+    }
+}
+
+struct RyukyokuAnalyzer;
+
+impl Analyzer for RyukyokuAnalyzer {
+    fn name(&self) -> &str {
+        "ryukyoku_decisions"
+    }
+
+    fn finish(&mut self, kyokus: &mut [KyokuReview], target_actor: u8) -> Result<Option<Output>> {
+        let decisions: Vec<_> = kyokus
+            .iter()
+            .flat_map(|k| ryukyoku::analyze(k, target_actor))
+            .collect();
+        if decisions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Output::Data(json::to_value(decisions)?)))
+    }
+}
+
+struct PostMortemAnalyzer;
+
+impl Analyzer for PostMortemAnalyzer {
+    fn name(&self) -> &str {
+        "post_mortems"
+    }
+
+    fn finish(&mut self, kyokus: &mut [KyokuReview], target_actor: u8) -> Result<Option<Output>> {
+        let post_mortems: Vec<_> = kyokus
+            .iter()
+            .filter_map(|k| post_mortem::analyze(k, target_actor))
+            .collect();
+        if post_mortems.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Output::Data(json::to_value(post_mortems)?)))
+    }
+}
+
+struct ScoreAuditAnalyzer {
+    auditor: Option<ScoreAuditor>,
+    mismatches: Vec<AuditedScore>,
+}
+
+#[derive(Serialize)]
+struct AuditedScore {
+    kyoku: u8,
+    honba: u8,
+    end_status: Event,
+    #[serde(flatten)]
+    mismatch: ScoreMismatch,
+}
+
+impl ScoreAuditAnalyzer {
+    fn new(kuitan: bool) -> Self {
+        Self {
+            auditor: Some(ScoreAuditor::new(kuitan)),
+            mismatches: vec![],
+        }
+    }
+}
+
+impl Analyzer for ScoreAuditAnalyzer {
+    fn name(&self) -> &str {
+        "score_audit"
+    }
+
+    fn on_event(&mut self, kyoku: &KyokuReview, _board: &BoardState, event: &Event) {
+        let auditor = match &mut self.auditor {
+            Some(a) => a,
+            None => return,
+        };
+        match auditor.update(event) {
+            Ok(Some(mismatch)) => self.mismatches.push(AuditedScore {
+                kyoku: kyoku.kyoku,
+                honba: kyoku.honba,
+                end_status: event.clone(),
+                mismatch,
+            }),
+            Ok(None) => (),
+            // the review has replayed the same events already, so this is
+            // not worth failing the report for
+            Err(err) => {
+                log!("WARNING: score audit stopped: {:#}", err);
+                self.auditor = None;
+            }
+        }
+    }
+
+    fn finish(&mut self, _kyokus: &mut [KyokuReview], _target_actor: u8) -> Result<Option<Output>> {
+        if self.mismatches.is_empty() {
+            return Ok(None);
+        }
+        log!(
+            "WARNING: {} hora(s) or ryuukyoku(s) have recorded deltas that differ from the recalculated ones",
+            self.mismatches.len(),
+        );
+        Ok(Some(Output::Data(json::to_value(&self.mismatches)?)))
+    }
+}
+
+/// Notes what akochan's dahai is building toward when it differs from the
+/// actual one, and what the actual one keeps for comparison.
+struct IntentAnalyzer {
+    kuitan: bool,
+    lang: Language,
+    terms: Terms,
+    oya: u8,
+    bakaze: Pai,
+}
+
+impl IntentAnalyzer {
+    fn new(kuitan: bool, lang: Language, terms: Terms) -> Self {
+        Self {
+            kuitan,
+            lang,
+            terms,
+            oya: 0,
+            bakaze: Pai::East,
+        }
+    }
+}
+
+impl Analyzer for IntentAnalyzer {
+    fn name(&self) -> &str {
+        "intent"
+    }
+
+    fn on_event(&mut self, _kyoku: &KyokuReview, _board: &BoardState, event: &Event) {
+        if let Event::StartKyoku { bakaze, oya, .. } = *event {
+            self.bakaze = bakaze;
+            self.oya = oya;
+        }
+    }
+
+    fn on_entry(&mut self, _kyoku: &KyokuReview, entry: &Entry) -> Option<String> {
+        if entry.acceptance == Acceptance::Agree {
+            return None;
+        }
+        let expected = dahai_pai(&entry.expected)?;
+        let is_reach = |action: &[Event]| matches!(action.first(), Some(Event::Reach { .. }));
+        let args = |reach| IntentArgs {
+            state: &entry.state,
+            actor: entry.actor,
+            oya: self.oya,
+            bakaze: self.bakaze,
+            reach,
+            kuitan: self.kuitan,
+        };
+
+        let intent = intent::infer(expected, &args(is_reach(&entry.expected)))?;
+        let played = dahai_pai(&entry.actual)
+            .and_then(|pai| intent::infer(pai, &args(is_reach(&entry.actual))))
+            .and_then(|i| i.average);
+        let mut text = intent.describe(self.lang, self.terms);
+        if let Some(average) = played {
+            let points = average.to_string();
+            text += &i18n::message(self.lang, "intent_actual", &[("points", &points)]);
+        }
+        Some(text)
+    }
+
+    fn finish(&mut self, _kyokus: &mut [KyokuReview], _target_actor: u8) -> Result<Option<Output>> {
+        Ok(None)
+    }
+}
+
+/// An analyzer in another program, from `--analyzer`.
+///
+/// The program reads the review as JSON from stdin, with `target_actor` and
+/// `kyokus`, and writes a `Section` as JSON to stdout. The section may also
+/// have `annotations`, each with the index of the `kyoku`, the index of the
+/// `entry` in it and the `text`.
+pub struct ExternalAnalyzer {
+    name: String,
+    program: PathBuf,
+}
+
+#[derive(Serialize)]
+struct ExternalInput<'a> {
+    target_actor: u8,
+    kyokus: &'a [KyokuReview],
+}
+
+#[derive(Deserialize)]
+struct ExternalOutput {
+    #[serde(flatten)]
+    section: Section,
+    #[serde(default)]
+    annotations: Vec<ExternalAnnotation>,
+}
+
+#[derive(Deserialize)]
+struct ExternalAnnotation {
+    kyoku: usize,
+    entry: usize,
+    text: String,
+}
+
+impl ExternalAnalyzer {
+    pub fn new(program: &Path) -> Self {
+        let name = program.file_stem().map_or_else(
+            || program.display().to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        );
+        Self {
+            name,
+            program: program.to_owned(),
+        }
+    }
+}
+
+impl Analyzer for ExternalAnalyzer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the program, attaching the annotations it returns to `kyokus`.
+    fn finish(&mut self, kyokus: &mut [KyokuReview], target_actor: u8) -> Result<Option<Output>> {
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to spawn analyzer {:?}", self.program))?;
+
+        let input = ExternalInput {
+            target_actor,
+            kyokus: &*kyokus,
+        };
+        {
+            let stdin = child.stdin.take().context("failed to get stdin")?;
+            json::to_writer(stdin, &input).context("failed to write to the analyzer")?;
+        }
+
+        let mut stdout = String::new();
+        child
+            .stdout
+            .take()
+            .context("failed to get stdout")?
+            .read_to_string(&mut stdout)
+            .context("failed to read from the analyzer")?;
+        let status = child.wait()?;
+        ensure!(status.success(), "analyzer exited with {}", status);
+
+        let output: ExternalOutput =
+            json::from_str(&stdout).context("failed to parse the output of the analyzer")?;
+        for a in output.annotations {
+            match kyokus
+                .get_mut(a.kyoku)
+                .and_then(|k| k.entries.get_mut(a.entry))
+            {
+                Some(entry) => entry.annotations.push(Annotation {
+                    analyzer: self.name.clone(),
+                    text: a.text,
+                }),
+                None => log!(
+                    "WARNING: analyzer {} annotated a missing entry {} of kyoku {}",
+                    self.name,
+                    a.entry,
+                    a.kyoku,
+                ),
+            }
+        }
+
+        Ok(Some(Output::Section(output.section)))
+    }
+}
@@ -0,0 +1,78 @@
+use crate::i18n::Language;
+use crate::render::{AnkiCardView, AnkiSide};
+use crate::review::{Acceptance, KyokuReview, Severity};
+use std::io::prelude::*;
+
+use anyhow::{Context, Result};
+
+pub struct AnkiArgs<'a> {
+    pub target_actor: u8,
+    pub use_placement_ev: bool,
+    pub lang: Language,
+    /// Added to the tags of every note, like the log ID.
+    pub tags: &'a [&'a str],
+}
+
+/// Writes the disagreements in `kyokus` as notes of an Anki deck in the
+/// tab-separated text format of File > Import, one note per line with the
+/// front, the back and the tags, and returns the number of notes.
+///
+/// The sides are HTML with inline styles, so they look the same with any note
+/// type.
+pub fn write_deck<W>(kyokus: &[KyokuReview], args: &AnkiArgs, w: &mut W) -> Result<usize>
+where
+    W: Write,
+{
+    writeln!(w, "#separator:tab")?;
+    writeln!(w, "#html:true")?;
+    writeln!(w, "#tags column:3")?;
+
+    let mut count = 0;
+    for kyoku in kyokus {
+        for entry in &kyoku.entries {
+            if entry.acceptance != Acceptance::Disagree {
+                continue;
+            }
+
+            let mut view = AnkiCardView::new(
+                kyoku,
+                entry,
+                args.target_actor,
+                args.use_placement_ev,
+                args.lang,
+            );
+            let front = render_field(&view)?;
+            view.side = AnkiSide::Back;
+            let back = render_field(&view)?;
+
+            let mut tags = vec!["akochan-reviewer"];
+            tags.extend(args.tags);
+            tags.extend(entry.severity.map(|severity| match severity {
+                Severity::Minor => "minor",
+                Severity::Significant => "significant",
+                Severity::Blunder => "blunder",
+            }));
+            writeln!(w, "{}\t{}\t{}", front, back, tags.join(" "))?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Renders one side of a card on a single line, as fields of the text format
+/// can contain neither tabs nor newlines.
+fn render_field(view: &AnkiCardView) -> Result<String> {
+    let mut html = vec![];
+    view.render(&mut html)
+        .context("failed to render Anki card")?;
+    let html = String::from_utf8(html).context("invalid UTF-8 in Anki card")?;
+
+    Ok(html
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\t', " "))
+}
@@ -0,0 +1,206 @@
+use crate::check::Validator;
+use crate::engine::{is_review_timing, Akochan, EngineArgs};
+use crate::log;
+use crate::review::{fill_ev_gaps, DetailedAction};
+use crate::state::State;
+use std::io::prelude::*;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde_json as json;
+
+/// Number of candidates to print for each decision.
+const TOP_N: usize = 3;
+
+pub struct AssistArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    pub tactics_config: &'a Path,
+    pub target_actor: u8,
+    /// Passed to akochan as [`crate::engine::SEED_ENV`].
+    pub seed: Option<u64>,
+    /// Print the candidates as a line of JSON instead of plain text.
+    pub json: bool,
+    pub verbose: bool,
+}
+
+/// Reads live mjai events line by line from `input` and writes akochan's
+/// recommendation to `out` whenever the target actor has a decision to make.
+///
+/// A new akochan process is spawned at every start_game, so `input` may carry
+/// more than one game. Lines that are not mjai events known to convlog, such
+/// as the "hello" of mjai servers, are skipped, and events that cannot happen
+/// in a real game are warned about, as akochan may go wrong after them.
+pub fn run<R, W>(args: &AssistArgs, input: R, out: &mut W) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    let target_actor = args.target_actor;
+    // the running akochan of the current game
+    let mut session: Option<Akochan> = None;
+    let mut state = State::new(target_actor);
+    let mut validator = Validator::new();
+    let (mut kyoku, mut honba, mut junme) = (0, 0, 0);
+    let mut is_reached = false;
+
+    for line in input.lines() {
+        let line = line.context("failed to read input")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: Event = match json::from_str(&line) {
+            Ok(ev) => ev,
+            Err(err) => {
+                log!("WARNING: skipping unknown input {:?}: {}", line, err);
+                continue;
+            }
+        };
+
+        if let Event::StartGame { .. } = event {
+            // finish the previous one first, if any
+            finish(session.take());
+            validator = Validator::new();
+            session = Some(Akochan::spawn(EngineArgs {
+                akochan_exe: args.akochan_exe,
+                akochan_dir: args.akochan_dir,
+                tactics_config: args.tactics_config,
+                akochan_lib: None,
+                server: None,
+                transcript: None,
+                seed: args.seed,
+                target_actor,
+                verbose: args.verbose,
+                max_restarts: 0,
+                timeout: None,
+            })?);
+        }
+        let akochan = match &mut session {
+            Some(a) => a,
+            None => {
+                log!("WARNING: skipping {:?} before start_game", line);
+                continue;
+            }
+        };
+
+        for message in validator.update(&event) {
+            log!(
+                "WARNING: {}: {}",
+                validator.kyoku().unwrap_or("outside of kyokus"),
+                message,
+            );
+        }
+        akochan.send(&event)?;
+
+        if let Err(err) = state.update(&event) {
+            log!("WARNING: failed to update state: {:?}", err);
+        }
+        match event {
+            Event::StartKyoku {
+                bakaze,
+                kyoku: kk,
+                honba: hb,
+                ..
+            } => {
+                kyoku = (bakaze.as_u8() - Pai::East.as_u8()) * 4 + kk - 1;
+                honba = hb;
+                junme = 0;
+                is_reached = false;
+            }
+            Event::Tsumo { actor, .. } if actor == target_actor => junme += 1,
+            Event::Chi { actor, .. } | Event::Pon { actor, .. } if actor == target_actor => {
+                junme += 1
+            }
+            Event::ReachAccepted { actor } if actor == target_actor => is_reached = true,
+            _ => (),
+        }
+
+        if !is_review_timing(&event, target_actor) {
+            if let Event::EndGame = event {
+                finish(session.take());
+            }
+            continue;
+        }
+
+        let line = akochan.recv()?;
+        let mut actions: Vec<DetailedAction> =
+            json::from_str(&line).context("failed to parse JSON output of akochan")?;
+        fill_ev_gaps(&mut actions);
+
+        // nothing to decide, see the same check in `review`
+        if actions.is_empty()
+            || actions.iter().any(|a| a.moves.is_empty())
+            || actions.len() == 1 && (is_reached || actions[0].moves[0] == Event::None)
+        {
+            continue;
+        }
+
+        if args.json {
+            writeln!(out, "{}", line.trim())?;
+        } else {
+            let tehai: String = state.tehai.view().iter().map(|p| p.to_string()).collect();
+            writeln!(
+                out,
+                "kyoku={} honba={} junme={} tehai={}",
+                kyoku, honba, junme, tehai
+            )?;
+            for (i, action) in actions.iter().take(TOP_N).enumerate() {
+                let stat = &action.review;
+                writeln!(
+                    out,
+                    "  {}. {:<24} EV {:>9}  deal-in {:>7}  loss {:>9}",
+                    i + 1,
+                    describe(&action.moves),
+                    stat.pt_exp_total
+                        .map(|v| format!("{:.3}", v))
+                        .unwrap_or_else(|| "N/A".to_owned()),
+                    stat.total_houjuu_hai_prob_now
+                        .map(|v| format!("{:.2}%", v * 100.))
+                        .unwrap_or_else(|| "N/A".to_owned()),
+                    action
+                        .deal_in_loss
+                        .map(|v| format!("{:.3}", v))
+                        .unwrap_or_else(|| "N/A".to_owned()),
+                )?;
+            }
+        }
+        out.flush()?;
+    }
+    finish(session);
+
+    Ok(())
+}
+
+fn finish(session: Option<Akochan>) {
+    if let Some(akochan) = session {
+        if let Err(err) = akochan.finish() {
+            log!("WARNING: akochan did not exit cleanly: {:?}", err);
+        }
+    }
+}
+
+pub fn describe(moves: &[Event]) -> String {
+    moves
+        .iter()
+        .map(|ev| match *ev {
+            Event::None => "pass".to_owned(),
+            Event::Dahai { pai, .. } => format!("discard {}", pai),
+            Event::Reach { .. } => "riichi".to_owned(),
+            Event::Chi { pai, consumed, .. } => {
+                let [a, b] = consumed.as_array();
+                format!("chi {} with {}{}", pai, a, b)
+            }
+            Event::Pon { pai, .. } => format!("pon {}", pai),
+            Event::Daiminkan { pai, .. } => format!("kan {}", pai),
+            Event::Kakan { pai, .. } => format!("kakan {}", pai),
+            Event::Ankan { consumed, .. } => format!("ankan {}", consumed.as_array()[0]),
+            Event::Hora { actor, target, .. } if actor == target => "tsumo".to_owned(),
+            Event::Hora { .. } => "ron".to_owned(),
+            Event::Ryukyoku { .. } => "ryukyoku".to_owned(),
+            ref other => format!("{:?}", other),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
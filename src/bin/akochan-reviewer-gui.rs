@@ -0,0 +1,439 @@
+//! A small desktop GUI of akochan-reviewer, for those who would rather not use
+//! the command line.
+//!
+//! A log file can be dropped on the window or a log URL pasted. It does not
+//! review by itself but runs the `akochan-reviewer` executable next to this
+//! one, which must be shipped along, as package-win.sh does. The reviewer is
+//! given `--stream 127.0.0.1:0` and logs the address it is bound to, from
+//! which the progress is followed, and the report is opened once it is done.
+#![windows_subsystem = "windows"]
+
+use std::env;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use akochan_reviewer::stream::BOUND_LOG_PREFIX;
+use anyhow::{anyhow, Context, Result};
+use eframe::egui;
+use serde::Deserialize;
+use serde_json as json;
+use tempfile::TempDir;
+use tungstenite::Message;
+
+/// Lines of the reviewer's log kept for the window.
+const MAX_LOG_LINES: usize = 200;
+
+const LANGS: &[(&str, &str)] = &[("ja", "Japanese"), ("en", "English"), ("zh", "Chinese")];
+
+fn main() {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([520., 560.])
+            .with_drag_and_drop(true),
+        ..Default::default()
+    };
+    let result = eframe::run_native(
+        "akochan-reviewer",
+        options,
+        Box::new(|_| Ok(Box::new(App::new()))),
+    );
+    if let Err(err) = result {
+        eprintln!("Error: {:?}", err);
+        std::process::exit(1);
+    }
+}
+
+/// What the stream and the log of the reviewer tell about the running review.
+#[derive(Default)]
+struct Shared {
+    progress: f32,
+    kyoku: Option<(u8, u8, u8)>,
+    log: Vec<String>,
+}
+
+impl Shared {
+    fn push_log(&mut self, line: String) {
+        self.log.push(line);
+        if self.log.len() > MAX_LOG_LINES {
+            let excess = self.log.len() - MAX_LOG_LINES;
+            self.log.drain(..excess);
+        }
+    }
+}
+
+/// The one review at a time.
+struct Job {
+    child: Child,
+    shared: Arc<Mutex<Shared>>,
+    /// Holds the report.
+    dir: TempDir,
+}
+
+impl Job {
+    fn report_path(&self) -> PathBuf {
+        self.dir.path().join("report.html")
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    Progress {
+        kyoku: u8,
+        honba: u8,
+        junme: u8,
+        progress: f32,
+    },
+    #[serde(other)]
+    Other,
+}
+
+struct App {
+    /// The `akochan-reviewer` executable in the same directory.
+    reviewer: Result<PathBuf, String>,
+    url: String,
+    log_file: Option<PathBuf>,
+    actor: Option<u8>,
+    akochan_dir: String,
+    lang: &'static str,
+
+    job: Option<Job>,
+    /// `None` while running or before any review.
+    success: Option<bool>,
+    /// The report of the last successful review, kept until the next one.
+    last_job: Option<Job>,
+    error: Option<String>,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            reviewer: reviewer_exe().map_err(|err| format!("{:#}", err)),
+            url: String::new(),
+            log_file: None,
+            actor: None,
+            akochan_dir: String::new(),
+            lang: LANGS[0].0,
+            job: None,
+            success: None,
+            last_job: None,
+            error: None,
+        }
+    }
+
+    fn poll(&mut self) {
+        let status = match &mut self.job {
+            Some(job) => job.child.try_wait(),
+            None => return,
+        };
+        let success = match status {
+            Ok(None) => return,
+            Ok(Some(status)) => status.success(),
+            Err(err) => {
+                self.error = Some(format!("failed to wait for the reviewer: {}", err));
+                false
+            }
+        };
+
+        self.success = Some(success);
+        let job = self.job.take();
+        if success {
+            if let Some(job) = &job {
+                if let Err(err) = opener::open(job.report_path()) {
+                    self.error = Some(format!("failed to open the report: {}", err));
+                }
+            }
+        }
+        self.last_job = job;
+    }
+
+    fn start(&mut self, ctx: &egui::Context) -> Result<()> {
+        let reviewer = self.reviewer.as_ref().map_err(|err| anyhow!("{}", err))?;
+        let dir = tempfile::tempdir().context("failed to create temporary directory")?;
+
+        let mut command = Command::new(reviewer);
+        command
+            .arg("--no-open")
+            .arg("--stream")
+            .arg("127.0.0.1:0")
+            .arg("-o")
+            .arg(dir.path().join("report.html"))
+            .arg("--lang")
+            .arg(self.lang);
+        match (self.url.trim(), &self.log_file) {
+            (url, _) if !url.is_empty() => {
+                command.arg(url);
+            }
+            (_, Some(path)) => {
+                command.arg("-i").arg(path);
+            }
+            _ => return Err(anyhow!("either a log URL or a log file is required")),
+        }
+        if let Some(actor) = self.actor {
+            command.arg("-a").arg(actor.to_string());
+        }
+        let akochan_dir = self.akochan_dir.trim();
+        if !akochan_dir.is_empty() {
+            command.arg("-d").arg(akochan_dir);
+        }
+
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to spawn {:?}", reviewer))?;
+        let stderr = child.stderr.take().context("failed to get stderr")?;
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        {
+            let shared = Arc::clone(&shared);
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if let Some(addr) = bound_stream_addr(&line) {
+                        let shared = Arc::clone(&shared);
+                        let ctx = ctx.clone();
+                        thread::spawn(move || {
+                            if let Err(err) = follow_stream(&addr, &shared, &ctx) {
+                                let line =
+                                    format!("WARNING: failed to follow the progress: {:#}", err);
+                                shared.lock().unwrap().push_log(line);
+                            }
+                        });
+                    }
+                    shared.lock().unwrap().push_log(line);
+                    ctx.request_repaint();
+                }
+            });
+        }
+
+        self.job = Some(Job { child, shared, dir });
+        self.success = None;
+        self.error = None;
+        Ok(())
+    }
+
+    fn form(&mut self, ui: &mut egui::Ui) {
+        ui.label("Tenhou or Mahjong Soul log URL");
+        ui.add(
+            egui::TextEdit::singleline(&mut self.url)
+                .hint_text("https://tenhou.net/0/?log=...&tw=2")
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.add_space(8.);
+        ui.label("or a log file, dropped on this window");
+        let file_label = match &self.log_file {
+            Some(path) => path.display().to_string(),
+            None => "(none)".to_owned(),
+        };
+        ui.horizontal(|ui| {
+            ui.monospace(file_label);
+            if self.log_file.is_some() && ui.small_button("Clear").clicked() {
+                self.log_file = None;
+            }
+        });
+
+        ui.add_space(8.);
+        egui::Grid::new("options").num_columns(2).show(ui, |ui| {
+            ui.label("Seat");
+            let seat = |actor: Option<u8>| match actor {
+                None => "From the URL (&tw=)".to_owned(),
+                Some(actor) => format!(
+                    "{} ({} at the start)",
+                    actor,
+                    ["East", "South", "West", "North"][actor as usize]
+                ),
+            };
+            egui::ComboBox::from_id_salt("actor")
+                .selected_text(seat(self.actor))
+                .show_ui(ui, |ui| {
+                    for actor in [None, Some(0), Some(1), Some(2), Some(3)] {
+                        ui.selectable_value(&mut self.actor, actor, seat(actor));
+                    }
+                });
+            ui.end_row();
+
+            ui.label("akochan directory");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.akochan_dir)
+                    .hint_text("from the config file if empty"),
+            );
+            ui.end_row();
+
+            ui.label("Report language");
+            let lang_name = |lang: &str| {
+                LANGS
+                    .iter()
+                    .find(|(l, _)| *l == lang)
+                    .map_or("", |(_, name)| name)
+            };
+            egui::ComboBox::from_id_salt("lang")
+                .selected_text(lang_name(self.lang))
+                .show_ui(ui, |ui| {
+                    for &(lang, name) in LANGS {
+                        ui.selectable_value(&mut self.lang, lang, name);
+                    }
+                });
+            ui.end_row();
+        });
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll();
+
+        let dropped = ctx.input(|i| i.raw.dropped_files.iter().find_map(|f| f.path.clone()));
+        if let Some(path) = dropped {
+            self.log_file = Some(path);
+            self.url.clear();
+        }
+        let is_hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("akochan-reviewer");
+            ui.add_space(8.);
+            if let Err(err) = &self.reviewer {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                return;
+            }
+            if is_hovering {
+                ui.strong("Drop the log file to review it");
+            }
+
+            let is_running = self.job.is_some();
+            ui.add_enabled_ui(!is_running, |ui| self.form(ui));
+
+            ui.add_space(8.);
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!is_running, egui::Button::new("Review"))
+                    .clicked()
+                {
+                    if let Err(err) = self.start(ctx) {
+                        self.error = Some(format!("{:#}", err));
+                    }
+                }
+                let report = self
+                    .last_job
+                    .as_ref()
+                    .filter(|_| self.success == Some(true))
+                    .map(Job::report_path);
+                if let Some(report) = report {
+                    if ui.button("Open the report").clicked() {
+                        if let Err(err) = opener::open(report) {
+                            self.error = Some(format!("failed to open the report: {}", err));
+                        }
+                    }
+                }
+            });
+
+            if let Some(err) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            match self.success {
+                Some(true) => {
+                    ui.label("Done.");
+                }
+                Some(false) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        "The review failed, see the log below.",
+                    );
+                }
+                None => (),
+            }
+
+            let job = self.job.as_ref().or(self.last_job.as_ref());
+            if let Some(job) = job {
+                let shared = job.shared.lock().unwrap();
+                if self.job.is_some() {
+                    let text = match shared.kyoku {
+                        Some((kyoku, honba, junme)) => {
+                            format!("{}-{} junme {}", kyoku_name(kyoku), honba, junme,)
+                        }
+                        None => "Starting...".to_owned(),
+                    };
+                    ui.add(
+                        egui::ProgressBar::new(shared.progress)
+                            .show_percentage()
+                            .text(text),
+                    );
+                }
+                ui.add_space(8.);
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &shared.log {
+                            ui.monospace(line);
+                        }
+                    });
+            }
+        });
+
+        if self.job.is_some() {
+            // notice the reviewer exiting even without new output
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+    }
+}
+
+/// The address in the line the reviewer logs once its stream is bound, if
+/// `line` is that line.
+fn bound_stream_addr(line: &str) -> Option<String> {
+    let start = line.find(BOUND_LOG_PREFIX)? + BOUND_LOG_PREFIX.len();
+    Some(line[start..].trim().to_owned())
+}
+
+/// Reads the progress from the stream of the reviewer at `addr`, which is
+/// already bound, until it closes.
+fn follow_stream(addr: &str, shared: &Mutex<Shared>, ctx: &egui::Context) -> Result<()> {
+    let stream = TcpStream::connect(addr).context("failed to connect to the stream")?;
+    let (mut socket, _) = tungstenite::client(format!("ws://{}", addr), stream)
+        .context("failed to handshake with the stream")?;
+
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => continue,
+            Err(err) => return Err(err).context("failed to read from the stream"),
+        };
+        if let Ok(StreamEvent::Progress {
+            kyoku,
+            honba,
+            junme,
+            progress,
+        }) = json::from_str(&text)
+        {
+            let mut shared = shared.lock().unwrap();
+            shared.progress = progress / 100.;
+            shared.kyoku = Some((kyoku, honba, junme));
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// "E1", "S2" and so on from the kyoku counted from 0.
+fn kyoku_name(kyoku: u8) -> String {
+    let wind = ["E", "S", "W", "N"].get(kyoku as usize / 4).unwrap_or(&"?");
+    format!("{}{}", wind, kyoku % 4 + 1)
+}
+
+/// The `akochan-reviewer` executable in the same directory.
+fn reviewer_exe() -> Result<PathBuf> {
+    let exe = env::current_exe().context("failed to get the path of this executable")?;
+    let reviewer = exe.with_file_name(format!("akochan-reviewer{}", env::consts::EXE_SUFFIX));
+    if !reviewer.is_file() {
+        return Err(anyhow!("{:?} is not found", reviewer));
+    }
+    Ok(reviewer)
+}
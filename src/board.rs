@@ -0,0 +1,156 @@
+use crate::scoring::TOTAL_TSUMO;
+use crate::shanten::{get_shanten, get_waits, tiles_from_pais};
+use crate::state::State;
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+
+/// The whole table as seen from above: the `State` of every actor and what
+/// they share, updated from mjai events of all actors.
+///
+/// Tehais hidden in the log, as in a mjai log recorded from one seat, are
+/// tracked as unknown pais, see `is_known`.
+#[derive(Debug, Clone)]
+pub struct BoardState {
+    states: [State; 4],
+    is_known: [bool; 4],
+
+    pub bakaze: Pai,
+    /// Counts from 1, as in start_kyoku.
+    pub kyoku: u8,
+    pub oya: u8,
+    pub honba: u8,
+    /// Reach sticks on the table, including the ones of this kyoku, until
+    /// the first winner takes them.
+    pub kyotaku: u8,
+    /// With the reach sticks of this kyoku paid and the deltas of the horas
+    /// and ryuukyoku applied.
+    pub scores: [i32; 4],
+    /// Dora indicators revealed so far, including the ones after kans.
+    pub dora_markers: Vec<Pai>,
+    /// Draws left in the live wall. Rinshan draws count too, as the dead wall
+    /// is refilled from the live wall.
+    pub tiles_left: u8,
+    pub reached: [bool; 4],
+}
+
+impl Default for BoardState {
+    fn default() -> Self {
+        Self {
+            states: [State::new(0), State::new(1), State::new(2), State::new(3)],
+            is_known: [false; 4],
+            bakaze: Pai::East,
+            kyoku: 1,
+            oya: 0,
+            honba: 0,
+            kyotaku: 0,
+            scores: [0; 4],
+            dora_markers: vec![],
+            tiles_left: TOTAL_TSUMO,
+            reached: [false; 4],
+        }
+    }
+}
+
+impl BoardState {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The state of `actor` as if `actor` were the target actor.
+    #[inline]
+    pub fn state(&self, actor: u8) -> &State {
+        &self.states[actor as usize]
+    }
+
+    /// Whether the tehai of `actor` is in the log.
+    #[inline]
+    pub fn is_known(&self, actor: u8) -> bool {
+        self.is_known[actor as usize]
+    }
+
+    pub fn update(&mut self, event: &Event) -> Result<()> {
+        for state in &mut self.states {
+            state
+                .update(event)
+                .context("failed to update state of the board")?;
+        }
+
+        match *event {
+            Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                scores,
+                ref tehais,
+            } => {
+                for (known, tehai) in self.is_known.iter_mut().zip(tehais) {
+                    *known = !tehai.contains(&Pai::Unknown);
+                }
+                self.bakaze = bakaze;
+                self.kyoku = kyoku;
+                self.oya = oya;
+                self.honba = honba;
+                self.kyotaku = kyotaku;
+                self.scores = scores;
+                self.dora_markers = vec![dora_marker];
+                self.tiles_left = TOTAL_TSUMO;
+                self.reached = [false; 4];
+            }
+
+            Event::Tsumo { .. } => self.tiles_left = self.tiles_left.saturating_sub(1),
+
+            Event::Dora { dora_marker } => self.dora_markers.push(dora_marker),
+
+            Event::ReachAccepted { actor } => {
+                self.reached[actor as usize] = true;
+                self.kyotaku += 1;
+                self.scores[actor as usize] -= 1000;
+            }
+
+            Event::Hora { deltas, .. } | Event::Ryukyoku { deltas, .. } => {
+                if let Some(deltas) = deltas {
+                    for (s, d) in self.scores.iter_mut().zip(&deltas) {
+                        *s += d;
+                    }
+                }
+                // only the first winner of a double ron gets the sticks
+                if matches!(event, Event::Hora { .. }) {
+                    self.kyotaku = 0;
+                }
+            }
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Whether `actor` is tenpai for the tenpai payments of a ryuukyoku. A
+    /// reached actor always is, while a hand waiting only on pais it holds
+    /// all four of is not.
+    ///
+    /// Returns `None` if the tehai of `actor` is not known.
+    pub fn is_tenpai(&self, actor: u8) -> Option<bool> {
+        if self.reached[actor as usize] {
+            return Some(true);
+        }
+        if !self.is_known(actor) {
+            return None;
+        }
+
+        let state = self.state(actor);
+        let tiles = tiles_from_pais(state.tehai.view());
+        let fuuro_count = state.fuuro_count(actor);
+        let is_tenpai = get_shanten(&tiles, fuuro_count) == 0
+            && get_waits(&tiles, fuuro_count)
+                .iter()
+                .any(|w| w.as_index().is_some_and(|i| tiles[i] < 4));
+        Some(is_tenpai)
+    }
+}
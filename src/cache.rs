@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Bump this when the way keys are derived changes.
+const CACHE_VERSION: &str = "v1";
+
+/// On-disk cache of akochan's responses, keyed by the hash of everything
+/// akochan has received before giving the response.
+///
+/// Each entry is stored as a file named after its key, containing exactly
+/// the line akochan wrote.
+pub struct EvalCache {
+    dir: PathBuf,
+}
+
+impl EvalCache {
+    pub fn new<P>(dir: P) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create cache dir {:?}", dir))?;
+        Ok(Self { dir })
+    }
+
+    fn path_of(&self, key: &str) -> PathBuf {
+        let mut path = self.dir.clone();
+        path.push(&key[..2]);
+        path.push(key);
+        path
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.path_of(key).is_file()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.path_of(key)).ok()
+    }
+
+    pub fn put(&self, key: &str, line: &str) -> Result<()> {
+        let path = self.path_of(key);
+        let parent = path.parent().unwrap(); // always has one
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create cache dir {:?}", parent))?;
+
+        write_atomic(&path, line.as_bytes()).context("failed to write cache")
+    }
+}
+
+/// Writes `bytes` to a temp file next to `path` first and then renames it to
+/// `path`, so that a killed run never leaves a truncated file there.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).with_context(|| format!("failed to write {:?}", tmp))?;
+    fs::rename(&tmp, path).with_context(|| format!("failed to rename {:?} to {:?}", tmp, path))
+}
+
+/// Incrementally derives cache keys from the engine input.
+///
+/// The key of a response covers the engine config, the actor, the start_game
+/// event and every event from the start of the kyoku, which is all akochan
+/// uses to make a decision.
+#[derive(Clone)]
+pub struct KeyHasher {
+    prefix: Sha256,
+    current: Sha256,
+}
+
+impl KeyHasher {
+    pub fn new(config: &[u8], actor: u8, seed: Option<u64>) -> Self {
+        let mut prefix = Sha256::new();
+        prefix.update(CACHE_VERSION);
+        prefix.update((config.len() as u64).to_le_bytes());
+        prefix.update(config);
+        prefix.update([actor]);
+        // keys without a seed stay the same as before seeds existed
+        if let Some(seed) = seed {
+            prefix.update(b"seed");
+            prefix.update(seed.to_le_bytes());
+        }
+        Self {
+            current: prefix.clone(),
+            prefix,
+        }
+    }
+
+    /// Feed a line of the engine input that is shared by all kyokus, i.e.
+    /// start_game.
+    pub fn update_prefix(&mut self, line: &str) {
+        self.prefix.update(line);
+        self.prefix.update("\n");
+        self.current = self.prefix.clone();
+    }
+
+    /// Start a new kyoku, forgetting the events of the previous one.
+    pub fn reset(&mut self) {
+        self.current = self.prefix.clone();
+    }
+
+    pub fn update(&mut self, line: &str) {
+        self.current.update(line);
+        self.current.update("\n");
+    }
+
+    pub fn key(&self) -> String {
+        self.current
+            .clone()
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
@@ -0,0 +1,953 @@
+use crate::position::kyoku_label;
+use crate::score_audit::ScoreAuditor;
+use crate::shanten::{get_shanten, get_waits, tiles_from_pais};
+use crate::state::State;
+use std::io::prelude::*;
+
+use anyhow::Result;
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde::Serialize;
+use serde_json as json;
+
+/// Draws from the live wall, that is 136 pais minus the dead wall of 14 and
+/// the haipai of 52. Every kan moves one pai of it to the dead wall, so
+/// rinshan draws stay within the limit too.
+const MAX_TSUMOS: u8 = 70;
+
+/// Draws that must be left in the live wall to reach.
+const MIN_TSUMOS_LEFT_TO_REACH: u8 = 4;
+
+/// Result of `check`.
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    /// The entry of a zip archive the log is from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub kyokus: usize,
+    pub events: usize,
+    pub issues: Vec<Issue>,
+}
+
+/// An inconsistency found at one event.
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    /// Counts from 0.
+    pub event_index: usize,
+    /// Line of the event in a mjai log, counting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Like "E3 honba 1", or `None` outside of any kyoku.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kyoku: Option<String>,
+    pub event: Event,
+    pub message: String,
+}
+
+/// Replays `events` and reports every event that cannot happen in a real
+/// game: pais that run out or appear from nowhere, calls and discards out of
+/// turn, kuikae, reaches without tenpai, scores that do not add up and deltas
+/// of a hora or ryuukyoku that differ from the recalculated ones. `lines`
+/// gives the line of each event when the events come from a mjai log.
+///
+/// After a pai goes missing from a tehai, the rest of the kyoku is skipped,
+/// as everything after it would be reported as well.
+pub fn check(events: &[Event], lines: Option<&[usize]>) -> CheckResult {
+    let mut validator = Validator::new();
+    let mut issues = vec![];
+    let mut kyokus = 0;
+
+    for (event_index, event) in events.iter().enumerate() {
+        if matches!(event, Event::StartKyoku { .. }) {
+            kyokus += 1;
+        }
+        let messages = validator.update(event);
+        let kyoku = validator.kyoku().map(str::to_owned);
+        issues.extend(messages.into_iter().map(|message| Issue {
+            event_index,
+            line: lines.map(|l| l[event_index]),
+            kyoku: kyoku.clone(),
+            event: event.clone(),
+            message,
+        }));
+    }
+    if validator.is_in_kyoku() {
+        issues.push(Issue {
+            event_index: events.len().saturating_sub(1),
+            line: lines.and_then(|l| l.last().copied()),
+            kyoku: validator.kyoku().map(str::to_owned),
+            event: events.last().cloned().unwrap_or(Event::None),
+            message: "the log ends in the middle of a kyoku".to_owned(),
+        });
+    }
+
+    CheckResult {
+        name: None,
+        kyokus,
+        events: events.len(),
+        issues,
+    }
+}
+
+/// Validates mjai events one at a time as `check` does, for events that come
+/// as the game goes, like those of a live game.
+pub struct Validator {
+    checker: Checker,
+    auditor: ScoreAuditor,
+    event_index: usize,
+    /// The last event is an end_kyoku, after which the kyoku is still the one
+    /// of `kyoku` until the next event.
+    has_ended_kyoku: bool,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self {
+            checker: Checker::default(),
+            auditor: ScoreAuditor::new(true),
+            event_index: 0,
+            has_ended_kyoku: false,
+        }
+    }
+}
+
+impl Validator {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with the next event, returns what is wrong with it, if
+    /// anything.
+    pub fn update(&mut self, event: &Event) -> Vec<String> {
+        if self.has_ended_kyoku {
+            self.checker.kyoku = None;
+        }
+
+        let mut messages = self.checker.step(self.event_index, event);
+        // a tehai that does not add up has been reported by the checker
+        if let Ok(Some(m)) = self.auditor.update(event) {
+            messages.push(format!(
+                "the deltas {:?} are recorded, but {:?} is expected from the events",
+                m.recorded, m.expected,
+            ));
+        }
+
+        self.has_ended_kyoku = matches!(event, Event::EndKyoku);
+        self.event_index += 1;
+        messages
+    }
+
+    /// The kyoku of the last event, like "E3 honba 1".
+    pub fn kyoku(&self) -> Option<&str> {
+        self.checker.kyoku.as_ref().map(|k| k.label.as_str())
+    }
+
+    /// Whether the events so far end in the middle of a kyoku.
+    pub fn is_in_kyoku(&self) -> bool {
+        self.checker.kyoku.is_some() && !self.has_ended_kyoku
+    }
+}
+
+pub fn write_text<W: Write>(result: &CheckResult, out: &mut W) -> Result<()> {
+    if let Some(name) = &result.name {
+        writeln!(out, "{}:", name)?;
+    }
+    for issue in &result.issues {
+        let kyoku = issue.kyoku.as_deref().unwrap_or("outside of kyokus");
+        match issue.line {
+            Some(line) => write!(out, "{}, line {}: ", kyoku, line)?,
+            None => write!(out, "{}, event {}: ", kyoku, issue.event_index)?,
+        }
+        writeln!(out, "{}", json::to_string(&issue.event)?)?;
+        writeln!(out, "  {}", issue.message)?;
+    }
+
+    if result.issues.is_empty() {
+        writeln!(
+            out,
+            "{} kyokus and {} events checked, no inconsistency found",
+            result.kyokus, result.events,
+        )?;
+    } else {
+        writeln!(
+            out,
+            "{} kyokus and {} events checked, {} inconsistencies found",
+            result.kyokus,
+            result.events,
+            result.issues.len(),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Checker {
+    aka_flag: bool,
+    has_started: bool,
+    has_ended: bool,
+    kyoku: Option<Kyoku>,
+    /// Scores and kyotaku the next kyoku must start with.
+    next_start: Option<([i32; 4], u8)>,
+    /// Replayed from every seat, as a review would.
+    states: Vec<State>,
+}
+
+struct Kyoku {
+    label: String,
+    scores: [i32; 4],
+    kyotaku: u8,
+    tehais: [Vec<Pai>; 4],
+    /// Number of melds of each actor, ankans included.
+    melds: [u8; 4],
+    /// Kinds of the pons of each actor, which may become kakans.
+    pons: [Vec<Pai>; 4],
+    is_menzen: [bool; 4],
+    reached: [bool; 4],
+    reach_declared: Option<u8>,
+    /// The actor who has just called and the kinds of pai it cannot discard
+    /// right after.
+    kuikae: Option<(u8, Vec<Pai>)>,
+    /// The pai drawn by the actor to move, for tsumogiri.
+    drawn: Option<Pai>,
+    last_dahai: Option<(u8, Pai)>,
+    /// The last event other than Dora.
+    last: Event,
+    tsumos: u8,
+    kans: u8,
+    kan_doras: u8,
+    /// Count of each kind of pai seen so far, and of each aka.
+    seen: [u8; 34],
+    seen_aka: [u8; 3],
+    has_ura_markers: bool,
+    deltas: Option<[i32; 4]>,
+    has_hora: bool,
+    /// Set after a pai goes missing, to skip the rest of the kyoku.
+    is_broken: bool,
+}
+
+impl Checker {
+    fn step(&mut self, event_index: usize, event: &Event) -> Vec<String> {
+        let mut messages = vec![];
+
+        match *event {
+            Event::StartGame { aka_flag, .. } => {
+                if self.has_started || event_index != 0 {
+                    messages.push("start_game is not the first event".to_owned());
+                }
+                self.has_started = true;
+                self.aka_flag = aka_flag;
+            }
+
+            Event::EndGame => {
+                if self.kyoku.is_some() {
+                    messages.push("end_game in the middle of a kyoku".to_owned());
+                }
+                self.has_ended = true;
+            }
+
+            Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                scores,
+                tehais,
+            } => {
+                if !self.has_started {
+                    messages.push("start_kyoku before start_game".to_owned());
+                    self.has_started = true;
+                }
+                if self.has_ended {
+                    messages.push("start_kyoku after end_game".to_owned());
+                }
+                if self.kyoku.is_some() {
+                    messages.push("start_kyoku before the end_kyoku of the last kyoku".to_owned());
+                }
+                if oya != (kyoku + 3) % 4 {
+                    messages.push(format!("oya {} cannot deal kyoku {}", oya, kyoku));
+                }
+                if let Some((expected_scores, expected_kyotaku)) = self.next_start.take() {
+                    if scores != expected_scores {
+                        messages.push(format!(
+                            "the scores are {:?}, but the last kyoku ended with {:?}",
+                            scores, expected_scores,
+                        ));
+                    }
+                    if kyotaku != expected_kyotaku {
+                        messages.push(format!(
+                            "there are {} kyotaku, but the last kyoku left {}",
+                            kyotaku, expected_kyotaku,
+                        ));
+                    }
+                }
+
+                let bakaze_idx = match bakaze {
+                    Pai::East => 0,
+                    Pai::South => 1,
+                    Pai::West => 2,
+                    _ => 3,
+                };
+                let mut state = Kyoku {
+                    label: format!(
+                        "{} honba {}",
+                        kyoku_label(bakaze_idx * 4 + (kyoku + 3) % 4),
+                        honba,
+                    ),
+                    scores,
+                    kyotaku,
+                    tehais: Default::default(),
+                    melds: [0; 4],
+                    pons: Default::default(),
+                    is_menzen: [true; 4],
+                    reached: [false; 4],
+                    reach_declared: None,
+                    kuikae: None,
+                    drawn: None,
+                    last_dahai: None,
+                    last: event.clone(),
+                    tsumos: 0,
+                    kans: 0,
+                    kan_doras: 0,
+                    seen: [0; 34],
+                    seen_aka: [0; 3],
+                    has_ura_markers: false,
+                    deltas: None,
+                    has_hora: false,
+                    is_broken: false,
+                };
+                for (tehai, haipai) in state.tehais.iter_mut().zip(&tehais) {
+                    *tehai = haipai.to_vec();
+                }
+                for &pai in tehais.iter().flatten().chain([dora_marker].iter()) {
+                    state.see(pai, self.aka_flag, &mut messages);
+                }
+                self.kyoku = Some(state);
+                self.states = (0..4).map(State::new).collect();
+            }
+
+            _ => match &mut self.kyoku {
+                Some(kyoku) if !kyoku.is_broken => {
+                    kyoku.step(event, self.aka_flag, &mut messages);
+                    if let Event::EndKyoku = *event {
+                        self.next_start = kyoku.next_start();
+                    }
+                }
+                Some(_) => return messages,
+                None => {
+                    messages.push(format!("{} outside of any kyoku", event_type(event),));
+                    return messages;
+                }
+            },
+        }
+
+        // The review replays the log from the seat of the target actor, so
+        // anything it rejects would fail a review from that seat.
+        if messages.is_empty() {
+            for (actor, state) in self.states.iter_mut().enumerate() {
+                if let Err(err) = state.update(event) {
+                    messages.push(format!(
+                        "replaying from the seat of actor {} fails: {:#}",
+                        actor, err,
+                    ));
+                    break;
+                }
+            }
+            if !messages.is_empty() {
+                if let Some(kyoku) = &mut self.kyoku {
+                    kyoku.break_off(&mut messages);
+                }
+            }
+        }
+
+        messages
+    }
+}
+
+impl Kyoku {
+    fn step(&mut self, event: &Event, aka_flag: bool, messages: &mut Vec<String>) {
+        if !matches!(event, Event::Dora { .. }) && !self.may_follow(event) {
+            messages.push(format!(
+                "{} cannot come right after {}",
+                event_type(event),
+                json::to_string(&self.last).unwrap_or_default(),
+            ));
+        }
+
+        match *event {
+            Event::Tsumo { actor, pai } => {
+                self.tsumos += 1;
+                if self.tsumos > MAX_TSUMOS {
+                    messages.push(format!("more than {} draws in a kyoku", MAX_TSUMOS));
+                }
+                self.see(pai, aka_flag, messages);
+                self.tehais[actor as usize].push(pai);
+                self.drawn = Some(pai);
+            }
+
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                // others' draws may be hidden in a mjai log
+                if tsumogiri && self.drawn != Some(pai) && self.drawn != Some(Pai::Unknown) {
+                    messages.push(match self.drawn {
+                        Some(drawn) => format!(
+                            "{} is marked tsumogiri, but the pai drawn is {}",
+                            pai, drawn,
+                        ),
+                        None => format!("{} is marked tsumogiri without a draw", pai),
+                    });
+                }
+                if self.reached[actor as usize] && !tsumogiri {
+                    messages.push(format!(
+                        "actor {} discards {} from the tehai after reach",
+                        actor, pai,
+                    ));
+                }
+                if let Some((a, pais)) = self.kuikae.take() {
+                    if a == actor && pais.contains(&pai.deaka()) {
+                        messages.push(format!(
+                            "actor {} discards {} right after the call, which is kuikae",
+                            actor, pai,
+                        ));
+                    }
+                }
+                self.take(actor, &[pai], messages);
+                if self.reach_declared == Some(actor) && !self.is_broken {
+                    self.check_reach_tenpai(actor, messages);
+                }
+                self.last_dahai = Some((actor, pai));
+                self.drawn = None;
+            }
+
+            Event::Reach { actor } => {
+                if self.reached[actor as usize] {
+                    messages.push(format!("actor {} has already reached", actor));
+                }
+                if !self.is_menzen[actor as usize] {
+                    messages.push(format!("actor {} reaches with an open hand", actor));
+                }
+                if self.scores[actor as usize] < 1000 {
+                    messages.push(format!(
+                        "actor {} reaches with only {} points",
+                        actor, self.scores[actor as usize],
+                    ));
+                }
+                let tsumos_left = MAX_TSUMOS.saturating_sub(self.tsumos);
+                if tsumos_left < MIN_TSUMOS_LEFT_TO_REACH {
+                    messages.push(format!(
+                        "actor {} reaches with only {} draws left in the wall",
+                        actor, tsumos_left,
+                    ));
+                }
+                self.reach_declared = Some(actor);
+            }
+
+            Event::ReachAccepted { actor } => {
+                if self.reach_declared.take() != Some(actor) {
+                    messages.push(format!("actor {} has not declared reach", actor));
+                }
+                self.reached[actor as usize] = true;
+            }
+
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if target != (actor + 3) % 4 {
+                    messages.push(format!(
+                        "actor {} cannot chi from actor {}, who is not the kamicha",
+                        actor, target,
+                    ));
+                }
+                let [a, b] = consumed.as_array();
+                if is_shuntsu([pai, a, b]) {
+                    self.kuikae = Some((actor, chi_kuikae(pai, [a, b])));
+                } else {
+                    messages.push(format!("{}, {} and {} are not a shuntsu", pai, a, b));
+                }
+                self.call(actor, target, pai, &consumed.as_array(), messages);
+            }
+
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if !is_same_kind(pai, &consumed.as_array()) {
+                    messages.push(format!("{:?} cannot pon {}", consumed, pai));
+                }
+                self.pons[actor as usize].push(pai.deaka());
+                self.kuikae = Some((actor, vec![pai.deaka()]));
+                self.call(actor, target, pai, &consumed.as_array(), messages);
+            }
+
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if !is_same_kind(pai, &consumed.as_array()) {
+                    messages.push(format!("{:?} cannot kan {}", consumed, pai));
+                }
+                self.add_kan(messages);
+                self.call(actor, target, pai, &consumed.as_array(), messages);
+            }
+
+            Event::Ankan { actor, consumed } => {
+                let pais = consumed.as_array();
+                if !is_same_kind(pais[0], &pais) {
+                    messages.push(format!("{:?} are not the same kind", consumed));
+                } else if self.reached[actor as usize] {
+                    self.check_ankan_after_reach(actor, pais[0], messages);
+                }
+                self.add_kan(messages);
+                self.melds[actor as usize] += 1;
+                self.take(actor, &pais, messages);
+            }
+
+            Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                if !is_same_kind(pai, &consumed.as_array()) {
+                    messages.push(format!("{} cannot be added to {:?}", pai, consumed));
+                }
+                let pons = &mut self.pons[actor as usize];
+                match pons.iter().position(|&p| p == pai.deaka()) {
+                    Some(idx) => {
+                        pons.remove(idx);
+                    }
+                    None => messages.push(format!(
+                        "actor {} has no pon of {} to add to",
+                        actor,
+                        pai.deaka(),
+                    )),
+                }
+                self.add_kan(messages);
+                self.take(actor, &[pai], messages);
+            }
+
+            Event::Dora { dora_marker } => {
+                if self.kan_doras >= self.kans {
+                    messages.push("a new dora indicator without a kan".to_owned());
+                }
+                self.kan_doras += 1;
+                self.see(dora_marker, aka_flag, messages);
+            }
+
+            Event::Hora {
+                actor,
+                deltas,
+                ref ura_markers,
+                ..
+            } => {
+                if let Some(deltas) = deltas {
+                    if deltas[actor as usize] <= 0 {
+                        messages.push(format!(
+                            "actor {} wins, but gets {} points from the deltas {:?}",
+                            actor, deltas[actor as usize], deltas,
+                        ));
+                    }
+                }
+                if let Some(ura_markers) = ura_markers {
+                    let dora_count = self.kan_doras as usize + 1;
+                    if ura_markers.len() != dora_count {
+                        messages.push(format!(
+                            "{} ura dora indicators for {} dora indicators",
+                            ura_markers.len(),
+                            dora_count,
+                        ));
+                    }
+                    // every hora of a double ron carries the same ones
+                    if !self.has_ura_markers {
+                        for &pai in ura_markers {
+                            self.see(pai, aka_flag, messages);
+                        }
+                        self.has_ura_markers = true;
+                    }
+                }
+                self.add_deltas(deltas);
+                self.has_hora = true;
+            }
+
+            Event::Ryukyoku { deltas, .. } => self.add_deltas(deltas),
+
+            Event::EndKyoku => {
+                if let Some(deltas) = self.deltas {
+                    let sum: i32 = deltas.iter().sum();
+                    let pot = self.pot();
+                    if sum != pot {
+                        messages.push(format!(
+                            "the deltas {:?} add up to {}, but {} is expected from the kyotaku \
+                            and reaches",
+                            deltas, sum, pot,
+                        ));
+                    }
+                }
+            }
+
+            _ => (),
+        }
+
+        self.last_event(event);
+    }
+
+    /// Whether `event` may come right after the last event other than Dora.
+    fn may_follow(&self, event: &Event) -> bool {
+        let last = &self.last;
+        match *event {
+            Event::Tsumo { actor, .. } => match *last {
+                Event::StartKyoku { oya, .. } => actor == oya,
+                Event::Dahai { actor: a, .. } | Event::ReachAccepted { actor: a } => {
+                    actor == (a + 1) % 4
+                }
+                // rinshan
+                Event::Daiminkan { actor: a, .. }
+                | Event::Ankan { actor: a, .. }
+                | Event::Kakan { actor: a, .. } => actor == a,
+                _ => false,
+            },
+
+            Event::Dahai { actor, .. } => matches!(
+                *last,
+                Event::Tsumo { actor: a, .. }
+                | Event::Reach { actor: a }
+                | Event::Chi { actor: a, .. }
+                | Event::Pon { actor: a, .. } if a == actor
+            ),
+
+            Event::Reach { actor } | Event::Ankan { actor, .. } | Event::Kakan { actor, .. } => {
+                matches!(*last, Event::Tsumo { actor: a, .. } if a == actor)
+            }
+
+            Event::ReachAccepted { actor } => {
+                matches!(*last, Event::Dahai { actor: a, .. } if a == actor)
+            }
+
+            Event::Chi {
+                actor, target, pai, ..
+            }
+            | Event::Pon {
+                actor, target, pai, ..
+            }
+            | Event::Daiminkan {
+                actor, target, pai, ..
+            } => {
+                actor != target
+                    && matches!(
+                        *last,
+                        Event::Dahai { actor: a, .. } | Event::ReachAccepted { actor: a }
+                        if a == target
+                    )
+                    && self.last_dahai == Some((target, pai))
+            }
+
+            Event::Hora { actor, target, .. } if actor == target => {
+                matches!(*last, Event::Tsumo { actor: a, .. } if a == actor)
+            }
+            Event::Hora { actor, target, .. } => match *last {
+                // chankan
+                Event::Dahai { actor: a, .. }
+                | Event::Kakan { actor: a, .. }
+                | Event::Ankan { actor: a, .. } => a == target,
+                // double ron
+                Event::Hora {
+                    actor: a,
+                    target: t,
+                    ..
+                } => a != t && t == target && a != actor,
+                _ => false,
+            },
+
+            Event::Ryukyoku { .. } => !matches!(
+                *last,
+                Event::StartKyoku { .. }
+                    | Event::Reach { .. }
+                    | Event::Chi { .. }
+                    | Event::Pon { .. }
+                    | Event::Hora { .. }
+                    | Event::Ryukyoku { .. }
+            ),
+
+            Event::EndKyoku => matches!(*last, Event::Hora { .. } | Event::Ryukyoku { .. }),
+
+            _ => false,
+        }
+    }
+
+    fn last_event(&mut self, event: &Event) {
+        if !matches!(event, Event::Dora { .. }) {
+            self.last = event.clone();
+        }
+    }
+
+    /// Moves `pai` from the river of `target` into a meld of `actor` along
+    /// with `consumed` from the tehai.
+    fn call(
+        &mut self,
+        actor: u8,
+        target: u8,
+        pai: Pai,
+        consumed: &[Pai],
+        messages: &mut Vec<String>,
+    ) {
+        if self.reached[actor as usize] {
+            messages.push(format!("actor {} calls after reach", actor));
+        }
+        if self.last_dahai != Some((target, pai)) {
+            messages.push(format!(
+                "{} is not the last discard of actor {}",
+                pai, target,
+            ));
+        }
+        self.is_menzen[actor as usize] = false;
+        self.melds[actor as usize] += 1;
+        self.take(actor, consumed, messages);
+    }
+
+    /// Removes `pais` from the tehai of `actor`, or breaks off the kyoku if
+    /// any of them is not there.
+    fn take(&mut self, actor: u8, pais: &[Pai], messages: &mut Vec<String>) {
+        let tehai = &mut self.tehais[actor as usize];
+        for &pai in pais {
+            // others' tehais may be hidden in a mjai log
+            let found = tehai
+                .iter()
+                .position(|&p| p == pai)
+                .or_else(|| tehai.iter().position(|&p| p == Pai::Unknown));
+            match found {
+                Some(idx) => {
+                    tehai.remove(idx);
+                }
+                None => {
+                    messages.push(format!(
+                        "{} is not in the tehai of actor {}: {}",
+                        pai,
+                        actor,
+                        format_tehai(tehai),
+                    ));
+                    self.break_off(messages);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Checks the tehai of `actor` right after the discard that declares
+    /// reach. A hidden tehai is taken as tenpai.
+    fn check_reach_tenpai(&self, actor: u8, messages: &mut Vec<String>) {
+        let tehai = &self.tehais[actor as usize];
+        if tehai.contains(&Pai::Unknown) {
+            return;
+        }
+        let tiles = tiles_from_pais(tehai);
+        if get_shanten(&tiles, self.melds[actor as usize]) > 0 {
+            messages.push(format!(
+                "actor {} reaches without tenpai: {}",
+                actor,
+                format_tehai(tehai),
+            ));
+        }
+    }
+
+    /// After reach, an ankan may only be of the pai just drawn, and must not
+    /// change the waits.
+    fn check_ankan_after_reach(&self, actor: u8, pai: Pai, messages: &mut Vec<String>) {
+        let tehai = &self.tehais[actor as usize];
+        if tehai.contains(&Pai::Unknown) {
+            return;
+        }
+        if self.drawn.map(Pai::deaka) != Some(pai.deaka()) {
+            messages.push(format!(
+                "actor {} ankans {} after reach, which is not the pai drawn",
+                actor,
+                pai.deaka(),
+            ));
+            return;
+        }
+
+        let idx = match pai.as_index() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let mut before = tiles_from_pais(tehai);
+        // a missing pai is reported by `take`
+        if before[idx] < 4 {
+            return;
+        }
+        before[idx] -= 1;
+        let mut after = before;
+        after[idx] = 0;
+        let melds = self.melds[actor as usize];
+        if get_waits(&before, melds) != get_waits(&after, melds + 1) {
+            messages.push(format!(
+                "actor {} ankans {} after reach, which changes the waits",
+                actor,
+                pai.deaka(),
+            ));
+        }
+    }
+
+    fn add_kan(&mut self, messages: &mut Vec<String>) {
+        self.kans += 1;
+        if self.kans == 5 {
+            messages.push("a fifth kan in a kyoku".to_owned());
+        }
+    }
+
+    fn break_off(&mut self, messages: &mut Vec<String>) {
+        if !self.is_broken {
+            messages.push("the rest of the kyoku is not checked".to_owned());
+            self.is_broken = true;
+        }
+    }
+
+    /// Counts a pai that comes out of the wall, which holds 4 of each kind
+    /// and one aka of each suit when there are aka.
+    fn see(&mut self, pai: Pai, aka_flag: bool, messages: &mut Vec<String>) {
+        let idx = match pai.as_index() {
+            Some(idx) => idx,
+            None => return,
+        };
+        self.seen[idx] += 1;
+        if self.seen[idx] == 5 {
+            messages.push(format!("a fifth {} comes out of the wall", pai.deaka()));
+        }
+        if pai.is_aka() {
+            if !aka_flag {
+                messages.push(format!("{} in a game without aka", pai));
+            }
+            let aka = &mut self.seen_aka[idx / 9];
+            *aka += 1;
+            if *aka == 2 {
+                messages.push(format!("a second {} comes out of the wall", pai));
+            }
+        }
+    }
+
+    fn add_deltas(&mut self, deltas: Option<[i32; 4]>) {
+        if let Some(deltas) = deltas {
+            let sum = self.deltas.get_or_insert([0; 4]);
+            for (s, d) in sum.iter_mut().zip(&deltas) {
+                *s += d;
+            }
+        }
+    }
+
+    /// Points from the kyotaku and the reaches of this kyoku, which the
+    /// deltas hand out only on a hora.
+    fn pot(&self) -> i32 {
+        if self.has_hora {
+            (self.kyotaku as i32 + self.reach_count()) * 1000
+        } else {
+            0
+        }
+    }
+
+    fn reach_count(&self) -> i32 {
+        self.reached.iter().filter(|&&r| r).count() as i32
+    }
+
+    /// Scores and kyotaku the next kyoku must start with, if the deltas are
+    /// known.
+    fn next_start(&self) -> Option<([i32; 4], u8)> {
+        let deltas = self.deltas?;
+        let kyotaku = if self.has_hora {
+            0
+        } else {
+            self.kyotaku + self.reach_count() as u8
+        };
+
+        let mut scores = self.scores;
+        for ((score, delta), &reached) in scores.iter_mut().zip(&deltas).zip(&self.reached) {
+            *score += delta - reached as i32 * 1000;
+        }
+        Some((scores, kyotaku))
+    }
+}
+
+/// The mjai type of `event`, like "dahai".
+pub fn event_type(event: &Event) -> &'static str {
+    match event {
+        Event::None => "none",
+        Event::StartGame { .. } => "start_game",
+        Event::StartKyoku { .. } => "start_kyoku",
+        Event::Tsumo { .. } => "tsumo",
+        Event::Dahai { .. } => "dahai",
+        Event::Chi { .. } => "chi",
+        Event::Pon { .. } => "pon",
+        Event::Daiminkan { .. } => "daiminkan",
+        Event::Kakan { .. } => "kakan",
+        Event::Ankan { .. } => "ankan",
+        Event::Dora { .. } => "dora",
+        Event::Reach { .. } => "reach",
+        Event::ReachAccepted { .. } => "reach_accepted",
+        Event::Hora { .. } => "hora",
+        Event::Ryukyoku { .. } => "ryukyoku",
+        Event::EndKyoku => "end_kyoku",
+        Event::EndGame => "end_game",
+    }
+}
+
+fn is_same_kind(pai: Pai, others: &[Pai]) -> bool {
+    others.iter().all(|p| p.deaka() == pai.deaka())
+}
+
+fn is_shuntsu(pais: [Pai; 3]) -> bool {
+    let mut indices = [0; 3];
+    for (idx, pai) in indices.iter_mut().zip(&pais) {
+        match pai.as_index() {
+            Some(i) if i < 27 => *idx = i,
+            _ => return false,
+        }
+    }
+    indices.sort_unstable();
+    indices[0] / 9 == indices[2] / 9 && indices[1] == indices[0] + 1 && indices[2] == indices[1] + 1
+}
+
+/// Kinds of pai that cannot be discarded right after a chi of `pai` with
+/// `consumed`: the called one, and the one at the other end of a ryanmen,
+/// like 1m after a chi of 4m with 2m and 3m.
+fn chi_kuikae(pai: Pai, consumed: [Pai; 2]) -> Vec<Pai> {
+    let mut pais = vec![pai.deaka()];
+    let (called, a, b) = match (
+        pai.as_index(),
+        consumed[0].as_index(),
+        consumed[1].as_index(),
+    ) {
+        (Some(called), Some(a), Some(b)) => (called, a, b),
+        _ => return pais,
+    };
+    let (low, high) = (a.min(b), a.max(b));
+    let suji = if called < low && high % 9 < 8 {
+        Pai::from_index(high + 1)
+    } else if called > high && low % 9 > 0 {
+        Pai::from_index(low - 1)
+    } else {
+        None
+    };
+    pais.extend(suji);
+    pais
+}
+
+/// Sorted, like "1m 2m 3m".
+fn format_tehai(tehai: &[Pai]) -> String {
+    let mut sorted = tehai.to_vec();
+    sorted.sort_by_key(|p| p.as_ord());
+    sorted
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,108 @@
+use crate::i18n::Language;
+use crate::render;
+use crate::review::{self, Category, Review, SeverityCounts};
+use std::cmp::Ordering;
+use std::io::prelude::*;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// The four players of one game side by side, each reviewed from their own
+/// seat.
+#[derive(Debug, Serialize)]
+pub struct Comparison<'a> {
+    pub log_id: Option<&'a str>,
+    /// From the highest agree rate.
+    pub players: Vec<PlayerStat<'a>>,
+    /// Categories anyone has a mistake in, in the order of
+    /// `PlayerStat::category_counts`.
+    pub categories: Vec<Category>,
+    pub lang: Language,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerStat<'a> {
+    pub actor: u8,
+    pub name: &'a str,
+    pub reviewed: usize,
+    pub agreed: usize,
+    pub agree_rate: f64,
+    pub ev_loss: f64,
+    /// Counts from 1, the lowest EV loss being the 1st.
+    pub ev_loss_rank: usize,
+    pub rating: Option<f64>,
+    pub severity_counts: SeverityCounts,
+    pub category_counts: Vec<usize>,
+}
+
+impl<'a> Comparison<'a> {
+    /// Ranks the players from `reviews` of each seat, in the order of the
+    /// seats. `ev_range` is passed to `review::rating`.
+    pub fn new(
+        log_id: Option<&'a str>,
+        names: &'a [String; 4],
+        reviews: &[Review],
+        ev_range: f64,
+        lang: Language,
+    ) -> Self {
+        let categories: Vec<Category> = {
+            let mut categories: Vec<_> = reviews
+                .iter()
+                .flat_map(|r| r.category_counts.keys().copied())
+                .collect();
+            categories.sort_unstable();
+            categories.dedup();
+            categories
+        };
+
+        let mut players: Vec<_> = reviews
+            .iter()
+            .enumerate()
+            .map(|(actor, r)| {
+                let agreed = r.total_reviewed - r.total_problems - r.total_tolerated;
+                PlayerStat {
+                    actor: actor as u8,
+                    name: &names[actor],
+                    reviewed: r.total_reviewed,
+                    agreed,
+                    agree_rate: if r.total_reviewed > 0 {
+                        agreed as f64 / r.total_reviewed as f64
+                    } else {
+                        0.
+                    },
+                    ev_loss: r.ev_trend.total_loss,
+                    ev_loss_rank: 0,
+                    rating: review::rating(r.ev_trend.total_loss, r.total_reviewed, ev_range),
+                    severity_counts: r.severity_counts,
+                    category_counts: categories
+                        .iter()
+                        .map(|c| r.category_counts.get(c).copied().unwrap_or(0))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let losses: Vec<f64> = players.iter().map(|p| p.ev_loss).collect();
+        for p in &mut players {
+            p.ev_loss_rank = 1 + losses.iter().filter(|&&l| l < p.ev_loss).count();
+        }
+        players.sort_by(|a, b| {
+            b.agree_rate
+                .partial_cmp(&a.agree_rate)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.ev_loss_rank.cmp(&b.ev_loss_rank))
+        });
+
+        Self {
+            log_id,
+            players,
+            categories,
+            lang,
+        }
+    }
+
+    /// Renders the comparison in HTML.
+    pub fn render<W: Write>(&self, w: &mut W) -> Result<()> {
+        render::render_template("comparison.html", self, self.lang, w)
+    }
+}
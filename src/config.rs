@@ -0,0 +1,115 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Persistent defaults read from a TOML file. Every field is optional and is
+/// overridden by the corresponding command line option.
+///
+/// ```toml
+/// akochan-dir = "/opt/akochan"
+/// tactics-config = "tactics.json"
+/// lang = "en"
+/// theme = "colorblind"
+/// yaku-names = "english"
+/// pai-names = "mjai"
+/// out-dir = "reports"
+/// timeout = 60
+/// retries = 5
+/// proxy = "socks5://127.0.0.1:1080"
+/// discord-webhook = "https://discord.com/api/webhooks/..."
+/// log-cache-dir = "/var/cache/akochan-reviewer"
+/// template = "club-templates"
+///
+/// [rules]
+/// kuitan = false
+/// aka = false
+/// game-length = "tonpuusen"
+/// starting-points = 30000
+/// uma = [30, 10, -10, -30]
+/// ```
+///
+/// Relative paths are resolved against the directory of the config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub akochan_dir: Option<PathBuf>,
+    pub tactics_config: Option<PathBuf>,
+    pub lang: Option<String>,
+    pub theme: Option<String>,
+    pub yaku_names: Option<String>,
+    pub pai_names: Option<String>,
+    pub out_dir: Option<PathBuf>,
+    pub timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub proxy: Option<String>,
+    pub discord_webhook: Option<String>,
+    pub log_cache_dir: Option<PathBuf>,
+    pub template: Option<PathBuf>,
+    pub rules: RulesConfig,
+}
+
+/// The `[rules]` table, see [`crate::rules::Rules`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RulesConfig {
+    pub kuitan: Option<bool>,
+    pub aka: Option<bool>,
+    pub game_length: Option<String>,
+    pub starting_points: Option<i32>,
+    pub uma: Option<[i32; 4]>,
+}
+
+impl Config {
+    /// Load the config from `path` if given, otherwise from the default
+    /// location if it exists. A missing default config is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(p) => (p.to_owned(), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        if !required && !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let body = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config file {:?}", path))?;
+        let mut config: Self = toml::from_str(&body)
+            .with_context(|| format!("failed to parse config file {:?}", path))?;
+
+        if let Some(base) = path.parent() {
+            for p in [
+                &mut config.akochan_dir,
+                &mut config.tactics_config,
+                &mut config.out_dir,
+                &mut config.log_cache_dir,
+                &mut config.template,
+            ]
+            .iter_mut()
+            .filter_map(|p| p.as_mut())
+            {
+                if p.is_relative() {
+                    *p = base.join(&*p);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/akochan-reviewer/config.toml`, falling back to
+/// `~/.config/akochan-reviewer/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+    Some(config_home.join("akochan-reviewer").join("config.toml"))
+}
@@ -0,0 +1,68 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{ensure, Context, Result};
+use flate2::read::MultiGzDecoder;
+use zip::ZipArchive;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// A log input after decompression.
+pub enum Input {
+    Log(String),
+    /// Logs in a zip archive of more than one, with the names of their
+    /// entries.
+    Batch(Vec<(String, String)>),
+}
+
+/// Reads the log in `bytes`, decompressing gzip and zstd and unpacking zip
+/// archives, whose entries may be compressed as well. The format is told by
+/// the leading bytes instead of the file extension, so that it works for
+/// stdin too.
+pub fn read_input(bytes: Vec<u8>) -> Result<Input> {
+    if !bytes.starts_with(ZIP_MAGIC) {
+        return decompress(bytes).map(Input::Log);
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).context("failed to open zip archive")?;
+    let mut logs = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).context("failed to read zip archive")?;
+        // resource forks added by macOS
+        if file.is_dir() || file.name().starts_with("__MACOSX/") {
+            continue;
+        }
+
+        let name = file.name().to_owned();
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)
+            .with_context(|| format!("failed to extract {:?} from zip archive", name))?;
+        let body =
+            decompress(buf).with_context(|| format!("failed to read {:?} in zip archive", name))?;
+        logs.push((name, body));
+    }
+    ensure!(!logs.is_empty(), "no file in zip archive");
+
+    if logs.len() == 1 {
+        let (_, body) = logs.pop().unwrap();
+        return Ok(Input::Log(body));
+    }
+    Ok(Input::Batch(logs))
+}
+
+fn decompress(bytes: Vec<u8>) -> Result<String> {
+    let bytes = if bytes.starts_with(GZIP_MAGIC) {
+        let mut buf = vec![];
+        MultiGzDecoder::new(&bytes[..])
+            .read_to_end(&mut buf)
+            .context("failed to decompress gzip")?;
+        buf
+    } else if bytes.starts_with(ZSTD_MAGIC) {
+        zstd::decode_all(&bytes[..]).context("failed to decompress zstd")?
+    } else {
+        bytes
+    };
+
+    String::from_utf8(bytes).context("log is not valid UTF-8")
+}
@@ -0,0 +1,170 @@
+use crate::state::{Fuuro, State};
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+
+use serde::Serialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Number of fuuros from which an actor is considered to be tenpai even
+/// without reach.
+const OBVIOUS_TENPAI_FUURO_COUNT: u8 = 3;
+
+/// Safety of a pai against a specific actor, from the safest to the most
+/// dangerous.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Safety {
+    /// 現物
+    Genbutsu,
+    /// 筋
+    Suji,
+    /// ノーチャンス, every two-sided wait on the pai is impossible.
+    NoChance,
+    /// ワンチャンス, every two-sided wait on the pai needs the last copy of a
+    /// pai.
+    OneChance,
+    /// 無筋
+    Live,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ThreatSafety {
+    pub actor: u8,
+    pub safety: Safety,
+}
+
+/// What the target actor knows about a threat.
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreatState {
+    pub actor: u8,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reach_pai: Option<Pai>,
+    pub fuuros: Vec<Fuuro>,
+    /// Kinds of pais that are genbutsu against the actor.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub genbutsu: Vec<Pai>,
+}
+
+impl ThreatState {
+    pub fn new(state: &State, actor: u8) -> Self {
+        Self {
+            actor,
+            reach_pai: state.reach_pai(actor),
+            fuuros: state.fuuros_of(actor).to_vec(),
+            genbutsu: state.genbutsu(actor),
+        }
+    }
+}
+
+/// Returns the actors other than the target actor that have reached or have
+/// enough fuuros to be obviously tenpai.
+pub fn threats(state: &State, target_actor: u8) -> Vec<u8> {
+    (0..4)
+        .filter(|&actor| {
+            actor != target_actor
+                && (state.reached[actor as usize]
+                    || state.fuuro_count(actor) >= OBVIOUS_TENPAI_FUURO_COUNT)
+        })
+        .collect()
+}
+
+/// Whether each event of `events` belongs to a kyoku where `target_actor`
+/// never decides under threat, in the same shape as the plan of the cache.
+///
+/// These kyokus are not sent to akochan in defense mode. akochan needs every
+/// event of a kyoku and evaluates every decision it is sent, so the
+/// decisions before the first threat of the other kyokus are still sent.
+pub fn plan(events: &[Event], target_actor: u8) -> Result<Vec<bool>> {
+    let mut skipped = vec![false; events.len()];
+    let mut state = State::new(target_actor);
+    let mut kyoku_start = 0;
+    let mut is_threatened = false;
+
+    for (i, event) in events.iter().enumerate() {
+        state.update(event).context("failed to update state")?;
+        match *event {
+            Event::StartKyoku { .. } => {
+                kyoku_start = i;
+                is_threatened = false;
+            }
+            Event::EndKyoku => {
+                skipped[kyoku_start..=i]
+                    .iter_mut()
+                    .for_each(|s| *s = !is_threatened);
+            }
+            Event::Tsumo { actor, .. } if actor == target_actor => {
+                is_threatened |= !threats(&state, target_actor).is_empty();
+            }
+            Event::Dahai { actor, .. } | Event::Kakan { actor, .. } if actor != target_actor => {
+                is_threatened |= !threats(&state, target_actor).is_empty();
+            }
+            _ => (),
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Classifies `pai` against every actor in `threats`.
+pub fn analyze(state: &State, threats: &[u8], pai: Pai) -> Vec<ThreatSafety> {
+    threats
+        .iter()
+        .map(|&actor| ThreatSafety {
+            actor,
+            safety: classify(state, actor, pai),
+        })
+        .collect()
+}
+
+/// Classifies `pai` against `actor` based on the information visible to the
+/// target actor of `state`.
+pub fn classify(state: &State, actor: u8, pai: Pai) -> Safety {
+    if state.is_genbutsu(actor, pai) {
+        return Safety::Genbutsu;
+    }
+
+    let idx = match pai.as_index() {
+        // jihai can only be waited by tanki or shanpon, none of the rules
+        // below apply.
+        Some(idx) if idx < 27 => idx,
+        _ => return Safety::Live,
+    };
+    let num = (idx % 9) as i8 + 1;
+    let pai_at = |offset: i8| -> Option<Pai> {
+        let n = num + offset;
+        if (1..=9).contains(&n) {
+            Pai::from_index((idx as i8 + offset) as usize)
+        } else {
+            None
+        }
+    };
+
+    // suji: both sides of the ryanmen waits on this pai are genbutsu
+    let is_suji_side = |offset: i8| match pai_at(offset) {
+        Some(p) => state.is_genbutsu(actor, p),
+        None => true,
+    };
+    if (num <= 3 || is_suji_side(-3)) && (num >= 7 || is_suji_side(3)) {
+        return Safety::Suji;
+    }
+
+    // kabe: a two-sided wait on this pai is blocked when one of its two pais
+    // has at least `min_visible` copies visible.
+    let is_blocked = |near: i8, far: i8, min_visible: u8| match (pai_at(near), pai_at(far)) {
+        (Some(a), Some(b)) => {
+            state.visible_count(a) >= min_visible || state.visible_count(b) >= min_visible
+        }
+        _ => true,
+    };
+    if is_blocked(-1, -2, 4) && is_blocked(1, 2, 4) {
+        Safety::NoChance
+    } else if is_blocked(-1, -2, 3) && is_blocked(1, 2, 3) {
+        Safety::OneChance
+    } else {
+        Safety::Live
+    }
+}
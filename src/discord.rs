@@ -0,0 +1,130 @@
+use crate::assist::describe;
+use crate::download::Client;
+use crate::metadata::Metadata;
+use crate::position::{format_pais, kyoku_label};
+use crate::review::{self, Entry, KyokuReview};
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use serde_json::{self as json, json};
+
+/// Discord rejects messages longer than this.
+const MAX_CONTENT_LEN: usize = 2000;
+
+/// How many of the worst decisions are listed.
+const TOP_MISTAKES: usize = 3;
+
+/// A compact summary of a review, posted to a Discord channel.
+pub struct Summary<'a> {
+    pub player: &'a str,
+    pub target_actor: u8,
+    pub kyokus: &'a [KyokuReview],
+    pub metadata: &'a Metadata<'a>,
+    /// Where the report can be found, like a URL to the HTML report uploaded
+    /// to a web server.
+    pub report_url: Option<&'a str>,
+}
+
+impl Summary<'_> {
+    /// Renders the summary as a Discord message in markdown.
+    pub fn message(&self) -> String {
+        let meta = self.metadata;
+        let agreed = meta.total_reviewed - meta.total_problems - meta.total_tolerated;
+        let agree_rate = if meta.total_reviewed > 0 {
+            agreed as f64 / meta.total_reviewed as f64 * 100.
+        } else {
+            0.
+        };
+
+        let mut msg = format!("**{}**", self.player);
+        if let Some(log_id) = meta.log_id {
+            write!(msg, " `{}`", log_id).unwrap();
+        }
+        writeln!(msg).unwrap();
+        writeln!(
+            msg,
+            "agree rate {:.1}% ({}/{}), score {:.3}",
+            agree_rate,
+            agreed,
+            meta.total_reviewed,
+            meta.score * 100.,
+        )
+        .unwrap();
+        let counts = &meta.severity_counts;
+        writeln!(
+            msg,
+            "{} blunder(s), {} significant, {} minor",
+            counts.blunder, counts.significant, counts.minor,
+        )
+        .unwrap();
+
+        let worst = review::worst_entries(self.kyokus, TOP_MISTAKES);
+        if !worst.is_empty() {
+            writeln!(msg, "top mistakes:").unwrap();
+        }
+        for (i, (kyoku, entry)) in worst.into_iter().enumerate() {
+            writeln!(
+                msg,
+                "{}. {}-{} junme {}: `{}` akochan {}, actual {} (-{:.3})",
+                i + 1,
+                kyoku_label(kyoku.kyoku),
+                kyoku.honba,
+                entry.junme,
+                self.hand(entry),
+                describe(&entry.expected),
+                describe(&entry.actual),
+                entry.ev_loss.unwrap_or_default(),
+            )
+            .unwrap();
+        }
+
+        if let Some(url) = self.report_url {
+            writeln!(msg, "report: {}", url).unwrap();
+        }
+
+        truncate(&mut msg, MAX_CONTENT_LEN);
+        msg
+    }
+
+    /// The tehai at the decision, with the pai just drawn or discarded by
+    /// someone else set apart.
+    fn hand(&self, entry: &Entry) -> String {
+        let tehai = entry.state.tehai.view();
+        if entry.actor == self.target_actor {
+            match tehai.split_last() {
+                Some((tsumo, rest)) if tehai.len() % 3 == 2 => {
+                    format!("{} +{}", format_pais(rest), format_pais(&[*tsumo]))
+                }
+                _ => format_pais(tehai),
+            }
+        } else {
+            format!("{} +{}", format_pais(tehai), format_pais(&[entry.pai]))
+        }
+    }
+}
+
+/// Posts `summary` to the Discord webhook at `webhook_url`.
+pub fn post(client: &Client, webhook_url: &str, summary: &Summary) -> Result<()> {
+    let body = json!({
+        "username": "akochan-reviewer",
+        "content": summary.message(),
+    });
+    client
+        .post_json(webhook_url, &json::to_string(&body)?)
+        .context("failed to post to Discord webhook")?;
+
+    Ok(())
+}
+
+fn truncate(s: &mut String, max_len: usize) {
+    if s.chars().count() <= max_len {
+        return;
+    }
+    let end = s
+        .char_indices()
+        .nth(max_len - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(s.len());
+    s.truncate(end);
+    s.push('…');
+}
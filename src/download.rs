@@ -1,57 +1,295 @@
+use crate::log;
+use crate::log_cache::{LogCache, LogKind};
+use std::cmp::Reverse;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::anyhow;
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{self as json, Value};
 use url::form_urlencoded::Serializer;
+use url::Url;
 
 const TENSOUL_ENDPOINT: &str = "https://tensoul.herokuapp.com/convert";
+const NODOCCHI_ENDPOINT: &str = "https://nodocchi.moe/api/listuser.php";
+const AMAE_KOROMO_ENDPOINT: &str = "https://5-data.amae-koromo.com/api/v2/pl4";
 
-pub fn tenhou_log(log_id: &str) -> Result<String> {
-    let url = format!("https://tenhou.net/5/mjlog2json.cgi?{}", log_id);
+/// Mode IDs of the 4-player ranked rooms on amae-koromo: East and South
+/// games of the Gold, Jade and Throne rooms.
+const AMAE_KOROMO_RANKED_MODES: &str = "8.9.11.12.15.16";
 
-    let mut req = ureq::get(&url);
-    req.set("Referer", "https://tenhou.net/");
-    req.timeout_connect(10_000);
-    proxy_from_env(&mut req, &url)?;
+/// Delay before the first retry, doubled after each failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
 
-    let res = req.call();
-    if res.ok() {
-        Ok(res.into_string()?)
-    } else {
-        Err(anyhow!(
-            "get tenhou log: {} {}",
-            res.status(),
-            res.status_text()
-        ))
-    }
+#[derive(Debug, Deserialize)]
+struct AmaeKoromoPlayer {
+    id: u64,
+    nickname: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AmaeKoromoRecord {
+    uuid: String,
+    /// In seat order, from the first oya.
+    players: Vec<AmaeKoromoRecordPlayer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AmaeKoromoRecordPlayer {
+    account_id: u64,
+}
+
+/// Settings shared by all downloads.
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// Timeout of a whole request, from connecting to reading the body.
+    pub timeout: Duration,
+    /// How many more times to try after the first failed attempt.
+    pub retries: u32,
+    /// Proxy URL such as "http://127.0.0.1:8080" or "socks5://127.0.0.1:1080".
+    /// If `None`, it is taken from the environment variables like
+    /// `HTTPS_PROXY` and `ALL_PROXY`.
+    pub proxy: Option<String>,
+    /// Where downloaded logs are kept, `None` to always download them.
+    pub log_cache: Option<LogCache>,
 }
 
-pub fn mahjong_soul_log(log_id: &str) -> Result<String> {
-    let mut ser = Serializer::new(String::new());
-    ser.append_pair("id", log_id);
-    let query = ser.finish();
-    let url = format!("{}?{}", TENSOUL_ENDPOINT, query);
-
-    let mut req = ureq::get(&url);
-    req.timeout_connect(20_000);
-    proxy_from_env(&mut req, &url)?;
-
-    let res = req.call();
-    if res.ok() {
-        Ok(res.into_string()?)
-    } else {
-        Err(anyhow!(
-            "get mahjong soul log: {} {}",
-            res.status(),
-            res.status_text()
-        ))
+impl Default for Client {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            proxy: None,
+            log_cache: None,
+        }
     }
 }
 
-fn proxy_from_env(req: &mut ureq::Request, url: &str) -> Result<()> {
-    if let Some(proxy_url) = env_proxy::for_url_str(&url).raw_value() {
-        let proxy_str: String = proxy_url.chars().skip("http://".len()).collect();
-        let proxy = ureq::Proxy::new(proxy_str).context("failed to parse proxy")?;
-        req.set_proxy(proxy);
+impl Client {
+    pub fn tenhou_log(&self, log_id: &str) -> Result<String> {
+        self.cached(LogKind::Tenhou, log_id, || {
+            let url = format!("https://tenhou.net/5/mjlog2json.cgi?{}", log_id);
+            self.get(&url, Some("https://tenhou.net/"))
+                .context("get tenhou log")
+        })
+    }
+
+    pub fn mahjong_soul_log(&self, log_id: &str) -> Result<String> {
+        self.cached(LogKind::MahjongSoul, log_id, || {
+            let mut ser = Serializer::new(String::new());
+            ser.append_pair("id", log_id);
+            let query = ser.finish();
+            let url = format!("{}?{}", TENSOUL_ENDPOINT, query);
+
+            self.get(&url, None).context("get mahjong soul log")
+        })
+    }
+
+    /// Reads the log from the log cache if it is there, otherwise downloads
+    /// it with `download` and puts it in the cache.
+    fn cached<F>(&self, kind: LogKind, log_id: &str, download: F) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        let cache = match &self.log_cache {
+            Some(cache) => cache,
+            None => return download(),
+        };
+        if let Some(body) = cache.get(kind, log_id) {
+            log!("using cached {} log {}", kind.as_str(), log_id);
+            return Ok(body);
+        }
+
+        let body = download()?;
+        if let Err(err) = cache.put(kind, log_id, &body) {
+            log!("WARNING: failed to cache log {}: {:#}", log_id, err);
+        }
+        Ok(body)
+    }
+
+    /// Tenhou log IDs of the 4-player games played by `name`, as indexed by
+    /// nodocchi.moe, from the newest.
+    pub fn tenhou_player_log_ids(&self, name: &str) -> Result<Vec<String>> {
+        let mut ser = Serializer::new(String::new());
+        ser.append_pair("name", name);
+        let query = ser.finish();
+        let url = format!("{}?{}", NODOCCHI_ENDPOINT, query);
+
+        let body = self.get(&url, None).context("get nodocchi game list")?;
+        let root: Value = json::from_str(&body).context("failed to parse nodocchi game list")?;
+
+        // numbers are sometimes given as strings
+        let as_u64 = |v: &Value| match v {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        };
+        let mut games: Vec<_> = root["list"]
+            .as_array()
+            .map(|list| list.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|game| as_u64(&game["playernum"]) == Some(4))
+            .filter_map(|game| {
+                let url = Url::parse(game["url"].as_str()?).ok()?;
+                let log_id = url
+                    .query_pairs()
+                    .find(|(k, _)| k == "log")
+                    .map(|(_, v)| v.into_owned())?;
+                Some((as_u64(&game["starttime"]).unwrap_or_default(), log_id))
+            })
+            .collect();
+        games.sort_by_key(|&(starttime, _)| Reverse(starttime));
+
+        Ok(games.into_iter().map(|(_, log_id)| log_id).collect())
     }
 
-    Ok(())
+    /// Mahjong Soul account ID of the player whose nickname is exactly `name`,
+    /// as indexed by amae-koromo.
+    pub fn majsoul_account_id(&self, name: &str) -> Result<u64> {
+        let mut url = Url::parse(AMAE_KOROMO_ENDPOINT)?;
+        url.path_segments_mut()
+            .unwrap() // has a base
+            .push("search_player")
+            .push(name);
+        url.query_pairs_mut().append_pair("limit", "20");
+
+        let body = self
+            .get(url.as_str(), None)
+            .context("get amae-koromo player search")?;
+        let players: Vec<AmaeKoromoPlayer> =
+            json::from_str(&body).context("failed to parse amae-koromo player search")?;
+
+        players
+            .iter()
+            .find(|p| p.nickname == name)
+            .map(|p| p.id)
+            .ok_or_else(|| {
+                let similar: Vec<_> = players.iter().map(|p| p.nickname.as_str()).collect();
+                anyhow!(
+                    "player {:?} not found on amae-koromo, similar names: {:?}",
+                    name,
+                    similar,
+                )
+            })
+    }
+
+    /// Mahjong Soul log IDs of the last `last` ranked 4-player games played by
+    /// `account_id`, as indexed by amae-koromo, from the newest, each with the
+    /// seat of the player.
+    pub fn majsoul_player_games(&self, account_id: u64, last: usize) -> Result<Vec<(String, u8)>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let url = format!(
+            "{}/player_records/{}/{}/1262304000000?limit={}&mode={}&descending=true",
+            AMAE_KOROMO_ENDPOINT, account_id, now, last, AMAE_KOROMO_RANKED_MODES,
+        );
+
+        let body = self
+            .get(&url, None)
+            .context("get amae-koromo player records")?;
+        let records: Vec<AmaeKoromoRecord> =
+            json::from_str(&body).context("failed to parse amae-koromo player records")?;
+
+        Ok(records
+            .into_iter()
+            .filter_map(|r| {
+                let seat = r.players.iter().position(|p| p.account_id == account_id)?;
+                Some((r.uuid, seat as u8))
+            })
+            .take(last)
+            .collect())
+    }
+
+    /// Posts `body` as JSON to `url`, like a webhook, and returns the response
+    /// body.
+    pub fn post_json(&self, url: &str, body: &str) -> Result<String> {
+        self.call(
+            url,
+            |agent| agent.post(url).set("Content-Type", "application/json"),
+            Some(body),
+        )
+    }
+
+    fn get(&self, url: &str, referer: Option<&str>) -> Result<String> {
+        self.call(
+            url,
+            |agent| {
+                let req = agent.get(url);
+                match referer {
+                    Some(referer) => req.set("Referer", referer),
+                    None => req,
+                }
+            },
+            None,
+        )
+    }
+
+    /// Sends the request made by `request`, with `body` if any, and reads the
+    /// response body, retrying with backoff on transport errors and server
+    /// errors.
+    fn call<F>(&self, url: &str, request: F, body: Option<&str>) -> Result<String>
+    where
+        F: Fn(&ureq::Agent) -> ureq::Request,
+    {
+        let agent = self.agent(url)?;
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let req = request(&agent);
+            let res = match body {
+                Some(body) => req.send_string(body),
+                None => req.call(),
+            };
+            let err = match res {
+                Ok(res) => match res.into_string() {
+                    Ok(body) => return Ok(body),
+                    Err(err) => anyhow!(err).context("failed to read response body"),
+                },
+                Err(ureq::Error::Status(status, res)) => {
+                    let err = anyhow!("{} {}", status, res.status_text());
+                    // client errors other than rate limits will not go away by retrying
+                    if (400..500).contains(&status) && status != 429 {
+                        return Err(err);
+                    }
+                    err
+                }
+                Err(err @ ureq::Error::Transport(_)) => anyhow!(err),
+            };
+
+            if attempt >= self.retries {
+                return Err(err);
+            }
+            attempt += 1;
+            log!(
+                "WARNING: {:#}, retrying in {:?} ({}/{})",
+                err,
+                backoff,
+                attempt,
+                self.retries,
+            );
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+    }
+
+    fn agent(&self, url: &str) -> Result<ureq::Agent> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout_connect(self.timeout.min(Duration::from_secs(20)))
+            .timeout(self.timeout);
+
+        let proxy_url = match &self.proxy {
+            Some(p) => Some(p.clone()),
+            None => env_proxy::for_url_str(url).raw_value(),
+        };
+        if let Some(proxy_url) = proxy_url {
+            let proxy = ureq::Proxy::new(&proxy_url)
+                .with_context(|| format!("failed to parse proxy {:?}", proxy_url))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(builder.build())
+    }
 }
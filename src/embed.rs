@@ -0,0 +1,38 @@
+use std::io::prelude::*;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// The tag the review JSON is embedded in, in the HTML report.
+const OPENING_TAG: &str = r#"<script type="application/gzip" id="review-json">"#;
+const CLOSING_TAG: &str = "</script>";
+
+/// Gzips and base64 encodes `json` to be embedded in the HTML report.
+pub fn encode(json: &[u8]) -> Result<String> {
+    let mut encoder = GzEncoder::new(vec![], Compression::best());
+    encoder.write_all(json)?;
+    let gz = encoder.finish().context("failed to compress review JSON")?;
+    Ok(STANDARD.encode(gz))
+}
+
+/// Extracts the review JSON embedded in an HTML report. Returns `None` if
+/// there is none, e.g. for a report from an older version.
+pub fn extract(html: &str) -> Result<Option<Vec<u8>>> {
+    let encoded = match html.split_once(OPENING_TAG) {
+        Some((_, rest)) => rest.split_once(CLOSING_TAG).map_or(rest, |(e, _)| e),
+        None => return Ok(None),
+    };
+    let gz = STANDARD
+        .decode(encoded.trim())
+        .context("failed to decode embedded review JSON")?;
+
+    let mut json = vec![];
+    GzDecoder::new(&*gz)
+        .read_to_end(&mut json)
+        .context("failed to decompress embedded review JSON")?;
+    Ok(Some(json))
+}
@@ -0,0 +1,708 @@
+use crate::engine_server::Hello;
+#[cfg(feature = "ffi")]
+use crate::ffi::InProcess;
+use crate::log;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::mem;
+use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde_json as json;
+
+/// How many bytes at the end of akochan's stderr are kept for error reports.
+const STDERR_TAIL_SIZE: usize = 4096;
+
+/// Hands of the kyoku played by [`Akochan::probe`], in the order of seats.
+const PROBE_TEHAIS: [&str; 4] = [
+    "1m 2m 3m 4m 5m 6m 7m 8m 9m 1p 2p 3p 4p",
+    "5p 6p 7p 8p 9p 1s 2s 3s 4s 5s 6s 7s 8s",
+    "9s E S W N P F C 1m 2m 3m 4m 5m",
+    "6m 7m 8m 9m 1p 2p 3p 4p 5p 6p 7p 8p 9p",
+];
+
+/// Environment variable that carries `--seed` to akochan. Upstream akochan
+/// does not read it, so it is a no-op there. Only builds that seed their
+/// Monte Carlo simulations with it give the same EVs on every run.
+pub const SEED_ENV: &str = "AKOCHAN_SEED";
+
+#[derive(Clone, Copy)]
+pub struct EngineArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    pub tactics_config: &'a Path,
+    /// Load akochan from this shared library in-process instead of spawning
+    /// `akochan_exe`. Requires the `ffi` feature.
+    pub akochan_lib: Option<&'a Path>,
+    /// Address of an `engine-server` to get akochan from instead of spawning
+    /// `akochan_exe`.
+    pub server: Option<&'a str>,
+    /// Where to record the conversation with akochan, if anywhere.
+    pub transcript: Option<&'a Transcript>,
+    /// Passed to akochan as [`SEED_ENV`]. In-process akochan has no
+    /// environment of its own, so it fails to start with a seed.
+    pub seed: Option<u64>,
+    pub target_actor: u8,
+    pub verbose: bool,
+    /// How many times in a row `recover` may restart akochan before giving
+    /// up.
+    pub max_restarts: u32,
+    /// How long to wait for each response. `None` waits forever.
+    pub timeout: Option<Duration>,
+}
+
+/// A running akochan in `pipe_detailed` mode, which can be restarted after
+/// it crashes or hangs.
+pub struct Akochan<'a> {
+    args: EngineArgs<'a>,
+    failures: u32,
+    /// Whether the response to the probe has passed [`check_protocol`].
+    probed: bool,
+    backend: Backend,
+}
+
+/// The akochan build cannot talk `pipe_detailed`, so restarting it is no use.
+#[derive(Debug)]
+pub struct Unsupported {
+    akochan: PathBuf,
+    reason: &'static str,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "akochan build {:?} is unsupported, as {}; need a build of critter-mj/akochan \
+            with pipe_detailed mode",
+            self.akochan, self.reason,
+        )
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Everything sent to and received from akochan, along with its stderr,
+/// written to a file as it happens so that it survives a crash of either
+/// side.
+#[derive(Clone)]
+pub struct Transcript {
+    file: Arc<Mutex<File>>,
+}
+
+impl Transcript {
+    pub fn create<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create engine transcript {:?}", path))?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Writes `line` marked with `mark`, which is `>` for what is sent, `<`
+    /// for what is received, `!` for stderr and `#` for notes.
+    fn record(&self, mark: char, line: &str) {
+        let now = chrono::Local::now().time().format("%H:%M:%S%.6f");
+        // a broken transcript is not worth failing the review for
+        let _ = writeln!(self.file.lock().unwrap(), "{} {} {}", now, mark, line);
+    }
+}
+
+enum Backend {
+    Process(Process),
+    Remote(Remote),
+    #[cfg(feature = "ffi")]
+    InProcess(InProcess),
+}
+
+struct Process {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// Lines of stdout, read by another thread so that waiting for them can
+    /// time out.
+    stdout_lines: Receiver<io::Result<String>>,
+    stderr_tail: Arc<Mutex<VecDeque<u8>>>,
+    stderr_thread: Option<JoinHandle<()>>,
+}
+
+/// A connection to an akochan of an `engine-server`.
+struct Remote {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl<'a> Akochan<'a> {
+    pub fn spawn(args: EngineArgs<'a>) -> Result<Self> {
+        if let Some(transcript) = args.transcript {
+            transcript.record(
+                '#',
+                &format!(
+                    "starting akochan for actor {} with {:?}",
+                    args.target_actor, args.tactics_config
+                ),
+            );
+        }
+        let backend = match args.akochan_lib {
+            #[cfg(feature = "ffi")]
+            Some(_) if args.seed.is_some() => bail!("in-process akochan cannot take a seed"),
+            #[cfg(feature = "ffi")]
+            Some(lib) => Backend::InProcess(InProcess::load(
+                lib,
+                args.akochan_dir,
+                args.tactics_config,
+                args.target_actor,
+            )?),
+            #[cfg(not(feature = "ffi"))]
+            Some(_) => bail!("in-process akochan requires building with the ffi feature"),
+            None => match args.server {
+                Some(addr) => Backend::Remote(Remote::connect(addr, &args)?),
+                None => Backend::Process(Process::spawn(&args)?),
+            },
+        };
+
+        Ok(Self {
+            args,
+            failures: 0,
+            probed: false,
+            backend,
+        })
+    }
+
+    /// Sends `event`. The first start_game is followed by [`probe`], so that
+    /// an unsupported build fails here, before the review starts.
+    ///
+    /// [`probe`]: Self::probe
+    pub fn send(&mut self, event: &Event) -> Result<()> {
+        let to_write = json::to_string(event).unwrap();
+        if let Err(err) = self.write_line(&to_write) {
+            return Err(self.diagnose(err));
+        }
+        if self.args.verbose {
+            log!("> {}", to_write);
+        }
+        if let Some(transcript) = self.args.transcript {
+            transcript.record('>', &to_write);
+        }
+        if !self.probed && matches!(event, Event::StartGame { .. }) {
+            self.probe()?;
+        }
+
+        Ok(())
+    }
+
+    /// Plays the first draw of a fixed kyoku and checks the response with
+    /// [`check_protocol`], failing with [`Unsupported`] if akochan does not
+    /// talk `pipe_detailed`. It needs the settings of start_game, hence it
+    /// comes right after that. The start_kyoku that follows resets the kyoku.
+    fn probe(&mut self) -> Result<()> {
+        if let Some(transcript) = self.args.transcript {
+            transcript.record('#', "probing the protocol");
+        }
+        let target_actor = self.args.target_actor;
+        let mut tehais = PROBE_TEHAIS.iter().map(|tehai| {
+            let mut pais = tehai.split(' ').map(|p| p.parse::<Pai>().unwrap()); // constant
+            [(); 13].map(|_| pais.next().unwrap())
+        });
+        let start_kyoku = Event::StartKyoku {
+            bakaze: "E".parse().unwrap(),
+            dora_marker: "9s".parse().unwrap(),
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [25000; 4],
+            tehais: [(); 4].map(|_| tehais.next().unwrap()),
+        };
+        let tsumo = Event::Tsumo {
+            actor: target_actor,
+            pai: "E".parse().unwrap(),
+        };
+        self.send(&start_kyoku)?;
+        self.send(&tsumo)?;
+
+        let line = self.read_line().map_err(|err| self.diagnose(err))?;
+        let akochan = self.args.akochan_lib.unwrap_or(self.args.akochan_exe);
+        check_protocol(&line).map_err(|reason| Unsupported {
+            akochan: akochan.to_owned(),
+            reason,
+        })?;
+        self.probed = true;
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        match &mut self.backend {
+            Backend::Process(p) => {
+                let stdin = p.stdin.as_mut().context("stdin of akochan is closed")?;
+                writeln!(stdin, "{}", line).context("failed to write to akochan")?;
+            }
+            Backend::Remote(r) => {
+                writeln!(r.stream, "{}", line).context("failed to write to akochan server")?
+            }
+            #[cfg(feature = "ffi")]
+            Backend::InProcess(lib) => lib.feed(line, self.args.timeout)?,
+        }
+
+        Ok(())
+    }
+
+    /// Reads one line of response. Be careful, this may block if there is no
+    /// timeout.
+    pub fn recv(&mut self) -> Result<String> {
+        let line = self.read_line().map_err(|err| self.diagnose(err))?;
+        self.failures = 0;
+        Ok(line)
+    }
+
+    /// Feeds `events` in order and returns the response to the last review
+    /// timing among them, discarding the others.
+    pub fn replay<'e, I>(&mut self, events: I) -> Result<Option<String>>
+    where
+        I: IntoIterator<Item = &'e Event>,
+    {
+        let mut last_line = None;
+        for event in events {
+            self.send(event)?;
+            if is_review_timing(event, self.args.target_actor) {
+                last_line = Some(self.recv()?);
+            }
+        }
+
+        Ok(last_line)
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let line = match &mut self.backend {
+            Backend::Process(p) => match p.read_line(self.args.timeout) {
+                Ok(line) => line,
+                Err(err) if !self.probed => {
+                    return Err(match p.child.try_wait() {
+                        Ok(Some(status)) => err.context(format!(
+                            "akochan exited with {} before answering the probe, \
+                            is {:?} a build with pipe_detailed mode?",
+                            status, self.args.akochan_exe,
+                        )),
+                        _ => err,
+                    });
+                }
+                Err(err) => return Err(err),
+            },
+            Backend::Remote(r) => r.read_line(self.args.timeout)?,
+            #[cfg(feature = "ffi")]
+            Backend::InProcess(lib) => lib
+                .pop_response()
+                .context("akochan did not respond to the last event")?,
+        };
+        if self.args.verbose {
+            log!("< {}", line.trim());
+        }
+        if let Some(transcript) = self.args.transcript {
+            transcript.record('<', line.trim());
+        }
+
+        Ok(line)
+    }
+
+    /// Restarts akochan after `err` occurred in `send` or `recv`, including
+    /// timeouts, and feeds `history` to the new process so that it picks up
+    /// where the old one died. Responses to all but the last event of
+    /// `history` are discarded.
+    ///
+    /// `history` should be the start_game event followed by the events of the
+    /// current kyoku up to the one that failed. Fails if akochan has failed
+    /// more than `max_restarts` times in a row.
+    pub fn recover(&mut self, mut err: Error, history: &[&Event]) -> Result<()> {
+        if err.downcast_ref::<Unsupported>().is_some() {
+            self.kill();
+            return Err(err);
+        }
+        loop {
+            self.kill();
+            err = self.with_stderr_tail(err);
+            self.failures += 1;
+            if self.failures > self.args.max_restarts {
+                return Err(
+                    err.context(format!("akochan failed {} time(s) in a row", self.failures))
+                );
+            }
+            log!(
+                "WARNING: akochan failed, restarting ({}/{}): {:?}",
+                self.failures,
+                self.args.max_restarts,
+                err,
+            );
+            if let Some(transcript) = self.args.transcript {
+                transcript.record('#', &format!("restarting after failure: {:#}", err));
+            }
+
+            match self.restart(history) {
+                Ok(()) => return Ok(()),
+                Err(e) => err = e,
+            }
+        }
+    }
+
+    fn restart(&mut self, history: &[&Event]) -> Result<()> {
+        // the old one goes first, e.g. to free the resources of in-process
+        // akochan before loading another
+        self.kill();
+        let failures = self.failures;
+        *self = Self::spawn(self.args)?;
+        self.failures = failures;
+
+        if let Some((last, replayed)) = history.split_last() {
+            for event in replayed {
+                self.send(event)?;
+                if is_review_timing(event, self.args.target_actor) {
+                    self.read_line()?;
+                }
+            }
+            self.send(last)?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes stdin and waits for akochan to exit by itself.
+    pub fn finish(mut self) -> Result<()> {
+        match &mut self.backend {
+            Backend::Process(p) => p.finish(),
+            Backend::Remote(r) => r.finish(),
+            #[cfg(feature = "ffi")]
+            Backend::InProcess(_) => Ok(()),
+        }
+    }
+
+    fn kill(&mut self) {
+        match &mut self.backend {
+            Backend::Process(p) => p.kill(),
+            Backend::Remote(r) => r.close(),
+            #[cfg(feature = "ffi")]
+            Backend::InProcess(lib) => lib.close(),
+        }
+    }
+
+    /// Attaches the stderr tail to `err` if akochan has exited by itself,
+    /// which is when all of its stderr can be read.
+    fn diagnose(&mut self, err: Error) -> Error {
+        match &mut self.backend {
+            Backend::Process(p) => {
+                if !matches!(p.child.try_wait(), Ok(Some(_))) {
+                    return err;
+                }
+                p.join_stderr();
+                p.with_stderr_tail(err)
+            }
+            _ => err,
+        }
+    }
+
+    fn with_stderr_tail(&self, err: Error) -> Error {
+        match &self.backend {
+            Backend::Process(p) => p.with_stderr_tail(err),
+            Backend::Remote(_) => err,
+            #[cfg(feature = "ffi")]
+            Backend::InProcess(_) => err,
+        }
+    }
+}
+
+impl Process {
+    fn spawn(args: &EngineArgs) -> Result<Self> {
+        let mut child = spawn_child(
+            args.akochan_exe,
+            args.akochan_dir,
+            args.tactics_config,
+            args.target_actor,
+            args.seed,
+            args.verbose,
+        )?;
+        let stdin = child
+            .stdin
+            .take()
+            .context("failed to get stdin of akochan")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("failed to get stdout of akochan")?;
+        let stderr = child
+            .stderr
+            .take()
+            .context("failed to get stderr of akochan")?;
+
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_TAIL_SIZE)));
+        let stderr_thread = {
+            let tail = Arc::clone(&stderr_tail);
+            let verbose = args.verbose;
+            let transcript = args.transcript.cloned();
+            thread::spawn(move || collect_stderr(stderr, &tail, verbose, transcript.as_ref()))
+        };
+        // this thread ends by itself once akochan exits
+        let (tx, stdout_lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin: Some(stdin),
+            stdout_lines,
+            stderr_tail,
+            stderr_thread: Some(stderr_thread),
+        })
+    }
+
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String> {
+        let received = match timeout {
+            Some(timeout) => match self.stdout_lines.recv_timeout(timeout) {
+                Ok(line) => Some(line),
+                Err(RecvTimeoutError::Timeout) => {
+                    bail!("akochan did not respond within {:?}", timeout)
+                }
+                Err(RecvTimeoutError::Disconnected) => None,
+            },
+            None => self.stdout_lines.recv().ok(),
+        };
+
+        received
+            .context("failed to read from akochan: unexpected EOF")?
+            .context("failed to read from akochan")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        drop(self.stdin.take());
+        let ecode = self.child.wait()?;
+        self.join_stderr();
+        if !ecode.success() {
+            let err = match ecode.code() {
+                Some(code) => anyhow!("non-zero exit code: {}", code),
+                None => anyhow!("non-zero exit code: Process terminated by signal"),
+            };
+            return Err(self.with_stderr_tail(err));
+        }
+
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        drop(self.stdin.take());
+        // it may have exited already
+        let _ = self.child.kill();
+        if let Err(err) = self.child.wait() {
+            log!("WARNING: failed to wait for akochan: {}", err);
+        }
+        self.join_stderr();
+    }
+
+    fn join_stderr(&mut self) {
+        if let Some(handle) = self.stderr_thread.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Attaches what akochan has printed to stderr to `err`, taking it so
+    /// that it is reported only once.
+    fn with_stderr_tail(&self, err: Error) -> Error {
+        let tail = mem::take(&mut *self.stderr_tail.lock().unwrap());
+        if tail.is_empty() {
+            return err;
+        }
+        let text = String::from_utf8_lossy(&Vec::from(tail)).trim().to_owned();
+        err.context(format!("stderr of akochan:\n{}", text))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if self.stdin.is_some() {
+            // not finished, e.g. the review has failed
+            self.kill();
+        }
+    }
+}
+
+impl Remote {
+    fn connect(addr: &str, args: &EngineArgs) -> Result<Self> {
+        let mut stream = TcpStream::connect(addr)
+            .with_context(|| format!("failed to connect to akochan server {}", addr))?;
+        stream
+            .set_read_timeout(args.timeout)
+            .context("failed to set timeout")?;
+        let hello = Hello {
+            tactics_config: args.tactics_config.to_owned(),
+            target_actor: args.target_actor,
+            seed: args.seed,
+        };
+        writeln!(stream, "{}", json::to_string(&hello).unwrap())
+            .context("failed to write to akochan server")?;
+        let reader = BufReader::new(
+            stream
+                .try_clone()
+                .context("failed to clone the connection")?,
+        );
+
+        Ok(Self { stream, reader })
+    }
+
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => bail!("failed to read from akochan server: unexpected EOF"),
+            Ok(_) => (),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                bail!(
+                    "akochan did not respond within {:?}",
+                    timeout.unwrap_or_default()
+                )
+            }
+            Err(err) => return Err(err).context("failed to read from akochan server"),
+        }
+        line.truncate(line.trim_end().len());
+
+        Ok(line)
+    }
+
+    /// Ends the conversation and waits for the server to let akochan go.
+    fn finish(&mut self) -> Result<()> {
+        self.stream
+            .shutdown(Shutdown::Write)
+            .context("failed to close the connection to akochan server")?;
+        io::copy(&mut self.reader, &mut io::sink())
+            .context("failed to read from akochan server")?;
+
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        // it may have been closed already
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+pub fn spawn_child(
+    akochan_exe: &Path,
+    akochan_dir: &Path,
+    tactics_config: &Path,
+    target_actor: u8,
+    seed: Option<u64>,
+    verbose: bool,
+) -> Result<Child> {
+    let target_actor_string = target_actor.to_string();
+    let args = &[
+        "pipe_detailed".as_ref(),
+        tactics_config,
+        target_actor_string.as_ref(),
+    ];
+
+    if verbose {
+        log!("$ cd {:?}", akochan_dir);
+        if let Some(seed) = seed {
+            log!("$ export {}={}", SEED_ENV, seed);
+        }
+        log!(
+            "$ {:?}{}",
+            akochan_exe,
+            args.iter()
+                .fold("".to_owned(), |acc, p| format!("{} {:?}", acc, p))
+        );
+    }
+
+    let mut command = Command::new(akochan_exe);
+    command
+        .args(args)
+        .current_dir(akochan_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(seed) = seed {
+        command.env(SEED_ENV, seed.to_string());
+    }
+    command.spawn().context("failed to spawn akochan")
+}
+
+/// Keeps the last `STDERR_TAIL_SIZE` bytes of `stderr` in `tail`, also
+/// forwarding them to our stderr in verbose mode and recording them line by
+/// line in `transcript`.
+fn collect_stderr<R: Read>(
+    stderr: R,
+    tail: &Mutex<VecDeque<u8>>,
+    verbose: bool,
+    transcript: Option<&Transcript>,
+) {
+    let mut reader = BufReader::new(stderr);
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        };
+        if verbose {
+            let _ = io::stderr().write_all(&buf);
+        }
+        if let Some(transcript) = transcript {
+            transcript.record('!', String::from_utf8_lossy(&buf).trim_end());
+        }
+
+        let mut tail = tail.lock().unwrap();
+        tail.extend(&buf);
+        let excess = tail.len().saturating_sub(STDERR_TAIL_SIZE);
+        tail.drain(..excess);
+    }
+}
+
+/// Tells if `line`, the response to the probe, is in the format of
+/// `pipe_detailed` mode, returning what is wrong otherwise. akochan has no
+/// version to ask for, so this is how a build too old or of another mode is
+/// told apart from one that crashes.
+fn check_protocol(line: &str) -> Result<(), &'static str> {
+    match json::from_str(line) {
+        Ok(json::Value::Array(candidates)) => {
+            let is_detailed = candidates
+                .iter()
+                .all(|c| c.get("moves").is_some() && c.get("review").is_some());
+            if is_detailed {
+                Ok(())
+            } else {
+                Err("its candidates come without reviews")
+            }
+        }
+        Ok(json::Value::Object(o)) if o.contains_key("type") => {
+            Err("it answers with a single action as in pipe mode")
+        }
+        _ => Err("its response is not a list of candidates"),
+    }
+}
+
+/// Whether or not akochan responds to `event`.
+pub fn is_review_timing(event: &Event, target_actor: u8) -> bool {
+    match *event {
+        Event::Dahai { actor, .. } | Event::Kakan { actor, .. } => actor != target_actor,
+        Event::Tsumo { actor, .. } => actor == target_actor,
+        _ => false,
+    }
+}
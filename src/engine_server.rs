@@ -0,0 +1,231 @@
+use crate::engine::spawn_child;
+use crate::log;
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+/// The first line a client sends after connecting, choosing the akochan to
+/// talk to. Everything after it is the usual `pipe_detailed` conversation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hello {
+    /// Absolute path, as the server may run in another directory.
+    pub tactics_config: PathBuf,
+    pub target_actor: u8,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+pub struct ServerArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    /// Processes with this tactics config are spawned ahead of time. Clients
+    /// asking for another one, or for a seed, get a freshly spawned process.
+    pub tactics_config: &'a Path,
+    /// Idle processes kept for each seat.
+    pub pool_size: usize,
+    pub verbose: bool,
+}
+
+/// Idle akochan processes, spawned before anyone asks for them so that their
+/// startup does not count towards the reviews.
+///
+/// akochan cannot play another game once it has seen end_game, so a process
+/// serves exactly one connection and a new one takes its place right away.
+struct Pool<'a> {
+    args: &'a ServerArgs<'a>,
+    idle: Mutex<HashMap<u8, Vec<Child>>>,
+}
+
+impl<'a> Pool<'a> {
+    fn new(args: &'a ServerArgs<'a>) -> Result<Self> {
+        let pool = Self {
+            args,
+            idle: Mutex::new(HashMap::new()),
+        };
+        for target_actor in 0..4 {
+            for _ in 0..args.pool_size {
+                let child = pool.spawn(args.tactics_config, target_actor, None)?;
+                pool.put(target_actor, child);
+            }
+        }
+
+        Ok(pool)
+    }
+
+    fn spawn(&self, tactics_config: &Path, target_actor: u8, seed: Option<u64>) -> Result<Child> {
+        let mut child = spawn_child(
+            self.args.akochan_exe,
+            self.args.akochan_dir,
+            tactics_config,
+            target_actor,
+            seed,
+            self.args.verbose,
+        )?;
+        // nobody reads it otherwise, and akochan would block once the pipe is
+        // full
+        let mut stderr = child
+            .stderr
+            .take()
+            .context("failed to get stderr of akochan")?;
+        let verbose = self.args.verbose;
+        thread::spawn(move || {
+            let _ = if verbose {
+                io::copy(&mut stderr, &mut io::stderr())
+            } else {
+                io::copy(&mut stderr, &mut io::sink())
+            };
+        });
+
+        Ok(child)
+    }
+
+    fn put(&self, target_actor: u8, child: Child) {
+        self.idle
+            .lock()
+            .unwrap()
+            .entry(target_actor)
+            .or_default()
+            .push(child);
+    }
+
+    /// Takes an idle process if `hello` asks for one like those of the pool,
+    /// spawning a replacement, otherwise spawns a new one.
+    fn take(&self, hello: &Hello) -> Result<Child> {
+        let (tactics_config, target_actor) = (&*hello.tactics_config, hello.target_actor);
+        if tactics_config != self.args.tactics_config || hello.seed.is_some() {
+            log!("spawning akochan for {:?}", hello);
+            return self.spawn(tactics_config, target_actor, hello.seed);
+        }
+
+        let taken = loop {
+            let child = self
+                .idle
+                .lock()
+                .unwrap()
+                .get_mut(&target_actor)
+                .and_then(|idle| idle.pop());
+            let mut child = match child {
+                Some(child) => child,
+                None => break None,
+            };
+            // it may have died while waiting
+            if let Ok(Some(status)) = child.try_wait() {
+                log!(
+                    "WARNING: an idle akochan has exited with {}, discarded",
+                    status
+                );
+                continue;
+            }
+            break Some(child);
+        };
+        match self.spawn(tactics_config, target_actor, None) {
+            Ok(child) => self.put(target_actor, child),
+            Err(err) => log!("WARNING: failed to refill the pool: {:?}", err),
+        }
+
+        match taken {
+            Some(child) => Ok(child),
+            None => self.spawn(tactics_config, target_actor, None),
+        }
+    }
+}
+
+/// Accepts connections on `listener` forever, each served by its own thread
+/// with its own akochan.
+pub fn run(args: &ServerArgs, listener: TcpListener) -> Result<()> {
+    let pool = Pool::new(args)?;
+    log!(
+        "{} akochan process(es) ready at {}",
+        4 * args.pool_size,
+        listener.local_addr()?,
+    );
+
+    thread::scope(|s| {
+        for stream in listener.incoming() {
+            let stream = stream.context("failed to accept connection")?;
+            let pool = &pool;
+            s.spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_owned(), |a| a.to_string());
+                if let Err(err) = serve(pool, stream) {
+                    log!(
+                        "WARNING: connection from {} closed with error: {:?}",
+                        peer,
+                        err
+                    );
+                }
+            });
+        }
+
+        Ok(())
+    })
+}
+
+/// Relays the events of one client to an akochan and its responses back,
+/// until the client closes the connection.
+fn serve(pool: &Pool, stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .context("failed to clone the connection")?,
+    );
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .context("failed to read hello")?;
+    let hello: Hello = json::from_str(&line).context("failed to parse hello")?;
+    if pool.args.verbose {
+        log!("serving {:?}", hello);
+    }
+
+    let mut child = pool.take(&hello)?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("failed to get stdin of akochan")?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("failed to get stdout of akochan")?;
+    let mut writer = stream;
+    // ends once akochan exits
+    let forward = thread::spawn(move || io::copy(&mut stdout, &mut writer));
+
+    // a client that goes away before end_game has given up on this akochan,
+    // which may well be hanging
+    let mut has_ended = false;
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        has_ended = matches!(json::from_str(&line), Ok(Event::EndGame));
+        if writeln!(stdin, "{}", line).is_err() {
+            break;
+        }
+    }
+    drop(stdin);
+
+    if !has_ended {
+        let _ = child.kill();
+    }
+    let status = child.wait().context("failed to wait for akochan")?;
+    let _ = forward.join();
+    if has_ended && !status.success() {
+        log!("WARNING: akochan exited with {}", status);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! In-process akochan through a shared library, enabled by the `ffi` feature.
+//!
+//! akochan itself has no C API, so the library must export the one declared
+//! in ffi/akochan_ffi.h, which answers each event like `system.exe
+//! pipe_detailed` does. ffi/akochan_ffi.cpp implements it by running
+//! akochan's own main in a thread, one for each instance.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_int, c_long, c_void};
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use libloading::Library;
+
+type NewFn = unsafe extern "C" fn(*const c_char, *const c_char, c_int) -> *mut c_void;
+type FeedFn = unsafe extern "C" fn(*mut c_void, *const c_char, c_long, *mut *mut c_char) -> c_int;
+type FreeStringFn = unsafe extern "C" fn(*mut c_char);
+type FreeFn = unsafe extern "C" fn(*mut c_void);
+
+// statuses of akochan_feed
+const AKOCHAN_OK: c_int = 0;
+const AKOCHAN_TIMED_OUT: c_int = 2;
+
+pub struct InProcess {
+    /// Null once closed.
+    ctx: *mut c_void,
+    feed: FeedFn,
+    free_string: FreeStringFn,
+    free: FreeFn,
+    responses: VecDeque<String>,
+    /// Set after a timeout, when akochan's thread may outlive `ctx`.
+    timed_out: bool,
+    // must outlive the function pointers above, and that thread
+    lib: Option<Library>,
+}
+
+impl InProcess {
+    pub fn load(
+        lib_path: &Path,
+        akochan_dir: &Path,
+        tactics_config: &Path,
+        target_actor: u8,
+    ) -> Result<Self> {
+        let akochan_dir = path_to_cstring(akochan_dir)?;
+        let tactics_config = path_to_cstring(tactics_config)?;
+
+        // SAFETY: the library is trusted to export the functions above with
+        // exactly these signatures.
+        unsafe {
+            let lib = Library::new(lib_path)
+                .with_context(|| format!("failed to load akochan library {:?}", lib_path))?;
+            let new: NewFn = *lib
+                .get(b"akochan_new\0")
+                .context("failed to find akochan_new")?;
+            let feed: FeedFn = *lib
+                .get(b"akochan_feed\0")
+                .context("failed to find akochan_feed")?;
+            let free_string: FreeStringFn = *lib
+                .get(b"akochan_free_string\0")
+                .context("failed to find akochan_free_string")?;
+            let free: FreeFn = *lib
+                .get(b"akochan_free\0")
+                .context("failed to find akochan_free")?;
+
+            let ctx = new(
+                akochan_dir.as_ptr(),
+                tactics_config.as_ptr(),
+                target_actor as c_int,
+            );
+            if ctx.is_null() {
+                bail!("akochan_new failed");
+            }
+
+            Ok(Self {
+                ctx,
+                feed,
+                free_string,
+                free,
+                responses: VecDeque::new(),
+                timed_out: false,
+                lib: Some(lib),
+            })
+        }
+    }
+
+    /// Feeds `event` and waits for akochan to be done with it, for at most
+    /// `timeout` if any.
+    pub fn feed(&mut self, event: &str, timeout: Option<Duration>) -> Result<()> {
+        if self.ctx.is_null() {
+            bail!("akochan is closed");
+        }
+        let event = CString::new(event).context("event contains a nul byte")?;
+        let timeout_ms = match timeout {
+            Some(t) => c_long::try_from(t.as_millis()).unwrap_or(c_long::MAX),
+            None => -1,
+        };
+
+        // SAFETY: `ctx` is alive until closed, and the returned string is
+        // owned by us until passed to `free_string`.
+        let response = unsafe {
+            let mut ptr = ptr::null_mut();
+            match (self.feed)(self.ctx, event.as_ptr(), timeout_ms, &mut ptr) {
+                AKOCHAN_OK => (),
+                AKOCHAN_TIMED_OUT => {
+                    self.timed_out = true;
+                    bail!(
+                        "akochan did not respond within {:?}",
+                        timeout.unwrap_or_default()
+                    );
+                }
+                _ => bail!("akochan_feed failed"),
+            }
+            let s = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            (self.free_string)(ptr);
+            s
+        };
+        if !response.is_empty() {
+            self.responses.push_back(response);
+        }
+
+        Ok(())
+    }
+
+    pub fn pop_response(&mut self) -> Option<String> {
+        self.responses.pop_front()
+    }
+
+    /// Frees akochan. Any later `feed` fails.
+    pub fn close(&mut self) {
+        if self.ctx.is_null() {
+            return;
+        }
+        // SAFETY: `ctx` came from `akochan_new` and is freed only once.
+        unsafe { (self.free)(self.ctx) };
+        self.ctx = ptr::null_mut();
+        if self.timed_out {
+            // akochan's thread is still busy with the library
+            mem::forget(self.lib.take());
+        }
+    }
+}
+
+impl Drop for InProcess {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    let s = path
+        .to_str()
+        .with_context(|| format!("path {:?} is not valid UTF-8", path))?;
+    CString::new(s).with_context(|| format!("path {:?} contains a nul byte", path))
+}
@@ -0,0 +1,67 @@
+use crate::review::{Acceptance, KyokuReview};
+
+use serde::Serialize;
+
+/// EV losses of the disagreements by kyoku and junme.
+#[derive(Serialize)]
+pub struct Heatmap {
+    max_junme: u8,
+    max_loss: f64,
+    rows: Vec<HeatmapRow>,
+}
+
+#[derive(Serialize)]
+pub struct HeatmapRow {
+    kyoku: u8,
+    honba: u8,
+    /// Sum of the EV losses at each junme, counting from 1, or `None` if no
+    /// decision at the junme is reviewed.
+    cells: Vec<Option<f64>>,
+}
+
+impl Heatmap {
+    /// Returns `None` if there is no EV loss to show.
+    pub fn new(kyokus: &[KyokuReview]) -> Option<Self> {
+        let max_junme = kyokus
+            .iter()
+            .flat_map(|k| &k.entries)
+            .map(|e| e.junme.max(1))
+            .max()?;
+
+        let rows: Vec<_> = kyokus
+            .iter()
+            .map(|k| {
+                let mut cells = vec![None; max_junme as usize];
+                for entry in &k.entries {
+                    let loss = match entry.acceptance {
+                        Acceptance::Disagree => entry.ev_loss.unwrap_or(0.),
+                        _ => 0.,
+                    };
+                    // calls before the first draw are at junme 0
+                    let i = entry.junme.saturating_sub(1) as usize;
+                    *cells[i].get_or_insert(0.) += loss;
+                }
+                HeatmapRow {
+                    kyoku: k.kyoku,
+                    honba: k.honba,
+                    cells,
+                }
+            })
+            .collect();
+
+        let max_loss = rows
+            .iter()
+            .flat_map(|r| r.cells.iter().flatten())
+            .copied()
+            .fold(0., f64::max);
+        if max_loss <= 0. {
+            return None;
+        }
+
+        Some(Self {
+            max_junme,
+            max_loss,
+            rows,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// A local SQLite database of completed reviews.
+pub struct History {
+    conn: Connection,
+}
+
+/// A row in the history, without the report itself.
+#[derive(Debug, Serialize)]
+pub struct HistoryRow {
+    pub id: i64,
+    /// RFC 3339 in UTC, like "2020-06-01T12:34:56Z".
+    pub reviewed_at: String,
+    pub log_id: Option<String>,
+    pub actor: u8,
+    pub total_reviewed: usize,
+    pub total_problems: usize,
+    pub score: Option<f64>,
+}
+
+/// A single review to be saved into the history.
+pub struct NewReview<'a> {
+    pub log_id: Option<&'a str>,
+    pub actor: u8,
+    pub total_reviewed: usize,
+    pub total_problems: usize,
+    pub score: f64,
+    /// The JSON report, the same as the output of `--json`.
+    pub report: &'a str,
+}
+
+impl History {
+    /// Open the database at `path`, creating it if it does not exist.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let conn = Connection::open(path.as_ref())
+            .with_context(|| format!("failed to open database {:?}", path.as_ref()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS reviews (
+                id             INTEGER PRIMARY KEY AUTOINCREMENT,
+                reviewed_at    TEXT    NOT NULL,
+                log_id         TEXT,
+                actor          INTEGER NOT NULL,
+                total_reviewed INTEGER NOT NULL,
+                total_problems INTEGER NOT NULL,
+                score          REAL,
+                report         TEXT    NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS reviews_reviewed_at ON reviews (reviewed_at);",
+        )
+        .context("failed to initialize database")?;
+        to_utc(&conn).context("failed to migrate database")?;
+
+        Ok(Self { conn })
+    }
+
+    /// Save a review, returns the ID of the new row.
+    pub fn insert(&self, review: &NewReview) -> Result<i64> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        // NaN is not a valid REAL in SQLite
+        let score = Some(review.score).filter(|s| s.is_finite());
+        self.conn
+            .execute(
+                "INSERT INTO reviews
+                    (reviewed_at, log_id, actor, total_reviewed, total_problems, score, report)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    now,
+                    review.log_id,
+                    review.actor,
+                    review.total_reviewed as i64,
+                    review.total_problems as i64,
+                    score,
+                    review.report,
+                ],
+            )
+            .context("failed to insert review into database")?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// List reviews in chronological order, since `since` if any, which is a
+    /// local date like "2020-06-01" or an RFC 3339 timestamp.
+    pub fn list(&self, since: Option<&str>) -> Result<Vec<HistoryRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, reviewed_at, log_id, actor, total_reviewed, total_problems, score
+            FROM reviews
+            WHERE reviewed_at >= ?1
+            ORDER BY reviewed_at",
+        )?;
+        let rows = stmt
+            .query_map(params![since.map(since_utc).unwrap_or_default()], |row| {
+                Ok(HistoryRow {
+                    id: row.get(0)?,
+                    reviewed_at: row.get(1)?,
+                    log_id: row.get(2)?,
+                    actor: row.get(3)?,
+                    total_reviewed: row.get::<_, i64>(4)? as usize,
+                    total_problems: row.get::<_, i64>(5)? as usize,
+                    score: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("failed to query database")?;
+
+        Ok(rows)
+    }
+
+    /// Returns the ID and the JSON report of reviews in chronological order,
+    /// since `since` as in `list`.
+    pub fn reports(&self, since: Option<&str>) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, report
+            FROM reviews
+            WHERE reviewed_at >= ?1
+            ORDER BY reviewed_at",
+        )?;
+        let rows = stmt
+            .query_map(params![since.map(since_utc).unwrap_or_default()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("failed to query database")?;
+
+        Ok(rows)
+    }
+}
+
+/// Rewrites the timestamps of reviews saved in local time by older versions
+/// in UTC, so that they compare and sort as text with the new ones.
+fn to_utc(conn: &Connection) -> Result<()> {
+    let mut stmt =
+        conn.prepare("SELECT id, reviewed_at FROM reviews WHERE reviewed_at NOT LIKE '%Z'")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, reviewed_at) in rows {
+        let utc = DateTime::parse_from_rfc3339(&reviewed_at)
+            .with_context(|| format!("invalid timestamp {:?} of review {}", reviewed_at, id))?
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        conn.execute(
+            "UPDATE reviews SET reviewed_at = ?1 WHERE id = ?2",
+            params![utc, id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `since` of `list` in the format of the stored timestamps. Anything else is
+/// compared as it is.
+fn since_utc(since: &str) -> String {
+    let local = match NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        Ok(date) => date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| Local.from_local_datetime(&t).earliest())
+            .map(|t| t.with_timezone(&Utc)),
+        Err(_) => DateTime::parse_from_rfc3339(since)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+    };
+    match local {
+        Some(t) => t.to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => since.to_owned(),
+    }
+}
@@ -0,0 +1,675 @@
+use crate::scoring::Yaku;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use convlog::Pai;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json as json;
+use tera::{Tera, Value};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum Language {
+    // The string is used in html lang attribute, as per BCP47.
+    #[serde(rename = "ja")]
+    Japanese,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "zh")]
+    Chinese,
+}
+
+/// How yakus are named in the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YakuNames {
+    /// Like "断幺九".
+    Kanji,
+    /// Like "Tanyao".
+    Romaji,
+    /// Like "All Simples".
+    English,
+    /// Like "断幺九" in simplified Chinese.
+    Chinese,
+}
+
+/// How pais are written in the text report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaiNames {
+    /// Like "5m", "5mr" and "E", as in mjai logs.
+    Mjai,
+    /// Like "五萬", "赤五萬" and "東".
+    Kanji,
+    /// Like "五万", "赤五万" and "东".
+    Chinese,
+}
+
+/// The naming of yakus and pais, by default after the language of the report.
+#[derive(Debug, Clone, Copy)]
+pub struct Terms {
+    pub yaku: YakuNames,
+    pub pai: PaiNames,
+}
+
+/// Report strings in Japanese, English and Chinese, in that order.
+///
+/// `{name}` in a message is replaced with the argument `name` passed to `t`.
+const MESSAGES: &[(&str, [&str; 3])] = &[
+    ("title", ["牌譜検討", "Replay Examination", "牌谱复盘"]),
+    (
+        "defense_note",
+        [
+            "押し引き検討：リーチまたは明らかなテンパイの副露者に対する選択のみを検討しています。",
+            "Defense review: only decisions made while facing a riichi or an obviously tenpai open hand are reviewed.",
+            "押退复盘：仅复盘面对立直或明显听牌的副露者时的选择。",
+        ],
+    ),
+    ("mistakes", ["悪手：", "Mistakes: ", "恶手："]),
+    ("separator", ["、", ", ", "、"]),
+    ("blunder_count", ["大悪手 {n}", "{n} blunder(s)", "大恶手 {n}"]),
+    ("significant_count", ["悪手 {n}", "{n} significant", "恶手 {n}"]),
+    ("minor_count", ["疑問手 {n}", "{n} minor", "疑问手 {n}"]),
+    ("blunder", ["大悪手", "blunder", "大恶手"]),
+    ("significant", ["悪手", "significant", "恶手"]),
+    ("minor", ["疑問手", "minor", "疑问手"]),
+    ("by_category", ["分類：", "By category: ", "分类："]),
+    (
+        "by_decision_type",
+        ["判断別一致率：", "Agreement by decision: ", "按判断一致率："],
+    ),
+    ("decision_discard", ["打牌", "discard", "打牌"]),
+    ("decision_call", ["鳴き", "call", "鸣牌"]),
+    ("decision_riichi", ["リーチ", "riichi", "立直"]),
+    ("decision_kan", ["カン", "kan", "杠"]),
+    ("efficiency", ["牌効率", "efficiency", "牌效"]),
+    ("push_fold", ["押し引き", "push/fold", "押退"]),
+    ("riichi_judgment", ["リーチ判断", "riichi judgment", "立直判断"]),
+    ("call_judgment", ["鳴き判断", "call judgment", "鸣牌判断"]),
+    ("yaku_selection", ["役・打点", "yaku selection", "役种选择"]),
+    ("kan_judgment", ["カン判断", "kan judgment", "杠判断"]),
+    ("missed_call", ["鳴き逃し", "missed call", "漏鸣"]),
+    (
+        "placement_trend",
+        ["期待順位の推移", "Expected Placement", "期望顺位走势"],
+    ),
+    (
+        "ryukyoku_decisions",
+        ["流局間際の押し引き", "Decisions Near Ryuukyoku", "流局前的押退"],
+    ),
+    (
+        "ryukyoku_note",
+        [
+            "残り 8 枚以下で聴牌まで 1 向聴以内の打牌。最も向聴数の低い打牌を押し、それ以外を降りとしています。聴牌料で順位が変わりうる点差（4000 点以内）は太字です。",
+            "Discards with 8 tiles or fewer left while at most 1-shanten. A discard that keeps the lowest shanten is a push, otherwise a fold. Gaps a tenpai payment can close, within 4000, are in bold.",
+            "剩余 8 张以下且距听牌 1 向听以内的打牌。保持最低向听数的打牌为押，否则为弃和。听牌罚符可改变顺位的点差（4000 点以内）以粗体表示。",
+        ],
+    ),
+    (
+        "deal_in_post_mortems",
+        ["放銃の振り返り", "Deal-in Post-mortems", "放铳复盘"],
+    ),
+    (
+        "post_mortem_note",
+        [
+            "放銃した局について、リーチや仕掛けが入ってからの打牌を振り返ります。太字の巡目は akochan がより安全な牌で降りた最初の巡目で、そこから放銃までの EV 損失を合計しています。安全な牌の放銃率は akochan の推定です。",
+            "Discards of each kyoku you dealt in, from the first one under threat. The turn in bold is the first where akochan folds with a safer tile, and the EV losses from there to the deal-in are summed up. Deal-in probabilities of the safest tiles are akochan's estimates.",
+            "放铳的各局中，从受到威胁起的打牌。粗体的巡目是 akochan 以更安全的牌弃和的第一巡，并合计从该巡到放铳的 EV 损失。安全牌的放铳率为 akochan 的估计。",
+        ],
+    ),
+    ("dealt_in_to", ["放銃：", "Dealt in to", "放铳给"]),
+    ("fold_from", ["{n} 巡目から降りるべき", "Should fold from turn {n}", "应从第 {n} 巡弃和"]),
+    ("push_ev_loss", ["押し続けた EV 損失", "EV lost by pushing", "继续押的 EV 损失"]),
+    (
+        "no_clear_fold",
+        ["明らかに降りるべき巡目なし", "No turn where folding was clearly better", "没有明显应该弃和的巡目"],
+    ),
+    ("safest_tiles", ["安全な牌", "Safest tiles", "安全牌"]),
+    ("score_audit", ["点数の検算", "Score Audit", "点数核对"]),
+    (
+        "score_audit_note",
+        [
+            "和了と流局の点数移動を本場、供託、聴牌料を含めて牌譜の進行から計算し直し、牌譜に記録されたものと食い違う局を挙げています。変換の誤りや改ざんされた牌譜の可能性があります。",
+            "Score changes of the horas and ryuukyokus recalculated from the events, honba, kyotaku and tenpai payments included, where they differ from the ones recorded in the log. The log may have been converted wrongly or tampered with.",
+            "根据牌谱进程重新计算和了与流局的点数变动（含本场、供托与听牌罚符），列出与牌谱记录不符的局。牌谱可能转换有误或被篡改。",
+        ],
+    ),
+    ("result", ["結果", "Result", "结果"]),
+    ("recalculated_deltas", ["計算上の点数移動", "Recalculated", "计算的点数变动"]),
+    ("recorded_deltas", ["記録された点数移動", "Recorded", "记录的点数变动"]),
+    (
+        "oorasu_focus",
+        ["オーラスの順位条件", "Final Kyoku Rank Targets", "尾局顺位条件"],
+    ),
+    (
+        "oorasu_note",
+        [
+            "最終局を最終順位期待値で再検討した結果です（低いほど良い）。和了の打点は順位が入れ替わる最小のもので、本場と供託を含めて計算しています。",
+            "The final kyoku reviewed again with placement EV, the expected final placement, where lower is better. Agari values are the smallest that swap the order, counting honba and riichi sticks.",
+            "以最终顺位期望值重新检讨尾局的结果（越低越好）。和了打点为可交换顺位的最小打点，已计入本场与供托。",
+        ],
+    ),
+    (
+        "rank_target_overtake",
+        [
+            "{rival}を逆転：直撃 {direct}、他家から {other}、ツモ {tsumo}",
+            "To overtake {rival}: {direct}+ direct, {other}+ from others or {tsumo}+ tsumo",
+            "逆转{rival}：直击 {direct}、他家放铳 {other}、自摸 {tsumo}",
+        ],
+    ),
+    (
+        "rank_target_defend",
+        [
+            "{rival}の逆転条件：自分から直撃 {direct}、他家から {other}、ツモ {tsumo}",
+            "{rival} overtakes you with {direct}+ direct from you, {other}+ from others or {tsumo}+ tsumo",
+            "{rival}的逆转条件：直击自家 {direct}、他家放铳 {other}、自摸 {tsumo}",
+        ],
+    ),
+    ("tsumo_all", ["{n} オール", "{n} all", "{n} all"]),
+    ("out_of_reach", ["不可", "out of reach", "不可能"]),
+    ("expected_placement", ["平均順位", "Exp. placement", "平均顺位"]),
+    ("rank_target", ["順位条件", "Rank target", "顺位条件"]),
+    ("gap_above_below", ["上 / 下との点差", "Gap above / below", "与上 / 下家点差"]),
+    ("draw_result", ["流局時の収支", "At ryuukyoku", "流局收支"]),
+    ("push", ["押し", "push", "押"]),
+    ("fold", ["降り", "fold", "弃和"]),
+    ("verdict", ["判定", "Verdict", "判定"]),
+    ("correct", ["正解", "correct", "正确"]),
+    ("should_push", ["押すべき", "should push", "应该押"]),
+    ("should_fold", ["降りるべき", "should fold", "应该弃和"]),
+    ("mistake_heatmap", ["悪手ヒートマップ", "Mistake Heatmap", "恶手热力图"]),
+    ("pt_trend", ["期待 pt の推移", "Expected pt", "期望 pt 走势"]),
+    (
+        "placement_loss",
+        [
+            "悪手による期待順位の損失は約 {x} 位。",
+            "Flagged mistakes cost about {x} placements this game.",
+            "恶手造成的期望顺位损失约为 {x} 位。",
+        ],
+    ),
+    (
+        "pt_loss",
+        [
+            "悪手による期待 pt の損失は約 {x} pt。",
+            "Flagged mistakes cost about {x} pt this game.",
+            "恶手造成的期望 pt 损失约为 {x} pt。",
+        ],
+    ),
+    ("at_turn", ["{kyoku} {n} 巡", "{kyoku}, turn {n}", "{kyoku} 第 {n} 巡"]),
+    ("game_summary", ["目次", "Game Summary", "目录"]),
+    ("kan_report", ["槓の検討", "Kan Report", "杠的分析"]),
+    (
+        "second_review_report",
+        ["比較検討との相違", "Disagreements with the Second Review", "与对比检讨的分歧"],
+    ),
+    (
+        "second_review_note",
+        [
+            "二つの検討で最善手が分かれた局面です。どちらが正しいとも言い切れないため、研究する価値があります。",
+            "Decisions where the two reviews prefer different moves. Neither is clearly right, which makes them worth studying.",
+            "两次检讨的最佳选择不同的局面。哪一方都不一定正确，值得研究。",
+        ],
+    ),
+    ("second_review", ["比較", "Second", "对比"]),
+    ("rating", ["評価：", "Rating:", "评分："]),
+    ("kyoku", ["局", "Kyoku", "局"]),
+    ("hanchan", ["半荘", "hanchan", "半庄"]),
+    ("tonpuu", ["東風", "tonpuu", "东风"]),
+    ("turn", ["巡目", "Turn", "巡目"]),
+    ("turn_n", ["{n} 巡", "Turn {n}", "第 {n} 巡"]),
+    ("kan", ["槓", "Kan", "杠"]),
+    ("taken", ["実行", "Taken", "执行"]),
+    ("ev_with_kan", ["槓の期待値", "EV with kan", "杠的期望值"]),
+    ("ev_without_kan", ["槓以外の期待値", "EV without kan", "不杠的期望值"]),
+    ("difference", ["差", "Difference", "差值"]),
+    ("tiles_left", ["残り枚数", "Tiles left", "剩余牌数"]),
+    ("dora_indicators", ["ドラ表示牌", "Dora indicators", "宝牌指示牌"]),
+    ("ura_dora_indicators", ["裏ドラ表示牌", "Ura dora indicators", "里宝牌指示牌"]),
+    ("dora_in_hand", ["ドラ {n}", "Dora {n}", "宝牌 {n}"]),
+    ("aka_dora_n", ["赤ドラ {n}", "Red {n}", "赤宝牌 {n}"]),
+    ("threats", ["脅威", "Threats", "威胁"]),
+    ("ankan", ["暗槓", "Closed", "暗杠"]),
+    ("kakan", ["加槓", "Added", "加杠"]),
+    ("daiminkan", ["大明槓", "Open", "大明杠"]),
+    ("yakuman", ["役満", "Yakuman", "役满"]),
+    ("fu_han", ["{fu}符{han}飜", "{fu} fu {han} han", "{fu}符{han}番"]),
+    (
+        "recorded_score",
+        [
+            "（記録：{n}、積み棒・供託込み）",
+            "(recorded {n}, including sticks)",
+            "（记录：{n}，含本场棒和供托）",
+        ],
+    ),
+    ("yaku_han", ["{yaku}（{han}飜）", "{yaku} ({han} han)", "{yaku}（{han}番）"]),
+    (
+        "no_yaku",
+        [
+            "記録された和了に役が見つからない",
+            "No valid yaku found for the recorded hora",
+            "记录的和了中找不到役",
+        ],
+    ),
+    ("replay_viewer", ["牌譜ビューア", "Replay Viewer", "牌谱查看器"]),
+    ("open_in_new_tab", ["新しいタブで開く", "open in new tab", "在新标签页中打开"]),
+    ("board_replay", ["盤面再生", "Board Replay", "牌局回放"]),
+    ("show_on_board", ["盤面で見る", "Show on board", "在牌局回放中查看"]),
+    (
+        "hide_agreed",
+        ["一致した判断を隠す", "Hide agreed decisions", "隐藏一致的决策"],
+    ),
+    (
+        "key_help",
+        [
+            "j / k：次 / 前の判断、n / p：次 / 前の悪手、o：開閉、h：一致を隠す",
+            "j / k: next / previous decision, n / p: next / previous mistake, o: open or close, h: hide agreed",
+            "j / k：下一个 / 上一个决策，n / p：下一个 / 上一个失误，o：展开或收起，h：隐藏一致",
+        ],
+    ),
+    (
+        "open_on_tenhou",
+        [
+            "天鳳の牌譜で開く（{n}巡目）",
+            "Open on Tenhou (turn {n})",
+            "在天凤牌谱中打开（第{n}巡）",
+        ],
+    ),
+    (
+        "acceptable_alternative",
+        ["許容範囲の別解", "acceptable alternative", "可接受的其他选择"],
+    ),
+    (
+        "equivalent_move",
+        ["どちらでも可", "either is fine", "两者皆可"],
+    ),
+    ("configs_differ", ["設定間で相違", "Configs differ", "设置间存在分歧"]),
+    ("furiten", ["フリテン", "Furiten", "振听"]),
+    (
+        "expected_decision",
+        ["akochan の最善手：", "akochan's decision:", "akochan 的最佳选择："],
+    ),
+    ("actual_decision", ["自家：", "Your decision:", "自家："]),
+    (
+        "shallow_decision",
+        [
+            "検証前の最善手：",
+            "Decision before verification:",
+            "验证前的最佳选择：",
+        ],
+    ),
+    (
+        "second_decision",
+        [
+            "比較設定の最善手：",
+            "Second config's decision:",
+            "对比设置的最佳选择：",
+        ],
+    ),
+    ("deal_in_prob", ["放銃率：", "Deal-in probability:", "放铳率："]),
+    (
+        "rollout_result",
+        [
+            "局終了までの点数収支（{n} 回のシミュレーション）：",
+            "Score change to the end of the kyoku ({n} rollouts):",
+            "到本局结束的点数收支（{n} 次模拟）：",
+        ],
+    ),
+    ("rollout_cost", ["損失", "Cost", "损失"]),
+    ("average", ["平均", "avg", "平均"]),
+    ("deal_in_legend", ["放銃率", "Deal-in probability", "放铳率"]),
+    ("you", ["自家", "you", "自家"]),
+    (
+        "actual_furiten",
+        [
+            "自家の選択はフリテンの待ちになる。",
+            "Your decision leaves a furiten wait.",
+            "自家的选择会形成振听的听牌。",
+        ],
+    ),
+    (
+        "expected_furiten",
+        [
+            "akochan はフリテンの待ちをあえて選ぶ。",
+            "akochan's decision takes a furiten wait on purpose.",
+            "akochan 有意选择振听的听牌。",
+        ],
+    ),
+    ("th_placement_ev", ["最終順位期待値", "Placement EV", "最终顺位期望值"]),
+    ("th_pt_ev", ["pt 期待値", "pt EV", "pt 期望值"]),
+    ("th_deal_in", ["放銃率 (%)", "Deal-in (%)", "放铳率 (%)"]),
+    (
+        "th_deal_in_loss",
+        ["放銃時の損失", "Loss on Deal-in", "放铳时的损失"],
+    ),
+    (
+        "th_post_deal_in_placement_ev",
+        [
+            "放銃後の最終順位期待値",
+            "Post-Deal-in Placement EV",
+            "放铳后的最终顺位期望值",
+        ],
+    ),
+    (
+        "th_post_deal_in_pt_ev",
+        ["放銃後の pt 期待値", "Post-Deal-in pt EV", "放铳后的 pt 期望值"],
+    ),
+    (
+        "th_passed_placement_ev",
+        [
+            "通った後の最終順位期待値",
+            "Tile Passes Placement EV",
+            "通过后的最终顺位期望值",
+        ],
+    ),
+    (
+        "th_passed_pt_ev",
+        ["通った後の pt 期待値", "Tile Passes pt EV", "通过后的 pt 期望值"],
+    ),
+    ("th_ev_gap", ["最善との差", "Gap to Best", "与最佳之差"]),
+    ("runner_up_gap", ["次善との差", "Runner-up gap", "次优差距"]),
+    ("th_rank_probs", ["順位率", "Placement Odds", "顺位率"]),
+    ("rank_1", ["1位", "1st", "1位"]),
+    ("rank_2", ["2位", "2nd", "2位"]),
+    ("rank_3", ["3位", "3rd", "3位"]),
+    ("rank_4", ["4位", "4th", "4位"]),
+    ("th_safety", ["安全度", "Safety", "安全度"]),
+    ("genbutsu", ["現物", "Genbutsu", "现物"]),
+    (
+        "reveal_hands",
+        ["他家の手牌を表示", "Reveal opponents' hands", "显示他家手牌"],
+    ),
+    ("suji", ["筋", "Suji", "筋"]),
+    ("no_chance", ["ノーチャンス", "No-chance", "壁"]),
+    ("one_chance", ["ワンチャンス", "One-chance", "单壁"]),
+    ("live", ["無筋", "Live", "无筋"]),
+    ("discards", ["河", "Discards", "牌河"]),
+    ("riichi", ["リーチ", "Riichi", "立直"]),
+    ("pass", ["スルー", "Pass", "跳过"]),
+    ("discard", ["打", "Discard", "打"]),
+    ("tsumo", ["ツモ", "Tsumo", "自摸"]),
+    ("ron", ["ロン", "Ron", "荣和"]),
+    ("chi_cut", ["チー打", "Chii, cut", "吃打"]),
+    ("pon_cut", ["ポン打", "Pon, cut", "碰打"]),
+    ("ryukyoku", ["流局", "Ryuukyoku", "流局"]),
+    ("nagashimangan", ["流し満貫", "Nagashi mangan", "流局满贯"]),
+    ("kyushukyuhai", ["九種九牌", "Nine terminals", "九种九牌"]),
+    ("suuchariichi", ["四家立直", "Four riichi", "四家立直"]),
+    ("suukaikan", ["四槓散了", "Four kans", "四杠散了"]),
+    ("suufonrenda", ["四風連打", "Four winds", "四风连打"]),
+    ("sanchaho", ["三家和了", "Triple ron", "三家和了"]),
+    ("tenpai", ["聴牌", "Tenpai", "听牌"]),
+    ("shanten_n", ["{n}向聴", "{n}-shanten", "{n}向听"]),
+    ("ukeire_count", ["、受け入れ {n} 枚", ", {n} tiles", "，进张 {n} 枚"]),
+    ("paren_open", ["（", " (", "（"]),
+    ("paren_close", ["）", ")", "）"]),
+    ("shanten", ["向聴数", "Shanten", "向听数"]),
+    ("shimocha", ["下家", "Shimocha", "下家"]),
+    ("toimen", ["対面", "Toimen", "对家"]),
+    ("kamicha", ["上家", "Kamicha", "上家"]),
+    ("self", ["自家", "Self", "自家"]),
+    // the trailing spaces are NBSP so that they survive whitespace control
+    ("tsumo_by", ["ツモ：", "Tsumo by\u{a0}", "自摸："]),
+    ("ron_by", ["ロン：", "Ron by\u{a0}", "荣和："]),
+    ("draw", ["ツモ", "Draw:", "摸牌"]),
+    ("kan_by", ["カン ", " Kan ", "杠 "]),
+    ("cut_by", ["打 ", " Cut ", "打 "]),
+    ("session_title", ["対局まとめ", "Session Report", "对局总结"]),
+    ("game_n", ["第 {n} 戦", "Game {n}", "第 {n} 局"]),
+    ("games", ["対局", "Games", "对局"]),
+    ("cumulative_pt", ["累計 pt", "Cumulative pt", "累计 pt"]),
+    ("placement", ["順位", "Placement", "顺位"]),
+    ("final_score", ["終局点数", "Final score", "终局点数"]),
+    ("agree_rate", ["一致率", "Agree rate", "一致率"]),
+    ("ev_loss", ["EV 損失", "EV loss", "EV 损失"]),
+    ("review_score", ["評価", "Score", "评分"]),
+    ("mistake_list", ["悪手一覧", "Mistakes", "恶手一览"]),
+    (
+        "screened_note",
+        [
+            "疑わしい選択がないため検討していません（--full で検討します）。",
+            "Not reviewed, as no decision looked suboptimal (use --full to review it).",
+            "没有可疑的选择，因此未检讨（使用 --full 检讨）。",
+        ],
+    ),
+    ("index_title", ["検討一覧", "Reviews", "检讨一览"]),
+    ("log_file", ["牌譜", "Log", "牌谱"]),
+    ("date", ["日付", "Date", "日期"]),
+    ("room", ["部屋", "Room", "房间"]),
+    ("lobby", ["ロビー", "Lobby", "大厅"]),
+    ("dan", ["段位", "Rank", "段位"]),
+    ("rate", ["レート", "Rate", "R 值"]),
+    (
+        "skipped_kyokus",
+        ["壊れていて検討から外した局：", "Malformed kyokus left out: ", "因损坏未检讨的局："],
+    ),
+    (
+        "skeleton_note",
+        [
+            "--no-review で akochan を使わずに作った下書きです。各局の手牌と結果のみで、検討は含まれていません。",
+            "A skeleton made with --no-review, without akochan. It only has the hands and results of each kyoku, with no review.",
+            "使用 --no-review、未经 akochan 生成的草稿，仅包含各局的手牌与结果，没有检讨。",
+        ],
+    ),
+    ("kyoku_at", ["{n} 番目の局", "kyoku #{n}", "第 {n} 个局"]),
+    ("comparison_title", ["全員の比較", "Player Comparison", "全员比较"]),
+    (
+        "comparison_note",
+        [
+            "各プレイヤーを自分の席から検討した結果です。一致率の高い順に並べ、EV 損失の横の # はその少ない順の順位です。",
+            "Each player is reviewed from their own seat. Players are ranked by agree rate, and the # next to the EV loss is their rank by the lowest EV loss.",
+            "每位玩家都从自己的座位检讨。按一致率从高到低排列，EV 损失旁的 # 是其从少到多的排名。",
+        ],
+    ),
+    ("player", ["プレイヤー", "Player", "玩家"]),
+    ("rating_column", ["評価", "Rating", "评分"]),
+    ("th_mistakes", ["悪手", "Mistakes", "恶手"]),
+    ("anki_question", ["あなたならどうする？", "What would you do?", "你会怎么打？"]),
+    ("intent_no_yaku_tenpai", ["役なしテンパイ", "tenpai with no yaku", "无役听牌"]),
+    ("intent_no_yaku", ["役の見込みなし", "no yaku in sight", "看不到役"]),
+    ("intent_open", ["複数の役を残す", "keeps several yakus open", "保留多个役"]),
+    ("intent_live", ["{yakus}を残す", "keeps {yakus} live", "保留{yakus}"]),
+    ("intent_average", ["、平均 ~{points}", ", ~{points} average", "，平均 ~{points}"]),
+    ("intent_tenpai", ["（テンパイ）", " (tenpai)", "（听牌）"]),
+    (
+        "intent_actual",
+        ["；実際の打牌は平均 ~{points}", "; the actual move ~{points} average", "；实际打法平均 ~{points}"],
+    ),
+];
+
+static CATALOG: Lazy<HashMap<&str, [&str; 3]>> = Lazy::new(|| MESSAGES.iter().copied().collect());
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ja" => Ok(Self::Japanese),
+            "en" => Ok(Self::English),
+            "zh" => Ok(Self::Chinese),
+            _ => Err(anyhow!("unsupported language {}", s)),
+        }
+    }
+}
+
+impl FromStr for YakuNames {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kanji" => Ok(Self::Kanji),
+            "romaji" => Ok(Self::Romaji),
+            "english" => Ok(Self::English),
+            "chinese" => Ok(Self::Chinese),
+            _ => Err(anyhow!("unknown yaku names {:?}", s)),
+        }
+    }
+}
+
+impl FromStr for PaiNames {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mjai" => Ok(Self::Mjai),
+            "kanji" => Ok(Self::Kanji),
+            "chinese" => Ok(Self::Chinese),
+            _ => Err(anyhow!("unknown pai names {:?}", s)),
+        }
+    }
+}
+
+impl From<Language> for Terms {
+    fn from(lang: Language) -> Self {
+        match lang {
+            Language::Japanese => Self {
+                yaku: YakuNames::Kanji,
+                pai: PaiNames::Kanji,
+            },
+            Language::English => Self {
+                yaku: YakuNames::Romaji,
+                pai: PaiNames::Mjai,
+            },
+            Language::Chinese => Self {
+                yaku: YakuNames::Chinese,
+                pai: PaiNames::Chinese,
+            },
+        }
+    }
+}
+
+impl Language {
+    #[inline]
+    const fn index(self) -> usize {
+        match self {
+            Self::Japanese => 0,
+            Self::English => 1,
+            Self::Chinese => 2,
+        }
+    }
+}
+
+/// Registers the functions for localized strings:
+///
+/// - `t(key, ...)`: the message `key`, with placeholders filled by the other
+///   arguments.
+/// - `kyoku_name(kyoku, honba)`: like "東一局 1 本場".
+/// - `yaku_name(yaku)`: the name of a yaku in `terms.yaku`.
+/// - `pai_name(pai)`: a pai in mjai notation written in `terms.pai`.
+pub fn register(tera: &mut Tera, lang: Language, terms: Terms) {
+    tera.register_function("t", move |args: &HashMap<String, Value>| {
+        let key = args
+            .get("key")
+            .and_then(|k| k.as_str())
+            .ok_or("missing key")?;
+        let message = CATALOG
+            .get(key)
+            .ok_or_else(|| format!("unknown message key {:?}", key))?[lang.index()];
+
+        let mut s = message.to_owned();
+        for (name, value) in args.iter().filter(|(name, _)| *name != "key") {
+            let value = match value {
+                Value::String(v) => v.clone(),
+                v => v.to_string(),
+            };
+            s = s.replace(&format!("{{{}}}", name), &value);
+        }
+        Ok(Value::String(s))
+    });
+
+    tera.register_function("kyoku_name", move |args: &HashMap<String, Value>| {
+        let kyoku = args.get("kyoku").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
+        let honba = args.get("honba").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
+        Ok(Value::String(kyoku_name(lang, kyoku, honba)))
+    });
+
+    tera.register_function("yaku_name", move |args: &HashMap<String, Value>| {
+        let yaku: Yaku = json::from_value(args.get("yaku").cloned().unwrap_or_default())?;
+        Ok(Value::String(yaku_name(terms.yaku, yaku).to_owned()))
+    });
+
+    tera.register_function("pai_name", move |args: &HashMap<String, Value>| {
+        let pai = args
+            .get("pai")
+            .and_then(|p| p.as_str())
+            .ok_or("missing pai")?;
+        // unknown pais are kept as they are
+        let name = match pai.parse() {
+            Ok(pai) => pai_name(terms.pai, pai),
+            Err(_) => pai.to_owned(),
+        };
+        Ok(Value::String(name))
+    });
+}
+
+/// The message `key` in `lang`, with `{name}` replaced by the value of each
+/// of `args`, as `t` does in templates. `key` must be in the catalog.
+pub fn message(lang: Language, key: &str, args: &[(&str, &str)]) -> String {
+    let mut s = CATALOG[key][lang.index()].to_owned();
+    for (name, value) in args {
+        s = s.replace(&format!("{{{}}}", name), value);
+    }
+    s
+}
+
+/// Names `yaku` as in `names`.
+pub fn yaku_name(names: YakuNames, yaku: Yaku) -> &'static str {
+    match names {
+        YakuNames::Kanji => yaku.name_ja(),
+        YakuNames::Romaji => yaku.name_romaji(),
+        YakuNames::English => yaku.name_en(),
+        YakuNames::Chinese => yaku.name_zh(),
+    }
+}
+
+/// Writes `pai` in the notation of `names`.
+pub fn pai_name(names: PaiNames, pai: Pai) -> String {
+    const NUM_KANJI: &[&str] = &["一", "二", "三", "四", "五", "六", "七", "八", "九"];
+    const SUITS_KANJI: &[&str] = &["萬", "筒", "索"];
+    const SUITS_HANZI: &[&str] = &["万", "筒", "条"];
+    const JIHAI_KANJI: &[&str] = &["東", "南", "西", "北", "白", "發", "中"];
+    const JIHAI_HANZI: &[&str] = &["东", "南", "西", "北", "白", "发", "中"];
+
+    let (suits, jihai) = match names {
+        PaiNames::Mjai => return pai.to_string(),
+        PaiNames::Kanji => (SUITS_KANJI, JIHAI_KANJI),
+        PaiNames::Chinese => (SUITS_HANZI, JIHAI_HANZI),
+    };
+    if pai == Pai::Unknown {
+        return pai.to_string();
+    }
+
+    let id = pai.deaka().as_usize();
+    let (kind, num) = (id / 10, id % 10);
+    let name = if kind == 4 {
+        jihai[num - 1].to_owned()
+    } else {
+        format!("{}{}", NUM_KANJI[num - 1], suits[kind - 1])
+    };
+    if pai.is_aka() {
+        format!("赤{}", name)
+    } else {
+        name
+    }
+}
+
+fn kyoku_name(lang: Language, kyoku: usize, honba: usize) -> String {
+    const BAKAZE_KANJI: &[&str] = &["東", "南", "西", "北"];
+    const BAKAZE_HANZI: &[&str] = &["东", "南", "西", "北"];
+    const BAKAZE_ENG: &[&str] = &["East", "South", "West", "North"];
+    const NUM_KANJI: &[&str] = &["一", "二", "三", "四"];
+
+    let (bakaze, num) = (kyoku / 4, kyoku % 4);
+    match (lang, honba) {
+        (Language::Japanese, 0) => format!("{}{}局", BAKAZE_KANJI[bakaze], NUM_KANJI[num]),
+        (Language::Japanese, _) => format!(
+            "{}{}局 {} 本場",
+            BAKAZE_KANJI[bakaze], NUM_KANJI[num], honba,
+        ),
+        (Language::Chinese, 0) => format!("{}{}局", BAKAZE_HANZI[bakaze], NUM_KANJI[num]),
+        (Language::Chinese, _) => format!(
+            "{}{}局 {} 本场",
+            BAKAZE_HANZI[bakaze], NUM_KANJI[num], honba,
+        ),
+        (Language::English, 0) => format!("{} {}", BAKAZE_ENG[bakaze], num + 1),
+        (Language::English, _) => format!("{} {}-{}", BAKAZE_ENG[bakaze], num + 1, honba),
+    }
+}
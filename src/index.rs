@@ -0,0 +1,90 @@
+use crate::i18n::Language;
+use crate::render;
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+const INDEX_JSON: &str = "index.json";
+const INDEX_HTML: &str = "index.html";
+
+/// The reviews of batch runs and `--watch` in an output directory, kept as
+/// index.json there and rendered as index.html next to it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReviewIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Log ID, or name of the log file.
+    pub source: String,
+    /// Path of the report, relative to the index.
+    pub report: String,
+    /// Date of the game, or of the review when the log does not tell, in
+    /// YYYY-MM-DD.
+    pub date: String,
+    pub player: Option<String>,
+    pub actor: u8,
+    pub summary: SummaryJson,
+}
+
+/// The subset of the `--summary-json` line that is shown in the index.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummaryJson {
+    pub log_id: Option<String>,
+    pub placement: usize,
+    pub total_reviewed: usize,
+    pub total_tolerated: usize,
+    pub total_problems: usize,
+    pub agree_rate: f64,
+    pub total_ev_loss: f64,
+    // NaN is serialized as null
+    pub score: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct IndexView<'a> {
+    entries: Vec<&'a IndexEntry>,
+    lang: Language,
+}
+
+impl ReviewIndex {
+    /// Loads the index in `dir`, or an empty one if there is none yet.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(INDEX_JSON);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let body =
+            fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+        json::from_str(&body).with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    pub fn contains(&self, source: &str) -> bool {
+        self.entries.iter().any(|e| e.source == source)
+    }
+
+    /// Adds `entry`, replacing the one with the same report, if any.
+    pub fn push(&mut self, entry: IndexEntry) {
+        self.entries.retain(|e| e.report != entry.report);
+        self.entries.push(entry);
+    }
+
+    /// Writes index.json and index.html into `dir`. The HTML lists the
+    /// newest games first.
+    pub fn save(&self, dir: &Path, lang: Language) -> Result<()> {
+        let path = dir.join(INDEX_JSON);
+        let body = json::to_string_pretty(self).context("failed to serialize index")?;
+        fs::write(&path, body).with_context(|| format!("failed to write {:?}", path))?;
+
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|a, b| b.date.cmp(&a.date));
+        let path = dir.join(INDEX_HTML);
+        let mut out =
+            File::create(&path).with_context(|| format!("failed to create {:?}", path))?;
+        render::render_template("index.html", &IndexView { entries, lang }, lang, &mut out)
+    }
+}
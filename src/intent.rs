@@ -0,0 +1,265 @@
+use crate::i18n::{self, Language, Terms};
+use crate::scoring::{calculate, HoraContext, Yaku, YakuHan};
+use crate::shanten::{get_shanten, get_waits, tiles_from_pais, Tiles};
+use crate::state::{Fuuro, State};
+
+use convlog::Pai;
+
+/// Yakus kept by at least this share of the horas are said to be live.
+const LIVE_SHARE: f64 = 1. / 3.;
+
+/// What a dahai is building toward, from the horas it can reach in the next
+/// one or two draws.
+#[derive(Debug, Clone)]
+pub struct Intent {
+    /// Shanten after the dahai, 0 or 1.
+    pub shanten: i8,
+    /// Yakus of the reachable horas that are common enough, the most common
+    /// first. Dora are left out.
+    pub yakus: Vec<Yaku>,
+    /// Average points of the reachable horas that have a yaku, weighted by
+    /// the remaining pais, `None` if none of them has one.
+    pub average: Option<i32>,
+}
+
+/// Everything about the hand other than the tehai.
+pub struct IntentArgs<'a> {
+    pub state: &'a State,
+    pub actor: u8,
+    pub oya: u8,
+    pub bakaze: Pai,
+    /// Whether the dahai comes with a riichi.
+    pub reach: bool,
+    pub kuitan: bool,
+}
+
+/// Infers what discarding `pai` from the 3n+2 tehai in `args.state` aims
+/// at: the horas on its waits if it is tenpai, or on the waits of the best
+/// tenpai after each draw that gets it there if it is 1-shanten. Every hora
+/// is scored as a ron, and a menzen hand with no other yaku is assumed to
+/// riichi.
+///
+/// Returns `None` if `pai` is not in the tehai or the hand is still 2-shanten
+/// or worse after it, where the horas are too far away to tell.
+pub fn infer(pai: Pai, args: &IntentArgs<'_>) -> Option<Intent> {
+    let tehai = remove(args.state.tehai.view(), pai)?;
+    let fuuro_count = args.state.fuuros.len() as u8;
+    let shanten = get_shanten(&tiles_from_pais(&tehai), fuuro_count);
+
+    let horas = match shanten {
+        0 => horas_on_waits(&tehai, args, None, 1),
+        1 => horas_after_draws(&tehai, args),
+        _ => return None,
+    };
+
+    Some(summarize(shanten, &horas))
+}
+
+/// A reachable hora, `None` if it has no yaku, with its weight.
+type Hora = (Option<(Vec<Yaku>, i32)>, u32);
+
+fn horas_after_draws(tehai: &[Pai], args: &IntentArgs<'_>) -> Vec<Hora> {
+    let fuuro_count = args.state.fuuros.len() as u8;
+    let tiles = tiles_from_pais(tehai);
+
+    let mut horas = vec![];
+    for draw in (0..34).filter_map(Pai::from_index) {
+        let draw_idx = draw.as_index().unwrap_or_default();
+        let remaining = args.state.remaining(draw) as u32;
+        if remaining == 0 || tiles[draw_idx] >= 4 {
+            continue;
+        }
+
+        let mut drawn = tehai.to_vec();
+        drawn.push(draw);
+        let mut drawn_tiles = tiles;
+        drawn_tiles[draw_idx] += 1;
+        if get_shanten(&drawn_tiles, fuuro_count) > 0 {
+            continue;
+        }
+
+        // keep the tenpai with the most points to expect on its waits
+        let best = tenpai_dahais(&drawn_tiles, fuuro_count)
+            .filter_map(|dahai| remove(&drawn, dahai))
+            .map(|tenpai| horas_on_waits(&tenpai, args, Some(draw), remaining))
+            .max_by_key(|horas| {
+                horas
+                    .iter()
+                    .map(|(agari, weight)| {
+                        agari.as_ref().map_or(0, |(_, p)| *p) as i64 * *weight as i64
+                    })
+                    .sum::<i64>()
+            });
+        if let Some(best) = best {
+            horas.extend(best);
+        }
+    }
+
+    horas
+}
+
+/// Dahais from the 3n+2 `tiles` that leave a tenpai.
+fn tenpai_dahais(tiles: &Tiles, fuuro_count: u8) -> impl Iterator<Item = Pai> + '_ {
+    (0..34).filter_map(move |idx| {
+        if tiles[idx] == 0 {
+            return None;
+        }
+        let mut after = *tiles;
+        after[idx] -= 1;
+        if get_shanten(&after, fuuro_count) == 0 {
+            Pai::from_index(idx)
+        } else {
+            None
+        }
+    })
+}
+
+/// Scores a ron on each wait of the 3n+1 tenpai `tehai`, weighted by the
+/// remaining pais of the wait times `weight`. `drawn` is a pai assumed drawn
+/// already, so one less of it remains.
+fn horas_on_waits(
+    tehai: &[Pai],
+    args: &IntentArgs<'_>,
+    drawn: Option<Pai>,
+    weight: u32,
+) -> Vec<Hora> {
+    let state = args.state;
+    let fuuro_count = state.fuuros.len() as u8;
+    let is_menzen = state
+        .fuuros
+        .iter()
+        .all(|f| matches!(f, Fuuro::Ankan { .. }));
+    let is_reached = args.reach || state.reached[args.actor as usize];
+    let jikaze = match Pai::from_index(27 + ((args.actor + 4 - args.oya) % 4) as usize) {
+        Some(p) => p,
+        None => return vec![],
+    };
+
+    get_waits(&tiles_from_pais(tehai), fuuro_count)
+        .into_iter()
+        .filter_map(|wait| {
+            let remaining = state
+                .remaining(wait)
+                .saturating_sub(u8::from(drawn == Some(wait)));
+            if remaining == 0 {
+                return None;
+            }
+
+            let mut ctx = HoraContext {
+                win_pai: wait,
+                is_tsumo: false,
+                is_oya: args.actor == args.oya,
+                bakaze: args.bakaze,
+                jikaze,
+                dora_markers: state.dora_markers.clone(),
+                ura_markers: vec![],
+                situational: vec![],
+                kuitan: args.kuitan,
+            };
+            if is_reached {
+                ctx.situational.push(YakuHan::new(Yaku::Riichi, 1));
+            }
+            let mut hand = tehai.to_vec();
+            hand.push(wait);
+            let mut agari = calculate(&hand, &state.fuuros, &ctx, &[]);
+            if agari.is_none() && is_menzen && !is_reached {
+                ctx.situational.push(YakuHan::new(Yaku::Riichi, 1));
+                agari = calculate(&hand, &state.fuuros, &ctx, &[]);
+            }
+
+            let agari = agari.map(|a| {
+                let yakus = a
+                    .yakus
+                    .iter()
+                    .map(|y| y.yaku)
+                    .filter(|y| !matches!(y, Yaku::Dora | Yaku::AkaDora | Yaku::UraDora))
+                    .collect();
+                (yakus, a.points)
+            });
+            Some((agari, remaining as u32 * weight))
+        })
+        .collect()
+}
+
+fn summarize(shanten: i8, horas: &[Hora]) -> Intent {
+    let mut total = 0;
+    let mut points = 0;
+    let mut counts: Vec<(Yaku, u32)> = vec![];
+    for (agari, weight) in horas {
+        let (yakus, p) = match agari {
+            Some(a) => a,
+            None => continue,
+        };
+        total += weight;
+        points += *p as u64 * *weight as u64;
+        for &yaku in yakus {
+            match counts.iter_mut().find(|(y, _)| *y == yaku) {
+                Some((_, count)) => *count += weight,
+                None => counts.push((yaku, *weight)),
+            }
+        }
+    }
+
+    if total == 0 {
+        return Intent {
+            shanten,
+            yakus: vec![],
+            average: None,
+        };
+    }
+
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let yakus = counts
+        .into_iter()
+        .filter(|&(_, count)| count as f64 >= total as f64 * LIVE_SHARE)
+        .map(|(yaku, _)| yaku)
+        .collect();
+    // round to the nearest 100 like the points themselves
+    let average = ((points as f64 / total as f64 / 100.).round() * 100.) as i32;
+
+    Intent {
+        shanten,
+        yakus,
+        average: Some(average),
+    }
+}
+
+/// Removes one `pai` from `pais`. A red five may stand in for a plain one and
+/// the other way round, but an exact match is preferred.
+fn remove(pais: &[Pai], pai: Pai) -> Option<Vec<Pai>> {
+    let pos = pais
+        .iter()
+        .position(|&p| p == pai)
+        .or_else(|| pais.iter().position(|p| p.deaka() == pai.deaka()))?;
+    let mut ret = pais.to_vec();
+    ret.remove(pos);
+    Some(ret)
+}
+
+impl Intent {
+    /// Describes the intent in `lang`, with yakus named as in `terms`.
+    pub fn describe(&self, lang: Language, terms: Terms) -> String {
+        let average = match self.average {
+            Some(a) => a,
+            None if self.shanten == 0 => return i18n::message(lang, "intent_no_yaku_tenpai", &[]),
+            None => return i18n::message(lang, "intent_no_yaku", &[]),
+        };
+
+        let mut s = if self.yakus.is_empty() {
+            i18n::message(lang, "intent_open", &[])
+        } else {
+            let names: Vec<_> = self
+                .yakus
+                .iter()
+                .map(|&y| i18n::yaku_name(terms.yaku, y))
+                .collect();
+            let separator = i18n::message(lang, "separator", &[]);
+            i18n::message(lang, "intent_live", &[("yakus", &names.join(&separator))])
+        };
+        s += &i18n::message(lang, "intent_average", &[("points", &average.to_string())]);
+        if self.shanten == 0 {
+            s += &i18n::message(lang, "intent_tenpai", &[]);
+        }
+        s
+    }
+}
@@ -0,0 +1,310 @@
+use crate::board::BoardState;
+use crate::engine::{Akochan, EngineArgs};
+use crate::log;
+use crate::review::{fill_ev_gaps, DetailedAction};
+use crate::shanten::{get_waits, tiles_from_pais};
+use crate::state::{Fuuro, State};
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde::Serialize;
+use serde_json as json;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// No more kans can be declared once there are this many in the kyoku.
+const MAX_KANS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KanKind {
+    Ankan,
+    Kakan,
+    Daiminkan,
+}
+
+/// A chance for the target actor to kan, whether or not it was taken.
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct KanOpportunity {
+    pub junme: u8,
+    pub kind: KanKind,
+    #[serde_as(as = "DisplayFromStr")]
+    pub pai: Pai,
+    pub taken: bool,
+    /// EV of the kan from its own evaluation, see `KanEvaluator::evaluate`.
+    pub ev_with_kan: Option<f64>,
+    /// EV of the best action other than the kan.
+    pub ev_without_kan: Option<f64>,
+    /// Tiles left in the live wall before the kan.
+    pub tiles_left: u8,
+    /// Dora indicators revealed before the kan. Each kan reveals one more,
+    /// which benefits everyone including the threats.
+    pub dora_indicators: u8,
+    /// Actors that have reached or are obviously tenpai at this moment.
+    pub threats: Vec<u8>,
+}
+
+fn kan_of(event: &Event) -> Option<(KanKind, Pai)> {
+    match *event {
+        Event::Ankan { consumed, .. } => Some((KanKind::Ankan, consumed.as_array()[0].deaka())),
+        Event::Kakan { pai, .. } => Some((KanKind::Kakan, pai.deaka())),
+        Event::Daiminkan { pai, .. } => Some((KanKind::Daiminkan, pai.deaka())),
+        _ => None,
+    }
+}
+
+/// Every kan the target actor may declare right after `event`, from what
+/// `state` tracks of the target actor: ankan and kakan on its own tsumo,
+/// daiminkan on the dahai of others.
+pub fn legal_kans(
+    state: &State,
+    event: &Event,
+    target_actor: u8,
+    board: &BoardState,
+) -> Vec<Event> {
+    let kan_count: usize = (0..4)
+        .map(|actor| {
+            state
+                .fuuros_of(actor)
+                .iter()
+                .filter(|f| {
+                    matches!(
+                        f,
+                        Fuuro::Daiminkan { .. } | Fuuro::Kakan { .. } | Fuuro::Ankan { .. }
+                    )
+                })
+                .count()
+        })
+        .sum();
+    // no kan on the last draw or discard
+    if kan_count >= MAX_KANS || board.tiles_left == 0 {
+        return vec![];
+    }
+
+    let tehai = state.tehai.view();
+    let of_kind = |pai: Pai| -> Vec<Pai> {
+        tehai
+            .iter()
+            .copied()
+            .filter(|p| p.deaka() == pai.deaka())
+            .collect()
+    };
+
+    match *event {
+        Event::Tsumo { actor, pai } if actor == target_actor => {
+            let mut kans = vec![];
+            for kind in (0..34).filter_map(Pai::from_index) {
+                let pais = of_kind(kind);
+                if pais.len() == 4
+                    && (!state.reached[target_actor as usize]
+                        || keeps_waits_after_reach(state, pai, kind))
+                {
+                    kans.push(Event::Ankan {
+                        actor,
+                        consumed: [pais[0], pais[1], pais[2], pais[3]].into(),
+                    });
+                }
+            }
+            for fuuro in &state.fuuros {
+                if let Fuuro::Pon {
+                    pai: pon_pai,
+                    consumed,
+                    ..
+                } = *fuuro
+                {
+                    if let Some(&added) = of_kind(pon_pai).first() {
+                        let [a, b] = consumed.as_array();
+                        kans.push(Event::Kakan {
+                            actor,
+                            pai: added,
+                            consumed: [pon_pai, a, b].into(),
+                        });
+                    }
+                }
+            }
+            kans
+        }
+        Event::Dahai { actor, pai, .. }
+            if actor != target_actor && !state.reached[target_actor as usize] =>
+        {
+            let pais = of_kind(pai);
+            if pais.len() == 3 {
+                vec![Event::Daiminkan {
+                    actor: target_actor,
+                    target: actor,
+                    pai,
+                    consumed: [pais[0], pais[1], pais[2]].into(),
+                }]
+            } else {
+                vec![]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Whether an ankan of `kind` after reach keeps the waits, which is only
+/// allowed with the pai just drawn.
+fn keeps_waits_after_reach(state: &State, tsumo: Pai, kind: Pai) -> bool {
+    if tsumo.deaka() != kind.deaka() {
+        return false;
+    }
+    let fuuro_count = state.fuuros.len() as u8;
+    let tehai = state.tehai.view();
+
+    let mut before = tehai.to_vec();
+    if let Some(pos) = before.iter().position(|&p| p == tsumo) {
+        before.remove(pos);
+    }
+    let after: Vec<_> = tehai
+        .iter()
+        .copied()
+        .filter(|p| p.deaka() != kind.deaka())
+        .collect();
+
+    get_waits(&tiles_from_pais(&before), fuuro_count)
+        == get_waits(&tiles_from_pais(&after), fuuro_count + 1)
+}
+
+/// Evaluates kans with an akochan of their own, instead of relying on the
+/// kans akochan happens to list among its candidates. Only with
+/// `--kan-report`.
+pub struct KanEvaluator<'a> {
+    pub engine_args: EngineArgs<'a>,
+    /// akochan of the current kyoku, which replays it for every rinshan
+    /// tsumo.
+    pub engine: Option<Akochan<'a>>,
+}
+
+impl<'a> KanEvaluator<'a> {
+    /// EV of declaring `kan` right after `events[i]`, which is the EV of the
+    /// best action on the rinshan tsumo averaged over the pais that may be
+    /// drawn there, weighted by how many of each are left to the target
+    /// actor. `None` if akochan gives no EV for any of them.
+    ///
+    /// akochan cannot take back a move, so the kyoku beginning at
+    /// `kyoku_start` is replayed once for each pai, and akochan reviews every
+    /// decision of it again each time. The dora indicator the kan reveals is
+    /// unknown and left out.
+    pub fn evaluate(
+        &mut self,
+        events: &[Event],
+        kyoku_start: usize,
+        i: usize,
+        kan: &Event,
+        state: &State,
+    ) -> Result<Option<f64>> {
+        let mut weighted_ev = 0.;
+        let mut total = 0;
+
+        for pai in (0..34).filter_map(Pai::from_index) {
+            let remaining = state.remaining(pai);
+            if remaining == 0 {
+                continue;
+            }
+
+            let line = match self.review_rinshan(events, kyoku_start, i, kan, pai) {
+                Ok(line) => line,
+                Err(err) => {
+                    // start over in the next evaluation
+                    self.engine = None;
+                    return Err(err);
+                }
+            };
+            let mut actions: Vec<DetailedAction> =
+                json::from_str(&line).context("failed to parse JSON output of akochan")?;
+            fill_ev_gaps(&mut actions);
+            let ev = match actions.first().and_then(|a| a.review.pt_exp_total) {
+                Some(ev) => ev,
+                None => return Ok(None),
+            };
+            weighted_ev += ev * remaining as f64;
+            total += remaining as u32;
+        }
+
+        Ok(if total > 0 {
+            Some(weighted_ev / total as f64)
+        } else {
+            None
+        })
+    }
+
+    /// Replays the kyoku up to `events[i]`, declares `kan` and draws `pai`
+    /// from the dead wall, returning akochan's response to the draw. The
+    /// kyoku is ended afterwards so that the next replay starts afresh.
+    fn review_rinshan(
+        &mut self,
+        events: &[Event],
+        kyoku_start: usize,
+        i: usize,
+        kan: &Event,
+        pai: Pai,
+    ) -> Result<String> {
+        if self.engine.is_none() {
+            let mut akochan = Akochan::spawn(self.engine_args)?;
+            akochan.send(&events[0])?; // start_game
+            self.engine = Some(akochan);
+        }
+        let akochan = self.engine.as_mut().unwrap(); // just set
+
+        let rinshan = Event::Tsumo {
+            actor: self.engine_args.target_actor,
+            pai,
+        };
+        let line = akochan
+            .replay(events[kyoku_start.max(1)..=i].iter().chain([kan, &rinshan]))?
+            .context("the rinshan tsumo is not a review timing of akochan")?;
+        akochan.send(&Event::EndKyoku)?;
+
+        Ok(line)
+    }
+
+    /// Stops akochan of the current kyoku, if any.
+    pub fn finish(&mut self) {
+        if let Some(mut akochan) = self.engine.take() {
+            if let Err(err) = akochan.send(&Event::EndGame).and_then(|_| akochan.finish()) {
+                log!("WARNING: failed to stop akochan for kans: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Reports every kan in `legal`, with the EV of each from `evaluate`, and
+/// compares it with the best of akochan's candidates that is not a kan.
+pub fn find_opportunities<F>(
+    legal: &[Event],
+    actions: &[DetailedAction],
+    actual: &[Event],
+    junme: u8,
+    board: &BoardState,
+    threats: &[u8],
+    mut evaluate: F,
+) -> Vec<KanOpportunity>
+where
+    F: FnMut(&Event) -> Option<f64>,
+{
+    let actual_kan = actual.first().and_then(kan_of);
+    let ev_without_kan = actions
+        .iter()
+        .find(|a| a.moves.first().and_then(kan_of).is_none())
+        .and_then(|a| a.review.pt_exp_total);
+
+    legal
+        .iter()
+        .filter_map(|kan| {
+            let (kind, pai) = kan_of(kan)?;
+            Some(KanOpportunity {
+                junme,
+                kind,
+                pai,
+                taken: actual_kan == Some((kind, pai)),
+                ev_with_kan: evaluate(kan),
+                ev_without_kan,
+                tiles_left: board.tiles_left,
+                dora_indicators: board.dora_markers.len() as u8,
+                threats: threats.to_vec(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,130 @@
+//! The mahjong logic behind akochan-reviewer, for tools such as visualizers
+//! that want the same board state, shanten and scoring as the reviewer, and
+//! the command line itself, for binaries that add analyzers of their own:
+//!
+//! ```no_run
+//! # use akochan_reviewer::analyzer::{self, Analyzer, Output};
+//! # use akochan_reviewer::review::KyokuReview;
+//! struct Counter;
+//!
+//! impl Analyzer for Counter {
+//!     fn name(&self) -> &str {
+//!         "counter"
+//!     }
+//!
+//!     fn finish(
+//!         &mut self,
+//!         kyokus: &mut [KyokuReview],
+//!         _target_actor: u8,
+//!     ) -> anyhow::Result<Option<Output>> {
+//!         Ok(Some(Output::Data(kyokus.len().into())))
+//!     }
+//! }
+//!
+//! analyzer::register(|| Box::new(Counter));
+//! akochan_reviewer::cli::main();
+//! ```
+
+pub mod analyzer;
+pub mod board;
+pub mod cli;
+pub mod i18n;
+pub mod review;
+pub mod scoring;
+pub mod shanten;
+pub mod state;
+pub mod tehai;
+
+// the rest backs the akochan-reviewer binaries and is not a stable API
+#[doc(hidden)]
+pub mod anki;
+#[doc(hidden)]
+pub mod assist;
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod check;
+#[doc(hidden)]
+pub mod comparison;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod decompress;
+#[doc(hidden)]
+pub mod defense;
+#[doc(hidden)]
+pub mod discord;
+#[doc(hidden)]
+pub mod download;
+#[doc(hidden)]
+pub mod embed;
+#[doc(hidden)]
+pub mod engine;
+#[doc(hidden)]
+pub mod engine_server;
+#[cfg(feature = "ffi")]
+#[doc(hidden)]
+pub mod ffi;
+#[doc(hidden)]
+pub mod heatmap;
+#[doc(hidden)]
+pub mod history;
+#[doc(hidden)]
+pub mod index;
+#[doc(hidden)]
+pub mod intent;
+#[doc(hidden)]
+pub mod kan;
+mod log;
+#[doc(hidden)]
+pub mod log_cache;
+#[doc(hidden)]
+pub mod log_source;
+#[doc(hidden)]
+pub mod metadata;
+#[doc(hidden)]
+pub mod oorasu;
+#[doc(hidden)]
+pub mod outcome;
+#[doc(hidden)]
+pub mod pdf;
+#[doc(hidden)]
+pub mod position;
+#[doc(hidden)]
+pub mod post_mortem;
+#[doc(hidden)]
+pub mod prescreen;
+#[doc(hidden)]
+pub mod raw_log_ext;
+#[doc(hidden)]
+pub mod render;
+#[doc(hidden)]
+pub mod report_json;
+#[doc(hidden)]
+pub mod report_output;
+#[doc(hidden)]
+pub mod reveal;
+#[doc(hidden)]
+pub mod rollout;
+#[doc(hidden)]
+pub mod rules;
+#[doc(hidden)]
+pub mod ryukyoku;
+#[doc(hidden)]
+pub mod score_audit;
+#[doc(hidden)]
+pub mod script;
+#[doc(hidden)]
+pub mod session;
+#[doc(hidden)]
+pub mod snapshot;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod stream;
+#[doc(hidden)]
+pub mod tactics;
+#[doc(hidden)]
+pub mod ukeire;
+#[doc(hidden)]
+pub mod whatif;
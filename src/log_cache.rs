@@ -0,0 +1,151 @@
+use crate::cache::write_atomic;
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde_json::{self as json, Value};
+
+/// Where a cached log was downloaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogKind {
+    Tenhou,
+    MahjongSoul,
+}
+
+impl LogKind {
+    const ALL: &'static [Self] = &[Self::Tenhou, Self::MahjongSoul];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Tenhou => "tenhou",
+            Self::MahjongSoul => "majsoul",
+        }
+    }
+}
+
+/// Downloaded logs in tenhou.net/6 format, kept so that reviewing the same
+/// log again does not download it again.
+///
+/// Each log is stored as `<dir>/<kind>/<log id>.json`, containing exactly the
+/// body of the response.
+#[derive(Debug, Clone)]
+pub struct LogCache {
+    dir: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct CachedLog {
+    pub kind: LogKind,
+    pub log_id: String,
+    pub size: u64,
+    pub downloaded_at: Option<SystemTime>,
+}
+
+impl LogCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Returns `None` for an ID that cannot be a file name.
+    fn path_of(&self, kind: LogKind, log_id: &str) -> Option<PathBuf> {
+        if log_id.is_empty() || log_id.starts_with('.') || log_id.contains(['/', '\\']) {
+            return None;
+        }
+
+        let mut path = self.dir.join(kind.as_str());
+        path.push(format!("{}.json", log_id));
+        Some(path)
+    }
+
+    pub fn get(&self, kind: LogKind, log_id: &str) -> Option<String> {
+        fs::read_to_string(self.path_of(kind, log_id)?).ok()
+    }
+
+    /// Stores `body` if it looks like a log, so that an error message in place
+    /// of the log is never cached.
+    pub fn put(&self, kind: LogKind, log_id: &str, body: &str) -> Result<()> {
+        let is_log = matches!(json::from_str(body), Ok(Value::Object(o)) if o.contains_key("log"));
+        let path = match self.path_of(kind, log_id) {
+            Some(path) if is_log => path,
+            _ => return Ok(()),
+        };
+        let parent = path.parent().unwrap(); // always has one
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create log cache dir {:?}", parent))?;
+
+        write_atomic(&path, body.as_bytes()).context("failed to write log cache")
+    }
+
+    /// Every cached log, from the newest.
+    pub fn list(&self) -> Result<Vec<CachedLog>> {
+        let mut logs = vec![];
+        for &kind in LogKind::ALL {
+            let dir = self.dir.join(kind.as_str());
+            if !dir.is_dir() {
+                continue;
+            }
+
+            let entries =
+                fs::read_dir(&dir).with_context(|| format!("failed to read {:?}", dir))?;
+            for entry in entries {
+                let entry = entry.with_context(|| format!("failed to read {:?}", dir))?;
+                let path = entry.path();
+                if path.extension() != Some("json".as_ref()) {
+                    continue;
+                }
+                let log_id = match path.file_stem().and_then(|s| s.to_str()) {
+                    Some(stem) => stem.to_owned(),
+                    None => continue,
+                };
+                let meta = entry
+                    .metadata()
+                    .with_context(|| format!("failed to read {:?}", path))?;
+
+                logs.push(CachedLog {
+                    kind,
+                    log_id,
+                    size: meta.len(),
+                    downloaded_at: meta.modified().ok(),
+                });
+            }
+        }
+        logs.sort_by_key(|l| Reverse(l.downloaded_at));
+
+        Ok(logs)
+    }
+
+    /// Removes every cached log, returning how many there were.
+    pub fn clear(&self) -> Result<usize> {
+        let n = self.list()?.len();
+        for &kind in LogKind::ALL {
+            let dir = self.dir.join(kind.as_str());
+            if dir.is_dir() {
+                fs::remove_dir_all(&dir).with_context(|| format!("failed to remove {:?}", dir))?;
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+/// `$XDG_CACHE_HOME/akochan-reviewer/logs`, falling back to
+/// `~/.cache/akochan-reviewer/logs`.
+pub fn default_dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))?;
+
+    Some(cache_home.join("akochan-reviewer").join("logs"))
+}
@@ -29,6 +29,40 @@ impl LogSource {
         }
     }
 
+    /// Like `default_output_filename`, for the comparison of all seats.
+    pub fn default_comparison_filename(&self) -> OsString {
+        let mut filename: OsString = match self {
+            LogSource::Tenhou(id) => id.into(),
+            LogSource::MahjongSoul(full_id) => mjsoul_log_id_from_full(full_id).into(),
+            LogSource::File(filename) => filename.clone(),
+            LogSource::Stdin => "report".into(),
+        };
+        filename.push("_comparison");
+        filename
+    }
+
+    /// Date of the game in YYYY-MM-DD, from the log ID.
+    pub fn date(&self) -> Option<String> {
+        match self {
+            LogSource::Tenhou(id) => game_date("--tenhou-id", id),
+            LogSource::MahjongSoul(full_id) => game_date("--mjsoul-id", full_id),
+            _ => None,
+        }
+    }
+
+    /// URL of the kyoku at `position` of the log, counting from 0, in the
+    /// official tenhou.net/6 replayer from the seat of `actor`. Only logs
+    /// from Tenhou can be opened there.
+    pub fn tenhou_replayer_url(&self, actor: u8, position: usize) -> Option<String> {
+        match self {
+            LogSource::Tenhou(id) => Some(format!(
+                "https://tenhou.net/6/?log={}&tw={}&ts={}",
+                id, actor, position,
+            )),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn log_id(&self) -> Option<&str> {
         match self {
@@ -39,6 +73,28 @@ impl LogSource {
     }
 }
 
+/// Date of the game in YYYY-MM-DD, from the log ID, which starts with
+/// YYYYMMDDHH on Tenhou and YYMMDD on Mahjong Soul.
+pub fn game_date(source_flag: &str, log_id: &str) -> Option<String> {
+    let (digits, century) = match source_flag {
+        "--tenhou-id" => (log_id.get(..8)?, ""),
+        "--mjsoul-id" => (log_id.get(..6)?, "20"),
+        _ => return None,
+    };
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let n = digits.len();
+    Some(format!(
+        "{}{}-{}-{}",
+        century,
+        &digits[..n - 4],
+        &digits[n - 4..n - 2],
+        &digits[n - 2..],
+    ))
+}
+
 #[inline]
 fn mjsoul_log_id_from_full(full_id: &str) -> &str {
     full_id.find('_').map(|i| &full_id[..i]).unwrap_or(full_id)
@@ -47,9 +103,9 @@ fn mjsoul_log_id_from_full(full_id: &str) -> &str {
 fn deobfuse_mjsoul_log_id(id: &str) -> String {
     let mut ret = String::with_capacity(id.len());
     for (i, &code) in id.as_bytes().iter().enumerate() {
-        let o = if (b'0'..=b'9').contains(&code) {
+        let o = if code.is_ascii_digit() {
             code - b'0'
-        } else if (b'a'..=b'z').contains(&code) {
+        } else if code.is_ascii_lowercase() {
             code - b'a' + 10
         } else {
             ret.push(code as char);
@@ -1,13 +1,26 @@
+use crate::log_source::game_date;
+use crate::review::{Category, DecisionStat, DecisionType, ReviewMode, SeverityCounts};
+use crate::rules::Rules;
+use crate::session;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
+use convlog::tenhou::{InvalidKyoku, RawLog};
 use serde::Serialize;
 
 #[derive(Serialize)]
 pub struct Metadata<'a> {
     pub pt: &'a [i32; 4],
     pub game_length: &'a str,
+    pub rules: &'a Rules,
     pub log_id: Option<&'a str>,
+    pub game: GameInfo,
+    /// Kyokus of the log left out with `--lenient`.
+    pub skipped_kyokus: Vec<SkippedKyoku>,
+    /// Made with `--no-review`, with the hands and results but no review.
+    pub is_skeleton: bool,
     pub use_placement_ev: bool,
+    pub mode: ReviewMode,
 
     #[serde(with = "humantime_serde")]
     pub loading_time: Duration,
@@ -17,8 +30,118 @@ pub struct Metadata<'a> {
     pub deviation_threshold: f64,
     pub total_reviewed: usize,
     pub total_tolerated: usize,
+    /// Near ties, which count as agreements, see `--tie-epsilon`.
+    pub total_equivalent: usize,
     pub total_problems: usize,
+    pub severity_counts: SeverityCounts,
+    /// Number of disagreements of each category.
+    pub category_counts: BTreeMap<Category, usize>,
+    /// Agreements by the type of decision, only those with any reviewed.
+    pub by_decision_type: BTreeMap<DecisionType, DecisionStat>,
+    /// Number of decisions where the second tactics config disagrees with the
+    /// main one, if `--compare-tactics` is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine_disagreements: Option<usize>,
     pub score: f64,
+    /// See `review::rating`.
+    pub rating: Option<f64>,
 
     pub version: &'a str,
 }
+
+/// What the log tells about the game itself, so that an archived report says
+/// which game it is.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GameInfo {
+    /// In YYYY-MM-DD.
+    pub date: Option<String>,
+    /// Room and rule as the log names them, like "鳳南喰赤" on Tenhou or the
+    /// room level on Mahjong Soul.
+    pub room: Option<String>,
+    /// A Tenhou lobby other than the public one, like "L1234".
+    pub lobby: Option<String>,
+    /// By seat.
+    pub players: Vec<PlayerInfo>,
+}
+
+/// A kyoku that fails to parse or convert, left out of the review.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedKyoku {
+    /// Counts from 1.
+    pub position: usize,
+    /// Counts from 0, `None` along with `honba` if the kyoku is too broken to
+    /// tell.
+    pub kyoku: Option<u8>,
+    pub honba: Option<u8>,
+    pub reason: String,
+}
+
+impl From<&InvalidKyoku> for SkippedKyoku {
+    fn from(invalid: &InvalidKyoku) -> Self {
+        Self {
+            position: invalid.index + 1,
+            kyoku: invalid.meta.as_ref().map(|m| m.kyoku_num),
+            honba: invalid.meta.as_ref().map(|m| m.honba),
+            reason: invalid.reason.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub dan: Option<String>,
+    pub rate: Option<f64>,
+    pub final_score: Option<i32>,
+    /// Counts from 1.
+    pub placement: Option<usize>,
+}
+
+impl GameInfo {
+    /// `date` is the one of the log source, if any. A log converted from mjai
+    /// has a made-up room, which is left out with `is_converted`.
+    pub fn new(raw_log: &RawLog, date: Option<String>, is_converted: bool) -> Self {
+        let date = date.or_else(|| game_date("--tenhou-id", raw_log.log_ref()?));
+        let room = Some(raw_log.rule_disp())
+            .filter(|r| !is_converted && !r.is_empty())
+            .map(str::to_owned);
+        let lobby = raw_log
+            .lobby()
+            .filter(|&l| l > 0)
+            .map(|l| format!("L{:04}", l));
+
+        let players = raw_log
+            .names()
+            .iter()
+            .enumerate()
+            .map(|(i, name)| PlayerInfo {
+                name: name.clone(),
+                dan: raw_log.dans().and_then(|d| d.get(i)).cloned(),
+                rate: raw_log.rates().and_then(|r| r.get(i)).copied(),
+                ..Default::default()
+            })
+            .collect();
+        let mut info = Self {
+            date,
+            room,
+            lobby,
+            players,
+        };
+        if let Some(scores) = raw_log.final_scores() {
+            info.set_final_scores(&scores);
+        }
+        info
+    }
+
+    #[inline]
+    pub fn has_final_scores(&self) -> bool {
+        self.players.iter().any(|p| p.final_score.is_some())
+    }
+
+    pub fn set_final_scores(&mut self, scores: &[i32; 4]) {
+        for (actor, player) in self.players.iter_mut().enumerate() {
+            player.final_score = Some(scores[actor]);
+            player.placement = Some(session::placement(scores, actor));
+        }
+    }
+}
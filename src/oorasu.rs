@@ -0,0 +1,266 @@
+use crate::review::{Acceptance, Entry, KyokuReview};
+use convlog::mjai::Event;
+
+use serde::Serialize;
+
+/// jun_pt that makes akochan's EV the negated expected final placement, the
+/// same as `--use-placement-ev`.
+pub const PLACEMENT_PT: [i32; 4] = [-1, -2, -3, -4];
+
+/// Cuts `events` of a whole game down to its final kyoku, keeping the
+/// start_game and end_game events around it. Returns `None` if there is no
+/// kyoku at all.
+pub fn final_kyoku_events(events: &[Event]) -> Option<Vec<Event>> {
+    let start = events
+        .iter()
+        .rposition(|ev| matches!(ev, Event::StartKyoku { .. }))?;
+
+    let mut ret = Vec::with_capacity(events.len() - start + 1);
+    ret.extend(events.first().cloned());
+    ret.extend_from_slice(&events[start..]);
+    Some(ret)
+}
+
+/// The final kyoku reviewed with placement EV, with what it takes to change
+/// the placement of the target actor.
+#[derive(Debug, Clone, Serialize)]
+pub struct OorasuFocus<'a> {
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyotaku: u8,
+    pub scores: [i32; 4],
+    /// Counts from 1.
+    pub placement: usize,
+    /// At the start of the kyoku, for every other player.
+    pub targets: Vec<RankTarget>,
+    pub decisions: Vec<OorasuDecision<'a>>,
+}
+
+/// A decision of the final kyoku, with EVs in expected placements.
+#[derive(Debug, Clone, Serialize)]
+pub struct OorasuDecision<'a> {
+    pub junme: u8,
+    pub acceptance: Acceptance,
+    pub expected: &'a [Event],
+    pub actual: &'a [Event],
+    /// Expected final placement, counting from 1.
+    pub expected_placement: Option<f64>,
+    pub actual_placement: Option<f64>,
+    /// Placement right before the decision, counting from 1.
+    pub placement: usize,
+    /// Against the nearest rival at the moment, the player just above if any,
+    /// otherwise the one just below.
+    pub target: Option<RankTarget>,
+}
+
+/// The smallest agari that swaps the order of the target actor and `rival`.
+///
+/// When `rival` is above, the target actor is the winner of the agari, and
+/// `rival` is the one to overtake. When `rival` is below, it is the other way
+/// around, and the agari is what the target actor must not allow.
+#[derive(Debug, Clone, Serialize)]
+pub struct RankTarget {
+    pub rival: u8,
+    pub above: bool,
+    /// Points between the two, always positive.
+    pub gap: i32,
+    /// Ron from the one to overtake, in points without honba. `None` if even
+    /// a yakuman is not enough.
+    pub direct_ron: Option<i32>,
+    /// Ron from a third player.
+    pub other_ron: Option<i32>,
+    pub tsumo: Option<Tsumo>,
+}
+
+/// Payments of a tsumo agari without honba.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Tsumo {
+    /// Payment of each ko, or of everyone if the winner is the oya.
+    pub ko: i32,
+    /// Payment of the oya, `None` if the winner is the oya.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oya: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Situation {
+    scores: [i32; 4],
+    oya: u8,
+    honba: u8,
+    kyotaku: u8,
+}
+
+/// Works out the rank targets of `target_actor` in `kyoku`, which should be
+/// the only kyoku of a review of [`final_kyoku_events`].
+pub fn analyze(kyoku: &KyokuReview, target_actor: u8) -> Option<OorasuFocus<'_>> {
+    let (oya, kyotaku) = kyoku.events.iter().find_map(|ev| match *ev {
+        Event::StartKyoku { oya, kyotaku, .. } => Some((oya, kyotaku)),
+        _ => None,
+    })?;
+    let situation_at = |event_index| {
+        let reaches = kyoku
+            .events
+            .iter()
+            .take(event_index + 1)
+            .filter(|ev| matches!(ev, Event::ReachAccepted { .. }))
+            .count();
+        Situation {
+            scores: kyoku.scores_at(event_index),
+            oya,
+            honba: kyoku.honba,
+            kyotaku: kyotaku + reaches as u8,
+        }
+    };
+
+    let start = situation_at(0);
+    let targets = (0..4)
+        .filter(|&rival| rival != target_actor)
+        .map(|rival| rank_target(&start, target_actor, rival))
+        .collect();
+
+    let decisions = kyoku
+        .entries
+        .iter()
+        .filter(|e| e.actor == target_actor)
+        .map(|entry| {
+            let situation = situation_at(entry.event_index);
+            OorasuDecision {
+                junme: entry.junme,
+                acceptance: entry.acceptance,
+                expected: &entry.expected,
+                actual: &entry.actual,
+                expected_placement: entry
+                    .details
+                    .first()
+                    .and_then(|d| d.review.pt_exp_total)
+                    .map(|ev| -ev),
+                actual_placement: actual_placement(entry),
+                placement: placement(&situation.scores, target_actor),
+                target: nearest_rival(&situation.scores, target_actor)
+                    .map(|rival| rank_target(&situation, target_actor, rival)),
+            }
+        })
+        .collect();
+
+    Some(OorasuFocus {
+        kyoku: kyoku.kyoku,
+        honba: kyoku.honba,
+        kyotaku,
+        scores: start.scores,
+        placement: placement(&start.scores, target_actor),
+        targets,
+        decisions,
+    })
+}
+
+fn actual_placement(entry: &Entry) -> Option<f64> {
+    let rank = entry.actual_rank()?;
+    entry.details[rank - 1].review.pt_exp_total.map(|ev| -ev)
+}
+
+/// Whether `a` ranks above `b`. Ties are broken by the seat, the first oya
+/// being the highest.
+fn ranks_above(scores: &[i32; 4], a: u8, b: u8) -> bool {
+    let (sa, sb) = (scores[a as usize], scores[b as usize]);
+    sa > sb || (sa == sb && a < b)
+}
+
+fn placement(scores: &[i32; 4], actor: u8) -> usize {
+    1 + (0..4).filter(|&i| ranks_above(scores, i, actor)).count()
+}
+
+/// The player just above `actor`, or the one just below if `actor` is the
+/// top.
+fn nearest_rival(scores: &[i32; 4], actor: u8) -> Option<u8> {
+    let others = (0..4).filter(|&i| i != actor);
+    others
+        .clone()
+        .filter(|&i| ranks_above(scores, i, actor))
+        .min_by_key(|&i| scores[i as usize])
+        .or_else(|| others.max_by_key(|&i| scores[i as usize]))
+}
+
+fn rank_target(situation: &Situation, actor: u8, rival: u8) -> RankTarget {
+    let above = ranks_above(&situation.scores, rival, actor);
+    let (winner, loser) = if above {
+        (actor, rival)
+    } else {
+        (rival, actor)
+    };
+
+    RankTarget {
+        rival,
+        above,
+        gap: (situation.scores[rival as usize] - situation.scores[actor as usize]).abs(),
+        direct_ron: min_ron(situation, winner, loser, true),
+        other_ron: min_ron(situation, winner, loser, false),
+        tsumo: min_tsumo(situation, winner, loser),
+    }
+}
+
+/// Smallest ron by `winner` to rank above `loser`, either from `loser`
+/// directly or from a third player.
+fn min_ron(situation: &Situation, winner: u8, loser: u8, direct: bool) -> Option<i32> {
+    let is_oya = winner == situation.oya;
+    hand_bases().into_iter().find_map(|base| {
+        let points = ceil100(base * if is_oya { 6 } else { 4 });
+        let payment = points + 300 * situation.honba as i32;
+        let mut scores = situation.scores;
+        scores[winner as usize] += payment + 1000 * situation.kyotaku as i32;
+        if direct {
+            scores[loser as usize] -= payment;
+        }
+        ranks_above(&scores, winner, loser).then_some(points)
+    })
+}
+
+/// Smallest tsumo by `winner` to rank above `loser`.
+fn min_tsumo(situation: &Situation, winner: u8, loser: u8) -> Option<Tsumo> {
+    let is_oya = winner == situation.oya;
+    let honba = 100 * situation.honba as i32;
+    hand_bases().into_iter().find_map(|base| {
+        let tsumo = if is_oya {
+            Tsumo {
+                ko: ceil100(base * 2),
+                oya: None,
+            }
+        } else {
+            Tsumo {
+                ko: ceil100(base),
+                oya: Some(ceil100(base * 2)),
+            }
+        };
+        let payment_of = |actor| match tsumo.oya {
+            Some(oya) if actor == situation.oya => oya + honba,
+            _ => tsumo.ko + honba,
+        };
+
+        let mut scores = situation.scores;
+        let total: i32 = (0..4).filter(|&i| i != winner).map(payment_of).sum();
+        scores[winner as usize] += total + 1000 * situation.kyotaku as i32;
+        scores[loser as usize] -= payment_of(loser);
+        ranks_above(&scores, winner, loser).then_some(tsumo)
+    })
+}
+
+/// Base points of the possible hands up to a yakuman, in ascending order.
+fn hand_bases() -> Vec<i32> {
+    let mut bases: Vec<_> = (1..=4)
+        .flat_map(|han| {
+            [25, 30, 40, 50, 60, 70, 80, 90, 100, 110]
+                .iter()
+                // 25 fu is chiitoitsu, which is at least 2 han
+                .filter(move |&&fu| han >= 2 || fu != 25)
+                .map(move |&fu| (fu << (han + 2)).min(2000))
+        })
+        .chain([3000, 4000, 6000, 8000].iter().copied())
+        .collect();
+    bases.sort_unstable();
+    bases.dedup();
+
+    bases
+}
+
+fn ceil100(points: i32) -> i32 {
+    (points + 99) / 100 * 100
+}
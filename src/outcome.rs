@@ -0,0 +1,92 @@
+use crate::metadata::Metadata;
+use crate::review::{DecisionStat, DecisionType, SeverityCounts};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::Error;
+use serde::Serialize;
+
+/// Exit code of any failure without a code of its own, including invalid
+/// command line options.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code when the log cannot be parsed or converted.
+pub const EXIT_INVALID_LOG: i32 = 3;
+/// Exit code when akochan fails during the review.
+pub const EXIT_ENGINE_FAILURE: i32 = 4;
+
+/// Error context that decides the exit code, displayed as the message it
+/// carries so that the error output stays the same.
+#[derive(Debug, Clone, Copy)]
+pub enum Failure {
+    InvalidLog(&'static str),
+    Engine(&'static str),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::InvalidLog(msg) | Failure::Engine(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl Failure {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Failure::InvalidLog(_) => EXIT_INVALID_LOG,
+            Failure::Engine(_) => EXIT_ENGINE_FAILURE,
+        }
+    }
+}
+
+/// The exit code for `err`, taken from the outermost [`Failure`] context in
+/// it, if any.
+pub fn exit_code(err: &Error) -> i32 {
+    err.downcast_ref::<Failure>()
+        .map_or(EXIT_FAILURE, |f| f.exit_code())
+}
+
+/// One line summary of a finished review printed by `--summary-json`.
+#[derive(Debug, Serialize)]
+pub struct Summary<'a> {
+    pub log_id: Option<&'a str>,
+    pub actor: u8,
+    /// Placement of the actor at the end of the log, counts from 1.
+    pub placement: usize,
+    pub total_reviewed: usize,
+    pub total_tolerated: usize,
+    pub total_problems: usize,
+    /// Agreements over all reviewed decisions, from 0 to 1.
+    pub agree_rate: f64,
+    pub by_decision_type: BTreeMap<DecisionType, DecisionStat>,
+    pub total_ev_loss: f64,
+    pub severity_counts: SeverityCounts,
+    pub score: f64,
+    pub rating: Option<f64>,
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(meta: &Metadata<'a>, actor: u8, placement: usize, total_ev_loss: f64) -> Self {
+        let agreed = meta.total_reviewed - meta.total_problems - meta.total_tolerated;
+        let agree_rate = if meta.total_reviewed > 0 {
+            agreed as f64 / meta.total_reviewed as f64
+        } else {
+            0.
+        };
+
+        Self {
+            log_id: meta.log_id,
+            actor,
+            placement,
+            total_reviewed: meta.total_reviewed,
+            total_tolerated: meta.total_tolerated,
+            total_problems: meta.total_problems,
+            agree_rate,
+            by_decision_type: meta.by_decision_type.clone(),
+            total_ev_loss,
+            severity_counts: meta.severity_counts,
+            score: meta.score,
+            rating: meta.rating,
+        }
+    }
+}
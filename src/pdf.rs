@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument};
+
+const PAGE_WIDTH: f32 = 210.;
+const PAGE_HEIGHT: f32 = 297.;
+const MARGIN: f32 = 15.;
+const FONT_SIZE: f32 = 9.;
+const LINE_HEIGHT: f32 = 4.2;
+/// How many half-width characters fit in a line. Lines are wrapped by
+/// counting characters, which is good enough for a monospace font.
+const LINE_WIDTH: usize = 96;
+
+/// Writes `text`, the plain text variant of the report, to a printable A4
+/// PDF at `out`.
+///
+/// The builtin Courier font only covers Latin-1, so `font`, a TTF or OTF
+/// file, is required for reports with Japanese or Chinese text.
+pub fn write_pdf(text: &str, title: &str, font: Option<&Path>, out: &Path) -> Result<()> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "text");
+    let font_ref: IndirectFontRef = match font {
+        Some(path) => {
+            let file =
+                File::open(path).with_context(|| format!("failed to open font {:?}", path))?;
+            doc.add_external_font(file)
+                .with_context(|| format!("failed to load font {:?}", path))?
+        }
+        None => {
+            if let Some(c) = text.chars().find(|&c| c as u32 > 0xff) {
+                bail!(
+                    "the report contains {:?}, which the builtin font cannot show, \
+                    use --pdf-font to specify a font that covers it or --lang en",
+                    c,
+                );
+            }
+            doc.add_builtin_font(BuiltinFont::Courier)
+                .context("failed to load builtin font")?
+        }
+    };
+
+    let lines_per_page = ((PAGE_HEIGHT - MARGIN * 2.) / LINE_HEIGHT) as usize;
+    let lines: Vec<_> = text.lines().flat_map(wrap).collect();
+    let mut current_layer = doc.get_page(page).get_layer(layer);
+    for (i, chunk) in lines.chunks(lines_per_page).enumerate() {
+        if i > 0 {
+            let (page, layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "text");
+            current_layer = doc.get_page(page).get_layer(layer);
+        }
+        for (j, line) in chunk.iter().enumerate() {
+            let y = PAGE_HEIGHT - MARGIN - LINE_HEIGHT * (j + 1) as f32;
+            current_layer.use_text(line.as_str(), FONT_SIZE, Mm(MARGIN), Mm(y), &font_ref);
+        }
+    }
+
+    let file = File::create(out).with_context(|| format!("failed to create {:?}", out))?;
+    doc.save(&mut BufWriter::new(file))
+        .with_context(|| format!("failed to write PDF to {:?}", out))?;
+
+    Ok(())
+}
+
+/// Splits `line` into lines of at most `LINE_WIDTH` half-width characters,
+/// counting CJK characters as two.
+fn wrap(line: &str) -> Vec<String> {
+    let mut ret = vec![];
+    let mut current = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let w = if c as u32 >= 0x2e80 { 2 } else { 1 };
+        if width + w > LINE_WIDTH {
+            ret.push(current.split_off(0));
+            width = 0;
+        }
+        current.push(c);
+        width += w;
+    }
+    ret.push(current);
+
+    ret
+}
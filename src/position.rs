@@ -0,0 +1,621 @@
+use crate::assist::describe;
+use crate::engine::{Akochan, EngineArgs};
+use crate::review::{fill_ev_gaps, DetailedAction};
+use crate::state::State;
+use std::convert::TryInto;
+use std::io::prelude::*;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use convlog::mjai::{Consumed2, Consumed3, Consumed4, Event};
+use convlog::Pai;
+use serde_json as json;
+
+/// A single situation to ask akochan about, without a full log.
+#[derive(Debug, Clone)]
+pub struct Position {
+    /// The closed pais of the target actor, 14 minus 3 for every meld. The
+    /// last one is the pai just drawn.
+    pub hand: Vec<Pai>,
+    pub melds: Vec<Meld>,
+    pub dora_markers: Vec<Pai>,
+    pub bakaze: Pai,
+    /// Counts from 1.
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyotaku: u8,
+    pub target_actor: u8,
+    pub scores: [i32; 4],
+    /// Discarded pais of every actor, in the order they were discarded.
+    pub discards: [Vec<Pai>; 4],
+}
+
+pub struct PositionArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    pub tactics_config: &'a Path,
+    /// Passed to akochan as [`crate::engine::SEED_ENV`].
+    pub seed: Option<u64>,
+    /// Print akochan's output as a line of JSON instead of plain text.
+    pub json: bool,
+    pub verbose: bool,
+}
+
+/// Parses pais in the short notation of tenhou and most mahjong tools, such
+/// as "40m12356p4699s222z", where "0" is an aka 5 and "1z"~"7z" are ESWNPFC.
+/// The order of the pais is kept.
+pub fn parse_pais(s: &str) -> Result<Vec<Pai>> {
+    let mut pais = vec![];
+    let mut nums = vec![];
+
+    for c in s.chars() {
+        match c {
+            '0'..='9' => nums.push(c as u8 - b'0'),
+            'm' | 'p' | 's' | 'z' => {
+                ensure!(!nums.is_empty(), "no number before {:?} in {:?}", c, s);
+                for n in nums.drain(..) {
+                    let mjai_str = match (c, n) {
+                        ('z', 1..=7) => {
+                            ["E", "S", "W", "N", "P", "F", "C"][n as usize - 1].to_owned()
+                        }
+                        ('z', _) => bail!("invalid jihai {}z in {:?}", n, s),
+                        (_, 0) => format!("5{}r", c),
+                        _ => format!("{}{}", n, c),
+                    };
+                    pais.push(mjai_str.parse()?);
+                }
+            }
+            _ if c.is_whitespace() => (),
+            _ => bail!("unexpected character {:?} in {:?}", c, s),
+        }
+    }
+    ensure!(nums.is_empty(), "missing suffix at the end of {:?}", s);
+
+    Ok(pais)
+}
+
+/// A hand as written for `position`, such as
+/// "345m678p11z [777z pon] +6p seat=S dora=1z".
+///
+/// Besides the closed pais, a hand may have
+///
+/// * melds in brackets, each with its pais, the called or added one first,
+///   and one of `chi`, `pon`, `kan`, `ankan` or `kakan`, optionally followed
+///   by `kamicha`, `toimen` or `shimocha` for whom it was called from, which
+///   is kamicha by default,
+/// * the pai just drawn after `+`, which is otherwise the last closed pai,
+/// * `seat=` with the seat wind of the target actor, as in `E` or `3z`,
+/// * `dora=` with the dora markers.
+#[derive(Debug, Clone, Default)]
+pub struct Hand {
+    /// The closed pais with the pai just drawn last.
+    pub pais: Vec<Pai>,
+    pub melds: Vec<Meld>,
+    pub seat_wind: Option<Pai>,
+    pub dora_markers: Vec<Pai>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeldKind {
+    Chi,
+    Pon,
+    Daiminkan,
+    Ankan,
+    Kakan,
+}
+
+/// A naki of the target actor.
+#[derive(Debug, Clone)]
+pub struct Meld {
+    pub kind: MeldKind,
+    /// The called pai first, or for kakan the added pai and then the pon.
+    pub pais: Vec<Pai>,
+    /// Whom the pai was called from, relative to the target actor: 1 for
+    /// shimocha, 2 for toimen and 3 for kamicha. Unused for ankan.
+    pub from: u8,
+}
+
+impl Meld {
+    fn parse(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        let pais = parse_pais(words.next().unwrap_or_default())?;
+        let kind = match words.next() {
+            Some("chi") => MeldKind::Chi,
+            Some("pon") => MeldKind::Pon,
+            Some("kan" | "daiminkan") => MeldKind::Daiminkan,
+            Some("ankan") => MeldKind::Ankan,
+            Some("kakan") => MeldKind::Kakan,
+            _ => bail!("expected chi, pon, kan, ankan or kakan in [{}]", s),
+        };
+        let from = match words.next() {
+            None | Some("kamicha") => 3,
+            Some("toimen") => 2,
+            Some("shimocha") => 1,
+            Some(w) => bail!("unexpected {:?} in [{}]", w, s),
+        };
+        ensure!(words.next().is_none(), "unexpected words in [{}]", s);
+
+        let len = match kind {
+            MeldKind::Chi | MeldKind::Pon => 3,
+            _ => 4,
+        };
+        ensure!(pais.len() == len, "[{}] must have {} pais", s, len);
+        let kinds: Vec<_> = pais.iter().map(|p| p.deaka()).collect();
+        let is_valid = if kind == MeldKind::Chi {
+            let mut ids: Vec<_> = kinds.iter().filter_map(|p| p.as_index()).collect();
+            ids.sort_unstable();
+            ids.len() == 3
+                && ids[2] < 27
+                && ids[0] / 9 == ids[2] / 9
+                && ids[1] == ids[0] + 1
+                && ids[2] == ids[0] + 2
+        } else {
+            kinds.iter().all(|&p| p == kinds[0])
+        };
+        ensure!(is_valid, "[{}] is not a valid {:?}", s, kind);
+        ensure!(
+            kind != MeldKind::Chi || from == 3,
+            "chi can only be called from kamicha in [{}]",
+            s,
+        );
+
+        Ok(Self { kind, pais, from })
+    }
+
+    /// Pais of the meld that come from the hand of the target actor.
+    fn consumed(&self) -> &[Pai] {
+        match self.kind {
+            MeldKind::Ankan => &self.pais,
+            MeldKind::Kakan => &self.pais[2..],
+            _ => &self.pais[1..],
+        }
+    }
+
+    /// Counts as a meld of 3 pais in the hand.
+    fn is_kan(&self) -> bool {
+        !matches!(self.kind, MeldKind::Chi | MeldKind::Pon)
+    }
+}
+
+/// Parses a hand in the notation of `Hand`.
+pub fn parse_hand(s: &str) -> Result<Hand> {
+    let mut hand = Hand::default();
+    let mut tsumo = None;
+
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        if let Some(meld) = rest.strip_prefix('[') {
+            let end = meld
+                .find(']')
+                .ok_or_else(|| anyhow!("unclosed [ in {:?}", s))?;
+            hand.melds.push(Meld::parse(&meld[..end])?);
+            rest = meld[end + 1..].trim_start();
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '[')
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        rest = rest[end..].trim_start();
+
+        if let Some(pai) = word.strip_prefix('+') {
+            match parse_pais(pai)?[..] {
+                [pai] => tsumo = Some(pai),
+                _ => bail!("expected one pai after + in {:?}", s),
+            }
+        } else if let Some(wind) = word.strip_prefix("seat=") {
+            let wind = match wind {
+                "E" | "S" | "W" | "N" => wind.parse()?,
+                _ => match parse_pais(wind)
+                    .with_context(|| format!("invalid seat wind {:?}", wind))?[..]
+                {
+                    [pai @ (Pai::East | Pai::South | Pai::West | Pai::North)] => pai,
+                    _ => bail!("invalid seat wind {:?}", wind),
+                },
+            };
+            hand.seat_wind = Some(wind);
+        } else if let Some(dora) = word.strip_prefix("dora=") {
+            hand.dora_markers.extend(parse_pais(dora)?);
+        } else {
+            hand.pais.extend(parse_pais(word)?);
+        }
+    }
+    hand.pais.extend(tsumo);
+
+    Ok(hand)
+}
+
+/// Writes pais in the same notation as `parse_pais`, grouping consecutive pais
+/// of the same suit, such as "40m123p".
+pub fn format_pais(pais: &[Pai]) -> String {
+    let mut ret = String::new();
+    let mut last_suit = None;
+    for &pai in pais {
+        if pai == Pai::Unknown {
+            continue;
+        }
+        let id = pai.deaka().as_u8();
+        let (num, suit) = if pai.is_aka() {
+            (0, id / 10)
+        } else {
+            (id % 10, id / 10)
+        };
+        if let Some(last) = last_suit.filter(|&s| s != suit) {
+            ret.push(suit_char(last));
+        }
+        ret += &num.to_string();
+        last_suit = Some(suit);
+    }
+    if let Some(suit) = last_suit {
+        ret.push(suit_char(suit));
+    }
+
+    ret
+}
+
+fn suit_char(suit: u8) -> char {
+    ['m', 'p', 's', 'z'][suit as usize - 1]
+}
+
+/// Parses a kyoku like "E1" or "S3" into the bakaze and the kyoku counting
+/// from 1.
+pub fn parse_kyoku(s: &str) -> Result<(Pai, u8)> {
+    let mut chars = s.chars();
+    let bakaze = match chars.next() {
+        Some('E') => Pai::East,
+        Some('S') => Pai::South,
+        Some('W') => Pai::West,
+        Some('N') => Pai::North,
+        _ => bail!("invalid kyoku {:?}", s),
+    };
+    match chars.as_str().parse() {
+        Ok(kyoku @ 1..=4) => Ok((bakaze, kyoku)),
+        _ => bail!("invalid kyoku {:?}", s),
+    }
+}
+
+/// Writes a kyoku in tenhou.net/6 format, counting from 0, like "E1" or "S3".
+pub fn kyoku_label(kyoku: u8) -> String {
+    let bakaze = ["E", "S", "W", "N"][(kyoku / 4 % 4) as usize];
+    format!("{}{}", bakaze, kyoku % 4 + 1)
+}
+
+/// A step of the target actor other than a plain draw and discard.
+#[derive(Debug, Clone, Copy)]
+enum Naki {
+    /// Chi, pon or daiminkan of the meld at the index, or the pon before a
+    /// kakan.
+    Call(usize),
+    /// Ankan or kakan of the meld at the index.
+    SelfKan(usize),
+}
+
+impl Position {
+    /// Builds the mjai events leading to the tsumo of the target actor,
+    /// which is the position to review.
+    ///
+    /// Everyone discards in turn from the oya. The melds are made in the
+    /// order they are written, each as early as the turn order allows: a call
+    /// happens on the next turn of the actor it is called from, who discards
+    /// the called pai instead of one of `discards`, and a self kan on the
+    /// next turn of the target actor. The counts of discards must be
+    /// consistent with that, so that everyone runs out of discards right when
+    /// the target actor draws the last pai. Other actors draw unknown pais
+    /// and the target actor discards every drawn pai right away, except for
+    /// the discard after a chi or pon, which is from the hand.
+    pub fn to_events(&self) -> Result<Vec<Event>> {
+        let closed_len = 14 - 3 * self.melds.len().min(4);
+        ensure!(
+            self.melds.len() <= 4 && self.hand.len() == closed_len,
+            "the hand must have exactly {} closed pais with {} melds, got {}",
+            closed_len,
+            self.melds.len(),
+            self.hand.len(),
+        );
+        ensure!(
+            (1..=4).contains(&self.kyoku),
+            "kyoku must be in 1~4, got {}",
+            self.kyoku,
+        );
+        let (dora_marker, more_doras) = self
+            .dora_markers
+            .split_first()
+            .context("at least one dora marker is required")?;
+        let kan_count = self.melds.iter().filter(|m| m.is_kan()).count();
+        ensure!(
+            more_doras.len() >= kan_count,
+            "{} dora markers are required for {} kans, got {}",
+            kan_count + 1,
+            kan_count,
+            self.dora_markers.len(),
+        );
+        let (start_doras, kan_doras) = more_doras.split_at(more_doras.len() - kan_count);
+        let mut kan_doras = kan_doras.iter().copied();
+
+        let oya = self.kyoku - 1;
+        let target = self.target_actor;
+        let (tsumo, closed) = self.hand.split_last().unwrap(); // checked above
+        let mut haipai = closed.to_vec();
+
+        let mut events = vec![
+            Event::StartGame {
+                kyoku_first: 0,
+                aka_flag: true,
+                names: Default::default(),
+            },
+            // tehais are filled in at the end
+            Event::StartKyoku {
+                bakaze: self.bakaze,
+                dora_marker: *dora_marker,
+                kyoku: self.kyoku,
+                honba: self.honba,
+                kyotaku: self.kyotaku,
+                oya,
+                scores: self.scores,
+                tehais: [[Pai::Unknown; 13]; 4],
+            },
+        ];
+        events.extend(
+            start_doras
+                .iter()
+                .map(|&dora_marker| Event::Dora { dora_marker }),
+        );
+
+        let mut nakis: Vec<_> = self
+            .melds
+            .iter()
+            .enumerate()
+            .flat_map(|(i, m)| match m.kind {
+                MeldKind::Ankan => vec![Naki::SelfKan(i)],
+                MeldKind::Kakan => vec![Naki::Call(i), Naki::SelfKan(i)],
+                _ => vec![Naki::Call(i)],
+            })
+            .rev()
+            .collect();
+        let mut turns = self.discards.iter().map(|d| d.iter()).collect::<Vec<_>>();
+        let next_discard = |turns: &mut Vec<std::slice::Iter<Pai>>| {
+            turns[target as usize]
+                .next()
+                .copied()
+                .context("the target actor has no discard left after a meld")
+        };
+
+        let mut actor = oya;
+        loop {
+            let naki = nakis.last().copied();
+            if actor == target {
+                if let Some(Naki::SelfKan(i)) = naki {
+                    nakis.pop();
+                    let meld = &self.melds[i];
+                    let p = &meld.pais;
+                    events.push(Event::Tsumo { actor, pai: p[0] });
+                    if meld.kind == MeldKind::Ankan {
+                        haipai.extend(&p[1..]);
+                        events.push(Event::Ankan {
+                            actor,
+                            consumed: Consumed4::from([p[0], p[1], p[2], p[3]]),
+                        });
+                    } else {
+                        events.push(Event::Kakan {
+                            actor,
+                            pai: p[0],
+                            consumed: Consumed3::from([p[1], p[2], p[3]]),
+                        });
+                    }
+                    // the new dora is revealed before the rinshan tsumo for
+                    // both of them
+                    events.push(Event::Dora {
+                        dora_marker: kan_doras.next().unwrap(), // checked above
+                    });
+                    let pai = next_discard(&mut turns)?;
+                    events.push(Event::Tsumo { actor, pai });
+                    events.push(Event::Dahai {
+                        actor,
+                        pai,
+                        tsumogiri: true,
+                    });
+                    actor = (actor + 1) % 4;
+                    continue;
+                }
+
+                let pai = match turns[actor as usize].next() {
+                    Some(&pai) => pai,
+                    None => {
+                        ensure!(
+                            nakis.is_empty(),
+                            "the target actor has no discard left before the remaining melds",
+                        );
+                        break;
+                    }
+                };
+                events.push(Event::Tsumo { actor, pai });
+                events.push(Event::Dahai {
+                    actor,
+                    pai,
+                    tsumogiri: true,
+                });
+                actor = (actor + 1) % 4;
+                continue;
+            }
+
+            let called = match naki {
+                Some(Naki::Call(i)) if (actor + 4 - target) % 4 == self.melds[i].from => Some(i),
+                _ => None,
+            };
+            let Some(i) = called else {
+                let pai = *turns[actor as usize].next().with_context(|| {
+                    format!(
+                        "actor {} has no discard left before the target actor's draw",
+                        actor,
+                    )
+                })?;
+                events.push(Event::Tsumo {
+                    actor,
+                    pai: Pai::Unknown,
+                });
+                events.push(Event::Dahai {
+                    actor,
+                    pai,
+                    tsumogiri: false,
+                });
+                actor = (actor + 1) % 4;
+                continue;
+            };
+
+            nakis.pop();
+            let meld = &self.melds[i];
+            let pai = if meld.kind == MeldKind::Kakan {
+                meld.pais[1]
+            } else {
+                meld.pais[0]
+            };
+            let consumed = meld.consumed();
+            haipai.extend(consumed);
+            events.push(Event::Tsumo {
+                actor,
+                pai: Pai::Unknown,
+            });
+            events.push(Event::Dahai {
+                actor,
+                pai,
+                tsumogiri: false,
+            });
+
+            let caller = target;
+            match meld.kind {
+                MeldKind::Chi => events.push(Event::Chi {
+                    actor: caller,
+                    target: actor,
+                    pai,
+                    consumed: Consumed2::from([consumed[0], consumed[1]]),
+                }),
+                MeldKind::Pon | MeldKind::Kakan => events.push(Event::Pon {
+                    actor: caller,
+                    target: actor,
+                    pai,
+                    consumed: Consumed2::from([consumed[0], consumed[1]]),
+                }),
+                _ => events.push(Event::Daiminkan {
+                    actor: caller,
+                    target: actor,
+                    pai,
+                    consumed: Consumed3::from([consumed[0], consumed[1], consumed[2]]),
+                }),
+            }
+
+            let pai = next_discard(&mut turns)?;
+            if meld.kind == MeldKind::Daiminkan {
+                events.push(Event::Tsumo { actor: caller, pai });
+                events.push(Event::Dahai {
+                    actor: caller,
+                    pai,
+                    tsumogiri: true,
+                });
+                events.push(Event::Dora {
+                    dora_marker: kan_doras.next().unwrap(), // checked above
+                });
+            } else {
+                haipai.push(pai);
+                events.push(Event::Dahai {
+                    actor: caller,
+                    pai,
+                    tsumogiri: false,
+                });
+            }
+            actor = (caller + 1) % 4;
+        }
+
+        for (actor, turn) in turns.iter().enumerate() {
+            ensure!(
+                turn.as_slice().is_empty(),
+                "actor {} has {} discards left when the target actor draws",
+                actor,
+                turn.as_slice().len(),
+            );
+        }
+        events.push(Event::Tsumo {
+            actor: target,
+            pai: *tsumo,
+        });
+
+        // by construction
+        let tehai: [Pai; 13] = haipai.try_into().unwrap();
+        if let Event::StartKyoku { tehais, .. } = &mut events[1] {
+            tehais[target as usize] = tehai;
+        }
+
+        Ok(events)
+    }
+}
+
+/// Asks akochan about `position` and writes its ranked candidates to `out`.
+pub fn run<W: Write>(args: &PositionArgs, position: &Position, out: &mut W) -> Result<()> {
+    let target_actor = position.target_actor;
+    let events = position.to_events()?;
+
+    // catches impossible positions, like a fifth pai, before akochan does
+    let mut state = State::new(target_actor);
+    for event in &events {
+        state
+            .update(event)
+            .with_context(|| format!("invalid position at {:?}", event))?;
+    }
+
+    let mut akochan = Akochan::spawn(EngineArgs {
+        akochan_exe: args.akochan_exe,
+        akochan_dir: args.akochan_dir,
+        tactics_config: args.tactics_config,
+        akochan_lib: None,
+        server: None,
+        transcript: None,
+        seed: args.seed,
+        target_actor,
+        verbose: args.verbose,
+        max_restarts: 0,
+        timeout: None,
+    })?;
+    let line = akochan.replay(&events)?;
+    akochan.send(&Event::EndGame)?;
+    akochan.finish()?;
+
+    // the last event is always a tsumo of the target actor
+    let line = line.unwrap();
+    let mut actions: Vec<DetailedAction> =
+        json::from_str(&line).context("failed to parse JSON output of akochan")?;
+    fill_ev_gaps(&mut actions);
+
+    if args.json {
+        writeln!(out, "{}", line.trim())?;
+        return Ok(());
+    }
+
+    let tehai: String = state.tehai.view().iter().map(|p| p.to_string()).collect();
+    let tsumo = position.hand.last().unwrap(); // checked in to_events
+    write!(out, "tehai={} tsumo={}", tehai, tsumo)?;
+    for fuuro in &state.fuuros {
+        write!(out, " [{}]", format_pais(&fuuro.pais()))?;
+    }
+    writeln!(out)?;
+    for (i, action) in actions.iter().enumerate() {
+        let stat = &action.review;
+        writeln!(
+            out,
+            "  {:>2}. {:<24} EV {:>9}  deal-in {:>7}  loss {:>9}",
+            i + 1,
+            describe(&action.moves),
+            stat.pt_exp_total
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "N/A".to_owned()),
+            stat.total_houjuu_hai_prob_now
+                .map(|v| format!("{:.2}%", v * 100.))
+                .unwrap_or_else(|| "N/A".to_owned()),
+            action
+                .deal_in_loss
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "N/A".to_owned()),
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::review::{dahai_pai, Acceptance, Category, Entry, KyokuReview};
+use convlog::mjai::Event;
+use convlog::Pai;
+
+use serde::Serialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Safe tiles listed at each turn.
+const SAFEST_TILES: usize = 3;
+
+/// A kyoku where the target actor dealt in, looked back from the deal-in.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostMortem<'a> {
+    pub kyoku: u8,
+    pub honba: u8,
+    /// The one who won off the target actor, the first one in a double ron.
+    pub winner: u8,
+    /// Score change of the target actor.
+    pub delta: Option<i32>,
+    /// Junme of the first decision where akochan folds with a dahai that is
+    /// clearly safer than the actual one.
+    pub fold_junme: Option<u8>,
+    /// Sum of the EV losses from `fold_junme` to the deal-in.
+    pub push_ev_loss: f64,
+    /// Dahai decisions of the target actor from the first one under threat.
+    pub turns: Vec<Turn<'a>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Turn<'a> {
+    pub junme: u8,
+    pub threats: &'a [u8],
+    pub actual: &'a [Event],
+    pub actual_deal_in: Option<f64>,
+    /// The dahais akochan rates least likely to deal in, the safest first.
+    pub safest: Vec<SafeTile>,
+    pub ev_loss: Option<f64>,
+    pub acceptance: Acceptance,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SafeTile {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pai: Pai,
+    pub deal_in: f64,
+}
+
+/// Looks back at the decisions of `target_actor` in `kyoku` if they dealt in,
+/// finding where folding was clearly better and what they could have
+/// discarded instead.
+///
+/// Returns `None` if the target actor did not deal in, or had no decision
+/// under threat, e.g. dealing in to a dama hand.
+pub fn analyze(kyoku: &KyokuReview, target_actor: u8) -> Option<PostMortem<'_>> {
+    let deal_ins: Vec<_> = kyoku
+        .end_status
+        .iter()
+        .filter_map(|ev| match *ev {
+            Event::Hora {
+                actor,
+                target,
+                deltas,
+                ..
+            } if target == target_actor && actor != target_actor => Some((actor, deltas)),
+            _ => None,
+        })
+        .collect();
+    let winner = deal_ins.first()?.0;
+    // a double ron costs both
+    let delta = deal_ins
+        .iter()
+        .map(|(_, deltas)| deltas.map(|d| d[target_actor as usize]))
+        .sum();
+
+    let own: Vec<&Entry> = kyoku
+        .entries
+        .iter()
+        .filter(|e| e.actor == target_actor)
+        .collect();
+    let turns: Vec<_> = own
+        .iter()
+        .skip_while(|e| e.threats.is_empty())
+        .filter(|e| dahai_pai(&e.actual).is_some())
+        .map(|entry| Turn {
+            junme: entry.junme,
+            threats: &entry.threats,
+            actual: &entry.actual,
+            actual_deal_in: entry.actual_deal_in,
+            safest: safest_tiles(entry),
+            ev_loss: entry.ev_loss,
+            acceptance: entry.acceptance,
+        })
+        .collect();
+    if turns.is_empty() {
+        return None;
+    }
+
+    let fold_at = own.iter().position(|e| should_fold(e));
+    let push_ev_loss = fold_at.map_or(0., |i| {
+        own[i..]
+            .iter()
+            .filter(|e| e.acceptance == Acceptance::Disagree)
+            .filter_map(|e| e.ev_loss)
+            .sum()
+    });
+
+    Some(PostMortem {
+        kyoku: kyoku.kyoku,
+        honba: kyoku.honba,
+        winner,
+        delta,
+        fold_junme: fold_at.map(|i| own[i].junme),
+        push_ev_loss,
+        turns,
+    })
+}
+
+/// A push/fold mistake where akochan's dahai is the safer one.
+fn should_fold(entry: &Entry) -> bool {
+    entry.category == Some(Category::PushFold)
+        && matches!(
+            (entry.expected_deal_in, entry.actual_deal_in),
+            (Some(expected), Some(actual)) if expected < actual
+        )
+}
+
+fn safest_tiles(entry: &Entry) -> Vec<SafeTile> {
+    let mut tiles: Vec<_> = entry
+        .details
+        .iter()
+        .filter_map(|d| {
+            let pai = dahai_pai(&d.moves)?;
+            let deal_in = d.review.total_houjuu_hai_prob_now?;
+            Some(SafeTile { pai, deal_in })
+        })
+        .collect();
+    tiles.sort_by(|a, b| a.deal_in.total_cmp(&b.deal_in));
+    // a riichi and a plain dahai of the same pai are the same tile here
+    let mut seen = vec![];
+    tiles.retain(|t| {
+        let is_new = !seen.contains(&t.pai);
+        seen.push(t.pai);
+        is_new
+    });
+    tiles.truncate(SAFEST_TILES);
+    tiles
+}
@@ -0,0 +1,167 @@
+//! A first pass over the log that looks for decisions of the target actor
+//! where the played move is plausibly suboptimal, judging by shanten, ukeire
+//! and genbutsu alone, so that the kyokus without any need not be sent to
+//! akochan.
+//!
+//! akochan needs every event of a kyoku and evaluates every decision it is
+//! sent, so only whole kyokus can be left out.
+
+use crate::defense;
+use crate::shanten::{get_shanten, tiles_from_pais, Tiles};
+use crate::state::State;
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+
+/// A discard with less than this share of the widest ukeire at the same
+/// shanten is suspicious.
+const UKEIRE_RATIO: f64 = 0.8;
+
+/// Whether each event of `events` belongs to a kyoku without any suspicious
+/// decision of `target_actor`, in the same shape as the plan of the cache.
+pub fn plan(events: &[Event], target_actor: u8) -> Result<Vec<bool>> {
+    let mut screened = vec![false; events.len()];
+    let mut state = State::new(target_actor);
+    let mut kyoku_start = 0;
+    let mut is_plausible = true;
+
+    for (i, event) in events.iter().enumerate() {
+        state.update(event).context("failed to update state")?;
+        let next = events.get(i + 1);
+        match *event {
+            Event::StartKyoku { .. } => {
+                kyoku_start = i;
+                is_plausible = true;
+            }
+            Event::EndKyoku => {
+                screened[kyoku_start..=i]
+                    .iter_mut()
+                    .for_each(|s| *s = is_plausible);
+            }
+            Event::Tsumo { actor, .. } if actor == target_actor => {
+                is_plausible &= !is_suspicious_after_tsumo(&state, next, target_actor);
+            }
+            Event::Dahai { actor, pai, .. } | Event::Kakan { actor, pai, .. }
+                if actor != target_actor =>
+            {
+                is_plausible &= !is_suspicious_call(&state, pai, next, target_actor);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(screened)
+}
+
+/// After a tsumo of the target actor, these are suspicious:
+///
+/// * passing a tsumo agari, declaring a kan or kyuushu kyuuhai
+/// * without threats, a discard that raises the shanten more than needed,
+///   one with much less ukeire than the widest, or staying dama in tenpai
+/// * under threat, folding from tenpai, or pushing a pai that is not
+///   genbutsu with a hand two or more shanten away
+///
+/// Declaring riichi and discarding after it are not.
+fn is_suspicious_after_tsumo(state: &State, next: Option<&Event>, target_actor: u8) -> bool {
+    let pai = match next {
+        Some(&Event::Hora { .. }) | Some(&Event::Reach { .. }) => return false,
+        Some(&Event::Dahai { actor, pai, .. }) if actor == target_actor => pai,
+        _ => return true,
+    };
+    let tiles = tiles_from_pais(state.tehai.view());
+    if get_shanten(&tiles, state.fuuros.len() as u8) < 0 {
+        return true;
+    }
+    if state.reached[target_actor as usize] {
+        return false;
+    }
+
+    let actual = match state.ukeire_after_dahai(pai) {
+        Some(ukeire) => ukeire,
+        None => return true,
+    };
+    let (best_shanten, best_count) = state
+        .tehai
+        .view()
+        .iter()
+        .filter_map(|&p| state.ukeire_after_dahai(p))
+        .map(|u| (u.shanten, u32::MAX - u.count))
+        .min()
+        .map(|(shanten, count)| (shanten, u32::MAX - count))
+        .unwrap(); // has at least the actual one
+
+    let threats = defense::threats(state, target_actor);
+    if threats.is_empty() {
+        return actual.shanten > best_shanten
+            || (actual.shanten == best_shanten
+                && (actual.count as f64) < best_count as f64 * UKEIRE_RATIO)
+            || (actual.shanten == 0 && state.fuuros.is_empty());
+    }
+
+    if threats.iter().all(|&t| state.is_genbutsu(t, pai)) {
+        best_shanten == 0 && actual.shanten > 0
+    } else {
+        actual.shanten >= 2
+    }
+}
+
+/// On a dahai or kakan by another player, these are suspicious: passing a
+/// ron, a daiminkan, and a chi or pon that does not lower the shanten.
+/// Passing a call is not.
+fn is_suspicious_call(state: &State, pai: Pai, next: Option<&Event>, target_actor: u8) -> bool {
+    let consumed = match next {
+        Some(&Event::Hora { actor, .. }) if actor == target_actor => return false,
+        _ if completes(state, pai) => return true,
+        Some(&Event::Daiminkan { actor, .. }) if actor == target_actor => return true,
+        Some(&Event::Chi {
+            actor, consumed, ..
+        })
+        | Some(&Event::Pon {
+            actor, consumed, ..
+        }) if actor == target_actor => consumed.as_array(),
+        _ => return false,
+    };
+
+    let mut tiles = tiles_from_pais(state.tehai.view());
+    let fuuro_count = state.fuuros.len() as u8;
+    let shanten = get_shanten(&tiles, fuuro_count);
+    for pai in consumed {
+        match pai.as_index() {
+            Some(idx) if tiles[idx] > 0 => tiles[idx] -= 1,
+            _ => return true,
+        }
+    }
+    best_shanten_after_dahai(&mut tiles, fuuro_count + 1) >= shanten
+}
+
+/// Whether `pai` completes the 3n+1 tehai of the target actor, regardless
+/// of yaku and furiten.
+fn completes(state: &State, pai: Pai) -> bool {
+    let idx = match pai.as_index() {
+        Some(idx) => idx,
+        None => return false,
+    };
+    let mut tiles = tiles_from_pais(state.tehai.view());
+    if tiles[idx] >= 4 {
+        return false;
+    }
+    tiles[idx] += 1;
+    get_shanten(&tiles, state.fuuros.len() as u8) < 0
+}
+
+/// The lowest shanten of the 3n+2 `tiles` after any discard.
+fn best_shanten_after_dahai(tiles: &mut Tiles, fuuro_count: u8) -> i8 {
+    (0..34)
+        .filter_map(|i| {
+            if tiles[i] == 0 {
+                return None;
+            }
+            tiles[i] -= 1;
+            let shanten = get_shanten(tiles, fuuro_count);
+            tiles[i] += 1;
+            Some(shanten)
+        })
+        .min()
+        .unwrap_or(i8::MAX)
+}
@@ -1,78 +1,60 @@
+use crate::analyzer::{Analyses, Section};
+use crate::embed;
+use crate::i18n::{self, Language, Terms};
 use crate::metadata::Metadata;
-use crate::review::KyokuReview;
-use std::collections::HashMap;
+use crate::oorasu::OorasuFocus;
+use crate::review::{DetailedAction, Entry, EvTrend, KyokuReview};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::io::prelude::*;
+use std::path::Path;
+use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Error, Result};
+use convlog::mjai::Event;
 use convlog::tenhou::RawPartialLog;
+use convlog::Pai;
 use once_cell::sync::Lazy;
 use serde::Serialize;
 use serde_json as json;
+use serde_with::{serde_as, DisplayFromStr};
 use tera::{Tera, Value};
 
 static TEMPLATES: Lazy<Tera> = Lazy::new(|| {
     let mut tera = Tera::default();
-    tera.register_function("kyoku_to_string_ja", kyoku_to_string_ja);
-    tera.register_function("kyoku_to_string_en", kyoku_to_string_en);
     tera.register_function("pretty_round", pretty_round);
 
     tera.add_raw_templates(vec![
         ("macros.html", include_str!("../templates/macros.html")),
+        ("anki.html", include_str!("../templates/anki.html")),
+        (
+            "comparison.html",
+            include_str!("../templates/comparison.html"),
+        ),
+        ("index.html", include_str!("../templates/index.html")),
+        ("macros.txt", include_str!("../templates/macros.txt")),
         ("pai.svg", include_str!("../assets/pai.svg")),
         ("report.css", include_str!("../templates/report.css")),
         ("report.html", include_str!("../templates/report.html")),
+        ("report.txt", include_str!("../templates/report.txt")),
+        ("session.html", include_str!("../templates/session.html")),
+        ("snapshot.svg", include_str!("../templates/snapshot.svg")),
+        ("replay.js", include_str!("../templates/replay.js")),
+        ("navigate.js", include_str!("../templates/navigate.js")),
+        (
+            "theme-colorblind.css",
+            include_str!("../templates/theme-colorblind.css"),
+        ),
+        (
+            "theme-high-contrast.css",
+            include_str!("../templates/theme-high-contrast.css"),
+        ),
     ])
     .expect("failed to parse template");
 
     tera
 });
 
-#[derive(Serialize)]
-pub enum Language {
-    // The string is used in html lang attribute, as per BCP47.
-    #[serde(rename = "ja")]
-    Japanese,
-    #[serde(rename = "en")]
-    English,
-}
-
-#[allow(clippy::unnecessary_wraps)]
-fn kyoku_to_string_ja(args: &HashMap<String, Value>) -> tera::Result<Value> {
-    const BAKAZE_KANJI: &[&str] = &["東", "南", "西", "北"];
-    const NUM_KANJI: &[&str] = &["一", "二", "三", "四"];
-
-    let kyoku = args.get("kyoku").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
-    let honba = args.get("honba").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
-
-    let s = if honba == 0 {
-        format!("{}{}局", BAKAZE_KANJI[kyoku / 4], NUM_KANJI[kyoku % 4])
-    } else {
-        format!(
-            "{}{}局 {} 本場",
-            BAKAZE_KANJI[kyoku / 4],
-            NUM_KANJI[kyoku % 4],
-            honba,
-        )
-    };
-    Ok(Value::String(s))
-}
-
-#[allow(clippy::unnecessary_wraps)]
-fn kyoku_to_string_en(args: &HashMap<String, Value>) -> tera::Result<Value> {
-    const BAKAZE_ENG: &[&str] = &["East", "South", "West", "North"];
-    const NUM_ENG: &[&str] = &["1", "2", "3", "4"];
-
-    let kyoku = args.get("kyoku").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
-    let honba = args.get("honba").and_then(|p| p.as_u64()).unwrap_or(0) as usize;
-
-    let s = if honba == 0 {
-        format!("{} {}", BAKAZE_ENG[kyoku / 4], NUM_ENG[kyoku % 4])
-    } else {
-        format!("{} {}-{}", BAKAZE_ENG[kyoku / 4], NUM_ENG[kyoku % 4], honba)
-    };
-    Ok(Value::String(s))
-}
-
 #[allow(clippy::unnecessary_wraps)]
 fn pretty_round(args: &HashMap<String, Value>) -> tera::Result<Value> {
     let prec = args.get("prec").and_then(|p| p.as_u64()).unwrap_or(5);
@@ -88,17 +70,68 @@ fn pretty_round(args: &HashMap<String, Value>) -> tera::Result<Value> {
     Ok(Value::Null)
 }
 
+/// Palette of the HTML report. Each theme other than the default is a
+/// template named `theme-<name>.css`, applied on top of report.css.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    Default,
+    /// Safe for the common types of color blindness, with the aka doras
+    /// marked by more than their color.
+    Colorblind,
+    HighContrast,
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "default" => Ok(Self::Default),
+            "colorblind" => Ok(Self::Colorblind),
+            "high-contrast" => Ok(Self::HighContrast),
+            _ => Err(anyhow!("unknown theme: {:?}", s)),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct View<'a, L>
 where
     L: AsRef<[RawPartialLog<'a>]> + Serialize,
 {
     kyokus: &'a [KyokuReview],
+    ev_trend: &'a EvTrend,
     target_actor: u8,
     #[serde(skip_serializing_if = "Option::is_none")]
     splited_logs: Option<L>,
+    /// The whole mjai log, one event per line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mjai_log: Option<String>,
     metadata: &'a Metadata<'a>,
+    /// Links to each kyoku in the official tenhou.net/6 replayer, for logs
+    /// from Tenhou.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenhou_replayer_urls: Option<Vec<String>>,
+    /// Outputs of the built-in analyzers, by their names.
+    #[serde(flatten)]
+    analyses: BTreeMap<String, json::Value>,
+    /// Sections from the other analyzers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<Section>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oorasu: Option<OorasuFocus<'a>>,
     lang: Language,
+    /// The naming of yakus and pais, after `lang` unless set otherwise.
+    #[serde(skip)]
+    terms: Terms,
+    theme: Theme,
+    /// This view as in `--json`, from `embed::encode`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedded_report: Option<String>,
+    /// From `--template`, used instead of the built-in templates.
+    #[serde(skip)]
+    templates: Option<&'a Tera>,
 }
 
 impl<'a, L> View<'a, L>
@@ -108,16 +141,167 @@ where
     #[inline]
     pub fn new(
         kyoku_reviews: &'a [KyokuReview],
+        ev_trend: &'a EvTrend,
         target_actor: u8,
         splited_logs: Option<L>,
+        mjai_log: Option<String>,
         metadata: &'a Metadata<'a>,
         lang: Language,
     ) -> Self {
         Self {
             kyokus: kyoku_reviews,
+            ev_trend,
             target_actor,
             splited_logs,
+            mjai_log,
             metadata,
+            tenhou_replayer_urls: None,
+            analyses: BTreeMap::new(),
+            sections: vec![],
+            oorasu: None,
+            lang,
+            terms: lang.into(),
+            theme: Theme::Default,
+            embedded_report: None,
+            templates: None,
+        }
+    }
+
+    /// Attaches the outputs of the analyzers.
+    #[inline]
+    pub fn with_analyses(mut self, analyses: Analyses) -> Self {
+        self.analyses = analyses.data;
+        self.sections = analyses.sections;
+        self
+    }
+
+    /// Attaches the rank targets of the final kyoku from `--oorasu-focus`.
+    #[inline]
+    pub fn with_oorasu(mut self, oorasu: Option<OorasuFocus<'a>>) -> Self {
+        self.oorasu = oorasu;
+        self
+    }
+
+    /// Attaches the links to the tenhou.net/6 replayer, one for each kyoku.
+    #[inline]
+    pub fn with_tenhou_replayer_urls(mut self, urls: Option<Vec<String>>) -> Self {
+        self.tenhou_replayer_urls = urls;
+        self
+    }
+
+    #[inline]
+    pub fn with_terms(mut self, terms: Terms) -> Self {
+        self.terms = terms;
+        self
+    }
+
+    #[inline]
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Renders with `templates` from `load_templates` if any.
+    #[inline]
+    pub fn with_templates(mut self, templates: Option<&'a Tera>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Embeds the JSON of the view, so that the HTML report can be loaded
+    /// again by `stats` and `session`, or downloaded from the page.
+    pub fn with_embedded_report(mut self) -> Result<Self> {
+        self.embedded_report = None;
+        let json = json::to_vec(&self).context("failed to serialize review JSON")?;
+        self.embedded_report = Some(embed::encode(&json)?);
+        Ok(self)
+    }
+
+    pub fn render<W>(&self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.render_template("report.html", w)
+    }
+
+    /// Renders the plain text variant of the report, which has no replayer
+    /// or charts and is meant to be printed.
+    pub fn render_text<W>(&self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.render_template("report.txt", w)
+    }
+
+    fn render_template<W>(&self, name: &str, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let tera = self.templates.unwrap_or(&TEMPLATES);
+        render_with(tera, name, self, self.lang, self.terms, w)
+    }
+}
+
+/// A single reviewed decision, drawn as a standalone SVG image.
+#[derive(Serialize)]
+pub struct SnapshotView<'a> {
+    kyoku: u8,
+    honba: u8,
+    target_actor: u8,
+    entry: &'a Entry,
+    actual_rank: Option<usize>,
+    /// The top candidates, plus the actual action if it is not among them.
+    candidates: Vec<Candidate<'a>>,
+    use_placement_ev: bool,
+    lang: Language,
+}
+
+#[derive(Serialize)]
+struct Candidate<'a> {
+    rank: usize,
+    detail: &'a DetailedAction,
+    /// EV of the best candidate minus EV of this one.
+    ev_loss: Option<f64>,
+}
+
+/// Candidates ranked within `max`, plus the actual action if it is ranked
+/// lower.
+fn top_candidates(entry: &Entry, actual_rank: Option<usize>, max: usize) -> Vec<Candidate<'_>> {
+    entry
+        .details
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i < max || Some(i + 1) == actual_rank)
+        .map(|(i, detail)| Candidate {
+            rank: i + 1,
+            detail,
+            ev_loss: detail.ev_gap,
+        })
+        .collect()
+}
+
+impl<'a> SnapshotView<'a> {
+    const MAX_CANDIDATES: usize = 8;
+
+    #[inline]
+    pub fn new(
+        kyoku: &KyokuReview,
+        entry: &'a Entry,
+        target_actor: u8,
+        use_placement_ev: bool,
+        lang: Language,
+    ) -> Self {
+        let actual_rank = entry.actual_rank();
+        let candidates = top_candidates(entry, actual_rank, Self::MAX_CANDIDATES);
+
+        Self {
+            kyoku: kyoku.kyoku,
+            honba: kyoku.honba,
+            target_actor,
+            entry,
+            actual_rank,
+            candidates,
+            use_placement_ev,
             lang,
         }
     }
@@ -126,16 +310,140 @@ where
     where
         W: Write,
     {
-        let ctx = tera::Context::from_serialize(&self)?;
-        let result =
-            TEMPLATES.render("report.html", &ctx).with_context(|| {
-                match json::to_string(&self) {
-                    Ok(json_string) => format!("with values: {}", json_string),
-                    Err(err) => format!("even serializations failed: {}", err),
+        render_template("snapshot.svg", self, self.lang, w)
+    }
+}
+
+/// One side of an Anki card of a decision. The front has the situation and
+/// the back has akochan's candidates.
+#[serde_as]
+#[derive(Serialize)]
+pub struct AnkiCardView<'a> {
+    pub side: AnkiSide,
+    kyoku: u8,
+    honba: u8,
+    target_actor: u8,
+    entry: &'a Entry,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    dora_markers: Vec<Pai>,
+    /// Scores at the decision, with the reach sticks already paid.
+    scores: [i32; 4],
+    actual_rank: Option<usize>,
+    candidates: Vec<Candidate<'a>>,
+    use_placement_ev: bool,
+    lang: Language,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnkiSide {
+    Front,
+    Back,
+}
+
+impl<'a> AnkiCardView<'a> {
+    const MAX_CANDIDATES: usize = 5;
+
+    pub fn new(
+        kyoku: &KyokuReview,
+        entry: &'a Entry,
+        target_actor: u8,
+        use_placement_ev: bool,
+        lang: Language,
+    ) -> Self {
+        let dora_markers = kyoku
+            .events
+            .iter()
+            .take(entry.event_index + 1)
+            .filter_map(|ev| match *ev {
+                Event::StartKyoku { dora_marker, .. } | Event::Dora { dora_marker } => {
+                    Some(dora_marker)
                 }
-            })?;
-        w.write_all(result.as_bytes())?;
+                _ => None,
+            })
+            .collect();
+
+        let actual_rank = entry.actual_rank();
+        let candidates = top_candidates(entry, actual_rank, Self::MAX_CANDIDATES);
+
+        Self {
+            side: AnkiSide::Front,
+            kyoku: kyoku.kyoku,
+            honba: kyoku.honba,
+            target_actor,
+            entry,
+            dora_markers,
+            scores: kyoku.scores_at(entry.event_index),
+            actual_rank,
+            candidates,
+            use_placement_ev,
+            lang,
+        }
+    }
+
+    pub fn render<W>(&self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        render_template("anki.html", self, self.lang, w)
+    }
+}
 
-        Ok(())
+/// The built-in templates plus every file in `dir`, which replaces the
+/// built-in template of the same name, e.g. `report.html` or `report.css`.
+pub fn load_templates(dir: &Path) -> Result<Tera> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .with_context(|| format!("invalid template name {:?}", path))?
+            .to_owned();
+        files.push((path, Some(name)));
     }
+
+    let mut tera = TEMPLATES.clone();
+    tera.add_template_files(files)
+        .with_context(|| format!("failed to parse templates in {:?}", dir))?;
+    Ok(tera)
+}
+
+/// Renders the template `name` with `value` as its context.
+pub fn render_template<T, W>(name: &str, value: &T, lang: Language, w: &mut W) -> Result<()>
+where
+    T: Serialize,
+    W: Write,
+{
+    render_with(&TEMPLATES, name, value, lang, lang.into(), w)
+}
+
+fn render_with<T, W>(
+    tera: &Tera,
+    name: &str,
+    value: &T,
+    lang: Language,
+    terms: Terms,
+    w: &mut W,
+) -> Result<()>
+where
+    T: Serialize,
+    W: Write,
+{
+    let mut tera = tera.clone();
+    i18n::register(&mut tera, lang, terms);
+
+    let ctx = tera::Context::from_serialize(value)?;
+    let result = tera
+        .render(name, &ctx)
+        .with_context(|| match json::to_string(value) {
+            Ok(json_string) => format!("with values: {}", json_string),
+            Err(err) => format!("even serializations failed: {}", err),
+        })?;
+    w.write_all(result.as_bytes())?;
+
+    Ok(())
 }
@@ -0,0 +1,60 @@
+use crate::review::{Acceptance, Category, DetailedAction, Severity};
+
+use convlog::mjai::Event;
+use serde::Deserialize;
+
+/// The subset of a JSON report (`--json`) that is read back by `stats`,
+/// `session` and `whatif`.
+#[derive(Debug, Deserialize)]
+pub struct ReportJson {
+    pub target_actor: u8,
+    pub kyokus: Vec<KyokuJson>,
+    pub metadata: MetadataJson,
+    #[serde(default)]
+    pub splited_logs: Option<Vec<PartialLogJson>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetadataJson {
+    pub log_id: Option<String>,
+    pub pt: [i32; 4],
+    pub total_reviewed: usize,
+    pub total_tolerated: usize,
+    pub total_problems: usize,
+    // NaN is serialized as null
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartialLogJson {
+    #[serde(default)]
+    pub name: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KyokuJson {
+    pub kyoku: u8,
+    pub honba: u8,
+    pub events: Vec<Event>,
+    pub entries: Vec<EntryJson>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntryJson {
+    pub junme: u8,
+    /// Missing in reports made before `whatif` was introduced.
+    #[serde(default)]
+    pub event_index: Option<usize>,
+    pub acceptance: Acceptance,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub ev_loss: Option<f64>,
+    /// Missing in reports made before categories were introduced.
+    #[serde(default)]
+    pub category: Option<Category>,
+    pub expected: Vec<Event>,
+    pub actual: Vec<Event>,
+    #[serde(default)]
+    pub details: Vec<DetailedAction>,
+}
@@ -1,6 +1,97 @@
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error, Result};
+
+/// The format of the report, from `--out-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutFormat {
+    Html,
+    Json,
+    /// One review event per line, written as the review goes.
+    Ndjson,
+}
+
+impl OutFormat {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Html => ".html",
+            Self::Json => ".json",
+            Self::Ndjson => ".ndjson",
+        }
+    }
+}
+
+impl FromStr for OutFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(anyhow!("unknown output format: {:?}", s)),
+        }
+    }
+}
 
 pub enum ReportOutput {
     File(OsString),
     Stdout,
 }
+
+impl ReportOutput {
+    /// `out_file` if given, "-" being stdout, otherwise `default_name` with
+    /// `suffix` in `out_dir` or the current directory.
+    pub fn resolve(
+        out_file: Option<&OsStr>,
+        out_dir: Option<&Path>,
+        mut default_name: OsString,
+        suffix: &str,
+    ) -> Result<Self> {
+        if let Some(filename) = out_file {
+            if filename == "-" {
+                return Ok(ReportOutput::Stdout);
+            }
+            return Ok(ReportOutput::File(filename.to_owned()));
+        }
+
+        default_name.push(suffix);
+        match out_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+                // the default name of a file input is its whole path
+                let name = Path::new(&default_name).file_name().unwrap(); // has a suffix
+                Ok(ReportOutput::File(dir.join(name).into_os_string()))
+            }
+            None => Ok(ReportOutput::File(default_name)),
+        }
+    }
+
+    pub fn create(&self) -> Result<Box<dyn Write>> {
+        match self {
+            ReportOutput::File(filename) => {
+                let file = File::create(filename).with_context(|| {
+                    format!("failed to create output report file {:?}", filename)
+                })?;
+                Ok(Box::new(file))
+            }
+            ReportOutput::Stdout => Ok(Box::new(io::stdout())),
+        }
+    }
+
+    /// Opens the rendered HTML report with the default browser, unless it is
+    /// written to stdout.
+    pub fn open(&self) -> Result<()> {
+        if let ReportOutput::File(filepath) = self {
+            opener::open(filepath).with_context(|| {
+                format!("failed to open rendered HTML report file {:?}", filepath)
+            })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::board::BoardState;
+use crate::shanten::{get_shanten, get_waits, tiles_from_pais};
+
+use convlog::Pai;
+use serde::Serialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// The concealed tehai of an opponent at a decision of the target actor, for
+/// checking afterwards whether a push was actually safe.
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct OpponentHand {
+    pub actor: u8,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub tehai: Vec<Pai>,
+    pub shanten: i8,
+    /// Empty unless tenpai.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub waits: Vec<Pai>,
+}
+
+/// The tehais of the opponents of `target_actor` on `board`, each of 3n+1
+/// pais as the target actor only decides on others' dahai and kakan or on
+/// their own tsumo.
+///
+/// Opponents whose tehai is hidden, as in a mjai log recorded from one seat,
+/// are left out.
+pub fn opponent_hands(board: &BoardState, target_actor: u8) -> Vec<OpponentHand> {
+    (0..4)
+        .filter(|&actor| actor != target_actor && board.is_known(actor))
+        .map(|actor| {
+            let state = board.state(actor);
+            let mut tehai = state.tehai.view().to_vec();
+            tehai.sort_unstable_by_key(|p| p.as_ord());
+            let tiles = tiles_from_pais(&tehai);
+            let fuuro_count = state.fuuro_count(actor);
+            let shanten = get_shanten(&tiles, fuuro_count);
+            let waits = if shanten == 0 {
+                get_waits(&tiles, fuuro_count)
+            } else {
+                vec![]
+            };
+
+            OpponentHand {
+                actor,
+                tehai,
+                shanten,
+                waits,
+            }
+        })
+        .collect()
+}
@@ -1,13 +1,27 @@
+use crate::analyzer::Annotation;
+use crate::cache::{EvalCache, KeyHasher};
+use crate::defense::{self, ThreatSafety, ThreatState};
+use crate::engine::{self, Akochan, EngineArgs, Transcript};
+use crate::kan::{self, KanEvaluator, KanOpportunity};
 use crate::log;
-use crate::state::State;
-use std::io::prelude::*;
-use std::io::BufReader;
+use crate::prescreen;
+use crate::reveal::{self, OpponentHand};
+use crate::rollout::Rollout;
+use crate::scoring::{HoraScore, HoraTracker};
+use crate::state::{DoraCount, Furiten, State, Ukeire};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::iter;
+use std::mem;
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
 
-use anyhow::{bail, Context, Result};
-use convlog::mjai::Event;
+use anyhow::{anyhow, bail, Context, Error, Result};
+use convlog::mjai::{Event, RyukyokuReason};
 use convlog::Pai;
+use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 use serde_with::{serde_as, DisplayFromStr};
@@ -15,9 +29,39 @@ use serde_with::{serde_as, DisplayFromStr};
 pub struct Review {
     pub total_reviewed: usize,
     pub total_tolerated: usize,
+    /// Decisions judged `Acceptance::Equivalent`, which count as agreements.
+    pub total_equivalent: usize,
     pub total_problems: usize,
+    pub severity_counts: SeverityCounts,
+    /// Number of disagreements of each category.
+    pub category_counts: BTreeMap<Category, usize>,
+    pub by_decision_type: BTreeMap<DecisionType, DecisionStat>,
     pub score: f64,
     pub kyokus: Vec<KyokuReview>,
+    pub ev_trend: EvTrend,
+}
+
+/// EV of the target actor's actual action at every decision across the game.
+///
+/// With `--use-placement-ev` the EV is the negated expected final placement.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvTrend {
+    pub points: Vec<EvPoint>,
+    /// Sum of the EV losses of all disagreements.
+    pub total_loss: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvPoint {
+    pub kyoku: u8,
+    pub honba: u8,
+    pub junme: u8,
+    pub ev: f64,
+    /// Only set for disagreements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ev_loss: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
 }
 
 #[derive(Debug, Clone, Default, Serialize)]
@@ -25,10 +69,46 @@ pub struct KyokuReview {
     pub kyoku: u8, // in tenhou.net/6 format, counts from 0
     pub honba: u8,
     pub end_status: Vec<Event>, // must be either multiple Horas or one Ryukyoku
+    pub horas: Vec<HoraScore>,
+    pub kans: Vec<KanOpportunity>,
+    /// One point per dahai decision of the target actor.
+    pub timeline: Vec<TimelinePoint>,
+    /// Events from start_kyoku to end_kyoku, for the board replay.
+    pub events: Vec<Event>,
+    /// Left out of the review as no decision looked suboptimal, see
+    /// `prescreen`.
+    pub screened: bool,
 
     pub entries: Vec<Entry>,
 }
 
+impl KyokuReview {
+    /// Scores right after the event at `event_index`, with the reach sticks
+    /// already paid.
+    pub fn scores_at(&self, event_index: usize) -> [i32; 4] {
+        let mut scores = [0; 4];
+        for event in self.events.iter().take(event_index + 1) {
+            match *event {
+                Event::StartKyoku { scores: s, .. } => scores = s,
+                Event::ReachAccepted { actor } => scores[actor as usize] -= 1000,
+                _ => (),
+            }
+        }
+
+        scores
+    }
+}
+
+/// The target actor's situation right after a dahai decision.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelinePoint {
+    pub junme: u8,
+    /// akochan's estimated deal-in probability of the actual dahai.
+    pub deal_in: Option<f64>,
+    /// Shanten after the actual dahai.
+    pub shanten: Option<i8>,
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize)]
 pub struct Entry {
@@ -38,119 +118,673 @@ pub struct Entry {
     #[serde_as(as = "DisplayFromStr")]
     pub pai: Pai,
     pub is_kakan: bool, // for chankan
+    /// Index of the event being responded to in `KyokuReview::events`.
+    pub event_index: usize,
     pub state: State,
+    /// Dora in the hand at the decision, see `State::dora_count`.
+    pub dora: DoraCount,
+    pub furiten: Furiten,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub furiten_impact: Option<FuritenImpact>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_ukeire: Option<Ukeire>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_ukeire: Option<Ukeire>,
+    /// Actors that have reached or are obviously tenpai at this moment.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub threats: Vec<u8>,
+    /// Reach pais, fuuros and genbutsu of `threats`, in the same order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub threat_states: Vec<ThreatState>,
+    /// Concealed tehais and waits of the opponents whose tehais the log has.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub opponent_hands: Vec<OpponentHand>,
+    /// Deal-in probabilities of the expected and the actual action, only set
+    /// when there are threats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_deal_in: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_deal_in: Option<f64>,
 
     pub expected: Vec<Event>, // at most 2 events
     pub actual: Vec<Event>,   // at most 2 events
-
+    /// EV of the expected action minus EV of the actual action, `None` if
+    /// either of them is unavailable.
+    pub ev_loss: Option<f64>,
+    /// Only set for disagreements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Only set for disagreements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<Category>,
+    /// Best move of the first review of the decision, only set when it
+    /// disagreed and the decision was reviewed again with the heavier tactics
+    /// config of `--verify-tactics`, which the rest of the entry is from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shallow_expected: Option<Vec<Event>>,
+    /// Review of the same decision with the second tactics config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second_opinion: Option<SecondOpinion>,
+    /// Rollouts of the expected and the actual move, only for blunders when
+    /// `--rollouts` is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<Rollout>,
+    /// EV of the best candidate minus EV of the second best, which tells how
+    /// close the decision was. `None` if there is only one candidate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runner_up_gap: Option<f64>,
+
+    /// Every candidate akochan considered, from the best.
     pub details: Vec<DetailedAction>,
+
+    /// Notes the analyzers left on this decision.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
 }
 
+/// Sets `ev_gap` of each of `actions`, which akochan gives from the best,
+/// along with `deal_in_loss`.
+pub fn fill_ev_gaps(actions: &mut [DetailedAction]) {
+    let best_ev = actions.first().and_then(|d| d.review.pt_exp_total);
+    for action in actions {
+        action.ev_gap = best_ev
+            .zip(action.review.pt_exp_total)
+            .map(|(best, ev)| best - ev);
+        action.deal_in_loss = action
+            .review
+            .pt_exp_after
+            .zip(action.review.total_houjuu_hai_value_now)
+            .map(|(passed, dealt_in)| passed - dealt_in);
+    }
+}
+
+/// Average EV loss per decision, as a fraction of the EV range, at which the
+/// rating halves.
+const RATING_HALF_LOSS: f64 = 0.002;
+
+/// Rates a game from 0 to 100 by the average EV loss per reviewed decision,
+/// so that playing exactly like akochan scores 100.
+///
+/// The loss is taken relative to `ev_range`, the gap between the pt of the
+/// 1st and the 4th, so that ratings under different pt are comparable.
+/// Returns `None` if nothing is reviewed.
+pub fn rating(total_ev_loss: f64, total_reviewed: usize, ev_range: f64) -> Option<f64> {
+    if total_reviewed == 0 || ev_range <= 0. {
+        return None;
+    }
+    let avg_loss = total_ev_loss / total_reviewed as f64 / ev_range;
+    Some(100. * 0.5f64.powf(avg_loss / RATING_HALF_LOSS))
+}
+
+/// Returns up to `n` disagreements in `kyokus` with the largest EV loss, each
+/// with the kyoku it belongs to, from the worst.
+pub fn worst_entries(kyokus: &[KyokuReview], n: usize) -> Vec<(&KyokuReview, &Entry)> {
+    let mut worst: Vec<_> = kyokus
+        .iter()
+        .flat_map(|k| k.entries.iter().map(move |e| (k, e)))
+        .filter(|(_, e)| e.acceptance == Acceptance::Disagree && e.ev_loss.is_some())
+        .collect();
+    worst.sort_by(|(_, a), (_, b)| b.ev_loss.partial_cmp(&a.ev_loss).unwrap_or(Ordering::Equal));
+    worst.truncate(n);
+
+    worst
+}
+
+impl Entry {
+    /// Guesses the category of the disagreement from the moves, the threats
+    /// and the ukeire, taking the first that applies of:
+    ///
+    /// 1. kan judgment, if either move is a kan;
+    /// 2. riichi judgment, if either move declares riichi;
+    /// 3. call judgment, if either move is not a plain dahai, like chi, pon,
+    ///    agari or passing;
+    /// 4. push/fold, if someone is a threat and the deal-in probabilities of
+    ///    the moves differ by a few percent;
+    /// 5. yaku selection, if the actual dahai is no less efficient than the
+    ///    expected one, so the difference must be in the value of the hand;
+    /// 6. efficiency otherwise.
+    pub fn categorize(&self) -> Category {
+        const PUSH_FOLD_DEAL_IN_MARGIN: f64 = 0.03;
+
+        let moves = || self.expected.iter().chain(&self.actual);
+        if moves().any(|ev| {
+            matches!(
+                ev,
+                Event::Ankan { .. } | Event::Kakan { .. } | Event::Daiminkan { .. }
+            )
+        }) {
+            return Category::KanJudgment;
+        }
+        if moves().any(|ev| matches!(ev, Event::Reach { .. })) {
+            return Category::RiichiJudgment;
+        }
+        if dahai_pai(&self.expected).is_none() || dahai_pai(&self.actual).is_none() {
+            return Category::CallJudgment;
+        }
+
+        if let (Some(expected), Some(actual)) = (self.expected_deal_in, self.actual_deal_in) {
+            if (expected - actual).abs() >= PUSH_FOLD_DEAL_IN_MARGIN {
+                return Category::PushFold;
+            }
+        }
+
+        match (&self.expected_ukeire, &self.actual_ukeire) {
+            (Some(expected), Some(actual))
+                if actual.shanten < expected.shanten
+                    || (actual.shanten == expected.shanten && actual.count >= expected.count) =>
+            {
+                Category::YakuSelection
+            }
+            _ => Category::Efficiency,
+        }
+    }
+
+    /// Tells the type of the decision from the moves, taking the first that
+    /// applies of kan, riichi, call (if either move is not a plain dahai) and
+    /// discard, like `categorize` does for disagreements.
+    pub fn decision_type(&self) -> DecisionType {
+        let moves = || self.expected.iter().chain(&self.actual);
+        if moves().any(|ev| {
+            matches!(
+                ev,
+                Event::Ankan { .. } | Event::Kakan { .. } | Event::Daiminkan { .. }
+            )
+        }) {
+            DecisionType::Kan
+        } else if moves().any(|ev| matches!(ev, Event::Reach { .. })) {
+            DecisionType::Riichi
+        } else if dahai_pai(&self.expected).is_none() || dahai_pai(&self.actual).is_none() {
+            DecisionType::Call
+        } else {
+            DecisionType::Discard
+        }
+    }
+
+    /// 1-based rank of the actual action among akochan's candidates.
+    pub fn actual_rank(&self) -> Option<usize> {
+        self.details
+            .iter()
+            .position(|d| compare_action_strict(&self.actual, &d.moves))
+            .map(|i| i + 1)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecondOpinion {
+    pub acceptance: Acceptance,
+    pub expected: Vec<Event>,
+    pub ev_loss: Option<f64>,
+    /// Whether or not the best move is different from the main config's.
+    pub disagrees: bool,
+}
+
+/// Describes how furiten differs between the expected and the actual dahai.
+///
+/// For each side, `None` means not tenpai after the dahai, `Some(true)` means
+/// tenpai but furiten, and `Some(false)` means tenpai without furiten.
 #[derive(Debug, Clone, Copy, Serialize)]
+pub struct FuritenImpact {
+    pub expected: Option<bool>,
+    pub actual: Option<bool>,
+}
+
+/// Emitted by `review` as the review goes on, so that the caller can show
+/// partial results before the whole log is reviewed.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewEvent<'a> {
+    Progress {
+        kyoku: u8,
+        honba: u8,
+        junme: u8,
+        progress: f32,
+    },
+    Entry {
+        kyoku: u8,
+        honba: u8,
+        entry: &'a Entry,
+    },
+    EndKyoku {
+        kyoku_review: &'a KyokuReview,
+    },
+    Done {
+        total_reviewed: usize,
+        total_tolerated: usize,
+        total_equivalent: usize,
+        total_problems: usize,
+        score: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Acceptance {
     Disagree,
     Tolerable,
+    /// Not the best move, but within the noise of akochan's simulations from
+    /// it, see `is_near_tie`.
+    Equivalent,
     Agree,
 }
 
+/// How bad a disagreement is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Minor,
+    Significant,
+    Blunder,
+}
+
+/// What kind of judgment a disagreement is about, guessed by
+/// `Entry::categorize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Efficiency,
+    PushFold,
+    RiichiJudgment,
+    CallJudgment,
+    YakuSelection,
+    KanJudgment,
+}
+
+/// What a decision is about, see `Entry::decision_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionType {
+    Discard,
+    Call,
+    Riichi,
+    Kan,
+}
+
+/// Agreements on one type of decision.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DecisionStat {
+    pub reviewed: usize,
+    pub agreed: usize,
+    pub agree_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SeverityCounts {
+    pub minor: usize,
+    pub significant: usize,
+    pub blunder: usize,
+}
+
+impl Category {
+    /// The name used in JSON reports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Efficiency => "efficiency",
+            Category::PushFold => "push_fold",
+            Category::RiichiJudgment => "riichi_judgment",
+            Category::CallJudgment => "call_judgment",
+            Category::YakuSelection => "yaku_selection",
+            Category::KanJudgment => "kan_judgment",
+        }
+    }
+}
+
+impl SeverityCounts {
+    fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Minor => self.minor += 1,
+            Severity::Significant => self.significant += 1,
+            Severity::Blunder => self.blunder += 1,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stat {
     // these Options are None iff `rule_base_flag && !ori_flag` is true in akochan
-    total_houjuu_hai_prob_now: Option<f64>,
-    total_houjuu_hai_value_now: Option<f64>,
-    pt_exp_after: Option<f64>,
-    pt_exp_total: Option<f64>,
+    pub total_houjuu_hai_prob_now: Option<f64>,
+    pub total_houjuu_hai_value_now: Option<f64>,
+    pub pt_exp_after: Option<f64>,
+    pub pt_exp_total: Option<f64>,
+    /// Standard error of `pt_exp_total` over the simulations, only output by
+    /// akochan builds that report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pt_exp_total_std: Option<f64>,
+    /// Probabilities of finishing 1st to 4th, only output by akochan builds
+    /// that report `jun_prob`.
+    #[serde(default, alias = "jun_prob", skip_serializing_if = "Option::is_none")]
+    pub rank_probs: Option<[f64; 4]>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetailedAction {
     pub moves: Vec<Event>,
     pub review: Stat,
+    /// EV of the best candidate minus EV of this one, set by
+    /// [`fill_ev_gaps`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ev_gap: Option<f64>,
+    /// How much lower the EV is if the move deals in than if it passes, set
+    /// by [`fill_ev_gaps`]. Together with the deal-in probability, this is
+    /// the part of the EV that the deal-in risk costs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deal_in_loss: Option<f64>,
+    /// Safety of the dahai in `moves` against each threat.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub safety: Vec<ThreatSafety>,
+}
+
+/// Which decisions to review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewMode {
+    /// Every decision.
+    Full,
+    /// Only decisions made while facing a reached or obviously tenpai
+    /// opponent.
+    Defense,
+}
+
+impl FromStr for ReviewMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "full" => Ok(Self::Full),
+            "defense" => Ok(Self::Defense),
+            _ => Err(anyhow!("unknown review mode: {:?}", s)),
+        }
+    }
+}
+
+/// An inclusive range of junme to review. Format: "10", "5-8", "10-" or "-8".
+#[derive(Debug, Clone, Copy)]
+pub struct JunmeRange {
+    min: u8,
+    max: u8,
+}
+
+impl Default for JunmeRange {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: u8::MAX,
+        }
+    }
+}
+
+impl JunmeRange {
+    #[inline]
+    pub fn contains(self, junme: u8) -> bool {
+        (self.min..=self.max).contains(&junme)
+    }
+}
+
+impl FromStr for JunmeRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |v: &str, default| {
+            let v = v.trim();
+            if v.is_empty() {
+                Ok(default)
+            } else {
+                v.parse::<u8>()
+                    .with_context(|| format!("invalid junme: {:?}", v))
+            }
+        };
+
+        let range = if let Some((min, max)) = s.split_once('-') {
+            Self {
+                min: parse(min, 0)?,
+                max: parse(max, u8::MAX)?,
+            }
+        } else {
+            let junme = parse(s, 0)?;
+            Self {
+                min: junme,
+                max: junme,
+            }
+        };
+        if range.min > range.max {
+            return Err(anyhow!("empty junme range: {:?}", s));
+        }
+
+        Ok(range)
+    }
 }
 
 pub struct ReviewArgs<'a> {
     pub akochan_exe: &'a Path,
     pub akochan_dir: &'a Path,
+    pub akochan_lib: Option<&'a Path>,
+    /// Address of an `engine-server` to get akochan from.
+    pub engine_server: Option<&'a str>,
+    pub transcript: Option<&'a Transcript>,
+    /// Seed for akochan's Monte Carlo simulations, see [`engine::SEED_ENV`].
+    pub seed: Option<u64>,
     pub tactics_config: &'a Path,
     pub events: &'a [Event],
     pub target_actor: u8,
     pub deviation_threshold: f64,
+    /// EV gap under which a move counts as as good as the best one, when
+    /// akochan does not report the standard errors, see `is_near_tie`.
+    pub tie_epsilon: f64,
+    /// Only decisions within this range are reviewed. akochan still sees the
+    /// whole kyoku as it needs the full context.
+    pub junme_range: JunmeRange,
+    pub mode: ReviewMode,
     pub verbose: bool,
+    /// How many times in a row akochan may be restarted after crashing or
+    /// timing out.
+    pub max_restarts: u32,
+    /// How long to wait for akochan on each decision before restarting it.
+    pub timeout: Option<Duration>,
+    /// Called with every `ReviewEvent` the moment it is produced, in order,
+    /// ending with `ReviewEvent::Done`. Backs `--stream` and
+    /// `--out-format ndjson`.
+    pub on_event: Option<&'a dyn Fn(&ReviewEvent)>,
+    pub cache: Option<&'a EvalCache>,
+    /// Whether an open hand can have tanyao when scoring the horas.
+    pub kuitan: bool,
+    /// Leave out the kyokus where no decision looks suboptimal, see
+    /// `prescreen`.
+    pub prescreen: bool,
+    /// A heavier tactics config to review the disagreements again with,
+    /// judging them by that review instead.
+    pub verify_tactics_config: Option<&'a Path>,
+    /// Evaluate every legal kan with an akochan of its own, see
+    /// `KanEvaluator`.
+    pub kan_report: bool,
 }
 
 pub fn review(review_args: &ReviewArgs) -> Result<Review> {
     let &ReviewArgs {
         akochan_exe,
         akochan_dir,
+        akochan_lib,
+        engine_server,
+        transcript,
+        seed,
         tactics_config,
         events,
         target_actor,
         deviation_threshold,
+        tie_epsilon,
+        junme_range,
+        mode,
         verbose,
+        max_restarts,
+        timeout,
+        on_event,
+        cache,
+        kuitan,
+        prescreen,
+        verify_tactics_config,
+        kan_report,
     } = review_args;
+    let emit = |ev: ReviewEvent| {
+        if let Some(f) = on_event {
+            f(&ev);
+        }
+    };
+
+    // defense mode sends only the kyokus under threat, which leaves the time
+    // to review all of them with the heavier config in the first place
+    let (tactics_config, verify_tactics_config) = match (mode, verify_tactics_config) {
+        (ReviewMode::Defense, Some(verify)) => (verify, None),
+        _ => (tactics_config, verify_tactics_config),
+    };
 
     let mut kyoku_reviews = vec![];
 
-    let target_actor_string = target_actor.to_string();
-    let args = &[
-        "pipe_detailed".as_ref(),
+    let engine_args = EngineArgs {
+        akochan_exe,
+        akochan_dir,
         tactics_config,
-        target_actor_string.as_ref(),
-    ];
+        akochan_lib,
+        server: engine_server,
+        transcript,
+        seed,
+        target_actor,
+        verbose,
+        max_restarts,
+        timeout,
+    };
+    let mut akochan = Akochan::spawn(engine_args)?;
+    let mut verifier = verify_tactics_config.map(|tactics_config| Verifier {
+        engine_args: EngineArgs {
+            tactics_config,
+            transcript: None,
+            ..engine_args
+        },
+        engine: None,
+    });
+    let mut kan_evaluator = kan_report.then(|| KanEvaluator {
+        engine_args: EngineArgs {
+            transcript: None,
+            ..engine_args
+        },
+        engine: None,
+    });
+    let mut total_verified = 0;
+    let mut total_confirmed = 0;
 
-    if verbose {
-        log!("$ cd {:?}", akochan_dir);
+    let events_len = events.len();
+    let (cache_keys, from_cache) = if let Some(cache) = cache {
+        let config = fs::read(tactics_config)
+            .with_context(|| format!("failed to read {:?}", tactics_config))?;
+        let hasher = KeyHasher::new(&config, target_actor, seed);
+        let plan = plan_cache(events, target_actor, hasher, cache);
+        let cached_kyokus = events
+            .iter()
+            .zip(&plan.1)
+            .filter(|(ev, &c)| c && matches!(ev, Event::StartKyoku { .. }))
+            .count();
+        log!("{} kyoku(s) will be served from the cache", cached_kyokus);
+        plan
+    } else {
+        (vec![None; events_len], vec![false; events_len])
+    };
+
+    let screened = if prescreen {
+        let screened = prescreen::plan(events, target_actor)?;
+        let screened_kyokus = events
+            .iter()
+            .zip(&screened)
+            .filter(|(ev, &s)| s && matches!(ev, Event::StartKyoku { .. }))
+            .count();
         log!(
-            "$ {:?}{}",
-            akochan_exe,
-            args.iter()
-                .fold("".to_owned(), |acc, p| format!("{} {:?}", acc, p))
+            "{} kyoku(s) without suspicious decisions will not be reviewed",
+            screened_kyokus
         );
-    }
-
-    let mut akochan = Command::new(akochan_exe)
-        .args(args)
-        .current_dir(Path::new(akochan_dir))
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .context("failed to spawn akochan")?;
-
-    let stdin = akochan
-        .stdin
-        .as_mut()
-        .context("failed to get stdin of akochan")?;
-    let mut stdout_lines = BufReader::new(
-        akochan
-            .stdout
-            .as_mut()
-            .context("failed to get stdout of akochan")?,
-    )
-    .lines();
+        screened
+    } else {
+        vec![false; events_len]
+    };
+    let unthreatened = if mode == ReviewMode::Defense {
+        let unthreatened = defense::plan(events, target_actor)?;
+        let unthreatened_kyokus = events
+            .iter()
+            .zip(&unthreatened)
+            .filter(|(ev, &u)| u && matches!(ev, Event::StartKyoku { .. }))
+            .count();
+        log!(
+            "{} kyoku(s) without decisions under threat will not be reviewed",
+            unthreatened_kyokus
+        );
+        unthreatened
+    } else {
+        vec![false; events_len]
+    };
+
+    // the bar advances once per decision, so that its ETA is based on how
+    // long akochan takes for each one
+    let total_decisions = events
+        .iter()
+        .filter(|ev| engine::is_review_timing(ev, target_actor))
+        .count();
+    let bar = if verbose {
+        ProgressBar::hidden()
+    } else {
+        progress_bar(total_decisions as u64)
+    };
+    // fall back to the log lines when the bar is not shown, e.g. stderr is
+    // not a terminal
+    let log_progress = bar.is_hidden();
 
-    let events_len = events.len();
     let mut total_reviewed = 0;
     let mut total_tolerated = 0;
+    let mut total_equivalent = 0;
     let mut total_problems = 0;
+    let mut severity_counts = SeverityCounts::default();
+    let mut category_counts = BTreeMap::new();
+    let mut by_decision_type: BTreeMap<DecisionType, DecisionStat> = BTreeMap::new();
+    let mut ev_trend = EvTrend::default();
     let mut raw_score = 0.;
 
     let mut kyoku_review = KyokuReview::default();
     let mut state = State::new(target_actor);
+    let mut hora_tracker = HoraTracker::new().with_kuitan(kuitan);
     let mut junme = 0;
     let mut entries = vec![];
     let mut is_reached = false;
 
+    // where the current kyoku begins, for replaying it to a restarted akochan
+    let mut kyoku_start = 0;
+    let history = |i: usize, kyoku_start: usize| -> Vec<&Event> {
+        iter::once(&events[0])
+            .chain(&events[kyoku_start.max(1)..=i])
+            .collect()
+    };
+
     for (i, event) in events.iter().enumerate() {
-        let to_write = json::to_string(event).unwrap();
-        writeln!(stdin, "{}", to_write).context("failed to write to akochan")?;
-        if verbose {
-            log!("> {}", to_write);
+        if let Event::StartKyoku { .. } = event {
+            kyoku_start = i;
+        }
+        if !from_cache[i] && !screened[i] && !unthreatened[i] {
+            if let Err(err) = akochan.send(event) {
+                bar.suspend(|| akochan.recover(err, &history(i, kyoku_start)))?;
+            }
         }
 
         // upate the state
         state.update(event).context("failed to update state")?;
+        if !matches!(event, Event::StartGame { .. } | Event::EndGame) {
+            kyoku_review.events.push(event.clone());
+        }
+        if let Some(hora) = hora_tracker.update(event)? {
+            if !hora.is_consistent {
+                bar.suspend(|| {
+                    log!(
+                        "WARNING: recorded score of hora by {} differs from the calculated one: {:?}",
+                        hora.actor,
+                        hora,
+                    )
+                });
+            }
+            kyoku_review.horas.push(hora);
+        }
 
         // this match does two things:
         // 1. setting board metadata like bakaze, kyoku, honba, junme
@@ -165,7 +799,14 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
                 let kyoku = (bakaze.as_u8() - Pai::East.as_u8()) * 4 + kk - 1;
                 kyoku_review.kyoku = kyoku;
                 kyoku_review.honba = honba;
+                kyoku_review.screened = screened[i];
                 is_reached = false;
+                if let Some(verifier) = &mut verifier {
+                    verifier.finish();
+                }
+                if let Some(kan_evaluator) = &mut kan_evaluator {
+                    kan_evaluator.finish();
+                }
 
                 continue;
             }
@@ -174,6 +815,9 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
                 kyoku_review.entries = entries.clone();
                 entries.clear();
 
+                emit(ReviewEvent::EndKyoku {
+                    kyoku_review: &kyoku_review,
+                });
                 kyoku_reviews.push(kyoku_review.clone());
                 kyoku_review = KyokuReview::default();
 
@@ -216,35 +860,147 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
             _ => continue,
         };
 
-        log!(
-            "reviewing kyoku={} honba={} junme={} ({:.2}%)",
-            kyoku_review.kyoku,
-            kyoku_review.honba,
+        let progress = (i as f32) / (events_len as f32) * 100f32;
+        if log_progress {
+            log!(
+                "reviewing kyoku={} honba={} junme={} ({:.2}%)",
+                kyoku_review.kyoku,
+                kyoku_review.honba,
+                junme,
+                progress,
+            );
+        } else {
+            bar.set_message(format!(
+                "kyoku={} honba={} junme={}",
+                kyoku_review.kyoku, kyoku_review.honba, junme,
+            ));
+        }
+        emit(ReviewEvent::Progress {
+            kyoku: kyoku_review.kyoku,
+            honba: kyoku_review.honba,
             junme,
-            (i as f32) / (events_len as f32) * 100f32,
-        );
+            progress,
+        });
 
         // should have at least 4, e.g. dahai -> ryukyoku -> end_kyoku -> end_game
         if events.len() < i + 4 {
             bail!("wrong size of input events, expected to have 4 more");
         }
 
-        // be careful, stdout_lines.next() may block.
-        let line = stdout_lines
-            .next()
-            .context("failed to read from akochan: unexpected EOF")?
-            .context("failed to read from akochan")?;
-        if verbose {
-            log!("< {}", line.trim());
+        if screened[i] || unthreatened[i] {
+            bar.inc(1);
+            continue;
+        }
+        let line = match (cache, &cache_keys[i]) {
+            (Some(cache), Some(key)) if from_cache[i] => cache
+                .get(key)
+                .with_context(|| format!("cache entry {} vanished", key))?,
+            _ => {
+                let line = loop {
+                    match akochan.recv() {
+                        Ok(line) => break line,
+                        Err(err) => {
+                            bar.suspend(|| akochan.recover(err, &history(i, kyoku_start)))?
+                        }
+                    }
+                };
+                if let (Some(cache), Some(key)) = (cache, &cache_keys[i]) {
+                    cache.put(key, &line)?;
+                }
+                line
+            }
+        };
+        bar.inc(1);
+        // akochan responds at every timing, so the response must have been
+        // consumed before skipping the review
+        if !junme_range.contains(junme) {
+            continue;
+        }
+        let threats = defense::threats(&state, target_actor);
+        if mode == ReviewMode::Defense && threats.is_empty() {
+            continue;
         }
 
-        let actions: Vec<DetailedAction> =
+        let mut actions: Vec<DetailedAction> =
             json::from_str(&line).context("failed to parse JSON output of akochan")?;
+        fill_ev_gaps(&mut actions);
 
         if actions.is_empty() || actions.iter().any(|a| a.moves.is_empty()) {
-            log!("WARNING: actions or some moves in actions is empty");
+            bar.suspend(|| log!("WARNING: actions or some moves in actions is empty"));
+            continue;
+        }
+
+        let sanchaho = sanchaho_ron(&events[i..], target_actor);
+        let actual_for_compare = match &sanchaho {
+            Some(ron) => &ron[..],
+            None => next_action_for_compare(&events[(i + 1)..]),
+        };
+
+        // a disagreement of the first review may come from the noise of its
+        // simulations, so it is judged by the heavier review instead
+        let mut shallow_expected = None;
+        if let Some(verifier) = &mut verifier {
+            if !compare_action(actual_for_compare, &actions[0].moves, target_actor)
+                .context("invalid state in event")?
+            {
+                match verifier.review(events, kyoku_start, i) {
+                    Ok(deep_actions) => {
+                        shallow_expected = Some(actions[0].moves.clone());
+                        actions = deep_actions;
+                    }
+                    Err(err) => {
+                        bar.suspend(|| {
+                            log!(
+                                "WARNING: failed to verify the decision at kyoku={} honba={} junme={}: {:?}",
+                                kyoku_review.kyoku,
+                                kyoku_review.honba,
+                                junme,
+                                err,
+                            )
+                        });
+                    }
+                }
+            }
+        }
+        if is_preempted(&actions[0].moves, actual_for_compare, target_actor) {
+            // whatever the target actor declared, the pai went to someone else
+            if verbose {
+                log!(
+                    "skipping preempted call: {:?}, actual: {:?}",
+                    actions[0].moves,
+                    actual_for_compare,
+                );
+            }
             continue;
         }
+        // a kyoku from the cache is not worth replaying to akochan for kans
+        if let (Some(kan_evaluator), false) = (&mut kan_evaluator, from_cache[i]) {
+            let actual_for_kan = next_action_strict(actual_for_compare, target_actor);
+            let legal_kans = kan::legal_kans(&state, event, target_actor, hora_tracker.board());
+            kyoku_review.kans.extend(kan::find_opportunities(
+                &legal_kans,
+                &actions,
+                &actual_for_kan,
+                junme,
+                hora_tracker.board(),
+                &threats,
+                |kan| match kan_evaluator.evaluate(events, kyoku_start, i, kan, &state) {
+                    Ok(ev) => ev,
+                    Err(err) => {
+                        bar.suspend(|| {
+                            log!(
+                                "WARNING: failed to evaluate the kan at kyoku={} honba={} junme={}: {:?}",
+                                kyoku_review.kyoku,
+                                kyoku_review.honba,
+                                junme,
+                                err,
+                            )
+                        });
+                        None
+                    }
+                },
+            ));
+        }
 
         // skip the comparision when
         // 1. it is not our turn and there is no chance to naki
@@ -253,15 +1009,41 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
             continue;
         }
 
+        if !threats.is_empty() {
+            for detail in &mut actions {
+                let dahai = detail.moves.iter().find_map(|ev| match *ev {
+                    Event::Dahai { pai, .. } => Some(pai),
+                    _ => None,
+                });
+                if let Some(pai) = dahai {
+                    detail.safety = defense::analyze(&state, &threats, pai);
+                }
+            }
+        }
+
         let expected_action = &actions[0].moves; // best move
-        let actual_action = next_action_for_compare(&events[(i + 1)..]);
+        let actual_action = actual_for_compare;
 
         let is_equal_or_innocent = compare_action(actual_action, expected_action, target_actor)
             .context("invalid state in event")?;
         let actual_action_strict = next_action_strict(actual_action, target_actor);
 
+        let ev_loss = if is_equal_or_innocent {
+            Some(0.)
+        } else {
+            actions[0].review.pt_exp_total.and_then(|expected_ev| {
+                actions
+                    .iter()
+                    .find(|&ex| compare_action_strict(&actual_action_strict, &ex.moves))
+                    .and_then(|detail| detail.review.pt_exp_total)
+                    .map(|actual_ev| expected_ev - actual_ev)
+            })
+        };
+
         let (move_score, acceptance) = if is_equal_or_innocent {
             (1., Acceptance::Agree) // it is an acceptable move
+        } else if is_near_tie(&actions, &actual_action_strict, tie_epsilon) {
+            (1., Acceptance::Equivalent) // either is fine
         } else if deviation_threshold <= 0. {
             (1., Acceptance::Disagree) // not acceptable and no threshold set, deny
         } else if let Some(expected_ev) = actions[0].review.pt_exp_total {
@@ -283,11 +1065,13 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
                 None => {
                     // Usually it is some kind of kan. This is a known issue of akochan.
                     // It can be mitigated by setting `do_kan_ordinary` to true in tactics.json
-                    log!(
-                        "WARNING: unable to find player's action in akochan's return, expected to find: {:?}, list: {:?}",
-                        actual_action_strict,
-                        actions.iter().map(|a| a.moves.clone()).collect::<Vec<_>>(),
-                    );
+                    bar.suspend(|| {
+                        log!(
+                            "WARNING: unable to find player's action in akochan's return, expected to find: {:?}, list: {:?}",
+                            actual_action_strict,
+                            actions.iter().map(|a| a.moves.clone()).collect::<Vec<_>>(),
+                        )
+                    });
                     // Skip this situation as it is unclear for akochan, probably not what
                     // those who set --deviation-threshold expect.
                     continue;
@@ -339,58 +1123,472 @@ pub fn review(review_args: &ReviewArgs) -> Result<Review> {
             }
         };
 
+        let actual_detail = actions
+            .iter()
+            .find(|&ex| compare_action_strict(&actual_action_strict, &ex.moves));
+        let severity = if acceptance == Acceptance::Disagree {
+            Some(grade_severity(&actions, actual_detail))
+        } else {
+            None
+        };
+        let (expected_deal_in, actual_deal_in) = if threats.is_empty() {
+            (None, None)
+        } else {
+            (
+                actions[0].review.total_houjuu_hai_prob_now,
+                actual_detail.and_then(|d| d.review.total_houjuu_hai_prob_now),
+            )
+        };
+
+        if shallow_expected.is_some() {
+            total_verified += 1;
+            if acceptance == Acceptance::Disagree {
+                total_confirmed += 1;
+            }
+        }
         match acceptance {
             Acceptance::Disagree => total_problems += 1,
             Acceptance::Tolerable => total_tolerated += 1,
+            Acceptance::Equivalent => total_equivalent += 1,
             Acceptance::Agree => (),
         };
+        if let Some(severity) = severity {
+            severity_counts.add(severity);
+        }
         total_reviewed += 1;
         raw_score += move_score;
 
-        let entry = Entry {
+        let expected_dahai = dahai_pai(expected_action);
+        let actual_dahai = dahai_pai(&actual_action_strict);
+        let expected_ukeire = expected_dahai.and_then(|p| state.ukeire_after_dahai(p));
+        let actual_ukeire = actual_dahai.and_then(|p| state.ukeire_after_dahai(p));
+
+        let furiten_impact = match (expected_dahai, actual_dahai) {
+            (Some(expected_pai), Some(actual_pai)) if expected_pai != actual_pai => {
+                let impact = FuritenImpact {
+                    expected: state.furiten_after_dahai(expected_pai),
+                    actual: state.furiten_after_dahai(actual_pai),
+                };
+                // only when furiten is what makes the difference
+                if impact.expected != impact.actual
+                    && (impact.expected == Some(true) || impact.actual == Some(true))
+                {
+                    Some(impact)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        let actual_ev = actual_detail
+            .and_then(|d| d.review.pt_exp_total)
+            .or_else(|| {
+                if acceptance == Acceptance::Agree {
+                    actions[0].review.pt_exp_total
+                } else {
+                    None
+                }
+            });
+        if let Some(ev) = actual_ev {
+            let ev_loss = ev_loss.filter(|_| acceptance == Acceptance::Disagree);
+            ev_trend.total_loss += ev_loss.unwrap_or(0.);
+            ev_trend.points.push(EvPoint {
+                kyoku: kyoku_review.kyoku,
+                honba: kyoku_review.honba,
+                junme,
+                ev,
+                ev_loss,
+                severity,
+            });
+        }
+
+        if let (Event::Tsumo { .. }, Some(_)) = (event, actual_dahai) {
+            kyoku_review.timeline.push(TimelinePoint {
+                junme,
+                deal_in: actual_detail.and_then(|d| d.review.total_houjuu_hai_prob_now),
+                shanten: actual_ukeire.as_ref().map(|u| u.shanten),
+            });
+        }
+
+        let mut entry = Entry {
             acceptance,
             junme,
             actor,
             pai,
             is_kakan,
+            event_index: kyoku_review.events.len() - 1,
             state: state.clone(),
+            dora: state.dora_count(),
+            furiten: state.furiten(),
+            furiten_impact,
+            expected_ukeire,
+            actual_ukeire,
+            threat_states: threats
+                .iter()
+                .map(|&actor| ThreatState::new(&state, actor))
+                .collect(),
+            threats,
+            opponent_hands: reveal::opponent_hands(hora_tracker.board(), target_actor),
+            expected_deal_in,
+            actual_deal_in,
             expected: expected_action.to_vec(),
             actual: actual_action_strict,
+            ev_loss,
+            severity,
+            category: None,
+            shallow_expected,
+            second_opinion: None,
+            rollout: None,
+            runner_up_gap: actions.get(1).and_then(|d| d.ev_gap),
             details: actions,
+            annotations: vec![],
         };
-        log!(
-            "review entry created: {:?} ({}/{}/{}, {:.03})",
-            acceptance,
-            total_problems,
-            total_tolerated,
-            total_reviewed,
-            (raw_score / total_reviewed as f64).powf(2.) * 100.,
-        );
+        let stat = by_decision_type.entry(entry.decision_type()).or_default();
+        stat.reviewed += 1;
+        if matches!(acceptance, Acceptance::Agree | Acceptance::Equivalent) {
+            stat.agreed += 1;
+        }
+        if acceptance == Acceptance::Disagree {
+            let category = entry.categorize();
+            entry.category = Some(category);
+            *category_counts.entry(category).or_insert(0) += 1;
+        }
+        if log_progress {
+            log!(
+                "review entry created: {:?} ({}/{}/{}, {:.03})",
+                acceptance,
+                total_problems,
+                total_tolerated,
+                total_reviewed,
+                (raw_score / total_reviewed as f64).powf(2.) * 100.,
+            );
+        }
         if verbose {
             log!("{:?}", entry);
         }
+        emit(ReviewEvent::Entry {
+            kyoku: kyoku_review.kyoku,
+            honba: kyoku_review.honba,
+            entry: &entry,
+        });
 
         entries.push(entry);
     }
+    bar.finish_and_clear();
 
-    let ecode = akochan.wait()?;
-    if !ecode.success() {
-        if let Some(code) = ecode.code() {
-            bail!("non-zero exit code: {}", code);
-        } else {
-            bail!("non-zero exit code: Process terminated by signal");
-        }
+    akochan.finish()?;
+    if let Some(kan_evaluator) = &mut kan_evaluator {
+        kan_evaluator.finish();
+    }
+    if let Some(verifier) = &mut verifier {
+        verifier.finish();
+        log!(
+            "{} of {} verified disagreement(s) were confirmed",
+            total_confirmed,
+            total_verified,
+        );
+    }
+
+    for stat in by_decision_type.values_mut() {
+        stat.agree_rate = stat.agreed as f64 / stat.reviewed as f64;
     }
 
+    let score = (raw_score / total_reviewed as f64).powf(2.);
+    emit(ReviewEvent::Done {
+        total_reviewed,
+        total_tolerated,
+        total_equivalent,
+        total_problems,
+        score,
+    });
+
     Ok(Review {
         total_problems,
         total_tolerated,
+        total_equivalent,
+        severity_counts,
+        category_counts,
+        by_decision_type,
         total_reviewed,
-        score: (raw_score / total_reviewed as f64).powf(2.),
+        score,
         kyokus: kyoku_reviews,
+        ev_trend,
     })
 }
 
+/// Replays `events` without akochan into kyokus with the board replay, the
+/// horas and the results but no entry, for the skeleton report of
+/// `--no-review`.
+pub fn replay(events: &[Event], target_actor: u8, kuitan: bool) -> Result<Vec<KyokuReview>> {
+    let mut kyoku_reviews = vec![];
+    let mut kyoku_review = KyokuReview::default();
+    let mut state = State::new(target_actor);
+    let mut hora_tracker = HoraTracker::new().with_kuitan(kuitan);
+
+    for event in events {
+        state.update(event).context("failed to update state")?;
+        if let Some(hora) = hora_tracker.update(event)? {
+            kyoku_review.horas.push(hora);
+        }
+
+        match *event {
+            Event::StartGame { .. } | Event::EndGame => continue,
+            Event::StartKyoku {
+                bakaze,
+                kyoku,
+                honba,
+                ..
+            } => {
+                kyoku_review.kyoku = (bakaze.as_u8() - Pai::East.as_u8()) * 4 + kyoku - 1;
+                kyoku_review.honba = honba;
+            }
+            Event::Hora { .. } | Event::Ryukyoku { .. } => {
+                kyoku_review.end_status.push(event.clone());
+            }
+            _ => (),
+        }
+
+        kyoku_review.events.push(event.clone());
+        if let Event::EndKyoku = event {
+            kyoku_reviews.push(mem::take(&mut kyoku_review));
+        }
+    }
+
+    Ok(kyoku_reviews)
+}
+
+/// Attaches the entries of a review done with another tactics config to the
+/// matching entries in `kyokus`, returns the number of decisions where the
+/// best moves of the two differ.
+///
+/// Both reviews must be of the same log. Entries only present in one of them,
+/// e.g. because only one config has a choice other than tsumogiri, are left
+/// untouched.
+pub fn attach_second_opinions(kyokus: &mut [KyokuReview], second: &[KyokuReview]) -> usize {
+    let mut disagreements = 0;
+
+    for (kyoku, second_kyoku) in kyokus.iter_mut().zip(second) {
+        for entry in &mut kyoku.entries {
+            let other = second_kyoku.entries.iter().find(|o| {
+                o.junme == entry.junme
+                    && o.actor == entry.actor
+                    && o.pai == entry.pai
+                    && o.is_kakan == entry.is_kakan
+            });
+            if let Some(other) = other {
+                let disagrees = other.expected != entry.expected;
+                if disagrees {
+                    disagreements += 1;
+                }
+                entry.second_opinion = Some(SecondOpinion {
+                    acceptance: other.acceptance,
+                    expected: other.expected.clone(),
+                    ev_loss: other.ev_loss,
+                    disagrees,
+                });
+            }
+        }
+    }
+
+    disagreements
+}
+
+/// How many standard errors of the EV gap make a near tie.
+const TIE_SIGMAS: f64 = 2.;
+
+/// Whether the EV of `actual` is within the noise of akochan's simulations
+/// from that of the best move, i.e. `TIE_SIGMAS` times the standard error of
+/// the gap if akochan reports the standard errors, or `epsilon` otherwise.
+///
+/// Without a noise band, e.g. when `epsilon` is 0, nothing is a near tie.
+fn is_near_tie(actions: &[DetailedAction], actual: &[Event], epsilon: f64) -> bool {
+    let best = &actions[0].review;
+    let actual = match actions
+        .iter()
+        .find(|a| compare_action_strict(actual, &a.moves))
+    {
+        Some(detail) => &detail.review,
+        None => return false,
+    };
+    let (best_ev, actual_ev) = match (best.pt_exp_total, actual.pt_exp_total) {
+        (Some(best), Some(actual)) => (best, actual),
+        _ => return false,
+    };
+    let band = match (best.pt_exp_total_std, actual.pt_exp_total_std) {
+        (Some(best), Some(actual)) => TIE_SIGMAS * (best * best + actual * actual).sqrt(),
+        _ => epsilon,
+    };
+
+    band > 0. && best_ev - actual_ev <= band
+}
+
+/// akochan with the heavier tactics config of `--verify-tactics`. As akochan
+/// evaluates every decision it is sent, it is only sent the current kyoku up
+/// to the last decision being verified.
+struct Verifier<'a> {
+    engine_args: EngineArgs<'a>,
+    /// akochan of the current kyoku and the index of the next event to send
+    /// to it.
+    engine: Option<(Akochan<'a>, usize)>,
+}
+
+impl Verifier<'_> {
+    /// Reviews the decision at `events[i]` again, after sending the events of
+    /// the kyoku beginning at `kyoku_start` that akochan has not seen yet.
+    fn review(
+        &mut self,
+        events: &[Event],
+        kyoku_start: usize,
+        i: usize,
+    ) -> Result<Vec<DetailedAction>> {
+        if self.engine.is_none() {
+            let mut akochan = Akochan::spawn(self.engine_args)?;
+            akochan.send(&events[0])?; // start_game
+            self.engine = Some((akochan, kyoku_start.max(1)));
+        }
+        let (akochan, next) = self.engine.as_mut().unwrap(); // just set
+        let line = match akochan.replay(&events[*next..=i]) {
+            Ok(line) => line.context("the decision is not a review timing of akochan")?,
+            Err(err) => {
+                // start over in the next decision
+                self.engine = None;
+                return Err(err);
+            }
+        };
+        *next = i + 1;
+
+        let mut actions: Vec<DetailedAction> =
+            json::from_str(&line).context("failed to parse JSON output of akochan")?;
+        fill_ev_gaps(&mut actions);
+        if actions.is_empty() || actions.iter().any(|a| a.moves.is_empty()) {
+            bail!("actions or some moves in actions is empty");
+        }
+
+        Ok(actions)
+    }
+
+    /// Stops akochan of the current kyoku, if any.
+    fn finish(&mut self) {
+        if let Some((mut akochan, _)) = self.engine.take() {
+            if let Err(err) = akochan.send(&Event::EndGame).and_then(|_| akochan.finish()) {
+                log!(
+                    "WARNING: failed to stop akochan for verification: {:?}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Grades a disagreement by its EV loss relative to the spread of EVs among
+/// akochan's candidates, one tier worse if the actual move is much more likely
+/// to deal in than the expected one.
+///
+/// Disagreements without EVs, which happen in early turns or with high
+/// shanten, are considered minor.
+fn grade_severity(actions: &[DetailedAction], actual: Option<&DetailedAction>) -> Severity {
+    const SIGNIFICANT_LOSS: f64 = 0.1;
+    const BLUNDER_LOSS: f64 = 0.3;
+    const DEAL_IN_MARGIN: f64 = 0.1;
+
+    let actual = match actual {
+        Some(a) => &a.review,
+        None => return Severity::Minor,
+    };
+    let expected = &actions[0].review;
+
+    let mut severity = match (
+        expected.pt_exp_total,
+        actual.pt_exp_total,
+        actions.last().and_then(|a| a.review.pt_exp_total),
+    ) {
+        (Some(expected_ev), Some(actual_ev), Some(min_ev)) if expected_ev > min_ev => {
+            let loss = (expected_ev - actual_ev) / (expected_ev - min_ev);
+            if loss >= BLUNDER_LOSS {
+                Severity::Blunder
+            } else if loss >= SIGNIFICANT_LOSS {
+                Severity::Significant
+            } else {
+                Severity::Minor
+            }
+        }
+        _ => Severity::Minor,
+    };
+
+    if let (Some(expected_prob), Some(actual_prob)) = (
+        expected.total_houjuu_hai_prob_now,
+        actual.total_houjuu_hai_prob_now,
+    ) {
+        if actual_prob - expected_prob >= DEAL_IN_MARGIN {
+            severity = match severity {
+                Severity::Minor => Severity::Significant,
+                _ => Severity::Blunder,
+            };
+        }
+    }
+
+    severity
+}
+
+/// A progress bar of `len` decisions, with the time elapsed and the ETA.
+fn progress_bar(len: u64) -> ProgressBar {
+    let bar = ProgressBar::new(len);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {wide_bar} {percent:>3}% {msg} (ETA {eta})",
+        )
+        .unwrap(), // the template is static
+    );
+    bar
+}
+
+/// Returns the cache key of every event akochan responds to, and whether or
+/// not each event belongs to a kyoku that can be entirely served from the
+/// cache, in which case the kyoku is not sent to akochan at all.
+fn plan_cache(
+    events: &[Event],
+    target_actor: u8,
+    mut hasher: KeyHasher,
+    cache: &EvalCache,
+) -> (Vec<Option<String>>, Vec<bool>) {
+    let mut keys = vec![None; events.len()];
+    let mut from_cache = vec![false; events.len()];
+    let mut kyoku_start = 0;
+    let mut all_cached = true;
+
+    for (i, event) in events.iter().enumerate() {
+        let line = json::to_string(event).unwrap();
+        match event {
+            Event::StartGame { .. } => {
+                hasher.update_prefix(&line);
+                continue;
+            }
+            Event::StartKyoku { .. } => {
+                hasher.reset();
+                kyoku_start = i;
+                all_cached = true;
+            }
+            _ => (),
+        }
+        hasher.update(&line);
+
+        if engine::is_review_timing(event, target_actor) {
+            let key = hasher.key();
+            all_cached &= cache.contains(&key);
+            keys[i] = Some(key);
+        }
+
+        if let Event::EndKyoku = event {
+            from_cache[kyoku_start..=i]
+                .iter_mut()
+                .for_each(|c| *c = all_cached);
+        }
+    }
+
+    (keys, from_cache)
+}
+
 fn next_action_for_compare(events: &[Event]) -> &[Event] {
     match events[0] {
         Event::Dora { .. } | Event::ReachAccepted { .. } => next_action_for_compare(&events[1..]),
@@ -400,6 +1598,57 @@ fn next_action_for_compare(events: &[Event]) -> &[Event] {
     }
 }
 
+/// In sanchaho, everyone but the discarder has declared ron, which the log
+/// records as a ryukyoku right after the dahai or kakan at `events[0]`.
+/// Returns the ron of `target_actor` in that case.
+fn sanchaho_ron(events: &[Event], target_actor: u8) -> Option<[Event; 1]> {
+    let discarder = match events[0] {
+        Event::Dahai { actor, .. } | Event::Kakan { actor, .. } if actor != target_actor => actor,
+        _ => return None,
+    };
+    match events.get(1) {
+        Some(Event::Ryukyoku {
+            reason: Some(RyukyokuReason::Sanchaho),
+            ..
+        }) => Some([Event::Hora {
+            actor: target_actor,
+            target: discarder,
+            deltas: None,
+            ura_markers: None,
+        }]),
+        _ => None,
+    }
+}
+
+/// Returns true if the naki in `expected_action` could not have happened
+/// whatever the target actor declared, as another actor took the pai with a
+/// call of higher priority, i.e. a ron over any naki, or a pon or daiminkan
+/// over a chi.
+///
+/// `actual_action` must be the return value of `next_action_for_compare`.
+fn is_preempted(expected_action: &[Event], actual_action: &[Event], target_actor: u8) -> bool {
+    let is_chi = match expected_action[0] {
+        Event::Chi { .. } => true,
+        Event::Pon { .. } | Event::Daiminkan { .. } => false,
+        _ => return false,
+    };
+    // the target actor's own ron is a decision to review
+    if actual_action
+        .iter()
+        .any(|ev| matches!(*ev, Event::Hora { actor, .. } if actor == target_actor))
+    {
+        return false;
+    }
+
+    match actual_action[0] {
+        Event::Hora { actor, .. } => actor != target_actor,
+        Event::Pon { actor, .. } | Event::Daiminkan { actor, .. } => {
+            is_chi && actor != target_actor
+        }
+        _ => false,
+    }
+}
+
 /// Get actual action from target_actor's perspective, which will handle
 /// Event::None and multiple Event::Hora properly.
 ///
@@ -409,6 +1658,13 @@ fn next_action_strict(rough_action: &[Event], target_actor: u8) -> Vec<Event> {
         // passed when it's supposed to naki
         Event::Tsumo { .. } => vec![Event::None],
 
+        // passed and the kyoku is over, unless the target actor declared
+        // kyushukyuhai
+        Event::Ryukyoku {
+            reason: Some(reason),
+            ..
+        } if reason != RyukyokuReason::Kyushukyuhai => vec![Event::None],
+
         // filter the actor's hora from multiple horas
         Event::Hora { .. } => vec![rough_action
             .iter()
@@ -427,6 +1683,16 @@ fn next_action_strict(rough_action: &[Event], target_actor: u8) -> Vec<Event> {
     }
 }
 
+/// Returns the pai to discard in a Dahai or Reach action.
+pub(crate) fn dahai_pai(action: &[Event]) -> Option<Pai> {
+    match action {
+        [Event::Dahai { pai, .. }, ..] | [Event::Reach { .. }, Event::Dahai { pai, .. }, ..] => {
+            Some(*pai)
+        }
+        _ => None,
+    }
+}
+
 /// Returns true if actual_action is the same as expected_action.
 fn compare_action_strict(actual_action: &[Event], expected_action: &[Event]) -> bool {
     expected_action
@@ -437,6 +1703,8 @@ fn compare_action_strict(actual_action: &[Event], expected_action: &[Event]) ->
             (Event::Dahai { pai: ee, .. }, Event::Dahai { pai: aa, .. }) => ee == aa,
             // ignore `delta`
             (Event::Hora { actor: ee, .. }, Event::Hora { actor: aa, .. }) => ee == aa,
+            // akochan does not give the reason
+            (Event::Ryukyoku { .. }, Event::Ryukyoku { .. }) => true,
             _ => e == a,
         })
 }
@@ -463,6 +1731,17 @@ fn compare_action(
     // fallback to slow path.
     let actual = &actual_action[0];
 
+    // the kyoku is aborted or drawn out right after the target actor passed
+    if let Event::Ryukyoku {
+        reason: Some(reason),
+        ..
+    } = *actual
+    {
+        if reason != RyukyokuReason::Kyushukyuhai {
+            return Ok(expected_action[0] == Event::None);
+        }
+    }
+
     match expected_action[0] {
         Event::Dahai { pai, .. } => {
             match *actual {
@@ -0,0 +1,550 @@
+//! Monte Carlo rollouts from a decision, played out by akochan in every seat
+//! until the end of the kyoku, to express the cost of a mistake in points.
+
+use crate::engine::{is_review_timing, Akochan, EngineArgs};
+use crate::log;
+use crate::review::{DetailedAction, KyokuReview, Severity};
+use crate::scoring::{hora_deltas, HoraTracker};
+use std::iter;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde::Serialize;
+use serde_json as json;
+
+/// Upper bound of events in one rollout, which a real kyoku never reaches.
+const MAX_STEPS: usize = 1000;
+
+pub struct RolloutArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    pub akochan_lib: Option<&'a Path>,
+    pub engine_server: Option<&'a str>,
+    pub tactics_config: &'a Path,
+    pub target_actor: u8,
+    /// Number of rollouts for each of the actual and the expected move.
+    pub rollouts: u32,
+    pub timeout: Option<Duration>,
+    /// Makes the walls of the rollouts the same on every run, and is passed
+    /// to akochan as well.
+    pub seed: Option<u64>,
+    pub verbose: bool,
+}
+
+/// Score changes of the target actor from the decision to the end of the
+/// kyoku, over all rollouts of one move.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreDistribution {
+    pub samples: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: i32,
+    /// 10th, 50th and 90th percentiles.
+    pub percentiles: [i32; 3],
+    pub max: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Rollout {
+    pub expected: ScoreDistribution,
+    pub actual: ScoreDistribution,
+    /// Mean score change of the expected move minus that of the actual move.
+    pub cost: f64,
+}
+
+/// Runs rollouts from every blunder in `kyokus` and attaches the results to
+/// the entries. Returns the number of entries that got a result.
+///
+/// A failed rollout is skipped with a warning, so that hours of review are
+/// not lost to it.
+pub fn attach_rollouts(kyokus: &mut [KyokuReview], args: &RolloutArgs) -> usize {
+    let mut attached = 0;
+
+    for kyoku in kyokus {
+        for entry in &mut kyoku.entries {
+            if entry.severity != Some(Severity::Blunder) {
+                continue;
+            }
+            let history = &kyoku.events[..=entry.event_index];
+            log!(
+                "running rollouts for the blunder at kyoku {} junme {}...",
+                kyoku.kyoku,
+                entry.junme,
+            );
+
+            let expected = distribution(args, history, &entry.expected);
+            let actual = distribution(args, history, &entry.actual);
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                entry.rollout = Some(Rollout {
+                    cost: expected.mean - actual.mean,
+                    expected,
+                    actual,
+                });
+                attached += 1;
+            }
+        }
+    }
+
+    attached
+}
+
+fn distribution(
+    args: &RolloutArgs,
+    history: &[Event],
+    moves: &[Event],
+) -> Option<ScoreDistribution> {
+    let mut samples = vec![];
+    for i in 0..args.rollouts {
+        // the expected and the actual move get the same walls, so that the
+        // difference comes from the moves rather than the luck
+        let rng = match args.seed {
+            Some(seed) => {
+                fastrand::Rng::with_seed(seed ^ ((history.len() as u64) << 32) ^ u64::from(i))
+            }
+            None => fastrand::Rng::new(),
+        };
+        match rollout(args, history, moves, rng) {
+            Ok(delta) => samples.push(delta),
+            Err(err) => log!("WARNING: rollout #{} failed: {:?}", i + 1, err),
+        }
+    }
+    ScoreDistribution::new(samples)
+}
+
+impl ScoreDistribution {
+    fn new(mut samples: Vec<i32>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / n;
+        let variance = samples
+            .iter()
+            .map(|&s| (s as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let percentile = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+
+        Some(Self {
+            samples: samples.len(),
+            mean,
+            std_dev: variance.sqrt(),
+            min: samples[0],
+            percentiles: [percentile(0.1), percentile(0.5), percentile(0.9)],
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// Plays the kyoku out once from the last event of `history`, with the
+/// target actor making `moves` there. Returns the score change of the target
+/// actor.
+fn rollout(
+    args: &RolloutArgs,
+    history: &[Event],
+    moves: &[Event],
+    mut rng: fastrand::Rng,
+) -> Result<i32> {
+    let mut table = Table::new(args, history, &mut rng)?;
+    let target = args.target_actor;
+
+    let start_game = Event::StartGame {
+        kyoku_first: 0,
+        aka_flag: true,
+        names: Default::default(),
+    };
+    let (last, replayed) = history.split_last().context("empty history")?;
+    for event in iter::once(&start_game).chain(replayed) {
+        table.broadcast(event)?;
+    }
+    let mut responses = table.broadcast(last)?;
+    responses[target as usize] = Some(moves.to_vec());
+
+    let mut step = match *last {
+        Event::Tsumo { actor, .. } => Step::Turn {
+            actor,
+            moves: moves.to_vec(),
+        },
+        Event::Dahai { .. } | Event::Kakan { .. } => Step::Claims {
+            event: last.clone(),
+            responses,
+        },
+        ref other => bail!("unexpected decision point {:?}", other),
+    };
+    for _ in 0..MAX_STEPS {
+        step = match step {
+            Step::Draw(actor) => table.draw(actor)?,
+            Step::Turn { actor, moves } => table.turn(actor, moves)?,
+            Step::Claims { event, responses } => table.claims(&event, responses)?,
+            Step::End(deltas) => return Ok(deltas[target as usize]),
+        };
+    }
+
+    bail!("the kyoku did not end in {} steps", MAX_STEPS)
+}
+
+enum Step {
+    /// `actor` draws, from the dead wall if right after a kan.
+    Draw(u8),
+    /// `actor` does `moves` with the pai just drawn.
+    Turn { actor: u8, moves: Vec<Event> },
+    /// The others respond to the dahai or kakan `event`.
+    Claims {
+        event: Event,
+        responses: [Option<Vec<Event>>; 4],
+    },
+    /// The kyoku ends with these score changes from the decision.
+    End([i32; 4]),
+}
+
+/// The whole kyoku with every pai known, where each seat is played by its own
+/// akochan that sees only what that seat sees.
+struct Table<'a> {
+    seats: Vec<Akochan<'a>>,
+    tracker: HoraTracker,
+    /// Pais that nobody has seen, in random order. Both the live wall and the
+    /// dead wall are drawn from it.
+    pile: Vec<Pai>,
+    pending_reach: Option<u8>,
+    /// Score changes since the decision, only from reach so far.
+    deltas: [i32; 4],
+    verbose: bool,
+}
+
+impl<'a> Table<'a> {
+    fn new(args: &RolloutArgs<'a>, history: &[Event], rng: &mut fastrand::Rng) -> Result<Self> {
+        let seats = (0..4)
+            .map(|actor| {
+                Akochan::spawn(EngineArgs {
+                    akochan_exe: args.akochan_exe,
+                    akochan_dir: args.akochan_dir,
+                    tactics_config: args.tactics_config,
+                    akochan_lib: args.akochan_lib,
+                    server: args.engine_server,
+                    transcript: None,
+                    seed: args.seed,
+                    target_actor: actor,
+                    verbose: false,
+                    max_restarts: 0,
+                    timeout: args.timeout,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut pile = full_set();
+        for event in history {
+            if let Event::StartKyoku { tehais, .. } = event {
+                if tehais.iter().flatten().any(|&p| p == Pai::Unknown) {
+                    bail!("rollouts need a log with the tehais of everyone");
+                }
+            }
+            match event {
+                Event::StartKyoku {
+                    tehais,
+                    dora_marker,
+                    ..
+                } => {
+                    tehais
+                        .iter()
+                        .flatten()
+                        .chain(iter::once(dora_marker))
+                        .try_for_each(|&p| take(&mut pile, p))?;
+                }
+                Event::Tsumo { pai, .. } | Event::Dora { dora_marker: pai } => {
+                    take(&mut pile, *pai)?;
+                }
+                _ => (),
+            }
+        }
+        rng.shuffle(&mut pile);
+
+        Ok(Self {
+            seats,
+            tracker: HoraTracker::new(),
+            pile,
+            pending_reach: None,
+            deltas: [0; 4],
+            verbose: args.verbose,
+        })
+    }
+
+    /// Applies `event` to the table and sends it to every seat, hiding the
+    /// pais they are not supposed to see. Returns the best moves of the seats
+    /// that respond to it.
+    fn broadcast(&mut self, event: &Event) -> Result<[Option<Vec<Event>>; 4]> {
+        self.tracker.update(event)?;
+        if self.verbose {
+            log!("rollout: {}", json::to_string(event).unwrap());
+        }
+
+        let mut responses: [Option<Vec<Event>>; 4] = Default::default();
+        for (actor, akochan) in (0..).zip(&mut self.seats) {
+            akochan.send(&mask(event, actor))?;
+            if is_review_timing(event, actor) {
+                let line = akochan.recv()?;
+                let actions: Vec<DetailedAction> =
+                    json::from_str(&line).context("failed to parse JSON output of akochan")?;
+                let moves = actions.into_iter().next().map(|a| a.moves);
+                responses[actor as usize] = Some(moves.unwrap_or_default());
+            }
+        }
+
+        Ok(responses)
+    }
+
+    fn draw(&mut self, actor: u8) -> Result<Step> {
+        if self.tracker.board().tiles_left == 0 {
+            return Ok(Step::End(self.exhaustive_draw()));
+        }
+        let pai = self.pile.pop().context("ran out of pais")?;
+        let mut responses = self.broadcast(&Event::Tsumo { actor, pai })?;
+        let moves = responses[actor as usize].take().unwrap_or_default();
+        Ok(Step::Turn { actor, moves })
+    }
+
+    fn turn(&mut self, actor: u8, moves: Vec<Event>) -> Result<Step> {
+        match moves.first() {
+            Some(Event::Hora { .. }) => {
+                if let Some(deltas) = self.hora(&[actor], actor)? {
+                    return Ok(Step::End(deltas));
+                }
+            }
+            Some(Event::Ryukyoku { .. }) => return Ok(Step::End(self.deltas)),
+            Some(reach @ Event::Reach { .. }) => {
+                if let Some(dahai @ Event::Dahai { .. }) = moves.get(1) {
+                    self.broadcast(reach)?;
+                    self.pending_reach = Some(actor);
+                    return self.discard(dahai);
+                }
+            }
+            Some(dahai @ Event::Dahai { .. }) => return self.discard(dahai),
+            Some(ankan @ Event::Ankan { .. }) => {
+                self.broadcast(ankan)?;
+                self.reveal_dora()?;
+                return Ok(Step::Draw(actor));
+            }
+            Some(kakan @ Event::Kakan { .. }) => {
+                let responses = self.broadcast(kakan)?;
+                return Ok(Step::Claims {
+                    event: kakan.clone(),
+                    responses,
+                });
+            }
+            _ => (),
+        }
+
+        // not a valid choice, just discard the pai drawn
+        if self.verbose {
+            log!("rollout: replacing {:?} with tsumogiri", moves);
+        }
+        let tehai = self.tracker.state(actor).tehai.view();
+        let pai = *tehai.last().context("empty tehai")?;
+        self.discard(&Event::Dahai {
+            actor,
+            pai,
+            tsumogiri: true,
+        })
+    }
+
+    fn discard(&mut self, dahai: &Event) -> Result<Step> {
+        let responses = self.broadcast(dahai)?;
+        Ok(Step::Claims {
+            event: dahai.clone(),
+            responses,
+        })
+    }
+
+    fn claims(&mut self, event: &Event, mut responses: [Option<Vec<Event>>; 4]) -> Result<Step> {
+        let (from, is_kakan) = match *event {
+            Event::Dahai { actor, .. } => (actor, false),
+            Event::Kakan { actor, .. } => (actor, true),
+            _ => bail!("unexpected event to claim {:?}", event),
+        };
+        // in turn order, so that the kyotaku goes to the closest winner
+        let others: Vec<_> = (1..4).map(|i| (from + i) % 4).collect();
+        let first_of = |r: &[Option<Vec<Event>>; 4], actor: u8| {
+            r[actor as usize].as_ref().and_then(|m| m.first()).cloned()
+        };
+
+        let winners: Vec<_> = others
+            .iter()
+            .copied()
+            .filter(|&a| matches!(first_of(&responses, a), Some(Event::Hora { .. })))
+            .collect();
+        if !winners.is_empty() {
+            if let Some(deltas) = self.hora(&winners, from)? {
+                return Ok(Step::End(deltas));
+            }
+        }
+
+        if is_kakan {
+            self.reveal_dora()?;
+            return Ok(Step::Draw(from));
+        }
+
+        if let Some(actor) = self.pending_reach.take() {
+            self.broadcast(&Event::ReachAccepted { actor })?;
+            self.deltas[actor as usize] -= 1000;
+        }
+
+        let naki = others
+            .iter()
+            .copied()
+            .find(|&a| {
+                matches!(
+                    first_of(&responses, a),
+                    Some(Event::Pon { .. }) | Some(Event::Daiminkan { .. })
+                )
+            })
+            .or_else(|| {
+                let shimocha = others[0];
+                matches!(first_of(&responses, shimocha), Some(Event::Chi { .. }))
+                    .then_some(shimocha)
+            });
+        if let Some(actor) = naki {
+            let moves = responses[actor as usize].take().unwrap_or_default();
+            match (moves.first(), moves.get(1)) {
+                (Some(kan @ Event::Daiminkan { .. }), _) => {
+                    self.broadcast(kan)?;
+                    self.reveal_dora()?;
+                    return Ok(Step::Draw(actor));
+                }
+                (Some(naki), Some(dahai @ Event::Dahai { .. })) => {
+                    self.broadcast(naki)?;
+                    return self.discard(dahai);
+                }
+                _ => bail!("unexpected moves for naki {:?}", moves),
+            }
+        }
+
+        Ok(Step::Draw((from + 1) % 4))
+    }
+
+    fn reveal_dora(&mut self) -> Result<()> {
+        let dora_marker = self.pile.pop().context("ran out of pais")?;
+        self.broadcast(&Event::Dora { dora_marker })?;
+        Ok(())
+    }
+
+    /// Scores the hora of `winners` from `target`. Returns `None` if none of
+    /// them is a valid agari, in which case the hora is ignored.
+    fn hora(&mut self, winners: &[u8], target: u8) -> Result<Option<[i32; 4]>> {
+        let board = self.tracker.board();
+        let (oya, honba, mut kyotaku) = (board.oya, board.honba, board.kyotaku);
+        let ura_markers: Vec<_> = (0..board.dora_markers.len())
+            .map(|_| self.pile.pop().context("ran out of pais"))
+            .collect::<Result<_>>()?;
+
+        let mut deltas = self.deltas;
+        let mut any = false;
+        for &actor in winners {
+            let score = self.tracker.update(&Event::Hora {
+                actor,
+                target,
+                deltas: None,
+                ura_markers: Some(ura_markers.clone()),
+            })?;
+            let points = match score.and_then(|s| s.agari) {
+                Some(agari) => agari.points,
+                None => {
+                    if self.verbose {
+                        log!("rollout: ignoring invalid hora of actor {}", actor);
+                    }
+                    continue;
+                }
+            };
+            any = true;
+
+            let d = hora_deltas(points, actor, target, oya, honba, kyotaku);
+            deltas.iter_mut().zip(&d).for_each(|(o, n)| *o += n);
+            kyotaku = 0;
+        }
+
+        Ok(if any { Some(deltas) } else { None })
+    }
+
+    fn exhaustive_draw(&self) -> [i32; 4] {
+        // every tehai is known in a rollout
+        let board = self.tracker.board();
+        let tenpai: Vec<_> = (0..4)
+            .map(|actor| board.is_tenpai(actor).unwrap_or(false))
+            .collect();
+        let count = tenpai.iter().filter(|&&t| t).count() as i32;
+
+        let mut deltas = self.deltas;
+        if count > 0 && count < 4 {
+            for (d, &t) in deltas.iter_mut().zip(&tenpai) {
+                *d += if t { 3000 / count } else { -3000 / (4 - count) };
+            }
+        }
+        deltas
+    }
+}
+
+/// Hides the pais of others in `event` from `actor`.
+fn mask(event: &Event, actor: u8) -> Event {
+    match *event {
+        Event::StartKyoku {
+            bakaze,
+            dora_marker,
+            kyoku,
+            honba,
+            kyotaku,
+            oya,
+            scores,
+            tehais,
+        } => {
+            let mut masked = [[Pai::Unknown; 13]; 4];
+            masked[actor as usize] = tehais[actor as usize];
+            Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                scores,
+                tehais: masked,
+            }
+        }
+        Event::Tsumo { actor: a, .. } if a != actor => Event::Tsumo {
+            actor: a,
+            pai: Pai::Unknown,
+        },
+        ref other => other.clone(),
+    }
+}
+
+/// All 136 pais, with one aka of each of 5m, 5p and 5s.
+fn full_set() -> Vec<Pai> {
+    let mut pais = Vec::with_capacity(136);
+    for idx in 0..34 {
+        let pai = Pai::from_index(idx).unwrap();
+        let aka = match pai {
+            Pai::Man5 => Some(Pai::AkaMan5),
+            Pai::Pin5 => Some(Pai::AkaPin5),
+            Pai::Sou5 => Some(Pai::AkaSou5),
+            _ => None,
+        };
+        pais.extend(iter::repeat_n(pai, if aka.is_some() { 3 } else { 4 }));
+        pais.extend(aka);
+    }
+    pais
+}
+
+/// Removes `pai` from `pile`, or its aka or non-aka counterpart for logs of
+/// rules with a different number of akas.
+fn take(pile: &mut Vec<Pai>, pai: Pai) -> Result<()> {
+    let pos = pile
+        .iter()
+        .position(|&p| p == pai)
+        .or_else(|| pile.iter().position(|&p| p.deaka() == pai.deaka()))
+        .with_context(|| format!("more than 4 copies of {} in the log", pai))?;
+    pile.swap_remove(pos);
+    Ok(())
+}
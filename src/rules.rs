@@ -0,0 +1,56 @@
+use convlog::tenhou::GameLength;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// The points a player must return to, the difference from the starting
+/// points being the oka.
+pub const RETURN_POINTS: i32 = 30000;
+
+pub const DEFAULT_STARTING_POINTS: i32 = 25000;
+
+/// Rule variations of the reviewed game, from `--no-kuitan`, `--no-aka`,
+/// `--game-length`, `--starting-points` and `--uma` or the `[rules]` table of
+/// the config file.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Rules {
+    pub kuitan: bool,
+    pub aka: bool,
+    pub starting_points: i32,
+    /// Placement bonuses in thousands of points.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uma: Option<[i32; 4]>,
+}
+
+impl Rules {
+    /// jun_pt for akochan from the uma, with the oka going to the 1st, or
+    /// `None` if there is no uma.
+    pub fn jun_pt(&self) -> Option<[i32; 4]> {
+        let mut pt = self.uma?;
+        pt[0] += (RETURN_POINTS - self.starting_points).max(0) * 4 / 1000;
+        Some(pt)
+    }
+}
+
+pub fn parse_game_length(s: &str) -> Result<GameLength> {
+    match s {
+        "hanchan" => Ok(GameLength::Hanchan),
+        "tonpuusen" => Ok(GameLength::Tonpuu),
+        _ => Err(anyhow!(
+            "unknown game length: {:?} (expected \"hanchan\" or \"tonpuusen\")",
+            s
+        )),
+    }
+}
+
+pub fn parse_uma(s: &str) -> Result<[i32; 4]> {
+    let list = s
+        .split(',')
+        .map(|p| p.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| anyhow!("uma element must be a number: {}", err))?;
+    match *list.as_slice() {
+        [a, b, c, d] => Ok([a, b, c, d]),
+        _ => Err(anyhow!("uma must have exactly 4 elements")),
+    }
+}
@@ -0,0 +1,187 @@
+use crate::review::{Acceptance, Entry, KyokuReview};
+use convlog::mjai::Event;
+use convlog::Pai;
+
+use serde::Serialize;
+
+/// Decisions with at most this many tiles left in the live wall, which is the
+/// last two draws of everyone, are near the exhaustive draw.
+const LATE_TILES_LEFT: u8 = 8;
+
+/// The most a tenpai payment can change the difference between two players,
+/// when one is the only tenpai and the other is noten.
+const MAX_PAYMENT_SWING: i32 = 4000;
+
+/// Whether a dahai goes for tenpai at the exhaustive draw or gives it up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stance {
+    /// Keeps the lowest shanten possible with the tehai.
+    Push,
+    /// Gives up some shanten, usually for safety.
+    Fold,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The actual dahai takes the same stance as akochan's.
+    Correct,
+    ShouldPush,
+    ShouldFold,
+}
+
+/// A dahai of the target actor near the exhaustive draw, while tenpai is
+/// within reach, so the tenpai payments are at stake.
+#[derive(Debug, Clone, Serialize)]
+pub struct RyukyokuDecision<'a> {
+    pub kyoku: u8,
+    pub honba: u8,
+    pub junme: u8,
+    pub tiles_left: u8,
+    /// Counts from 1.
+    pub placement: usize,
+    /// Points behind the player ranked just above, if any.
+    pub gap_above: Option<i32>,
+    /// Points ahead of the player ranked just below, if any.
+    pub gap_below: Option<i32>,
+    /// Whether a tenpai payment alone can change the placement.
+    pub placement_at_stake: bool,
+    pub expected: Move<'a>,
+    pub actual: Move<'a>,
+    pub ev_loss: Option<f64>,
+    pub verdict: Verdict,
+    /// Score change of the target actor if the kyoku actually ended in an
+    /// exhaustive draw.
+    pub draw_delta: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Move<'a> {
+    pub moves: &'a [Event],
+    /// Shanten after the dahai.
+    pub shanten: i8,
+    pub stance: Stance,
+    /// Only set when there are threats.
+    pub deal_in: Option<f64>,
+}
+
+/// Finds the dahai decisions of `target_actor` near the exhaustive draw in
+/// `kyoku` where tenpai is at most one shanten away, and compares the stance
+/// of akochan's dahai with the actual one.
+pub fn analyze(kyoku: &KyokuReview, target_actor: u8) -> Vec<RyukyokuDecision<'_>> {
+    let draw_delta = kyoku.end_status.iter().find_map(|ev| match *ev {
+        Event::Ryukyoku {
+            deltas: Some(deltas),
+            reason,
+        } if !matches!(reason, Some(r) if r.is_abortive()) => Some(deltas[target_actor as usize]),
+        _ => None,
+    });
+
+    kyoku
+        .entries
+        .iter()
+        .filter(|e| e.actor == target_actor)
+        .filter_map(|entry| {
+            let tiles_left = tiles_left(kyoku, entry);
+            if tiles_left > LATE_TILES_LEFT {
+                return None;
+            }
+
+            let expected_shanten = entry.expected_ukeire.as_ref()?.shanten;
+            let actual_shanten = entry.actual_ukeire.as_ref()?.shanten;
+            let best_shanten = best_shanten(entry)?;
+            if best_shanten > 1 {
+                return None;
+            }
+            let stance = |shanten| {
+                if shanten <= best_shanten {
+                    Stance::Push
+                } else {
+                    Stance::Fold
+                }
+            };
+            let expected_stance = stance(expected_shanten);
+            let actual_stance = stance(actual_shanten);
+            let verdict = match (expected_stance, actual_stance) {
+                _ if entry.acceptance != Acceptance::Disagree => Verdict::Correct,
+                (Stance::Push, Stance::Fold) => Verdict::ShouldPush,
+                (Stance::Fold, Stance::Push) => Verdict::ShouldFold,
+                _ => Verdict::Correct,
+            };
+
+            let scores = kyoku.scores_at(entry.event_index);
+            let (placement, gap_above, gap_below) = standing(&scores, target_actor as usize);
+            let placement_at_stake = gap_above
+                .into_iter()
+                .chain(gap_below)
+                .any(|gap| gap <= MAX_PAYMENT_SWING);
+
+            Some(RyukyokuDecision {
+                kyoku: kyoku.kyoku,
+                honba: kyoku.honba,
+                junme: entry.junme,
+                tiles_left,
+                placement,
+                gap_above,
+                gap_below,
+                placement_at_stake,
+                expected: Move {
+                    moves: &entry.expected,
+                    shanten: expected_shanten,
+                    stance: expected_stance,
+                    deal_in: entry.expected_deal_in,
+                },
+                actual: Move {
+                    moves: &entry.actual,
+                    shanten: actual_shanten,
+                    stance: actual_stance,
+                    deal_in: entry.actual_deal_in,
+                },
+                ev_loss: entry.ev_loss,
+                verdict,
+                draw_delta,
+            })
+        })
+        .collect()
+}
+
+/// Tiles left in the live wall at the decision.
+fn tiles_left(kyoku: &KyokuReview, entry: &Entry) -> u8 {
+    let tsumos = kyoku
+        .events
+        .iter()
+        .take(entry.event_index + 1)
+        .filter(|ev| matches!(ev, Event::Tsumo { .. }))
+        .count();
+    70u8.saturating_sub(tsumos as u8)
+}
+
+/// The lowest shanten after any dahai from the tehai.
+fn best_shanten(entry: &Entry) -> Option<i8> {
+    let mut pais: Vec<Pai> = entry.state.tehai.view().to_vec();
+    pais.sort_unstable_by_key(|p| p.as_ord());
+    pais.dedup();
+    pais.into_iter()
+        .filter_map(|pai| entry.state.ukeire_after_dahai(pai))
+        .map(|u| u.shanten)
+        .min()
+}
+
+/// Placement of `actor`, counting from 1, and the gaps to the players just
+/// above and below. Ties are broken by the seat, the first oya being the
+/// highest.
+fn standing(scores: &[i32; 4], actor: usize) -> (usize, Option<i32>, Option<i32>) {
+    let above = |i: usize| scores[i] > scores[actor] || (scores[i] == scores[actor] && i < actor);
+    let placement = 1 + (0..4).filter(|&i| i != actor && above(i)).count();
+    let gap_above = (0..4)
+        .filter(|&i| i != actor && above(i))
+        .map(|i| scores[i] - scores[actor])
+        .min();
+    let gap_below = (0..4)
+        .filter(|&i| i != actor && !above(i))
+        .map(|i| scores[actor] - scores[i])
+        .min();
+
+    (placement, gap_above, gap_below)
+}
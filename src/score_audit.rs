@@ -0,0 +1,131 @@
+//! Recalculates the score changes of every hora and ryuukyoku from the events
+//! and compares them with the deltas recorded in the log, which catches both
+//! bugs in converting logs and logs that have been tampered with.
+
+use crate::scoring::{hora_deltas, is_yaochuu, HoraScore, HoraTracker, Yaku};
+
+use anyhow::Result;
+use convlog::mjai::{Event, RyukyokuReason};
+use serde::Serialize;
+
+/// Deltas of a hora or ryuukyoku that differ from the recalculated ones.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ScoreMismatch {
+    pub expected: [i32; 4],
+    pub recorded: [i32; 4],
+}
+
+/// Replays mjai events of all actors and audits the deltas of every hora and
+/// ryuukyoku.
+pub struct ScoreAuditor {
+    tracker: HoraTracker,
+}
+
+impl ScoreAuditor {
+    pub fn new(kuitan: bool) -> Self {
+        Self {
+            tracker: HoraTracker::new().with_kuitan(kuitan),
+        }
+    }
+
+    /// Update with `event`, returns the mismatch if it is a Hora or Ryukyoku
+    /// of which the recorded deltas differ from the recalculated ones.
+    ///
+    /// Deltas that cannot be recalculated are taken as they are, which
+    /// includes those of a hora of a hidden tehai, a hora without yaku, a
+    /// daisangen or daisuushii that may be paid by pao, and a ryuukyoku with
+    /// a hidden tehai.
+    pub fn update(&mut self, event: &Event) -> Result<Option<ScoreMismatch>> {
+        // the sticks are gone from the board after the hora
+        let kyotaku = self.tracker.board().kyotaku;
+        let hora = self.tracker.update(event)?;
+
+        let (expected, recorded) = match *event {
+            Event::Hora {
+                deltas: Some(recorded),
+                ..
+            } => {
+                let expected = hora.and_then(|h| self.hora_deltas(&h, kyotaku));
+                (expected, recorded)
+            }
+
+            Event::Ryukyoku {
+                deltas: Some(recorded),
+                reason,
+            } => {
+                let expected = self.ryukyoku_deltas(reason.unwrap_or(RyukyokuReason::Fanpai));
+                (expected, recorded)
+            }
+
+            _ => return Ok(None),
+        };
+
+        Ok(expected
+            .filter(|&e| e != recorded)
+            .map(|expected| ScoreMismatch { expected, recorded }))
+    }
+
+    fn hora_deltas(&self, hora: &HoraScore, kyotaku: u8) -> Option<[i32; 4]> {
+        let agari = hora.agari.as_ref()?;
+        let may_have_pao = agari
+            .yakus
+            .iter()
+            .any(|y| matches!(y.yaku, Yaku::Daisangen | Yaku::Daisuushii));
+        if may_have_pao && self.tracker.state(hora.actor).fuuro_count(hora.actor) > 0 {
+            return None;
+        }
+
+        let board = self.tracker.board();
+        Some(hora_deltas(
+            agari.points,
+            hora.actor,
+            hora.target,
+            board.oya,
+            board.honba,
+            kyotaku,
+        ))
+    }
+
+    /// An abortive draw moves no points. Nagashi mangan is paid like a tsumo
+    /// of mangan, without honba or tenpai payments.
+    fn ryukyoku_deltas(&self, reason: RyukyokuReason) -> Option<[i32; 4]> {
+        let board = self.tracker.board();
+        let mut deltas = [0; 4];
+        match reason {
+            _ if reason.is_abortive() => (),
+
+            RyukyokuReason::Nagashimangan => {
+                for actor in (0..4).filter(|&a| self.is_nagashi(a)) {
+                    let points = if actor == board.oya { 12000 } else { 8000 };
+                    let d = hora_deltas(points, actor, actor, board.oya, 0, 0);
+                    deltas.iter_mut().zip(d).for_each(|(a, b)| *a += b);
+                }
+            }
+
+            _ => {
+                let mut tenpai = [false; 4];
+                for (actor, t) in (0..4).zip(&mut tenpai) {
+                    *t = board.is_tenpai(actor)?;
+                }
+                let count = tenpai.iter().filter(|&&t| t).count() as i32;
+                if count > 0 && count < 4 {
+                    for (d, &t) in deltas.iter_mut().zip(&tenpai) {
+                        *d = if t { 3000 / count } else { -3000 / (4 - count) };
+                    }
+                }
+            }
+        }
+
+        Some(deltas)
+    }
+
+    /// Whether every discard of `actor` is a yaochuu pai and none of them
+    /// has been called.
+    fn is_nagashi(&self, actor: u8) -> bool {
+        let river = self.tracker.state(actor).river(actor);
+        !river.is_empty()
+            && river
+                .iter()
+                .all(|s| s.called_by.is_none() && s.pai.as_index().is_some_and(is_yaochuu))
+    }
+}
@@ -0,0 +1,1190 @@
+use crate::board::BoardState;
+use crate::shanten::{tiles_from_pais, Tiles};
+use crate::state::{Fuuro, State};
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Number of tsumo in a kyoku, including rinshan, before the wall runs out.
+pub const TOTAL_TSUMO: u8 = 136 - 14 - 13 * 4;
+
+const HAKU: usize = 31;
+const CHUN: usize = 33;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Yaku {
+    Riichi,
+    Ippatsu,
+    MenzenTsumo,
+    Pinfu,
+    Tanyao,
+    Iipeikou,
+    Haku,
+    Hatsu,
+    Chun,
+    Bakaze,
+    Jikaze,
+    Haitei,
+    Houtei,
+    Rinshan,
+    Chankan,
+    DoubleRiichi,
+    Chiitoitsu,
+    SanshokuDoujun,
+    Ittsu,
+    Chanta,
+    Toitoi,
+    Sanankou,
+    SanshokuDoukou,
+    Sankantsu,
+    Shousangen,
+    Honroutou,
+    Ryanpeikou,
+    Junchan,
+    Honitsu,
+    Chinitsu,
+
+    Tenhou,
+    Chiihou,
+    Kokushi,
+    Suuankou,
+    Daisangen,
+    Shousuushii,
+    Daisuushii,
+    Tsuuiisou,
+    Ryuuiisou,
+    Chinroutou,
+    Chuuren,
+    Suukantsu,
+
+    Dora,
+    AkaDora,
+    UraDora,
+}
+
+impl Yaku {
+    pub fn name_ja(self) -> &'static str {
+        match self {
+            Self::Riichi => "立直",
+            Self::Ippatsu => "一発",
+            Self::MenzenTsumo => "門前清自摸和",
+            Self::Pinfu => "平和",
+            Self::Tanyao => "断幺九",
+            Self::Iipeikou => "一盃口",
+            Self::Haku => "役牌 白",
+            Self::Hatsu => "役牌 發",
+            Self::Chun => "役牌 中",
+            Self::Bakaze => "場風",
+            Self::Jikaze => "自風",
+            Self::Haitei => "海底摸月",
+            Self::Houtei => "河底撈魚",
+            Self::Rinshan => "嶺上開花",
+            Self::Chankan => "槍槓",
+            Self::DoubleRiichi => "両立直",
+            Self::Chiitoitsu => "七対子",
+            Self::SanshokuDoujun => "三色同順",
+            Self::Ittsu => "一気通貫",
+            Self::Chanta => "混全帯幺九",
+            Self::Toitoi => "対々和",
+            Self::Sanankou => "三暗刻",
+            Self::SanshokuDoukou => "三色同刻",
+            Self::Sankantsu => "三槓子",
+            Self::Shousangen => "小三元",
+            Self::Honroutou => "混老頭",
+            Self::Ryanpeikou => "二盃口",
+            Self::Junchan => "純全帯幺九",
+            Self::Honitsu => "混一色",
+            Self::Chinitsu => "清一色",
+            Self::Tenhou => "天和",
+            Self::Chiihou => "地和",
+            Self::Kokushi => "国士無双",
+            Self::Suuankou => "四暗刻",
+            Self::Daisangen => "大三元",
+            Self::Shousuushii => "小四喜",
+            Self::Daisuushii => "大四喜",
+            Self::Tsuuiisou => "字一色",
+            Self::Ryuuiisou => "緑一色",
+            Self::Chinroutou => "清老頭",
+            Self::Chuuren => "九蓮宝燈",
+            Self::Suukantsu => "四槓子",
+            Self::Dora => "ドラ",
+            Self::AkaDora => "赤ドラ",
+            Self::UraDora => "裏ドラ",
+        }
+    }
+
+    pub fn name_romaji(self) -> &'static str {
+        match self {
+            Self::Riichi => "Riichi",
+            Self::Ippatsu => "Ippatsu",
+            Self::MenzenTsumo => "Menzen Tsumo",
+            Self::Pinfu => "Pinfu",
+            Self::Tanyao => "Tanyao",
+            Self::Iipeikou => "Iipeikou",
+            Self::Haku => "Yakuhai (Haku)",
+            Self::Hatsu => "Yakuhai (Hatsu)",
+            Self::Chun => "Yakuhai (Chun)",
+            Self::Bakaze => "Yakuhai (Round Wind)",
+            Self::Jikaze => "Yakuhai (Seat Wind)",
+            Self::Haitei => "Haitei",
+            Self::Houtei => "Houtei",
+            Self::Rinshan => "Rinshan Kaihou",
+            Self::Chankan => "Chankan",
+            Self::DoubleRiichi => "Double Riichi",
+            Self::Chiitoitsu => "Chiitoitsu",
+            Self::SanshokuDoujun => "Sanshoku Doujun",
+            Self::Ittsu => "Ittsu",
+            Self::Chanta => "Chanta",
+            Self::Toitoi => "Toitoi",
+            Self::Sanankou => "Sanankou",
+            Self::SanshokuDoukou => "Sanshoku Doukou",
+            Self::Sankantsu => "Sankantsu",
+            Self::Shousangen => "Shousangen",
+            Self::Honroutou => "Honroutou",
+            Self::Ryanpeikou => "Ryanpeikou",
+            Self::Junchan => "Junchan",
+            Self::Honitsu => "Honitsu",
+            Self::Chinitsu => "Chinitsu",
+            Self::Tenhou => "Tenhou",
+            Self::Chiihou => "Chiihou",
+            Self::Kokushi => "Kokushi Musou",
+            Self::Suuankou => "Suuankou",
+            Self::Daisangen => "Daisangen",
+            Self::Shousuushii => "Shousuushii",
+            Self::Daisuushii => "Daisuushii",
+            Self::Tsuuiisou => "Tsuuiisou",
+            Self::Ryuuiisou => "Ryuuiisou",
+            Self::Chinroutou => "Chinroutou",
+            Self::Chuuren => "Chuuren Poutou",
+            Self::Suukantsu => "Suukantsu",
+            Self::Dora => "Dora",
+            Self::AkaDora => "Aka Dora",
+            Self::UraDora => "Ura Dora",
+        }
+    }
+
+    pub fn name_en(self) -> &'static str {
+        match self {
+            Self::Riichi => "Ready Hand",
+            Self::Ippatsu => "One Shot",
+            Self::MenzenTsumo => "Fully Concealed Hand",
+            Self::Pinfu => "All Sequences",
+            Self::Tanyao => "All Simples",
+            Self::Iipeikou => "Pure Double Sequence",
+            Self::Haku => "White Dragon",
+            Self::Hatsu => "Green Dragon",
+            Self::Chun => "Red Dragon",
+            Self::Bakaze => "Prevalent Wind",
+            Self::Jikaze => "Seat Wind",
+            Self::Haitei => "Under the Sea",
+            Self::Houtei => "Under the River",
+            Self::Rinshan => "After a Kan",
+            Self::Chankan => "Robbing a Kan",
+            Self::DoubleRiichi => "Double Ready",
+            Self::Chiitoitsu => "Seven Pairs",
+            Self::SanshokuDoujun => "Mixed Triple Sequence",
+            Self::Ittsu => "Pure Straight",
+            Self::Chanta => "Half Outside Hand",
+            Self::Toitoi => "All Triplets",
+            Self::Sanankou => "Three Concealed Triplets",
+            Self::SanshokuDoukou => "Triple Triplets",
+            Self::Sankantsu => "Three Kans",
+            Self::Shousangen => "Little Three Dragons",
+            Self::Honroutou => "All Terminals and Honors",
+            Self::Ryanpeikou => "Twice Pure Double Sequence",
+            Self::Junchan => "Fully Outside Hand",
+            Self::Honitsu => "Half Flush",
+            Self::Chinitsu => "Full Flush",
+            Self::Tenhou => "Blessing of Heaven",
+            Self::Chiihou => "Blessing of Earth",
+            Self::Kokushi => "Thirteen Orphans",
+            Self::Suuankou => "Four Concealed Triplets",
+            Self::Daisangen => "Big Three Dragons",
+            Self::Shousuushii => "Little Four Winds",
+            Self::Daisuushii => "Big Four Winds",
+            Self::Tsuuiisou => "All Honors",
+            Self::Ryuuiisou => "All Green",
+            Self::Chinroutou => "All Terminals",
+            Self::Chuuren => "Nine Gates",
+            Self::Suukantsu => "Four Kans",
+            Self::Dora => "Dora",
+            Self::AkaDora => "Red Five",
+            Self::UraDora => "Ura Dora",
+        }
+    }
+
+    pub fn name_zh(self) -> &'static str {
+        match self {
+            Self::Riichi => "立直",
+            Self::Ippatsu => "一发",
+            Self::MenzenTsumo => "门前清自摸和",
+            Self::Pinfu => "平和",
+            Self::Tanyao => "断幺九",
+            Self::Iipeikou => "一杯口",
+            Self::Haku => "役牌 白",
+            Self::Hatsu => "役牌 发",
+            Self::Chun => "役牌 中",
+            Self::Bakaze => "场风",
+            Self::Jikaze => "自风",
+            Self::Haitei => "海底摸月",
+            Self::Houtei => "河底捞鱼",
+            Self::Rinshan => "岭上开花",
+            Self::Chankan => "抢杠",
+            Self::DoubleRiichi => "两立直",
+            Self::Chiitoitsu => "七对子",
+            Self::SanshokuDoujun => "三色同顺",
+            Self::Ittsu => "一气通贯",
+            Self::Chanta => "混全带幺九",
+            Self::Toitoi => "对对和",
+            Self::Sanankou => "三暗刻",
+            Self::SanshokuDoukou => "三色同刻",
+            Self::Sankantsu => "三杠子",
+            Self::Shousangen => "小三元",
+            Self::Honroutou => "混老头",
+            Self::Ryanpeikou => "二杯口",
+            Self::Junchan => "纯全带幺九",
+            Self::Honitsu => "混一色",
+            Self::Chinitsu => "清一色",
+            Self::Tenhou => "天和",
+            Self::Chiihou => "地和",
+            Self::Kokushi => "国士无双",
+            Self::Suuankou => "四暗刻",
+            Self::Daisangen => "大三元",
+            Self::Shousuushii => "小四喜",
+            Self::Daisuushii => "大四喜",
+            Self::Tsuuiisou => "字一色",
+            Self::Ryuuiisou => "绿一色",
+            Self::Chinroutou => "清老头",
+            Self::Chuuren => "九莲宝灯",
+            Self::Suukantsu => "四杠子",
+            Self::Dora => "宝牌",
+            Self::AkaDora => "赤宝牌",
+            Self::UraDora => "里宝牌",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct YakuHan {
+    pub yaku: Yaku,
+    /// 13 for each yakuman.
+    pub han: u8,
+}
+
+/// The calculated result of a hora.
+#[derive(Debug, Clone, Serialize)]
+pub struct Agari {
+    pub yakus: Vec<YakuHan>,
+    pub han: u8,
+    pub fu: u8,
+    /// Number of yakuman, 0 if it is not a yakuman.
+    pub yakuman: u8,
+    /// Points the winner gets, excluding honba and kyotaku.
+    pub points: i32,
+}
+
+/// A hora in the log along with the calculated score.
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct HoraScore {
+    pub actor: u8,
+    pub target: u8,
+    /// `None` if the hand has no yaku or is not agari at all.
+    pub agari: Option<Agari>,
+    /// Points the winner gets as recorded in the log.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded: Option<i32>,
+    pub is_consistent: bool,
+    /// Ura dora indicators, only revealed to a winner in riichi.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ura_markers: Vec<Pai>,
+}
+
+/// Everything about a hora other than the hand itself.
+#[derive(Debug, Clone)]
+pub struct HoraContext {
+    pub win_pai: Pai,
+    pub is_tsumo: bool,
+    pub is_oya: bool,
+    pub bakaze: Pai,
+    pub jikaze: Pai,
+    pub dora_markers: Vec<Pai>,
+    /// Only used when reached.
+    pub ura_markers: Vec<Pai>,
+    /// Yakus decided by the situation rather than the hand, like riichi,
+    /// ippatsu and haitei.
+    pub situational: Vec<YakuHan>,
+    /// Whether an open hand can have tanyao.
+    pub kuitan: bool,
+}
+
+/// Replays mjai events of all actors and scores every hora.
+#[derive(Default)]
+pub struct HoraTracker {
+    board: BoardState,
+
+    has_naki: bool,
+    has_discarded: [bool; 4],
+    is_double_riichi: [bool; 4],
+    ippatsu: [bool; 4],
+    is_rinshan_pending: bool,
+    is_rinshan: bool,
+    last_dahai: Option<Pai>,
+    last_kakan: Option<Pai>,
+    kuitan: bool,
+}
+
+impl HoraTracker {
+    pub fn new() -> Self {
+        Self {
+            kuitan: true,
+            ..Self::default()
+        }
+    }
+
+    /// Scores under a rule with or without open tanyao.
+    #[inline]
+    pub fn with_kuitan(mut self, kuitan: bool) -> Self {
+        self.kuitan = kuitan;
+        self
+    }
+
+    #[inline]
+    pub fn state(&self, actor: u8) -> &State {
+        self.board.state(actor)
+    }
+
+    /// The board after the last event.
+    #[inline]
+    pub fn board(&self) -> &BoardState {
+        &self.board
+    }
+
+    /// Update with `event`, returns the score if it is a Hora.
+    ///
+    /// `None` is also returned for a Hora of which the tehai is unknown.
+    pub fn update(&mut self, event: &Event) -> Result<Option<HoraScore>> {
+        if let Event::Hora {
+            actor,
+            target,
+            deltas,
+            ref ura_markers,
+        } = *event
+        {
+            let score = self.score(actor, target, deltas, ura_markers.as_deref());
+            self.board
+                .update(event)
+                .context("failed to update board for scoring")?;
+            return Ok(score);
+        }
+
+        self.board
+            .update(event)
+            .context("failed to update board for scoring")?;
+
+        self.last_kakan = None;
+        match *event {
+            Event::StartKyoku { .. } => {
+                let board = std::mem::take(&mut self.board);
+                *self = Self {
+                    board,
+                    kuitan: self.kuitan,
+                    ..Self::default()
+                };
+            }
+
+            Event::Tsumo { .. } => {
+                self.is_rinshan = self.is_rinshan_pending;
+                self.is_rinshan_pending = false;
+            }
+
+            Event::Reach { actor } => {
+                self.is_double_riichi[actor as usize] =
+                    !self.has_naki && !self.has_discarded[actor as usize];
+            }
+
+            Event::ReachAccepted { actor } => {
+                self.ippatsu[actor as usize] = true;
+            }
+
+            Event::Dahai { actor, pai, .. } => {
+                let a = actor as usize;
+                // `reached` is set after the dahai of the reach, so this is
+                // the next one.
+                self.ippatsu[a] = false;
+                self.has_discarded[a] = true;
+                self.is_rinshan = false;
+                self.last_dahai = Some(pai);
+            }
+
+            Event::Chi { .. } | Event::Pon { .. } => self.interrupt(),
+
+            Event::Daiminkan { .. } | Event::Ankan { .. } => {
+                self.interrupt();
+                self.is_rinshan_pending = true;
+            }
+
+            Event::Kakan { pai, .. } => {
+                self.interrupt();
+                self.is_rinshan_pending = true;
+                self.last_kakan = Some(pai);
+            }
+
+            _ => (),
+        };
+
+        Ok(None)
+    }
+
+    fn interrupt(&mut self) {
+        self.has_naki = true;
+        self.ippatsu = [false; 4];
+    }
+
+    fn score(
+        &self,
+        actor: u8,
+        target: u8,
+        deltas: Option<[i32; 4]>,
+        ura_markers: Option<&[Pai]>,
+    ) -> Option<HoraScore> {
+        let board = &self.board;
+        let state = board.state(actor);
+        let is_tsumo = actor == target;
+        let mut tehai = state.tehai.view().to_vec();
+        if tehai.contains(&Pai::Unknown) {
+            return None;
+        }
+
+        let is_chankan = !is_tsumo && self.last_kakan.is_some();
+        let win_pai = if is_tsumo {
+            // the tsumo pai is always the last one in the view
+            *tehai.last()?
+        } else {
+            let pai = self.last_kakan.or(self.last_dahai)?;
+            tehai.push(pai);
+            pai
+        };
+
+        let a = actor as usize;
+        let mut situational = vec![];
+        let mut yakuman_situational = vec![];
+        if board.reached[a] {
+            if self.is_double_riichi[a] {
+                situational.push(YakuHan::new(Yaku::DoubleRiichi, 2));
+            } else {
+                situational.push(YakuHan::new(Yaku::Riichi, 1));
+            }
+            if self.ippatsu[a] {
+                situational.push(YakuHan::new(Yaku::Ippatsu, 1));
+            }
+        }
+        if is_tsumo {
+            if self.is_rinshan {
+                situational.push(YakuHan::new(Yaku::Rinshan, 1));
+            } else if board.tiles_left == 0 {
+                situational.push(YakuHan::new(Yaku::Haitei, 1));
+            }
+            if !self.has_naki && !self.has_discarded[a] {
+                let yaku = if actor == board.oya {
+                    Yaku::Tenhou
+                } else {
+                    Yaku::Chiihou
+                };
+                yakuman_situational.push(YakuHan::new(yaku, 13));
+            }
+        } else if is_chankan {
+            situational.push(YakuHan::new(Yaku::Chankan, 1));
+        } else if board.tiles_left == 0 {
+            situational.push(YakuHan::new(Yaku::Houtei, 1));
+        }
+
+        let seat = (actor + 4 - board.oya) % 4;
+        let jikaze = Pai::from_index(27 + seat as usize)?;
+        let ctx = HoraContext {
+            win_pai,
+            is_tsumo,
+            is_oya: actor == board.oya,
+            bakaze: board.bakaze,
+            jikaze,
+            dora_markers: board.dora_markers.clone(),
+            ura_markers: ura_markers.map(|m| m.to_vec()).unwrap_or_default(),
+            situational,
+            kuitan: self.kuitan,
+        };
+        let agari = calculate(&tehai, &state.fuuros, &ctx, &yakuman_situational);
+        let is_reached = ctx
+            .situational
+            .iter()
+            .any(|y| matches!(y.yaku, Yaku::Riichi | Yaku::DoubleRiichi));
+        let ura_markers = if is_reached { ctx.ura_markers } else { vec![] };
+
+        let recorded = deltas.map(|d| d[a]);
+        let is_consistent = match (&agari, recorded) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(agari), Some(recorded)) => {
+                // in double ron, only one of the winners gets the sticks.
+                let honba = board.honba as i32 * 300;
+                let kyotaku = board.kyotaku as i32 * 1000;
+                [0, honba, honba + kyotaku]
+                    .iter()
+                    .any(|&bonus| agari.points + bonus == recorded)
+            }
+        };
+
+        Some(HoraScore {
+            actor,
+            target,
+            agari,
+            recorded,
+            is_consistent,
+            ura_markers,
+        })
+    }
+}
+
+impl YakuHan {
+    #[inline]
+    pub(crate) const fn new(yaku: Yaku, han: u8) -> Self {
+        Self { yaku, han }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MentsuKind {
+    Shuntsu,
+    Koutsu,
+    Kantsu,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mentsu {
+    kind: MentsuKind,
+    /// Index of the smallest pai.
+    idx: usize,
+    /// Not open and not completed by ron.
+    is_concealed: bool,
+    /// Whether or not it belongs to a fuuro, ankan included.
+    is_fuuro: bool,
+}
+
+/// A concealed mentsu found by `divide`, as its kind and the index of its
+/// smallest pai.
+type Block = (MentsuKind, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Shanpon,
+    Tanki,
+}
+
+/// Calculates the best interpretation of the hand. `tehai` must include the
+/// winning pai.
+///
+/// Returns `None` if the hand is not agari or has no yaku.
+pub fn calculate(
+    tehai: &[Pai],
+    fuuros: &[Fuuro],
+    ctx: &HoraContext,
+    yakuman_situational: &[YakuHan],
+) -> Option<Agari> {
+    let tiles = tiles_from_pais(tehai);
+    let win_idx = ctx.win_pai.as_index()?;
+    let is_menzen = fuuros.iter().all(|f| matches!(f, Fuuro::Ankan { .. }));
+
+    let mut all_pais = tehai.to_vec();
+    fuuros.iter().for_each(|f| all_pais.extend(fuuro_pais(f)));
+    let all_tiles = tiles_from_pais(&all_pais);
+
+    let fuuro_mentsu: Vec<_> = fuuros.iter().map(fuuro_mentsu).collect();
+
+    let mut candidates = vec![];
+
+    if fuuros.is_empty() && is_kokushi(&tiles) {
+        candidates.push(finish(
+            vec![YakuHan::new(Yaku::Kokushi, 13)],
+            0,
+            ctx,
+            yakuman_situational,
+            &all_pais,
+        ));
+    }
+
+    if fuuros.is_empty() && tiles.iter().filter(|&&c| c == 2).count() == 7 {
+        let mut yakus = ctx.situational.clone();
+        if ctx.is_tsumo {
+            yakus.push(YakuHan::new(Yaku::MenzenTsumo, 1));
+        }
+        yakus.push(YakuHan::new(Yaku::Chiitoitsu, 2));
+        yakus.extend(common_yakus(&all_tiles, true, ctx.kuitan));
+        let mut yakuman = vec![];
+        if all_tiles[..27].iter().all(|&c| c == 0) {
+            yakuman.push(YakuHan::new(Yaku::Tsuuiisou, 13));
+        }
+        candidates.push(if yakuman.is_empty() {
+            finish(yakus, 25, ctx, yakuman_situational, &all_pais)
+        } else {
+            finish(yakuman, 0, ctx, yakuman_situational, &all_pais)
+        });
+    }
+
+    let mut tiles_mut = tiles;
+    for (jantou, blocks) in divide(&mut tiles_mut) {
+        let mut waits = vec![];
+        if jantou == win_idx {
+            waits.push((Wait::Tanki, None));
+        }
+        for (i, &(kind, idx)) in blocks.iter().enumerate() {
+            match kind {
+                MentsuKind::Koutsu if idx == win_idx => waits.push((Wait::Shanpon, Some(i))),
+                MentsuKind::Shuntsu if (idx..idx + 3).contains(&win_idx) => {
+                    let wait = match win_idx - idx {
+                        1 => Wait::Kanchan,
+                        0 if idx % 9 == 6 => Wait::Penchan,
+                        2 if idx.is_multiple_of(9) => Wait::Penchan,
+                        _ => Wait::Ryanmen,
+                    };
+                    waits.push((wait, Some(i)));
+                }
+                _ => (),
+            }
+        }
+
+        for (wait, win_block) in waits {
+            let mentsu: Vec<_> = blocks
+                .iter()
+                .enumerate()
+                .map(|(i, &(kind, idx))| Mentsu {
+                    kind,
+                    idx,
+                    is_concealed: ctx.is_tsumo
+                        || win_block != Some(i)
+                        || kind == MentsuKind::Shuntsu,
+                    is_fuuro: false,
+                })
+                .chain(fuuro_mentsu.iter().copied())
+                .collect();
+            let (yakus, yakuman, fu) =
+                evaluate_normal(&mentsu, jantou, wait, is_menzen, &all_tiles, &tiles, ctx);
+            candidates.push(if yakuman.is_empty() {
+                finish(yakus, fu, ctx, yakuman_situational, &all_pais)
+            } else {
+                finish(yakuman, 0, ctx, yakuman_situational, &all_pais)
+            });
+        }
+    }
+
+    candidates
+        .into_iter()
+        .flatten()
+        .max_by_key(|a| (a.points, a.han, a.fu))
+}
+
+/// Adds dora and situational yakuman, and calculates the points.
+fn finish(
+    mut yakus: Vec<YakuHan>,
+    fu: u8,
+    ctx: &HoraContext,
+    yakuman_situational: &[YakuHan],
+    all_pais: &[Pai],
+) -> Option<Agari> {
+    let is_yakuman = yakus.iter().any(|y| y.han >= 13) || !yakuman_situational.is_empty();
+    if is_yakuman {
+        yakus.retain(|y| y.han >= 13);
+        yakus.extend_from_slice(yakuman_situational);
+        let yakuman = yakus.len() as u8;
+        let points = calc_points(8000 * yakuman as i32, ctx);
+        return Some(Agari {
+            yakus,
+            han: 13 * yakuman,
+            fu,
+            yakuman,
+            points,
+        });
+    }
+
+    if yakus.is_empty() {
+        return None;
+    }
+
+    let count_dora = |markers: &[Pai]| -> u8 {
+        markers
+            .iter()
+            .filter_map(|&m| dora_from_marker(m))
+            .map(|dora| all_pais.iter().filter(|p| p.deaka() == dora).count() as u8)
+            .sum()
+    };
+    let dora = count_dora(&ctx.dora_markers);
+    if dora > 0 {
+        yakus.push(YakuHan::new(Yaku::Dora, dora));
+    }
+    let aka = all_pais.iter().filter(|p| p.is_aka()).count() as u8;
+    if aka > 0 {
+        yakus.push(YakuHan::new(Yaku::AkaDora, aka));
+    }
+    let is_reached = yakus
+        .iter()
+        .any(|y| matches!(y.yaku, Yaku::Riichi | Yaku::DoubleRiichi));
+    if is_reached {
+        let ura = count_dora(&ctx.ura_markers);
+        if ura > 0 {
+            yakus.push(YakuHan::new(Yaku::UraDora, ura));
+        }
+    }
+
+    let han: u8 = yakus.iter().map(|y| y.han).sum();
+    let base = match han {
+        0..=4 => (fu as i32 * (1 << (han + 2))).min(2000),
+        5 => 2000,
+        6..=7 => 3000,
+        8..=10 => 4000,
+        11..=12 => 6000,
+        _ => 8000,
+    };
+    let points = calc_points(base, ctx);
+
+    Some(Agari {
+        yakus,
+        han,
+        fu,
+        yakuman: 0,
+        points,
+    })
+}
+
+fn calc_points(base: i32, ctx: &HoraContext) -> i32 {
+    let ceil100 = |n: i32| (n + 99) / 100 * 100;
+    match (ctx.is_oya, ctx.is_tsumo) {
+        (true, false) => ceil100(base * 6),
+        (false, false) => ceil100(base * 4),
+        (true, true) => ceil100(base * 2) * 3,
+        (false, true) => ceil100(base * 2) + ceil100(base) * 2,
+    }
+}
+
+/// Yakus that only depend on the set of pais.
+fn common_yakus(all_tiles: &Tiles, is_menzen: bool, kuitan: bool) -> Vec<YakuHan> {
+    let mut yakus = vec![];
+
+    if (is_menzen || kuitan)
+        && all_tiles
+            .iter()
+            .enumerate()
+            .all(|(i, &c)| c == 0 || !is_yaochuu(i))
+    {
+        yakus.push(YakuHan::new(Yaku::Tanyao, 1));
+    }
+    if all_tiles
+        .iter()
+        .enumerate()
+        .all(|(i, &c)| c == 0 || is_yaochuu(i))
+    {
+        yakus.push(YakuHan::new(Yaku::Honroutou, 2));
+    }
+
+    let suits = (0..3)
+        .filter(|s| all_tiles[s * 9..s * 9 + 9].iter().any(|&c| c > 0))
+        .count();
+    let has_jihai = all_tiles[27..].iter().any(|&c| c > 0);
+    if suits == 1 {
+        if has_jihai {
+            yakus.push(YakuHan::new(Yaku::Honitsu, if is_menzen { 3 } else { 2 }));
+        } else {
+            yakus.push(YakuHan::new(Yaku::Chinitsu, if is_menzen { 6 } else { 5 }));
+        }
+    }
+
+    yakus
+}
+
+#[allow(clippy::too_many_arguments)]
+fn evaluate_normal(
+    mentsu: &[Mentsu],
+    jantou: usize,
+    wait: Wait,
+    is_menzen: bool,
+    all_tiles: &Tiles,
+    tiles: &Tiles,
+    ctx: &HoraContext,
+) -> (Vec<YakuHan>, Vec<YakuHan>, u8) {
+    let bakaze = ctx.bakaze.as_index().unwrap_or(27);
+    let jikaze = ctx.jikaze.as_index().unwrap_or(27);
+    let is_yakuhai = |idx: usize| idx == bakaze || idx == jikaze || (HAKU..=CHUN).contains(&idx);
+    let is_triplet = |m: &Mentsu| m.kind != MentsuKind::Shuntsu;
+
+    let shuntsu: Vec<_> = mentsu
+        .iter()
+        .filter(|m| m.kind == MentsuKind::Shuntsu)
+        .map(|m| m.idx)
+        .collect();
+    let triplets: Vec<_> = mentsu
+        .iter()
+        .filter(|m| is_triplet(m))
+        .map(|m| m.idx)
+        .collect();
+    let ankou = mentsu
+        .iter()
+        .filter(|m| is_triplet(m) && m.is_concealed)
+        .count();
+    let kantsu = mentsu
+        .iter()
+        .filter(|m| m.kind == MentsuKind::Kantsu)
+        .count();
+    let sangen_triplets = triplets.iter().filter(|&&i| i >= HAKU).count();
+    let wind_triplets = triplets
+        .iter()
+        .filter(|&&i| (27..HAKU).contains(&i))
+        .count();
+
+    // yakuman
+    let mut yakuman = vec![];
+    if ankou == 4 {
+        yakuman.push(YakuHan::new(Yaku::Suuankou, 13));
+    }
+    if sangen_triplets == 3 {
+        yakuman.push(YakuHan::new(Yaku::Daisangen, 13));
+    }
+    if wind_triplets == 4 {
+        yakuman.push(YakuHan::new(Yaku::Daisuushii, 13));
+    } else if wind_triplets == 3 && (27..HAKU).contains(&jantou) {
+        yakuman.push(YakuHan::new(Yaku::Shousuushii, 13));
+    }
+    if all_tiles[..27].iter().all(|&c| c == 0) {
+        yakuman.push(YakuHan::new(Yaku::Tsuuiisou, 13));
+    }
+    const RYUUIISOU: [usize; 6] = [19, 20, 21, 23, 25, 32];
+    if all_tiles
+        .iter()
+        .enumerate()
+        .all(|(i, &c)| c == 0 || RYUUIISOU.contains(&i))
+    {
+        yakuman.push(YakuHan::new(Yaku::Ryuuiisou, 13));
+    }
+    if all_tiles
+        .iter()
+        .enumerate()
+        .all(|(i, &c)| c == 0 || (i < 27 && (i.is_multiple_of(9) || i % 9 == 8)))
+    {
+        yakuman.push(YakuHan::new(Yaku::Chinroutou, 13));
+    }
+    if mentsu.iter().all(|m| !m.is_fuuro) && is_chuuren(tiles) {
+        yakuman.push(YakuHan::new(Yaku::Chuuren, 13));
+    }
+    if kantsu == 4 {
+        yakuman.push(YakuHan::new(Yaku::Suukantsu, 13));
+    }
+    if !yakuman.is_empty() {
+        return (vec![], yakuman, 0);
+    }
+
+    let mut yakus = ctx.situational.clone();
+    if is_menzen && ctx.is_tsumo {
+        yakus.push(YakuHan::new(Yaku::MenzenTsumo, 1));
+    }
+
+    let is_pinfu = is_menzen && shuntsu.len() == 4 && !is_yakuhai(jantou) && wait == Wait::Ryanmen;
+    if is_pinfu {
+        yakus.push(YakuHan::new(Yaku::Pinfu, 1));
+    }
+
+    if is_menzen {
+        let mut sorted = shuntsu.clone();
+        sorted.sort_unstable();
+        let mut peikou = 0;
+        let mut i = 0;
+        while i + 1 < sorted.len() {
+            if sorted[i] == sorted[i + 1] {
+                peikou += 1;
+                i += 2;
+            } else {
+                i += 1;
+            }
+        }
+        match peikou {
+            1 => yakus.push(YakuHan::new(Yaku::Iipeikou, 1)),
+            2 => yakus.push(YakuHan::new(Yaku::Ryanpeikou, 3)),
+            _ => (),
+        }
+    }
+
+    for &idx in &triplets {
+        match idx {
+            31 => yakus.push(YakuHan::new(Yaku::Haku, 1)),
+            32 => yakus.push(YakuHan::new(Yaku::Hatsu, 1)),
+            33 => yakus.push(YakuHan::new(Yaku::Chun, 1)),
+            _ => (),
+        }
+        if idx == bakaze {
+            yakus.push(YakuHan::new(Yaku::Bakaze, 1));
+        }
+        if idx == jikaze {
+            yakus.push(YakuHan::new(Yaku::Jikaze, 1));
+        }
+    }
+
+    let naki_han = |menzen_han: u8| {
+        if is_menzen {
+            menzen_han
+        } else {
+            menzen_han - 1
+        }
+    };
+    if (0..9).any(|n| [n, n + 9, n + 18].iter().all(|i| shuntsu.contains(i))) {
+        yakus.push(YakuHan::new(Yaku::SanshokuDoujun, naki_han(2)));
+    }
+    if (0..3).any(|s| {
+        [s * 9, s * 9 + 3, s * 9 + 6]
+            .iter()
+            .all(|i| shuntsu.contains(i))
+    }) {
+        yakus.push(YakuHan::new(Yaku::Ittsu, naki_han(2)));
+    }
+
+    let mentsu_has_yaochuu = |m: &Mentsu| match m.kind {
+        MentsuKind::Shuntsu => m.idx.is_multiple_of(9) || m.idx % 9 == 6,
+        _ => is_yaochuu(m.idx),
+    };
+    if !shuntsu.is_empty() && is_yaochuu(jantou) && mentsu.iter().all(mentsu_has_yaochuu) {
+        if all_tiles[27..].iter().all(|&c| c == 0) {
+            yakus.push(YakuHan::new(Yaku::Junchan, naki_han(3)));
+        } else {
+            yakus.push(YakuHan::new(Yaku::Chanta, naki_han(2)));
+        }
+    }
+
+    if triplets.len() == 4 {
+        yakus.push(YakuHan::new(Yaku::Toitoi, 2));
+    }
+    if ankou == 3 {
+        yakus.push(YakuHan::new(Yaku::Sanankou, 2));
+    }
+    if (0..9).any(|n| [n, n + 9, n + 18].iter().all(|i| triplets.contains(i))) {
+        yakus.push(YakuHan::new(Yaku::SanshokuDoukou, 2));
+    }
+    if kantsu == 3 {
+        yakus.push(YakuHan::new(Yaku::Sankantsu, 2));
+    }
+    if sangen_triplets == 2 && jantou >= HAKU {
+        yakus.push(YakuHan::new(Yaku::Shousangen, 2));
+    }
+
+    yakus.extend(common_yakus(all_tiles, is_menzen, ctx.kuitan));
+
+    // fu
+    let fu = if is_pinfu && ctx.is_tsumo {
+        20
+    } else if is_pinfu {
+        30
+    } else {
+        let mut fu: u8 = 20;
+        if is_menzen && !ctx.is_tsumo {
+            fu += 10;
+        }
+        if ctx.is_tsumo {
+            fu += 2;
+        }
+        for m in mentsu {
+            let base = match m.kind {
+                MentsuKind::Shuntsu => 0,
+                MentsuKind::Koutsu => 2,
+                MentsuKind::Kantsu => 8,
+            };
+            let base = if is_yaochuu(m.idx) { base * 2 } else { base };
+            fu += if m.is_concealed { base * 2 } else { base };
+        }
+        if jantou >= HAKU {
+            fu += 2;
+        }
+        if jantou == bakaze {
+            fu += 2;
+        }
+        if jantou == jikaze {
+            fu += 2;
+        }
+        if matches!(wait, Wait::Kanchan | Wait::Penchan | Wait::Tanki) {
+            fu += 2;
+        }
+        let fu = fu.div_ceil(10) * 10;
+        // 喰い平和
+        if fu == 20 {
+            30
+        } else {
+            fu
+        }
+    };
+
+    (yakus, vec![], fu)
+}
+
+/// Enumerates every possible (jantou, concealed mentsu) division of `tiles`.
+fn divide(tiles: &mut Tiles) -> Vec<(usize, Vec<Block>)> {
+    let mut ret = vec![];
+    for jantou in 0..34 {
+        if tiles[jantou] < 2 {
+            continue;
+        }
+        tiles[jantou] -= 2;
+        let mut blocks = vec![];
+        divide_mentsu(tiles, 0, &mut blocks, &mut |blocks| {
+            ret.push((jantou, blocks.to_vec()))
+        });
+        tiles[jantou] += 2;
+    }
+    ret
+}
+
+fn divide_mentsu(
+    tiles: &mut Tiles,
+    start: usize,
+    blocks: &mut Vec<Block>,
+    on_found: &mut dyn FnMut(&[Block]),
+) {
+    let i = match (start..34).find(|&i| tiles[i] > 0) {
+        Some(i) => i,
+        None => {
+            on_found(blocks);
+            return;
+        }
+    };
+
+    if tiles[i] >= 3 {
+        tiles[i] -= 3;
+        blocks.push((MentsuKind::Koutsu, i));
+        divide_mentsu(tiles, i, blocks, on_found);
+        blocks.pop();
+        tiles[i] += 3;
+    }
+
+    if i < 27 && i % 9 <= 6 && tiles[i + 1] > 0 && tiles[i + 2] > 0 {
+        tiles[i] -= 1;
+        tiles[i + 1] -= 1;
+        tiles[i + 2] -= 1;
+        blocks.push((MentsuKind::Shuntsu, i));
+        divide_mentsu(tiles, i, blocks, on_found);
+        blocks.pop();
+        tiles[i] += 1;
+        tiles[i + 1] += 1;
+        tiles[i + 2] += 1;
+    }
+}
+
+fn fuuro_pais(fuuro: &Fuuro) -> Vec<Pai> {
+    match *fuuro {
+        Fuuro::Chi { pai, consumed, .. } | Fuuro::Pon { pai, consumed, .. } => {
+            let [a, b] = consumed.as_array();
+            vec![pai, a, b]
+        }
+        Fuuro::Daiminkan { pai, consumed, .. } => {
+            let [a, b, c] = consumed.as_array();
+            vec![pai, a, b, c]
+        }
+        Fuuro::Kakan {
+            pai,
+            previous_pon_pai,
+            consumed,
+            ..
+        } => {
+            let [a, b] = consumed.as_array();
+            vec![pai, previous_pon_pai, a, b]
+        }
+        Fuuro::Ankan { consumed } => consumed.as_array().to_vec(),
+    }
+}
+
+fn fuuro_mentsu(fuuro: &Fuuro) -> Mentsu {
+    let pais = fuuro_pais(fuuro);
+    let idx = pais.iter().filter_map(|p| p.as_index()).min().unwrap_or(0);
+    let (kind, is_concealed) = match fuuro {
+        Fuuro::Chi { .. } => (MentsuKind::Shuntsu, false),
+        Fuuro::Pon { .. } => (MentsuKind::Koutsu, false),
+        Fuuro::Daiminkan { .. } | Fuuro::Kakan { .. } => (MentsuKind::Kantsu, false),
+        Fuuro::Ankan { .. } => (MentsuKind::Kantsu, true),
+    };
+    Mentsu {
+        kind,
+        idx,
+        is_concealed,
+        is_fuuro: true,
+    }
+}
+
+#[inline]
+pub fn is_yaochuu(idx: usize) -> bool {
+    idx >= 27 || idx.is_multiple_of(9) || idx % 9 == 8
+}
+
+fn is_kokushi(tiles: &Tiles) -> bool {
+    (0..34).all(|i| is_yaochuu(i) == (tiles[i] > 0))
+        && tiles.iter().map(|&c| c as u32).sum::<u32>() == 14
+}
+
+fn is_chuuren(tiles: &Tiles) -> bool {
+    const PATTERN: [u8; 9] = [3, 1, 1, 1, 1, 1, 1, 1, 3];
+    (0..3).any(|s| {
+        let suit = &tiles[s * 9..s * 9 + 9];
+        tiles.iter().map(|&c| c as u32).sum::<u32>() == 14
+            && suit.iter().map(|&c| c as u32).sum::<u32>() == 14
+            && suit.iter().zip(PATTERN.iter()).all(|(&c, &p)| c >= p)
+    })
+}
+
+/// The dora that `marker` indicates.
+pub fn dora_from_marker(marker: Pai) -> Option<Pai> {
+    let idx = marker.as_index()?;
+    let next = match idx {
+        0..=26 => idx / 9 * 9 + (idx % 9 + 1) % 9,
+        27..=30 => 27 + (idx - 27 + 1) % 4,
+        _ => 31 + (idx - 31 + 1) % 3,
+    };
+    Pai::from_index(next)
+}
+
+/// Score changes of a hora worth `points`, honba and kyotaku included.
+pub fn hora_deltas(
+    points: i32,
+    actor: u8,
+    target: u8,
+    oya: u8,
+    honba: u8,
+    kyotaku: u8,
+) -> [i32; 4] {
+    let mut deltas = [0; 4];
+    let honba = honba as i32;
+
+    if actor != target {
+        deltas[target as usize] -= points + honba * 300;
+        deltas[actor as usize] += points + honba * 300;
+    } else {
+        for payer in (0..4).filter(|&p| p != actor) {
+            let share = if actor == oya {
+                points / 3
+            } else {
+                // the oya pays twice as much, both rounded up to 100
+                let ko = if points % 400 == 0 {
+                    points / 4
+                } else {
+                    (points + 100) / 4
+                };
+                if payer == oya {
+                    points - ko * 2
+                } else {
+                    ko
+                }
+            };
+            deltas[payer as usize] -= share + honba * 100;
+            deltas[actor as usize] += share + honba * 100;
+        }
+    }
+    deltas[actor as usize] += kyotaku as i32 * 1000;
+
+    deltas
+}
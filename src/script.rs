@@ -0,0 +1,114 @@
+use crate::analyzer::{Analyzer, Output};
+use crate::log;
+use crate::review::{Entry, KyokuReview};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+
+/// The function a script must define.
+const ANNOTATE_FN: &str = "annotate";
+
+/// A Rhai script from `--script`, run for every reviewed decision.
+///
+/// The script defines `fn annotate(entry)`, where `entry` is the decision as
+/// in `--json`, with `kyoku` and `honba` added. It returns a note, an array
+/// of tags, or nothing.
+pub struct Script {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+impl Script {
+    /// Compiles the script at `path` and runs its top level once, so that
+    /// constants defined there are visible to `annotate`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read script {:?}", path))?;
+        let engine = Engine::new();
+        let ast = engine
+            .compile(&source)
+            .map_err(|err| anyhow!("failed to compile script {:?}: {}", path, err))?;
+        ensure!(
+            ast.iter_functions()
+                .any(|f| f.name == ANNOTATE_FN && f.params.len() == 1),
+            "script {:?} does not define fn {}(entry)",
+            path,
+            ANNOTATE_FN,
+        );
+
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|err| anyhow!("failed to run script {:?}: {}", path, err))?;
+
+        let name = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        );
+        Ok(Self {
+            name,
+            engine,
+            ast,
+            scope,
+        })
+    }
+
+    fn annotate(&mut self, kyoku: &KyokuReview, entry: &Entry) -> Result<Option<String>> {
+        let mut arg = rhai::serde::to_dynamic(entry).map_err(|err| anyhow!("{}", err))?;
+        if let Some(mut map) = arg.write_lock::<rhai::Map>() {
+            map.insert("kyoku".into(), Dynamic::from_int(kyoku.kyoku.into()));
+            map.insert("honba".into(), Dynamic::from_int(kyoku.honba.into()));
+        }
+
+        let options = CallFnOptions::new().eval_ast(false);
+        let ret: Dynamic = self
+            .engine
+            .call_fn_with_options(options, &mut self.scope, &self.ast, ANNOTATE_FN, (arg,))
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let note = if ret.is_unit() {
+            None
+        } else if ret.is_array() {
+            let tags: Vec<_> = ret
+                .into_array()
+                .map_err(|t| anyhow!("unexpected {}", t))?
+                .into_iter()
+                .map(|t| t.to_string())
+                .collect();
+            (!tags.is_empty()).then(|| tags.join(", "))
+        } else {
+            Some(ret.to_string()).filter(|s| !s.is_empty())
+        };
+        Ok(note)
+    }
+}
+
+impl Analyzer for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_entry(&mut self, kyoku: &KyokuReview, entry: &Entry) -> Option<String> {
+        match self.annotate(kyoku, entry) {
+            Ok(note) => note,
+            Err(err) => {
+                log!(
+                    "WARNING: script {} failed at turn {} of kyoku {}: {}",
+                    self.name,
+                    entry.junme,
+                    kyoku.kyoku,
+                    err,
+                );
+                None
+            }
+        }
+    }
+
+    fn finish(&mut self, _kyokus: &mut [KyokuReview], _target_actor: u8) -> Result<Option<Output>> {
+        Ok(None)
+    }
+}
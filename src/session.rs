@@ -0,0 +1,198 @@
+use crate::i18n::Language;
+use crate::render;
+use crate::report_json::ReportJson;
+use crate::review::{Acceptance, Severity};
+use std::cmp::Ordering;
+use std::io::prelude::*;
+use std::path::Path;
+
+use anyhow::Result;
+use convlog::mjai::Event;
+use serde::Serialize;
+
+/// Reports of the games played in one sitting, in the order they were
+/// played.
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub player: Option<String>,
+    pub target_actor: u8,
+    pub games: Vec<SessionGame>,
+    /// Disagreements of every game, from the most severe.
+    pub mistakes: Vec<SessionMistake>,
+    pub lang: Language,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionGame {
+    /// File name or database row of the report.
+    pub source: String,
+    /// The HTML report of the game next to the JSON report, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<String>,
+    pub log_id: Option<String>,
+    /// Scores at the end of the last reviewed kyoku.
+    pub final_scores: [i32; 4],
+    /// Counts from 1.
+    pub placement: usize,
+    pub pt: i32,
+    pub cumulative_pt: i32,
+    pub reviewed: usize,
+    pub agreed: usize,
+    pub agree_rate: f64,
+    pub problems: usize,
+    pub ev_loss: f64,
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionMistake {
+    /// Index of the game in `Session::games`, counts from 1.
+    pub game: usize,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub junme: u8,
+    pub severity: Option<Severity>,
+    pub ev_loss: Option<f64>,
+    pub expected: Vec<Event>,
+    pub actual: Vec<Event>,
+}
+
+impl Session {
+    /// Merges `reports`, which must be given in the order they were played.
+    pub fn new(reports: Vec<(String, ReportJson)>, lang: Language) -> Self {
+        let target_actor = reports.first().map(|(_, r)| r.target_actor).unwrap_or(0);
+        let player = reports.iter().find_map(|(_, r)| {
+            r.splited_logs
+                .as_ref()?
+                .first()?
+                .name
+                .get(r.target_actor as usize)
+                .cloned()
+        });
+
+        let mut games = vec![];
+        let mut mistakes = vec![];
+        let mut cumulative_pt = 0;
+        for (i, (source, report)) in reports.into_iter().enumerate() {
+            let actor = report.target_actor as usize;
+            let events = report.kyokus.last().map(|k| k.events.as_slice());
+            let final_scores = final_scores(events.unwrap_or(&[]));
+            let placement = placement(&final_scores, actor);
+            let pt = report.metadata.pt[placement - 1];
+            cumulative_pt += pt;
+
+            let ev_loss = report
+                .kyokus
+                .iter()
+                .flat_map(|k| &k.entries)
+                .filter_map(|e| e.ev_loss)
+                .sum();
+            let meta = &report.metadata;
+            let agreed = meta.total_reviewed - meta.total_problems - meta.total_tolerated;
+
+            games.push(SessionGame {
+                report: html_report(&source),
+                source,
+                log_id: meta.log_id.clone(),
+                final_scores,
+                placement,
+                pt,
+                cumulative_pt,
+                reviewed: meta.total_reviewed,
+                agreed,
+                agree_rate: if meta.total_reviewed > 0 {
+                    agreed as f64 / meta.total_reviewed as f64
+                } else {
+                    0.
+                },
+                problems: meta.total_problems,
+                ev_loss,
+                score: meta.score,
+            });
+
+            for kyoku in report.kyokus {
+                for entry in kyoku.entries {
+                    if entry.acceptance != Acceptance::Disagree {
+                        continue;
+                    }
+                    mistakes.push(SessionMistake {
+                        game: i + 1,
+                        kyoku: kyoku.kyoku,
+                        honba: kyoku.honba,
+                        junme: entry.junme,
+                        severity: entry.severity,
+                        ev_loss: entry.ev_loss,
+                        expected: entry.expected,
+                        actual: entry.actual,
+                    });
+                }
+            }
+        }
+
+        mistakes.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| b.ev_loss.partial_cmp(&a.ev_loss).unwrap_or(Ordering::Equal))
+        });
+
+        Self {
+            player,
+            target_actor,
+            games,
+            mistakes,
+            lang,
+        }
+    }
+
+    /// Renders the session report in HTML.
+    pub fn render<W: Write>(&self, w: &mut W) -> Result<()> {
+        render::render_template("session.html", self, self.lang, w)
+    }
+}
+
+/// Scores after the last kyoku in `events`, from its start_kyoku, the reach
+/// sticks and the deltas of its end.
+pub fn final_scores(events: &[Event]) -> [i32; 4] {
+    let mut scores = [0; 4];
+    for event in events {
+        match *event {
+            Event::StartKyoku { scores: s, .. } => scores = s,
+            Event::ReachAccepted { actor } => scores[actor as usize] -= 1000,
+            Event::Hora {
+                deltas: Some(deltas),
+                ..
+            }
+            | Event::Ryukyoku {
+                deltas: Some(deltas),
+                ..
+            } => {
+                for (s, d) in scores.iter_mut().zip(deltas.iter()) {
+                    *s += d;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    scores
+}
+
+/// Placement of `actor`, counting from 1. Ties are broken by the seat, the
+/// first oya being the highest.
+pub fn placement(scores: &[i32; 4], actor: usize) -> usize {
+    1 + scores
+        .iter()
+        .enumerate()
+        .filter(|&(i, &s)| s > scores[actor] || (s == scores[actor] && i < actor))
+        .count()
+}
+
+/// The HTML report saved next to the JSON report at `source`, if any.
+fn html_report(source: &str) -> Option<String> {
+    let html = Path::new(source).with_extension("html");
+    if html.is_file() {
+        Some(html.display().to_string())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,298 @@
+use convlog::Pai;
+use once_cell::sync::Lazy;
+
+/// Counts of each kind of pai, indexed by `Pai::as_index`.
+pub type Tiles = [u8; 34];
+
+/// Collect `pais` into counts. `Pai::Unknown` is ignored.
+pub fn tiles_from_pais(pais: &[Pai]) -> Tiles {
+    let mut tiles = [0; 34];
+    pais.iter()
+        .filter_map(|pai| pai.as_index())
+        .for_each(|idx| tiles[idx] += 1);
+    tiles
+}
+
+/// Most pais of a suit that can be in a hand, which is also the size of the
+/// largest goal.
+const MAX_SUIT_PAIS: usize = 14;
+
+/// What a suit can contribute to the normal form, `mentsu * 2 + jantou` for
+/// up to 4 mentsu and 1 jantou.
+const GOALS: usize = 10;
+
+#[inline]
+const fn goal(mentsu: usize, jantou: usize) -> usize {
+    mentsu * 2 + jantou
+}
+
+/// Per suit lookup table for the normal form.
+///
+/// For every possible arrangement of one suit in a hand, it holds how many
+/// pais are missing to reach each goal, i.e. the least number of pais to
+/// add so that the arrangement contains `mentsu` mentsu and `jantou`
+/// jantou. The shanten of the whole hand is then the least sum of missing
+/// pais over the ways to split 4 mentsu and 1 jantou among the suits, minus
+/// one, with no search over the pais themselves.
+///
+/// An arrangement is the counts of the pais of the suit, at most 14 in
+/// total, and is indexed by its rank in lexicographic order among all
+/// arrangements of the same length.
+struct SuitTable {
+    len: usize,
+    /// `ways[k][n]` is the number of arrangements of length `k` with at most
+    /// `n` pais.
+    ways: Vec<[u32; MAX_SUIT_PAIS + 1]>,
+    missing: Vec<[u8; GOALS]>,
+}
+
+static SUUHAI_TABLE: Lazy<SuitTable> = Lazy::new(|| SuitTable::new(9, true));
+static JIHAI_TABLE: Lazy<SuitTable> = Lazy::new(|| SuitTable::new(7, false));
+
+impl SuitTable {
+    fn new(len: usize, has_shuntsu: bool) -> Self {
+        let mut ways = vec![[1; MAX_SUIT_PAIS + 1]; len + 1];
+        for k in 1..=len {
+            for n in 0..=MAX_SUIT_PAIS {
+                ways[k][n] = (0..=n.min(4)).map(|c| ways[k - 1][n - c]).sum();
+            }
+        }
+        let mut table = Self {
+            len,
+            ways,
+            missing: vec![],
+        };
+
+        // every arrangement in the order of their ranks
+        let mut arrangements = Vec::with_capacity(table.ways[len][MAX_SUIT_PAIS] as usize);
+        fn push_all(
+            counts: &mut [u8; 9],
+            i: usize,
+            len: usize,
+            left: usize,
+            out: &mut Vec<[u8; 9]>,
+        ) {
+            if i == len {
+                out.push(*counts);
+                return;
+            }
+            for c in 0..=left.min(4) {
+                counts[i] = c as u8;
+                push_all(counts, i + 1, len, left - c, out);
+            }
+            counts[i] = 0;
+        }
+        push_all(&mut [0; 9], 0, len, MAX_SUIT_PAIS, &mut arrangements);
+
+        // Marks the goals each arrangement is part of, which are the complete
+        // ones and everything below them.
+        let mut partial_of = vec![0u16; arrangements.len()];
+        let mut mentsus: Vec<[u8; 3]> = (0..len).map(|i| [i as u8; 3]).collect();
+        if has_shuntsu {
+            mentsus.extend((0..len - 2).map(|i| [i as u8, i as u8 + 1, i as u8 + 2]));
+        }
+        let mut counts = vec![0; len];
+        table.mark_complete(&mentsus, 0, 0, &mut counts, &mut partial_of);
+
+        let mut by_size: Vec<Vec<usize>> = vec![vec![]; MAX_SUIT_PAIS + 1];
+        for (idx, a) in arrangements.iter().enumerate() {
+            by_size[a.iter().map(|&c| c as usize).sum::<usize>()].push(idx);
+        }
+        for indices in by_size.iter().skip(1).rev() {
+            for &idx in indices {
+                let goals = partial_of[idx];
+                if goals == 0 {
+                    continue;
+                }
+                let mut a = arrangements[idx];
+                for i in 0..len {
+                    if a[i] > 0 {
+                        a[i] -= 1;
+                        partial_of[table.rank(&a[..len])] |= goals;
+                        a[i] += 1;
+                    }
+                }
+            }
+        }
+
+        // The most pais of each arrangement that can stay for each goal,
+        // which is its largest part that is a partial goal.
+        let mut kept = vec![[0u8; GOALS]; arrangements.len()];
+        for (size, indices) in by_size.iter().enumerate().skip(1) {
+            for &idx in indices {
+                let mut best = [0; GOALS];
+                let mut a = arrangements[idx];
+                for i in 0..len {
+                    if a[i] > 0 {
+                        a[i] -= 1;
+                        let smaller = &kept[table.rank(&a[..len])];
+                        for (b, &s) in best.iter_mut().zip(smaller) {
+                            *b = (*b).max(s);
+                        }
+                        a[i] += 1;
+                    }
+                }
+                for (g, b) in best.iter_mut().enumerate() {
+                    if partial_of[idx] & (1 << g) != 0 {
+                        *b = size as u8;
+                    }
+                }
+                kept[idx] = best;
+            }
+        }
+
+        table.missing = kept
+            .iter()
+            .map(|kept| {
+                let mut missing = [0; GOALS];
+                for (g, m) in missing.iter_mut().enumerate() {
+                    let goal_size = g / 2 * 3 + g % 2 * 2;
+                    *m = goal_size as u8 - kept[g].min(goal_size as u8);
+                }
+                missing
+            })
+            .collect();
+        table
+    }
+
+    /// Marks every complete arrangement of up to 4 mentsu from `mentsus`, the
+    /// ones from index `start` on, and at most one jantou.
+    fn mark_complete(
+        &self,
+        mentsus: &[[u8; 3]],
+        start: usize,
+        mentsu_count: usize,
+        counts: &mut [u8],
+        partial_of: &mut [u16],
+    ) {
+        partial_of[self.rank(counts)] |= 1 << goal(mentsu_count, 0);
+        for i in 0..self.len {
+            if counts[i] <= 2 {
+                counts[i] += 2;
+                partial_of[self.rank(counts)] |= 1 << goal(mentsu_count, 1);
+                counts[i] -= 2;
+            }
+        }
+
+        if mentsu_count == 4 {
+            return;
+        }
+        for (m, mentsu) in mentsus.iter().enumerate().skip(start) {
+            for &i in mentsu {
+                counts[i as usize] += 1;
+            }
+            if mentsu.iter().all(|&i| counts[i as usize] <= 4) {
+                self.mark_complete(mentsus, m, mentsu_count + 1, counts, partial_of);
+            }
+            for &i in mentsu {
+                counts[i as usize] -= 1;
+            }
+        }
+    }
+
+    fn rank(&self, counts: &[u8]) -> usize {
+        let mut rank = 0;
+        let mut left = MAX_SUIT_PAIS;
+        for (i, &c) in counts.iter().enumerate() {
+            let rest = &self.ways[self.len - i - 1];
+            for smaller in 0..c as usize {
+                rank += rest[left - smaller] as usize;
+            }
+            left -= c as usize;
+        }
+        rank
+    }
+
+    #[inline]
+    fn missing(&self, counts: &[u8]) -> &[u8; GOALS] {
+        &self.missing[self.rank(counts)]
+    }
+}
+
+/// Calculates shanten of the normal form (4 mentsu + 1 jantou) with the
+/// lookup tables of `SuitTable`.
+///
+/// `fuuro_count` mentsu are considered already completed by naki or ankan, so
+/// only the rest of them are looked for in `tiles`, which must have at most
+/// 14 pais.
+pub fn normal_shanten(tiles: &Tiles, fuuro_count: u8) -> i8 {
+    let mentsu = 4 - fuuro_count.min(4) as usize;
+
+    // least missing pais for each goal from the suits so far
+    let mut least = [u8::MAX; GOALS];
+    least[goal(0, 0)] = 0;
+    let suits = [
+        SUUHAI_TABLE.missing(&tiles[0..9]),
+        SUUHAI_TABLE.missing(&tiles[9..18]),
+        SUUHAI_TABLE.missing(&tiles[18..27]),
+        JIHAI_TABLE.missing(&tiles[27..34]),
+    ];
+    for missing in suits {
+        let mut next = [u8::MAX; GOALS];
+        for (g, &so_far) in least.iter().enumerate() {
+            if so_far == u8::MAX {
+                continue;
+            }
+            let (m, j) = (g / 2, g % 2);
+            for (h, &more) in missing.iter().enumerate() {
+                let (mm, jj) = (m + h / 2, j + h % 2);
+                if mm <= mentsu && jj <= 1 {
+                    let n = &mut next[goal(mm, jj)];
+                    *n = (*n).min(so_far + more);
+                }
+            }
+        }
+        least = next;
+    }
+
+    least[goal(mentsu, 1)] as i8 - 1
+}
+
+/// Calculates shanten of chiitoitsu.
+pub fn chiitoi_shanten(tiles: &Tiles) -> i8 {
+    let kinds = tiles.iter().filter(|&&c| c > 0).count() as i8;
+    let pairs = tiles.iter().filter(|&&c| c >= 2).count() as i8;
+    6 - pairs + (7 - kinds).max(0)
+}
+
+/// Calculates shanten of kokushi musou.
+pub fn kokushi_shanten(tiles: &Tiles) -> i8 {
+    const YAOCHUU: [usize; 13] = [0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33];
+
+    let kinds = YAOCHUU.iter().filter(|&&i| tiles[i] > 0).count() as i8;
+    let has_pair = YAOCHUU.iter().any(|&i| tiles[i] >= 2);
+    13 - kinds - has_pair as i8
+}
+
+/// Calculates the minimal shanten among normal form, chiitoitsu and kokushi
+/// musou. -1 means agari.
+///
+/// Chiitoitsu and kokushi musou are only considered when there is no fuuro.
+pub fn get_shanten(tiles: &Tiles, fuuro_count: u8) -> i8 {
+    let normal = normal_shanten(tiles, fuuro_count);
+    if fuuro_count > 0 {
+        return normal;
+    }
+
+    normal
+        .min(chiitoi_shanten(tiles))
+        .min(kokushi_shanten(tiles))
+}
+
+/// Returns the waits of a tenpai hand of 3n+1 pais. An empty list is returned
+/// if the hand is not tenpai.
+pub fn get_waits(tiles: &Tiles, fuuro_count: u8) -> Vec<Pai> {
+    let mut tiles = *tiles;
+    (0..34)
+        .filter(|&i| {
+            if tiles[i] >= 4 {
+                return false;
+            }
+            tiles[i] += 1;
+            let is_agari = get_shanten(&tiles, fuuro_count) == -1;
+            tiles[i] -= 1;
+            is_agari
+        })
+        .filter_map(Pai::from_index)
+        .collect()
+}
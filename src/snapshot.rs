@@ -0,0 +1,73 @@
+use crate::i18n::Language;
+use crate::position::kyoku_label;
+use crate::render::SnapshotView;
+use crate::review::{self, KyokuReview};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use resvg::{tiny_skia, usvg};
+
+/// Images are rendered at this multiple of the SVG size, so that they stay
+/// sharp when zoomed in on a phone.
+const SCALE: f32 = 2.;
+
+pub struct SnapshotArgs<'a> {
+    pub dir: &'a Path,
+    /// How many decisions to draw, counting from the one with the largest EV
+    /// loss.
+    pub count: usize,
+    pub target_actor: u8,
+    pub use_placement_ev: bool,
+    pub lang: Language,
+}
+
+/// Draws the board and akochan's candidates of the worst decisions in
+/// `kyokus` as PNG images in `args.dir`, and returns their paths.
+///
+/// Only disagreements with a known EV loss are drawn.
+pub fn write_snapshots(kyokus: &[KyokuReview], args: &SnapshotArgs) -> Result<Vec<PathBuf>> {
+    let worst = review::worst_entries(kyokus, args.count);
+
+    fs::create_dir_all(args.dir)
+        .with_context(|| format!("failed to create directory {:?}", args.dir))?;
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let mut paths = vec![];
+    for (i, (kyoku, entry)) in worst.into_iter().enumerate() {
+        let view = SnapshotView::new(
+            kyoku,
+            entry,
+            args.target_actor,
+            args.use_placement_ev,
+            args.lang,
+        );
+        let mut svg = vec![];
+        view.render(&mut svg).context("failed to render snapshot")?;
+
+        let tree = usvg::Tree::from_data(&svg, &options).context("failed to parse snapshot")?;
+        let size = tree.size().to_int_size().scale_by(SCALE).unwrap();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).unwrap();
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(SCALE, SCALE),
+            &mut pixmap.as_mut(),
+        );
+
+        let path = args.dir.join(format!(
+            "{:02}-{}-{}-{}.png",
+            i + 1,
+            kyoku_label(kyoku.kyoku),
+            kyoku.honba,
+            entry.junme,
+        ));
+        pixmap
+            .save_png(&path)
+            .with_context(|| format!("failed to write {:?}", path))?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
@@ -1,12 +1,15 @@
+use crate::scoring::dora_from_marker;
+use crate::shanten::{get_shanten, get_waits, tiles_from_pais, Tiles};
 use crate::tehai::Tehai;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use anyhow::{Context, Result};
 use convlog::mjai::{Consumed2, Consumed3, Consumed4, Event};
 use convlog::Pai;
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
 
+#[serde_as]
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct State {
     #[serde(skip)]
@@ -14,6 +17,101 @@ pub struct State {
 
     pub tehai: Tehai,
     pub fuuros: Vec<Fuuro>,
+
+    /// Discarded pais of every actor, including the ones taken by naki.
+    #[serde_as(as = "[Vec<DisplayFromStr>; 4]")]
+    pub kawas: [Vec<Pai>; 4],
+    pub reached: [bool; 4],
+    /// Dora indicators revealed so far, including the ones after kans.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub dora_markers: Vec<Pai>,
+
+    /// Waits of the target actor when the tehai is 3n+1, updated after every
+    /// change of the tehai but a tsumo, so that they still hold until the
+    /// dahai.
+    #[serde(skip)]
+    waits: Vec<Pai>,
+    /// Set when the target actor passes an agari pai, cleared by the next
+    /// dahai of the target actor.
+    #[serde(skip)]
+    is_temporary_furiten: bool,
+    /// Set when the target actor passes an agari pai after reach.
+    #[serde(skip)]
+    is_reach_furiten: bool,
+    /// The last pai discarded by others, or drawn by the target actor after
+    /// reach, which the target actor may still hora on with the next event.
+    #[serde(skip)]
+    pending_pass: Option<Pai>,
+
+    /// Count of each kind of pai visible to the target actor.
+    #[serde(skip)]
+    visible: Visible,
+    /// Fuuros of every actor, including ankan.
+    #[serde(skip)]
+    all_fuuros: [Vec<Fuuro>; 4],
+    /// Discards of every actor with how they were discarded, in the same
+    /// order as `kawas`.
+    #[serde(skip)]
+    rivers: [Vec<Sutehai>; 4],
+    /// Pais that are known to be safe against each actor, which are the
+    /// actor's own discards and the ones passed after the actor's reach.
+    #[serde(skip)]
+    genbutsu: [Vec<Pai>; 4],
+}
+
+/// A discarded pai as seen in a river.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sutehai {
+    pub pai: Pai,
+    pub tsumogiri: bool,
+    /// The pai discarded with the reach declaration.
+    pub is_reach: bool,
+    /// The actor that took the pai by naki, if any.
+    pub called_by: Option<u8>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct Ukeire {
+    pub shanten: i8,
+    /// Kinds of pais that reduce the shanten.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub pais: Vec<Pai>,
+    /// Number of the remaining copies of `pais`.
+    pub count: u32,
+}
+
+/// Dora in the tehai and fuuros of the target actor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DoraCount {
+    /// From the dora indicators, counting a pai once for every indicator
+    /// pointing at it.
+    pub dora: u8,
+    /// Red fives.
+    pub aka: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Visible(Tiles);
+
+impl Default for Visible {
+    fn default() -> Self {
+        Self([0; 34])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Furiten {
+    None,
+    /// 同巡内フリテン, lasts until the next dahai.
+    Temporary,
+    /// Either 捨て牌フリテン or 立直後の見逃し, lasts until the waits change,
+    /// or until the end of the kyoku for the latter.
+    Permanent,
+    /// The tehai is 3n+2, so whether it is furiten depends on the dahai, see
+    /// `State::furiten_after_dahai`.
+    Unknown,
 }
 
 impl State {
@@ -38,15 +136,44 @@ impl State {
     ///
     /// and the `actor` must be the target actor.
     ///
+    /// Dahai, Kakan and ReachAccepted of other actors are also taken to track
+    /// the kawas, reach status and furiten.
+    ///
     /// Otherwise this is a no-op.
     pub fn update(&mut self, event: &Event) -> Result<()> {
+        if let Some(pai) = self.pending_pass.take() {
+            if !matches!(*event, Event::Hora { actor, .. } if actor == self.actor) {
+                self.pass_pai(pai);
+            }
+        }
+
+        self.observe(event).context("tile conservation violated")?;
+
         match *event {
-            Event::StartKyoku { tehais, .. } => {
+            Event::StartKyoku {
+                dora_marker,
+                tehais,
+                ..
+            } => {
                 self.tehai.haipai(&tehais[self.actor as usize]);
+                self.dora_markers = vec![dora_marker];
                 self.fuuros.clear();
+                self.kawas = Default::default();
+                self.reached = [false; 4];
+                self.is_temporary_furiten = false;
+                self.is_reach_furiten = false;
+                self.pending_pass = None;
+                self.update_waits();
             }
 
-            Event::Tsumo { actor, pai } if actor == self.actor => self.tehai.tsumo(pai),
+            Event::Tsumo { actor, pai } if actor == self.actor => {
+                self.tehai.tsumo(pai);
+                // passing a tsumo agari is furiten as well once reached;
+                // `waits` are still the ones before the tsumo here
+                if self.reached[actor as usize] {
+                    self.pending_pass = Some(pai);
+                }
+            }
 
             Event::Dahai {
                 actor,
@@ -58,8 +185,27 @@ impl State {
                 } else {
                     self.tehai.tedashi(pai);
                 }
+                self.kawas[actor as usize].push(pai);
+                self.is_temporary_furiten = false;
+                self.update_waits();
             }
 
+            Event::Dahai { actor, pai, .. } => {
+                self.kawas[actor as usize].push(pai);
+                self.pending_pass = Some(pai);
+            }
+
+            Event::Kakan { actor, pai, .. } if actor != self.actor => {
+                // chankan is possible
+                self.pending_pass = Some(pai);
+            }
+
+            Event::ReachAccepted { actor } => {
+                self.reached[actor as usize] = true;
+            }
+
+            Event::Dora { dora_marker } => self.dora_markers.push(dora_marker),
+
             Event::Chi {
                 actor,
                 target,
@@ -74,6 +220,7 @@ impl State {
                     consumed,
                 };
                 self.fuuros.push(fuuro);
+                self.update_waits();
             }
 
             Event::Pon {
@@ -90,6 +237,7 @@ impl State {
                     consumed,
                 };
                 self.fuuros.push(fuuro);
+                self.update_waits();
             }
 
             Event::Daiminkan {
@@ -106,6 +254,7 @@ impl State {
                     consumed,
                 };
                 self.fuuros.push(fuuro);
+                self.update_waits();
             }
 
             Event::Kakan {
@@ -114,41 +263,8 @@ impl State {
                 consumed,
             } if actor == self.actor => {
                 self.tehai.tedashi(pai);
-
-                let (
-                    previous_pon_idx,
-                    previous_pon_target,
-                    previous_pon_pai,
-                    previous_pon_consumed,
-                ) = self
-                    .fuuros
-                    .iter()
-                    .enumerate()
-                    .find_map(|(idx, f)| match *f {
-                        Fuuro::Pon {
-                            target: pon_target,
-                            pai: pon_pai,
-                            consumed: pon_consumed,
-                        } if Consumed3::from([
-                            pon_pai,
-                            pon_consumed.as_array()[0],
-                            pon_consumed.as_array()[1],
-                        ]) == consumed =>
-                        {
-                            Some((idx, pon_target, pon_pai, pon_consumed))
-                        }
-
-                        _ => None,
-                    })
-                    .context(anyhow!("invalid state: previous Pon not found for Kakan"))?;
-
-                let fuuro = Fuuro::Kakan {
-                    pai,
-                    previous_pon_target,
-                    previous_pon_pai,
-                    consumed: previous_pon_consumed,
-                };
-                self.fuuros[previous_pon_idx] = fuuro;
+                upgrade_pon(&mut self.fuuros, pai, consumed)?;
+                self.update_waits();
             }
 
             Event::Ankan { actor, consumed } if actor == self.actor => {
@@ -156,6 +272,7 @@ impl State {
 
                 let fuuro = Fuuro::Ankan { consumed };
                 self.fuuros.push(fuuro);
+                self.update_waits();
             }
 
             _ => (),
@@ -163,6 +280,363 @@ impl State {
 
         Ok(())
     }
+
+    /// Returns how many pais of the kind of `pai` are visible to the target
+    /// actor.
+    #[inline]
+    pub fn visible_count(&self, pai: Pai) -> u8 {
+        pai.as_index().map(|idx| self.visible.0[idx]).unwrap_or(0)
+    }
+
+    /// Returns how many pais of the kind of `pai` are not visible to the
+    /// target actor, i.e. may still be drawn or are in others' tehais.
+    #[inline]
+    pub fn remaining(&self, pai: Pai) -> u8 {
+        4 - self.visible_count(pai)
+    }
+
+    /// Returns the shanten and the ukeire after discarding `pai`, or `None` if
+    /// `pai` is not in the tehai.
+    ///
+    /// The tehai must be 3n+2.
+    pub fn ukeire_after_dahai(&self, pai: Pai) -> Option<Ukeire> {
+        let mut tiles = tiles_from_pais(self.tehai.view());
+        let idx = pai.as_index()?;
+        if tiles[idx] == 0 {
+            return None;
+        }
+        tiles[idx] -= 1;
+
+        let fuuro_count = self.fuuros.len() as u8;
+        let shanten = get_shanten(&tiles, fuuro_count);
+        let pais: Vec<_> = (0..34)
+            .filter(|&i| {
+                if tiles[i] >= 4 {
+                    return false;
+                }
+                tiles[i] += 1;
+                let is_improved = get_shanten(&tiles, fuuro_count) < shanten;
+                tiles[i] -= 1;
+                is_improved
+            })
+            .filter_map(Pai::from_index)
+            .collect();
+        let count = pais.iter().map(|&p| self.remaining(p) as u32).sum();
+
+        Some(Ukeire {
+            shanten,
+            pais,
+            count,
+        })
+    }
+
+    /// Returns the dora in the tehai and fuuros of the target actor.
+    pub fn dora_count(&self) -> DoraCount {
+        let pais: Vec<_> = self
+            .tehai
+            .view()
+            .iter()
+            .copied()
+            .chain(self.fuuros.iter().flat_map(|f| f.pais()))
+            .collect();
+        let dora = self
+            .dora_markers
+            .iter()
+            .filter_map(|&m| dora_from_marker(m))
+            .map(|dora| pais.iter().filter(|p| p.deaka() == dora).count() as u8)
+            .sum();
+        let aka = pais.iter().filter(|p| p.is_aka()).count() as u8;
+
+        DoraCount { dora, aka }
+    }
+
+    #[inline]
+    pub fn fuuro_count(&self, actor: u8) -> u8 {
+        self.all_fuuros[actor as usize].len() as u8
+    }
+
+    /// Returns the fuuros of `actor`, which are the same as `fuuros` for the
+    /// target actor.
+    #[inline]
+    pub fn fuuros_of(&self, actor: u8) -> &[Fuuro] {
+        &self.all_fuuros[actor as usize]
+    }
+
+    /// Returns every discard of `actor` so far, including the ones taken by
+    /// naki.
+    #[inline]
+    pub fn river(&self, actor: u8) -> &[Sutehai] {
+        &self.rivers[actor as usize]
+    }
+
+    /// Returns the pai `actor` discarded to reach, or `None` if `actor` has
+    /// not reached.
+    pub fn reach_pai(&self, actor: u8) -> Option<Pai> {
+        self.river(actor).iter().find(|s| s.is_reach).map(|s| s.pai)
+    }
+
+    /// Returns true if `pai` is a genbutsu against `actor`.
+    #[inline]
+    pub fn is_genbutsu(&self, actor: u8, pai: Pai) -> bool {
+        self.genbutsu[actor as usize].contains(&pai.deaka())
+    }
+
+    /// Returns the kinds of pais that are genbutsu against `actor`, in the
+    /// order they became safe.
+    pub fn genbutsu(&self, actor: u8) -> Vec<Pai> {
+        let mut pais = self.genbutsu[actor as usize].clone();
+        let mut seen = [false; 34];
+        pais.retain(|p| match p.as_index() {
+            Some(idx) => !std::mem::replace(&mut seen[idx], true),
+            None => false,
+        });
+        pais
+    }
+
+    /// Returns the furiten status of the target actor.
+    ///
+    /// When the tehai is 3n+2, only 立直後の見逃し is known before the dahai,
+    /// and `Furiten::Unknown` is returned otherwise.
+    pub fn furiten(&self) -> Furiten {
+        if self.is_reach_furiten {
+            Furiten::Permanent
+        } else if self.tehai.view().len() % 3 == 2 {
+            Furiten::Unknown
+        } else if self.is_sutehai_furiten(&self.waits, None) {
+            Furiten::Permanent
+        } else if self.is_temporary_furiten {
+            Furiten::Temporary
+        } else {
+            Furiten::None
+        }
+    }
+
+    /// Returns whether or not the target actor will be in furiten after
+    /// discarding `pai`, or `None` if the tehai will not be tenpai.
+    ///
+    /// The tehai must be 3n+2.
+    pub fn furiten_after_dahai(&self, pai: Pai) -> Option<bool> {
+        let mut tiles = tiles_from_pais(self.tehai.view());
+        let idx = pai.as_index()?;
+        if tiles[idx] == 0 {
+            return None;
+        }
+        tiles[idx] -= 1;
+
+        let waits = get_waits(&tiles, self.fuuros.len() as u8);
+        if waits.is_empty() {
+            return None;
+        }
+
+        Some(self.is_reach_furiten || self.is_sutehai_furiten(&waits, Some(pai)))
+    }
+
+    /// Tracks the public information of all actors, as well as what the
+    /// target actor sees.
+    fn observe(&mut self, event: &Event) -> Result<()> {
+        match *event {
+            Event::StartKyoku {
+                dora_marker,
+                tehais,
+                ..
+            } => {
+                self.visible = Visible::default();
+                self.all_fuuros = Default::default();
+                self.rivers = Default::default();
+                self.genbutsu = Default::default();
+                self.see(&[dora_marker])?;
+                self.see(&tehais[self.actor as usize])?;
+            }
+
+            Event::Dora { dora_marker } => self.see(&[dora_marker])?,
+
+            Event::Tsumo { actor, pai } if actor == self.actor => self.see(&[pai])?,
+
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                if actor != self.actor {
+                    self.see(&[pai])?;
+                }
+                self.rivers[actor as usize].push(Sutehai {
+                    pai,
+                    tsumogiri,
+                    is_reach: false,
+                    called_by: None,
+                });
+
+                let pai = pai.deaka();
+                self.genbutsu[actor as usize].push(pai);
+                for other in 0..4 {
+                    if other != actor as usize && self.reached[other] {
+                        self.genbutsu[other].push(pai);
+                    }
+                }
+            }
+
+            // The reach is accepted after the dahai, which is the last one
+            // in the river.
+            Event::ReachAccepted { actor } => {
+                if let Some(sutehai) = self.rivers[actor as usize].last_mut() {
+                    sutehai.is_reach = true;
+                }
+            }
+
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if actor != self.actor {
+                    self.see(&consumed.as_array())?;
+                }
+                self.mark_called(actor, target);
+                self.all_fuuros[actor as usize].push(Fuuro::Chi {
+                    target,
+                    pai,
+                    consumed,
+                });
+            }
+
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if actor != self.actor {
+                    self.see(&consumed.as_array())?;
+                }
+                self.mark_called(actor, target);
+                self.all_fuuros[actor as usize].push(Fuuro::Pon {
+                    target,
+                    pai,
+                    consumed,
+                });
+            }
+
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                if actor != self.actor {
+                    self.see(&consumed.as_array())?;
+                }
+                self.mark_called(actor, target);
+                self.all_fuuros[actor as usize].push(Fuuro::Daiminkan {
+                    target,
+                    pai,
+                    consumed,
+                });
+            }
+
+            Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                if actor != self.actor {
+                    self.see(&[pai])?;
+                }
+                upgrade_pon(&mut self.all_fuuros[actor as usize], pai, consumed)?;
+            }
+
+            Event::Ankan { actor, consumed } => {
+                if actor != self.actor {
+                    self.see(&consumed.as_array())?;
+                }
+                self.all_fuuros[actor as usize].push(Fuuro::Ankan { consumed });
+            }
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn see(&mut self, pais: &[Pai]) -> Result<()> {
+        for &pai in pais {
+            if let Some(idx) = pai.as_index() {
+                let count = &mut self.visible.0[idx];
+                if *count >= 4 {
+                    bail!("more than 4 copies of {} are visible", pai.deaka());
+                }
+                *count += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Marks the last discard of `target` as taken by `actor`.
+    fn mark_called(&mut self, actor: u8, target: u8) {
+        if let Some(sutehai) = self.rivers[target as usize].last_mut() {
+            sutehai.called_by = Some(actor);
+        }
+    }
+
+    fn is_sutehai_furiten(&self, waits: &[Pai], extra_dahai: Option<Pai>) -> bool {
+        self.kawas[self.actor as usize]
+            .iter()
+            .chain(extra_dahai.iter())
+            .any(|&p| waits.contains(&p.deaka()))
+    }
+
+    fn pass_pai(&mut self, pai: Pai) {
+        if self.waits.contains(&pai.deaka()) {
+            self.is_temporary_furiten = true;
+            if self.reached[self.actor as usize] {
+                self.is_reach_furiten = true;
+            }
+        }
+    }
+
+    fn update_waits(&mut self) {
+        let view = self.tehai.view();
+        self.waits = if view.len() % 3 == 1 {
+            get_waits(&tiles_from_pais(view), self.fuuros.len() as u8)
+        } else {
+            vec![]
+        };
+    }
+}
+
+/// Replaces the pon in `fuuros` that `consumed` of a kakan came from with the
+/// kakan.
+fn upgrade_pon(fuuros: &mut [Fuuro], pai: Pai, consumed: Consumed3) -> Result<()> {
+    let (previous_pon_idx, previous_pon_target, previous_pon_pai, previous_pon_consumed) = fuuros
+        .iter()
+        .enumerate()
+        .find_map(|(idx, f)| match *f {
+            Fuuro::Pon {
+                target: pon_target,
+                pai: pon_pai,
+                consumed: pon_consumed,
+            } if Consumed3::from([
+                pon_pai,
+                pon_consumed.as_array()[0],
+                pon_consumed.as_array()[1],
+            ]) == consumed =>
+            {
+                Some((idx, pon_target, pon_pai, pon_consumed))
+            }
+
+            _ => None,
+        })
+        .context(anyhow!("invalid state: previous Pon not found for Kakan"))?;
+
+    fuuros[previous_pon_idx] = Fuuro::Kakan {
+        pai,
+        previous_pon_target,
+        previous_pon_pai,
+        consumed: previous_pon_consumed,
+    };
+    Ok(())
 }
 
 #[serde_as]
@@ -200,3 +674,29 @@ pub enum Fuuro {
         consumed: Consumed4,
     },
 }
+
+impl Fuuro {
+    /// Every pai of the fuuro, the called one included.
+    pub fn pais(&self) -> Vec<Pai> {
+        match *self {
+            Fuuro::Chi { pai, consumed, .. } | Fuuro::Pon { pai, consumed, .. } => {
+                let [a, b] = consumed.as_array();
+                vec![pai, a, b]
+            }
+            Fuuro::Daiminkan { pai, consumed, .. } => {
+                let [a, b, c] = consumed.as_array();
+                vec![pai, a, b, c]
+            }
+            Fuuro::Kakan {
+                pai,
+                previous_pon_pai,
+                consumed,
+                ..
+            } => {
+                let [a, b] = consumed.as_array();
+                vec![pai, previous_pon_pai, a, b]
+            }
+            Fuuro::Ankan { consumed } => consumed.as_array().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::check::event_type;
+use crate::embed;
+use crate::report_json::ReportJson;
+use crate::review::{Acceptance, Category, DecisionStat};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json as json;
+
+#[derive(Debug, Default, Serialize)]
+pub struct CategoryStat {
+    pub mistakes: usize,
+    pub ev_loss: f64,
+    /// Share of all categorized mistakes.
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GameStat {
+    /// File name or database row of the report.
+    pub source: String,
+    pub log_id: Option<String>,
+    pub kyokus: usize,
+    pub reviewed: usize,
+    pub problems: usize,
+    pub agree_rate: f64,
+    pub ev_loss: f64,
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub games: usize,
+    pub kyokus: usize,
+    /// Keyed by the type of akochan's decision, like "dahai" or "reach".
+    pub by_decision_type: BTreeMap<String, DecisionStat>,
+    /// Disagreements by the category of the judgment.
+    pub by_category: BTreeMap<Category, CategoryStat>,
+    pub avg_ev_loss_per_game: f64,
+    pub mistakes_per_kyoku: f64,
+    /// Every game in chronological order if it can be told from the log ID,
+    /// otherwise in the order of the input.
+    pub trend: Vec<GameStat>,
+}
+
+/// Loads the JSON reports in `files`, as any subset of the report like
+/// `ReportJson`.
+pub fn load_files<T: DeserializeOwned>(files: &[PathBuf]) -> Result<Vec<(String, T)>> {
+    files
+        .iter()
+        .map(|file| {
+            let report = load(file).with_context(|| format!("failed to load report {:?}", file))?;
+            Ok((file.display().to_string(), report))
+        })
+        .collect()
+}
+
+/// Parses a JSON report, as saved in the history database.
+pub fn parse_report<T: DeserializeOwned>(source: String, report: &str) -> Result<(String, T)> {
+    let report = json::from_str(report)
+        .with_context(|| format!("failed to parse report from {}", source))?;
+    Ok((source, report))
+}
+
+/// Aggregates `reports` and writes the result to `w`.
+pub fn run<W>(reports: &[(String, ReportJson)], as_json: bool, w: &mut W) -> Result<()>
+where
+    W: Write,
+{
+    let stats = aggregate(reports);
+    if as_json {
+        json::to_writer_pretty(&mut *w, &stats).context("failed to write stats")?;
+        writeln!(w)?;
+    } else {
+        print_text(&stats, w)?;
+    }
+
+    Ok(())
+}
+
+/// Loads a JSON report, or the JSON embedded in an HTML report.
+fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let body = fs::read_to_string(path)?;
+    let report = match embed::extract(&body)? {
+        Some(json) => json::from_slice(&json)?,
+        None => json::from_str(&body)?,
+    };
+    Ok(report)
+}
+
+fn aggregate(reports: &[(String, ReportJson)]) -> Stats {
+    let mut by_decision_type: BTreeMap<String, DecisionStat> = BTreeMap::new();
+    let mut by_category: BTreeMap<Category, CategoryStat> = BTreeMap::new();
+    let mut trend = vec![];
+    let mut total_kyokus = 0;
+    let mut total_problems = 0;
+
+    for (source, report) in reports {
+        let mut agreed = 0;
+        let mut entries = 0;
+        let mut ev_loss = 0.;
+
+        for entry in report.kyokus.iter().flat_map(|k| &k.entries) {
+            let ty = entry.expected.first().map_or("unknown", event_type);
+            let stat = by_decision_type.entry(ty.to_owned()).or_default();
+            stat.reviewed += 1;
+            if matches!(entry.acceptance, Acceptance::Agree | Acceptance::Equivalent) {
+                stat.agreed += 1;
+                agreed += 1;
+            }
+            entries += 1;
+            ev_loss += entry.ev_loss.unwrap_or(0.);
+
+            if let Some(category) = entry.category {
+                let stat = by_category.entry(category).or_default();
+                stat.mistakes += 1;
+                stat.ev_loss += entry.ev_loss.unwrap_or(0.);
+            }
+        }
+
+        total_kyokus += report.kyokus.len();
+        total_problems += report.metadata.total_problems;
+        trend.push(GameStat {
+            source: source.clone(),
+            log_id: report.metadata.log_id.clone(),
+            kyokus: report.kyokus.len(),
+            reviewed: report.metadata.total_reviewed,
+            problems: report.metadata.total_problems,
+            agree_rate: ratio(agreed, entries),
+            ev_loss,
+            score: report.metadata.score,
+        });
+    }
+
+    for stat in by_decision_type.values_mut() {
+        stat.agree_rate = ratio(stat.agreed, stat.reviewed);
+    }
+    let categorized = by_category.values().map(|s| s.mistakes).sum();
+    for stat in by_category.values_mut() {
+        stat.share = ratio(stat.mistakes, categorized);
+    }
+
+    // tenhou log IDs start with the date and hour, e.g. "2019050417gm-..."
+    let dated = |g: &GameStat| {
+        g.log_id
+            .as_ref()
+            .filter(|id| id.len() >= 10 && id.as_bytes()[..10].iter().all(u8::is_ascii_digit))
+            .map(|id| id[..10].to_owned())
+    };
+    if trend.iter().all(|g| dated(g).is_some()) {
+        trend.sort_by_key(|g| dated(g));
+    }
+
+    Stats {
+        games: reports.len(),
+        kyokus: total_kyokus,
+        by_decision_type,
+        by_category,
+        avg_ev_loss_per_game: if trend.is_empty() {
+            0.
+        } else {
+            trend.iter().map(|g| g.ev_loss).sum::<f64>() / trend.len() as f64
+        },
+        mistakes_per_kyoku: ratio(total_problems, total_kyokus),
+        trend,
+    }
+}
+
+#[inline]
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+fn print_text<W>(stats: &Stats, w: &mut W) -> Result<()>
+where
+    W: Write,
+{
+    writeln!(w, "games: {}, kyokus: {}", stats.games, stats.kyokus)?;
+    writeln!(
+        w,
+        "average EV loss per game: {:.5}",
+        stats.avg_ev_loss_per_game
+    )?;
+    writeln!(w, "mistakes per kyoku: {:.3}", stats.mistakes_per_kyoku)?;
+
+    writeln!(w)?;
+    writeln!(
+        w,
+        "{:<12} {:>8} {:>8} {:>8}",
+        "decision", "reviewed", "agreed", "rate"
+    )?;
+    for (ty, stat) in &stats.by_decision_type {
+        writeln!(
+            w,
+            "{:<12} {:>8} {:>8} {:>7.2}%",
+            ty,
+            stat.reviewed,
+            stat.agreed,
+            stat.agree_rate * 100.,
+        )?;
+    }
+
+    if !stats.by_category.is_empty() {
+        writeln!(w)?;
+        writeln!(
+            w,
+            "{:<16} {:>8} {:>8} {:>10}",
+            "category", "mistakes", "share", "EV loss"
+        )?;
+        for (category, stat) in &stats.by_category {
+            writeln!(
+                w,
+                "{:<16} {:>8} {:>7.2}% {:>10.5}",
+                category.as_str(),
+                stat.mistakes,
+                stat.share * 100.,
+                stat.ev_loss,
+            )?;
+        }
+    }
+
+    writeln!(w)?;
+    writeln!(
+        w,
+        "{:<40} {:>8} {:>8} {:>8} {:>10} {:>8}",
+        "game", "reviewed", "problems", "agree", "EV loss", "score"
+    )?;
+    for game in &stats.trend {
+        let name = game.log_id.clone().unwrap_or_else(|| game.source.clone());
+        let score = game
+            .score
+            .map(|s| format!("{:.2}", s))
+            .unwrap_or_else(|| "N/A".to_owned());
+        writeln!(
+            w,
+            "{:<40} {:>8} {:>8} {:>7.2}% {:>10.5} {:>8}",
+            name,
+            game.reviewed,
+            game.problems,
+            game.agree_rate * 100.,
+            game.ev_loss,
+            score,
+        )?;
+    }
+
+    Ok(())
+}
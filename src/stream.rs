@@ -0,0 +1,153 @@
+use crate::log;
+use std::mem;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tungstenite::{Message, WebSocket};
+
+/// Precedes the address in the line logged once the stream is bound, which
+/// tells the port picked for `--stream 127.0.0.1:0`.
+pub const BOUND_LOG_PREFIX: &str = "streaming review events at ws://";
+
+/// How many messages may wait for a client before it is dropped as too slow.
+const CLIENT_QUEUE_SIZE: usize = 1024;
+
+/// How long sending to a client may block before it is dropped as stalled.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Shared {
+    // every message broadcasted but the transient ones, so that late clients
+    // can catch up. It grows with the report, not with every decision.
+    backlog: Vec<String>,
+    // the last transient message, sent to late clients after the backlog
+    last_transient: Option<String>,
+    clients: Vec<Client>,
+}
+
+/// A connected client, written to by a thread of its own so that a slow one
+/// holds up neither the others nor the review.
+struct Client {
+    queue: SyncSender<String>,
+    writer: JoinHandle<()>,
+}
+
+/// Broadcasts review events as JSON text messages to every connected
+/// WebSocket client.
+///
+/// Each message is a single line of JSON, so the concatenated messages form a
+/// valid NDJSON stream.
+pub struct WsStream {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl WsStream {
+    /// Listen on `addr` and accept clients in the background.
+    pub fn bind<A>(addr: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr).context("failed to bind stream address")?;
+        let local_addr = listener.local_addr()?;
+        log!("{}{}", BOUND_LOG_PREFIX, local_addr);
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let shared_clone = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(err) => {
+                        log!("WARNING: failed to accept stream client: {}", err);
+                        continue;
+                    }
+                };
+
+                // a slow handshake must not hold up the clients after it
+                let shared = Arc::clone(&shared_clone);
+                thread::spawn(move || {
+                    if let Err(err) = stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)) {
+                        log!("WARNING: failed to set timeout of stream client: {}", err);
+                        return;
+                    }
+                    let ws = match tungstenite::accept(stream) {
+                        Ok(ws) => ws,
+                        Err(err) => {
+                            log!("WARNING: failed to handshake with stream client: {}", err);
+                            return;
+                        }
+                    };
+
+                    // taken along with joining the clients, so that nothing
+                    // is missed or sent twice in between
+                    let mut shared = shared.lock().unwrap();
+                    let mut catch_up = shared.backlog.clone();
+                    catch_up.extend(shared.last_transient.clone());
+                    let (queue, rx) = mpsc::sync_channel(CLIENT_QUEUE_SIZE);
+                    let writer = thread::spawn(move || write_client(ws, catch_up, rx));
+                    shared.clients.push(Client { queue, writer });
+                });
+            }
+        });
+
+        Ok(Self { shared })
+    }
+
+    /// Send `msg` to every connected client, keeping it for the ones that
+    /// connect later.
+    pub fn broadcast(&self, msg: String) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.send(&msg);
+        shared.backlog.push(msg);
+    }
+
+    /// Send `msg` to every connected client. Only the last transient message,
+    /// like the progress, is kept for the clients that connect later.
+    pub fn broadcast_transient(&self, msg: String) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.send(&msg);
+        shared.last_transient = Some(msg);
+    }
+
+    /// Close all client connections gracefully, after they have been sent
+    /// what is queued for them.
+    pub fn close(&self) {
+        let clients = mem::take(&mut self.shared.lock().unwrap().clients);
+        for client in clients {
+            drop(client.queue);
+            let _ = client.writer.join();
+        }
+    }
+}
+
+impl Shared {
+    /// Queues `msg` for every client, dropping the ones that have gone or
+    /// fallen too far behind.
+    fn send(&mut self, msg: &str) {
+        self.clients
+            .retain(|client| match client.queue.try_send(msg.to_owned()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log!("WARNING: dropping a stream client that falls behind");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+/// Sends `catch_up` and then everything queued to `ws`, until the queue is
+/// closed or `ws` fails.
+fn write_client(mut ws: WebSocket<TcpStream>, catch_up: Vec<String>, queue: Receiver<String>) {
+    for msg in catch_up.into_iter().chain(queue) {
+        if ws.send(Message::Text(msg)).is_err() {
+            return;
+        }
+    }
+    let _ = ws.close(None);
+    let _ = ws.flush();
+}
@@ -1,7 +1,13 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
+use anyhow::{anyhow, Error, Result};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{self as json, Value};
+
+/// The tactics.json shipped with akochan-reviewer, which all presets are
+/// based on.
+const BASE_TACTICS: &str = include_str!("../tactics.json");
 
 #[derive(Serialize, Deserialize)]
 pub struct TacticsJson {
@@ -15,3 +21,51 @@ pub struct Tactics {
     #[serde(flatten)]
     pub other_fields: HashMap<String, Value>,
 }
+
+/// Built-in tactics, so that a tactics.json does not have to be edited by
+/// hand for common rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// The shipped tactics.json as is, with the pt of Tenhou Houou hanchan.
+    Default,
+    /// Heavier penalty for 4th place, which makes akochan fold earlier.
+    Defensive,
+    /// Bigger reward for 1st place, which makes akochan push harder.
+    Aggressive,
+    /// The pt of Tenhou Houou tonpuusen.
+    Tonpuusen,
+}
+
+impl Preset {
+    pub const NAMES: &'static [&'static str] = &["default", "defensive", "aggressive", "tonpuusen"];
+
+    pub fn jun_pt(self) -> [i32; 4] {
+        match self {
+            Self::Default => [90, 45, 0, -135],
+            Self::Defensive => [90, 45, 0, -180],
+            Self::Aggressive => [135, 45, -45, -135],
+            Self::Tonpuusen => [60, 30, 0, -90],
+        }
+    }
+
+    pub fn tactics_json(self) -> TacticsJson {
+        let mut tactics_json: TacticsJson =
+            json::from_str(BASE_TACTICS).expect("the shipped tactics.json is invalid");
+        tactics_json.tactics.jun_pt = self.jun_pt();
+        tactics_json
+    }
+}
+
+impl FromStr for Preset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "default" => Ok(Self::Default),
+            "defensive" => Ok(Self::Defensive),
+            "aggressive" => Ok(Self::Aggressive),
+            "tonpuusen" => Ok(Self::Tonpuusen),
+            _ => Err(anyhow!("unknown tactics preset: {:?}", s)),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::position::{format_pais, Hand};
+use crate::shanten::{get_shanten, tiles_from_pais, Tiles};
+use std::io::prelude::*;
+
+use anyhow::{bail, ensure, Result};
+use convlog::Pai;
+use serde::Serialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Shanten and ukeire of a hand from `shanten`, calculated without akochan.
+#[derive(Debug, Serialize)]
+pub struct Efficiency {
+    pub shanten: i8,
+    /// Pais that reduce the shanten, only for a 3n+1 hand.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ukeire: Option<Ukeire>,
+    /// Every kind of discard, the widest ukeire first, only for a 3n+2
+    /// hand.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discards: Vec<Discard>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct Discard {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pai: Pai,
+    #[serde(flatten)]
+    pub ukeire: Ukeire,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ukeire {
+    pub shanten: i8,
+    pub pais: Vec<Acceptance>,
+    /// Sum of `Acceptance::remaining`.
+    pub count: u32,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct Acceptance {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pai: Pai,
+    /// Copies not in the hand or its melds.
+    pub remaining: u8,
+}
+
+/// Calculates the shanten and ukeire of `hand`. Other players' discards are
+/// not known, so every copy outside the hand counts as remaining.
+pub fn analyze(hand: &Hand) -> Result<Efficiency> {
+    let fuuro_count = hand.melds.len() as u8;
+    let len = hand.pais.len() + 3 * hand.melds.len();
+    ensure!(
+        hand.melds.len() <= 4 && (len == 13 || len == 14),
+        "the hand must have 13 or 14 pais with the melds, got {}",
+        len,
+    );
+
+    let mut tiles = tiles_from_pais(&hand.pais);
+    let mut visible = tiles;
+    for meld in &hand.melds {
+        for &pai in &meld.pais {
+            if let Some(idx) = pai.as_index() {
+                visible[idx] += 1;
+            }
+        }
+    }
+    if let Some(idx) = visible.iter().position(|&c| c > 4) {
+        bail!(
+            "more than 4 copies of {} in the hand",
+            format_pais(&[Pai::from_index(idx).unwrap()]),
+        );
+    }
+
+    let shanten = get_shanten(&tiles, fuuro_count);
+    if len == 13 {
+        return Ok(Efficiency {
+            shanten,
+            ukeire: Some(ukeire(&mut tiles, &visible, fuuro_count)),
+            discards: vec![],
+        });
+    }
+
+    let mut discards = vec![];
+    for idx in 0..34 {
+        if tiles[idx] == 0 {
+            continue;
+        }
+        tiles[idx] -= 1;
+        // prefers the non-aka one when there are both
+        let pai = hand
+            .pais
+            .iter()
+            .copied()
+            .filter(|p| p.as_index() == Some(idx))
+            .min_by_key(|p| p.is_aka())
+            .unwrap();
+        discards.push(Discard {
+            pai,
+            ukeire: ukeire(&mut tiles, &visible, fuuro_count),
+        });
+        tiles[idx] += 1;
+    }
+    discards.sort_by_key(|d| (d.ukeire.shanten, u32::MAX - d.ukeire.count));
+
+    Ok(Efficiency {
+        shanten,
+        ukeire: None,
+        discards,
+    })
+}
+
+/// The ukeire of the 3n+1 hand in `tiles`, which is left as is.
+fn ukeire(tiles: &mut Tiles, visible: &Tiles, fuuro_count: u8) -> Ukeire {
+    let shanten = get_shanten(tiles, fuuro_count);
+    let pais: Vec<_> = (0..34)
+        .filter(|&i| {
+            if visible[i] >= 4 {
+                return false;
+            }
+            tiles[i] += 1;
+            let is_improved = get_shanten(tiles, fuuro_count) < shanten;
+            tiles[i] -= 1;
+            is_improved
+        })
+        .map(|i| Acceptance {
+            pai: Pai::from_index(i).unwrap(),
+            remaining: 4 - visible[i],
+        })
+        .collect();
+    let count = pais.iter().map(|a| a.remaining as u32).sum();
+
+    Ukeire {
+        shanten,
+        pais,
+        count,
+    }
+}
+
+/// Writes `efficiency` in plain text, such as
+///
+/// ```plain
+/// shanten 1
+///   discard 9s  shanten  1  ukeire 22  3m(4) 6m(4) 3p(4) 4p(3) 7p(4) 8s(3)
+/// ```
+pub fn write_text<W: Write>(efficiency: &Efficiency, out: &mut W) -> Result<()> {
+    writeln!(out, "shanten {}", efficiency.shanten)?;
+    if let Some(ukeire) = &efficiency.ukeire {
+        writeln!(
+            out,
+            "  ukeire {:>2}  {}",
+            ukeire.count,
+            format_acceptance(ukeire)
+        )?;
+    }
+    for discard in &efficiency.discards {
+        let ukeire = &discard.ukeire;
+        writeln!(
+            out,
+            "  discard {:<3} shanten {:>2}  ukeire {:>2}  {}",
+            format_pais(&[discard.pai]),
+            ukeire.shanten,
+            ukeire.count,
+            format_acceptance(ukeire),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn format_acceptance(ukeire: &Ukeire) -> String {
+    ukeire
+        .pais
+        .iter()
+        .map(|a| format!("{}({})", format_pais(&[a.pai]), a.remaining))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,238 @@
+use crate::assist::describe;
+use crate::engine::{Akochan, EngineArgs};
+use crate::position::kyoku_label;
+use crate::report_json::ReportJson;
+use crate::review::{fill_ev_gaps, DetailedAction, Stat};
+use std::fs;
+use std::io::prelude::*;
+use std::iter;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use convlog::mjai::Event;
+use serde::Serialize;
+use serde_json as json;
+
+pub struct WhatIfArgs<'a> {
+    pub akochan_exe: &'a Path,
+    pub akochan_dir: &'a Path,
+    /// Usually a heavier tactics config than the one of the original review.
+    pub tactics_config: &'a Path,
+    /// In tenhou.net/6 format, counts from 0.
+    pub kyoku: u8,
+    pub honba: u8,
+    pub junme: u8,
+    /// A move to evaluate besides the expected and the actual one, written
+    /// the same way as the output, like "discard 5p" or "riichi, discard 5p".
+    pub alternative: Option<&'a str>,
+    /// Passed to akochan as [`crate::engine::SEED_ENV`].
+    pub seed: Option<u64>,
+    pub json: bool,
+    pub verbose: bool,
+}
+
+/// One move of the decision, evaluated by the original review and the new
+/// run.
+#[derive(Debug, Serialize)]
+struct Outcome {
+    label: &'static str,
+    moves: Vec<Event>,
+    /// 1-based rank among akochan's candidates.
+    original_rank: Option<usize>,
+    original: Option<Stat>,
+    rank: Option<usize>,
+    review: Option<Stat>,
+}
+
+#[derive(Debug, Serialize)]
+struct WhatIf {
+    kyoku: u8,
+    honba: u8,
+    junme: u8,
+    outcomes: Vec<Outcome>,
+    details: Vec<DetailedAction>,
+}
+
+/// Re-runs one decision of `report` with another tactics config and writes
+/// a comparison of the best, the expected, the actual and optionally an
+/// alternative move to `out`.
+///
+/// If more than one decision is made in the junme, like a call on someone's
+/// dahai followed by the next tsumo, the first one is taken.
+pub fn run<W: Write>(args: &WhatIfArgs, report: &ReportJson, out: &mut W) -> Result<()> {
+    let kyoku = report
+        .kyokus
+        .iter()
+        .find(|k| k.kyoku == args.kyoku && k.honba == args.honba)
+        .with_context(|| {
+            format!(
+                "{}-{} is not in the report",
+                kyoku_label(args.kyoku),
+                args.honba
+            )
+        })?;
+    let entry = kyoku
+        .entries
+        .iter()
+        .find(|e| e.junme == args.junme)
+        .with_context(|| {
+            format!(
+                "no reviewed decision at junme {} of {}-{}",
+                args.junme,
+                kyoku_label(args.kyoku),
+                args.honba,
+            )
+        })?;
+    let event_index = entry
+        .event_index
+        .context("the report has no event_index, review the log again to get one")?;
+    let history = kyoku
+        .events
+        .get(..=event_index)
+        .context("invalid event_index in the report")?;
+
+    let start_game = Event::StartGame {
+        kyoku_first: 0,
+        aka_flag: true,
+        names: Default::default(),
+    };
+    let mut akochan = Akochan::spawn(EngineArgs {
+        akochan_exe: args.akochan_exe,
+        akochan_dir: args.akochan_dir,
+        tactics_config: args.tactics_config,
+        akochan_lib: None,
+        server: None,
+        transcript: None,
+        seed: args.seed,
+        target_actor: report.target_actor,
+        verbose: args.verbose,
+        max_restarts: 0,
+        timeout: None,
+    })?;
+    let line = akochan
+        .replay(iter::once(&start_game).chain(history))?
+        .context("the decision is not a review timing of akochan")?;
+    akochan.send(&Event::EndGame)?;
+    akochan.finish()?;
+    let mut details: Vec<DetailedAction> =
+        json::from_str(&line).context("failed to parse JSON output of akochan")?;
+    fill_ev_gaps(&mut details);
+
+    let best = details
+        .first()
+        .context("akochan gave no candidate")?
+        .moves
+        .clone();
+    let mut moves = vec![
+        ("best", best),
+        ("expected", entry.expected.clone()),
+        ("actual", entry.actual.clone()),
+    ];
+    if let Some(alt) = args.alternative {
+        let found = details
+            .iter()
+            .find(|d| describe(&d.moves).eq_ignore_ascii_case(alt.trim()));
+        match found {
+            Some(d) => moves.push(("alternative", d.moves.clone())),
+            None => {
+                let candidates: Vec<_> = details.iter().map(|d| describe(&d.moves)).collect();
+                bail!(
+                    "{:?} is not one of akochan's candidates: {}",
+                    alt,
+                    candidates.join("; "),
+                );
+            }
+        }
+    }
+
+    let outcomes: Vec<_> = moves
+        .into_iter()
+        .map(|(label, moves)| {
+            let (original_rank, original) = find(&entry.details, &moves);
+            let (rank, review) = find(&details, &moves);
+            Outcome {
+                label,
+                moves,
+                original_rank,
+                original,
+                rank,
+                review,
+            }
+        })
+        .collect();
+
+    if args.json {
+        let what_if = WhatIf {
+            kyoku: args.kyoku,
+            honba: args.honba,
+            junme: args.junme,
+            outcomes,
+            details,
+        };
+        json::to_writer(&mut *out, &what_if).context("failed to write JSON result")?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "{}-{} junme={}",
+        kyoku_label(args.kyoku),
+        args.honba,
+        args.junme,
+    )?;
+    writeln!(
+        out,
+        "  {:<11} {:<24} {:>9}    {:>9}  {:>4}    {:>4}  {:>7}",
+        "", "move", "EV before", "EV after", "rank", "rank", "deal-in",
+    )?;
+    for o in &outcomes {
+        writeln!(
+            out,
+            "  {:<11} {:<24} {:>9} -> {:>9}  {:>4} -> {:>4}  {:>7}",
+            o.label,
+            describe(&o.moves),
+            fmt_ev(&o.original),
+            fmt_ev(&o.review),
+            fmt_rank(o.original_rank),
+            fmt_rank(o.rank),
+            o.review
+                .as_ref()
+                .and_then(|s| s.total_houjuu_hai_prob_now)
+                .map(|v| format!("{:.2}%", v * 100.))
+                .unwrap_or_else(|| "N/A".to_owned()),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Loads a JSON report produced by `--json`.
+pub fn load_report(path: &Path) -> Result<ReportJson> {
+    let body =
+        fs::read_to_string(path).with_context(|| format!("failed to read report {:?}", path))?;
+    json::from_str(&body).with_context(|| format!("failed to parse report {:?}", path))
+}
+
+/// Returns the 1-based rank and the stat of `moves` among `details`.
+fn find(details: &[DetailedAction], moves: &[Event]) -> (Option<usize>, Option<Stat>) {
+    let key = describe(moves);
+    details
+        .iter()
+        .position(|d| describe(&d.moves) == key)
+        .map_or((None, None), |i| {
+            (Some(i + 1), Some(details[i].review.clone()))
+        })
+}
+
+fn fmt_ev(stat: &Option<Stat>) -> String {
+    stat.as_ref()
+        .and_then(|s| s.pt_exp_total)
+        .map(|v| format!("{:.3}", v))
+        .unwrap_or_else(|| "N/A".to_owned())
+}
+
+fn fmt_rank(rank: Option<usize>) -> String {
+    rank.map(|r| format!("#{}", r))
+        .unwrap_or_else(|| "-".to_owned())
+}
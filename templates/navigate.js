@@ -0,0 +1,110 @@
+(function () {
+  'use strict';
+
+  var entries = Array.prototype.slice.call(document.querySelectorAll('details.entry'));
+  var hideAgreed = document.getElementById('hide-agreed');
+  var current = -1;
+
+  function isVisible(entry) {
+    return entry.offsetParent !== null;
+  }
+
+  function select(i) {
+    if (current >= 0) {
+      entries[current].classList.remove('entry-current');
+    }
+    current = i;
+    var entry = entries[i];
+    entry.classList.add('entry-current');
+    entry.scrollIntoView({ block: 'center' });
+  }
+
+  // Selects the first entry after (`dir` = 1) or before (`dir` = -1) the
+  // current one that passes `test`.
+  function move(dir, test) {
+    for (var i = current + dir; i >= 0 && i < entries.length; i += dir) {
+      if (isVisible(entries[i]) && test(entries[i])) {
+        if (entries[i].classList.contains('entry-disagree')) {
+          entries[i].open = true;
+        }
+        select(i);
+        return;
+      }
+    }
+  }
+
+  function any() {
+    return true;
+  }
+
+  function isMistake(entry) {
+    return entry.classList.contains('entry-disagree');
+  }
+
+  function setHideAgreed(hide) {
+    hideAgreed.checked = hide;
+    document.body.classList.toggle('hide-agreed', hide);
+    if (current >= 0 && !isVisible(entries[current])) {
+      entries[current].classList.remove('entry-current');
+      current = -1;
+    }
+  }
+
+  if (hideAgreed) {
+    hideAgreed.addEventListener('change', function () {
+      setHideAgreed(hideAgreed.checked);
+    });
+  }
+
+  entries.forEach(function (entry, i) {
+    entry.addEventListener('toggle', function () {
+      if (entry.open && current !== i) {
+        if (current >= 0) {
+          entries[current].classList.remove('entry-current');
+        }
+        current = i;
+        entry.classList.add('entry-current');
+      }
+    });
+  });
+
+  document.addEventListener('keydown', function (e) {
+    if (e.ctrlKey || e.metaKey || e.altKey) {
+      return;
+    }
+    var tag = e.target.tagName;
+    if (tag === 'INPUT' || tag === 'TEXTAREA' || tag === 'SELECT') {
+      return;
+    }
+
+    switch (e.key) {
+      case 'j':
+        move(1, any);
+        break;
+      case 'k':
+        move(-1, any);
+        break;
+      case 'n':
+        move(1, isMistake);
+        break;
+      case 'p':
+        move(-1, isMistake);
+        break;
+      case 'o':
+        if (current < 0) {
+          return;
+        }
+        entries[current].open = !entries[current].open;
+        break;
+      case 'h':
+        if (!hideAgreed) {
+          return;
+        }
+        setHideAgreed(!hideAgreed.checked);
+        break;
+      default:
+        return;
+    }
+    e.preventDefault();
+  });
+})();
@@ -0,0 +1,214 @@
+(function () {
+  'use strict';
+
+  var HONORS = ['E', 'S', 'W', 'N', 'P', 'F', 'C'];
+
+  function paiOrder(pai) {
+    var h = HONORS.indexOf(pai);
+    if (h >= 0) {
+      return 30 + h;
+    }
+    var n = parseInt(pai[0], 10);
+    // aka dora goes right before the normal 5
+    return 'mps'.indexOf(pai[1]) * 10 + n - (pai.length > 2 ? 0.5 : 0);
+  }
+
+  function deaka(pai) {
+    return pai.length > 2 ? pai.slice(0, 2) : pai;
+  }
+
+  function removePai(list, pai) {
+    var i = list.indexOf(pai);
+    if (i < 0) {
+      i = list.indexOf('?');
+    }
+    if (i >= 0) {
+      list.splice(i, 1);
+    }
+  }
+
+  function tileHtml(pai, classes) {
+    var cls = 'tile small' + (pai.length > 2 ? ' aka' : '') + (classes ? ' ' + classes : '');
+    if (pai === '?' || pai === 'back') {
+      return '<svg class="' + cls + '"><use class="back" href="#tile"></use></svg>';
+    }
+    return '<svg class="' + cls + '"><use class="face" href="#pai-' + pai.toLowerCase() + '"></use></svg>';
+  }
+
+  // Replays `events` up to and including `events[upTo]`.
+  function replay(events, upTo) {
+    var s = {
+      hands: [[], [], [], []],
+      tsumos: [null, null, null, null],
+      rivers: [[], [], [], []],
+      melds: [[], [], [], []],
+      doras: [],
+      tilesLeft: 70,
+      reaching: [false, false, false, false],
+      lastDiscard: null,
+    };
+
+    function mergeTsumo(actor) {
+      if (s.tsumos[actor] !== null) {
+        s.hands[actor].push(s.tsumos[actor]);
+        s.tsumos[actor] = null;
+      }
+    }
+
+    for (var i = 0; i <= upTo && i < events.length; i++) {
+      var ev = events[i];
+      var a = ev.actor;
+      s.lastDiscard = null;
+
+      switch (ev.type) {
+        case 'start_kyoku':
+          s.hands = ev.tehais.map(function (h) { return h.slice(); });
+          s.doras = [ev.dora_marker];
+          break;
+
+        case 'tsumo':
+          s.tsumos[a] = ev.pai;
+          s.tilesLeft -= 1;
+          break;
+
+        case 'dahai':
+          mergeTsumo(a);
+          removePai(s.hands[a], ev.pai);
+          s.rivers[a].push({
+            pai: ev.pai,
+            tsumogiri: ev.tsumogiri,
+            riichi: s.reaching[a],
+            taken: false,
+          });
+          s.reaching[a] = false;
+          s.lastDiscard = a;
+          break;
+
+        case 'reach':
+          s.reaching[a] = true;
+          break;
+
+        case 'chi':
+        case 'pon':
+        case 'daiminkan':
+          ev.consumed.forEach(function (p) { removePai(s.hands[a], p); });
+          var river = s.rivers[ev.target];
+          if (river.length > 0) {
+            river[river.length - 1].taken = true;
+          }
+          // the called tile sits on the side of whom it is called from
+          var pais = ev.consumed.slice();
+          var rel = (ev.target - a + 4) % 4;
+          var at = rel === 3 ? 0 : rel === 2 ? 1 : pais.length;
+          pais.splice(at, 0, ev.pai);
+          s.melds[a].push({ pais: pais, called: at });
+          break;
+
+        case 'ankan':
+          mergeTsumo(a);
+          ev.consumed.forEach(function (p) { removePai(s.hands[a], p); });
+          s.melds[a].push({ pais: ev.consumed.slice(), called: -1, closed: true });
+          break;
+
+        case 'kakan':
+          mergeTsumo(a);
+          removePai(s.hands[a], ev.pai);
+          s.melds[a].forEach(function (m) {
+            if (m.pais.length === 3 && deaka(m.pais[0]) === deaka(ev.pai) && deaka(m.pais[1]) === deaka(ev.pai)) {
+              m.pais.splice(m.called + 1, 0, ev.pai);
+              m.added = m.called + 1;
+            }
+          });
+          break;
+
+        case 'dora':
+          s.doras.push(ev.dora_marker);
+          break;
+      }
+    }
+
+    return s;
+  }
+
+  function renderSeat(seat, s, actor, isTarget) {
+    var hand = s.hands[actor].slice().sort(function (x, y) { return paiOrder(x) - paiOrder(y); });
+    var html = '';
+    hand.forEach(function (p) {
+      html += tileHtml(isTarget ? p : 'back');
+    });
+    if (s.tsumos[actor] !== null) {
+      html += '<span class="board-tsumo">' + tileHtml(isTarget ? s.tsumos[actor] : 'back') + '</span>';
+    }
+    s.melds[actor].forEach(function (m) {
+      html += '<span class="board-meld">';
+      m.pais.forEach(function (p, i) {
+        if (m.closed && (i === 0 || i === 3)) {
+          html += tileHtml('back');
+        } else {
+          html += tileHtml(p, i === m.called || i === m.added ? 'called' : '');
+        }
+      });
+      html += '</span>';
+    });
+    seat.querySelector('.board-hand').innerHTML = html;
+
+    var river = s.rivers[actor];
+    html = '';
+    river.forEach(function (d, i) {
+      var classes = [];
+      if (d.riichi) {
+        classes.push('called');
+      }
+      if (d.tsumogiri) {
+        classes.push('tsumogiri');
+      }
+      if (d.taken) {
+        classes.push('taken');
+      }
+      if (s.lastDiscard === actor && i === river.length - 1) {
+        classes.push('last');
+      }
+      html += tileHtml(d.pai, classes.join(' '));
+    });
+    seat.querySelector('.board-river').innerHTML = html;
+  }
+
+  function setup(board) {
+    var events = JSON.parse(board.querySelector('.board-events').textContent);
+    var targetActor = parseInt(board.dataset.target, 10);
+    var slider = board.querySelector('input[type=range]');
+    var step = board.querySelector('.board-step');
+
+    board.update = function (upTo) {
+      slider.value = upTo;
+      var s = replay(events, upTo);
+      board.querySelectorAll('.board-seat').forEach(function (seat) {
+        var actor = parseInt(seat.dataset.actor, 10);
+        renderSeat(seat, s, actor, actor === targetActor);
+      });
+      board.querySelector('.board-dora').innerHTML = s.doras.map(function (p) { return tileHtml(p); }).join('');
+      board.querySelector('.board-tiles-left').textContent = s.tilesLeft;
+      step.textContent = upTo + ' / ' + (events.length - 1);
+    };
+
+    slider.addEventListener('input', function () {
+      board.update(parseInt(slider.value, 10));
+    });
+    board.update(0);
+  }
+
+  document.querySelectorAll('.board-replay').forEach(setup);
+
+  document.querySelectorAll('.show-on-board').forEach(function (link) {
+    link.addEventListener('click', function (e) {
+      var board = document.getElementById(link.dataset.board);
+      if (!board) {
+        return;
+      }
+      e.preventDefault();
+      board.open = true;
+      board.update(parseInt(link.dataset.index, 10));
+      board.scrollIntoView({ block: 'center' });
+    });
+  });
+})();
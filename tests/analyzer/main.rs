@@ -0,0 +1,52 @@
+//! Checks that analyzers of another crate are run along with the built-in
+//! ones once registered.
+
+use akochan_reviewer::analyzer::{self, Analyzer, Output};
+use akochan_reviewer::i18n::{Language, Terms};
+use akochan_reviewer::review::{Entry, KyokuReview};
+use serde_json::json;
+
+struct EntryCounter {
+    entries: usize,
+}
+
+impl Analyzer for EntryCounter {
+    fn name(&self) -> &str {
+        "entry_counter"
+    }
+
+    fn on_entry(&mut self, _kyoku: &KyokuReview, _entry: &Entry) -> Option<String> {
+        self.entries += 1;
+        None
+    }
+
+    fn finish(
+        &mut self,
+        kyokus: &mut [KyokuReview],
+        _target_actor: u8,
+    ) -> anyhow::Result<Option<Output>> {
+        Ok(Some(Output::Data(json!({
+            "kyokus": kyokus.len(),
+            "entries": self.entries,
+        }))))
+    }
+}
+
+#[test]
+fn test_register() {
+    let lang = Language::English;
+    let builtin = analyzer::builtin(true, lang, Terms::from(lang)).len();
+    assert_eq!(analyzer::all(true, lang, Terms::from(lang)).len(), builtin);
+
+    analyzer::register(|| Box::new(EntryCounter { entries: 0 }));
+    let mut analyzers = analyzer::all(true, lang, Terms::from(lang));
+    assert_eq!(analyzers.len(), builtin + 1);
+    assert_eq!(analyzers[builtin].name(), "entry_counter");
+
+    let mut kyokus = vec![KyokuReview::default(); 2];
+    let analyses = analyzer::run(&mut analyzers[builtin..], &mut kyokus, 0).unwrap();
+    assert_eq!(
+        analyses.data["entry_counter"],
+        json!({"kyokus": 2, "entries": 0}),
+    );
+}
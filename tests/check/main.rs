@@ -0,0 +1,346 @@
+//! Checks real logs, which must have no issues, and corrupted ones.
+
+#[path = "../../convlog/tests/testdata/mod.rs"]
+mod testdata;
+
+use akochan_reviewer::check::check;
+use akochan_reviewer::position::parse_pais;
+use convlog::mjai::Event;
+use convlog::{tenhou, tenhou_to_mjai, Pai};
+use std::convert::TryInto;
+use testdata::{TestCase, TESTDATA};
+
+/// Logs whose nakis were edited by hand, leaving deltas that do not match the
+/// hora, which is all that is wrong with them.
+const EDITED: &[&str] = &["confusing_nakis_1", "confusing_nakis_2"];
+
+/// Haipai of actor 2, who waits on 1p and 4p.
+const TENPAI: &str = "123456789m1234p";
+
+fn testdata(description: &str) -> Vec<Event> {
+    let TestCase { data, .. } = TESTDATA
+        .iter()
+        .find(|t| t.description == description)
+        .unwrap();
+    tenhou_to_mjai(&tenhou::Log::from_json_str(data).unwrap()).unwrap()
+}
+
+/// Messages of the issues `check` finds in `events`.
+fn messages(events: &[Event]) -> Vec<String> {
+    check(events, None)
+        .issues
+        .into_iter()
+        .map(|i| i.message)
+        .collect()
+}
+
+fn has_message(events: &[Event], part: &str) -> bool {
+    messages(events).iter().any(|m| m.contains(part))
+}
+
+/// A kyoku of `draws` draws from a wall without aka, each discarded right
+/// away. Actor 2 has `TENPAI` and declares reach on their draw at
+/// `reach_at` if any, counting from 0.
+fn kyoku(draws: usize, reach_at: Option<usize>) -> Vec<Event> {
+    let tenpai = parse_pais(TENPAI).unwrap();
+    let mut wall = vec![];
+    for idx in 0..34 {
+        let pai = Pai::from_index(idx).unwrap();
+        let taken = tenpai.iter().filter(|&&p| p == pai).count();
+        wall.extend((taken..4).map(|_| pai));
+    }
+    let mut wall = wall.into_iter();
+    let mut haipai = || [(); 13].map(|_| wall.next().unwrap());
+    let tehais = [haipai(), haipai(), tenpai[..].try_into().unwrap(), haipai()];
+
+    let mut events = vec![
+        Event::StartGame {
+            kyoku_first: 4,
+            aka_flag: false,
+            names: Default::default(),
+        },
+        Event::StartKyoku {
+            bakaze: Pai::East,
+            dora_marker: wall.next().unwrap(),
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [25000; 4],
+            tehais,
+        },
+    ];
+    for (i, pai) in wall.take(draws).enumerate() {
+        let actor = (i % 4) as u8;
+        events.push(Event::Tsumo { actor, pai });
+        let is_reach = reach_at == Some(i);
+        if is_reach {
+            events.push(Event::Reach { actor });
+        }
+        events.push(Event::Dahai {
+            actor,
+            pai,
+            tsumogiri: true,
+        });
+        if is_reach {
+            events.push(Event::ReachAccepted { actor });
+        }
+    }
+    events
+}
+
+/// The start of a kyoku where the oya, actor 0, has the first of `tehais`.
+fn start(tehais: [&str; 4]) -> Vec<Event> {
+    vec![
+        Event::StartGame {
+            kyoku_first: 4,
+            aka_flag: true,
+            names: Default::default(),
+        },
+        Event::StartKyoku {
+            bakaze: Pai::East,
+            dora_marker: Pai::Pin8,
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [25000; 4],
+            tehais: tehais.map(|t| parse_pais(t).unwrap().try_into().unwrap()),
+        },
+    ]
+}
+
+/// A draw of `pai` by `actor`, discarded right away.
+fn turn(actor: u8, pai: &str) -> [Event; 2] {
+    let pai = parse_pais(pai).unwrap()[0];
+    [
+        Event::Tsumo { actor, pai },
+        Event::Dahai {
+            actor,
+            pai,
+            tsumogiri: true,
+        },
+    ]
+}
+
+/// Whether actor 1 discarding `discard` after a chi of `called` with
+/// `consumed`, discarded by actor 0, is kuikae.
+fn is_kuikae(called: &str, consumed: &str, discard: &str) -> bool {
+    let called_pai = parse_pais(called).unwrap()[0];
+    let consumed_pais = parse_pais(consumed).unwrap();
+    let discard_pai = parse_pais(discard).unwrap()[0];
+    let mut events = start([
+        "1111222233334z",
+        &format!("{}{}1111222233p", consumed, discard),
+        "1111444455556s",
+        "5555666677778p",
+    ]);
+    events.extend(turn(0, called));
+    events.push(Event::Chi {
+        actor: 1,
+        target: 0,
+        pai: called_pai,
+        consumed: [consumed_pais[0], consumed_pais[1]].into(),
+    });
+    events.push(Event::Dahai {
+        actor: 1,
+        pai: discard_pai,
+        tsumogiri: false,
+    });
+
+    let messages = messages(&events);
+    assert!(
+        messages
+            .iter()
+            .all(|m| m.contains("kuikae") || m.contains("ends in the middle")),
+        "{:?}",
+        messages,
+    );
+    messages.iter().any(|m| m.contains("kuikae"))
+}
+
+/// Whether the ankan of `pai` by actor 0 after reach with `tehai`, which is
+/// tenpai, is reported for changing the waits.
+fn is_ankan_rejected(tehai: &str, pai: &str) -> bool {
+    let mut events = start([tehai, "1111222233334p", "1111444455556s", "1111222233334z"]);
+    events.push(Event::Tsumo {
+        actor: 0,
+        pai: Pai::Man9,
+    });
+    events.push(Event::Reach { actor: 0 });
+    events.push(Event::Dahai {
+        actor: 0,
+        pai: Pai::Man9,
+        tsumogiri: true,
+    });
+    events.push(Event::ReachAccepted { actor: 0 });
+    events.extend(turn(1, "9p"));
+    events.extend(turn(2, "9s"));
+    events.extend(turn(3, "6z"));
+    let pai = parse_pais(pai).unwrap()[0];
+    events.push(Event::Tsumo { actor: 0, pai });
+    events.push(Event::Ankan {
+        actor: 0,
+        consumed: [pai; 4].into(),
+    });
+
+    let messages = messages(&events);
+    assert!(
+        messages
+            .iter()
+            .all(|m| m.contains("ankans") || m.contains("ends in the middle")),
+        "{:?}",
+        messages,
+    );
+    messages.iter().any(|m| m.contains("changes the waits"))
+}
+
+#[test]
+fn test_testdata_has_no_issues() {
+    for TestCase { description, .. } in TESTDATA {
+        let events = testdata(description);
+        let messages = messages(&events);
+        if EDITED.contains(description) {
+            assert!(
+                messages.iter().all(|m| m.contains("deltas")),
+                "{}: {:?}",
+                description,
+                messages,
+            );
+        } else {
+            assert_eq!(messages, Vec::<String>::new(), "{}", description);
+        }
+    }
+}
+
+#[test]
+fn test_fifth_pai() {
+    let mut events = kyoku(8, None);
+    assert_eq!(messages(&events), ["the log ends in the middle of a kyoku"]);
+
+    // every draw becomes the chun, of which there are only 4
+    for event in &mut events {
+        match event {
+            Event::Tsumo { pai, .. } | Event::Dahai { pai, .. } => *pai = Pai::Chun,
+            _ => (),
+        }
+    }
+    assert!(has_message(&events, "a fifth C comes out of the wall"));
+}
+
+#[test]
+fn test_out_of_turn() {
+    let mut events = kyoku(8, None);
+    // actor 1 draws right after the draw of actor 0
+    events.remove(3);
+    assert!(has_message(&events, "tsumo cannot come right after"));
+
+    let mut events = kyoku(8, None);
+    // actor 1 draws before the oya does
+    events.drain(2..4);
+    assert!(has_message(&events, "tsumo cannot come right after"));
+}
+
+#[test]
+fn test_reach_needs_draws_left() {
+    // the draw of actor 2 that leaves 7 draws in the wall
+    assert_eq!(
+        messages(&kyoku(70, Some(62))),
+        ["the log ends in the middle of a kyoku"],
+    );
+    // and the one that leaves 3
+    assert!(has_message(
+        &kyoku(70, Some(66)),
+        "actor 2 reaches with only 3 draws left in the wall",
+    ));
+}
+
+#[test]
+fn test_scores_follow_the_last_kyoku() {
+    let events = testdata("ranked_game");
+    let second_start = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| matches!(e, Event::StartKyoku { .. }))
+        .nth(1)
+        .unwrap()
+        .0;
+
+    let mut corrupted = events.clone();
+    if let Event::StartKyoku { scores, .. } = &mut corrupted[second_start] {
+        scores[0] += 100;
+    }
+    assert!(has_message(&corrupted, "but the last kyoku ended with"));
+
+    let mut corrupted = events;
+    if let Event::StartKyoku { kyotaku, .. } = &mut corrupted[second_start] {
+        *kyotaku += 1;
+    }
+    assert!(has_message(&corrupted, "but the last kyoku left"));
+}
+
+#[test]
+fn test_deltas_add_up_to_the_pot() {
+    let mut events = testdata("double_ron");
+    let deltas = events
+        .iter_mut()
+        .find_map(|e| match e {
+            Event::Hora {
+                target,
+                deltas: Some(deltas),
+                ..
+            } => Some((*target, deltas)),
+            _ => None,
+        })
+        .unwrap();
+    deltas.1[deltas.0 as usize] += 100;
+    assert!(has_message(
+        &events,
+        "is expected from the kyotaku and reaches"
+    ));
+}
+
+#[test]
+fn test_chi_kuikae() {
+    // (called, consumed, discard, is kuikae)
+    let table = [
+        // the called pai itself, in any shape
+        ("4m", "23m", "4m", true),
+        ("3m", "24m", "3m", true),
+        ("0m", "34m", "5m", true),
+        // the other end of a ryanmen, called from either side
+        ("4m", "23m", "1m", true),
+        ("1m", "23m", "4m", true),
+        ("6m", "78m", "9m", true),
+        ("9m", "78m", "6m", true),
+        ("2m", "34m", "5m", true),
+        // nothing beyond 1 and 9
+        ("3m", "12m", "4m", false),
+        ("7m", "89m", "6m", false),
+        ("3m", "12m", "9m", false),
+        // a kanchan has no other end
+        ("3m", "24m", "1m", false),
+        ("3m", "24m", "5m", false),
+        // only the suji
+        ("4m", "23m", "7m", false),
+        ("1m", "23m", "7m", false),
+    ];
+    for &(called, consumed, discard, expected) in &table {
+        assert_eq!(
+            is_kuikae(called, consumed, discard),
+            expected,
+            "discarding {} after a chi of {} with {}",
+            discard,
+            called,
+            consumed,
+        );
+    }
+}
+
+#[test]
+fn test_ankan_after_reach() {
+    // waiting on 2m, 4m and 5m, then only on 4m
+    assert!(is_ankan_rejected("3334m567p789s555z", "3m"));
+    // waiting on 1s and 4s either way
+    assert!(!is_ankan_rejected("111m456p789s23s55z", "1m"));
+}
@@ -0,0 +1,102 @@
+//! Checks which kyokus defense mode leaves out of akochan.
+
+#[path = "../../src/board.rs"]
+#[allow(dead_code)]
+mod board;
+#[path = "../../src/defense.rs"]
+#[allow(dead_code)]
+mod defense;
+#[path = "../../src/scoring.rs"]
+#[allow(dead_code)]
+mod scoring;
+#[path = "../../src/shanten.rs"]
+#[allow(dead_code)]
+mod shanten;
+#[path = "../../src/state.rs"]
+#[allow(dead_code)]
+mod state;
+#[path = "../../src/tehai.rs"]
+#[allow(dead_code)]
+mod tehai;
+
+use convlog::mjai::Event;
+use serde_json as json;
+
+const START_KYOKU: &str = r#"{
+    "type": "start_kyoku", "bakaze": "E", "dora_marker": "C", "kyoku": 1,
+    "honba": 0, "kyotaku": 0, "oya": 0, "scores": [25000, 25000, 25000, 25000],
+    "tehais": [
+        ["1m", "2m", "3m", "2m", "3m", "4p", "5p", "6p", "7s", "8s", "9s", "E", "E"],
+        ["?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?"],
+        ["?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?"],
+        ["?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", "?"]
+    ]
+}"#;
+
+/// A kyoku of two go-rounds where actor 1 reaches in the first one if
+/// `reach` is set, and nobody does otherwise.
+fn kyoku(reach: bool) -> Vec<String> {
+    let mut events = vec![
+        START_KYOKU.to_owned(),
+        r#"{"type": "tsumo", "actor": 0, "pai": "P"}"#.to_owned(),
+        r#"{"type": "dahai", "actor": 0, "pai": "P", "tsumogiri": true}"#.to_owned(),
+        r#"{"type": "tsumo", "actor": 1, "pai": "?"}"#.to_owned(),
+    ];
+    if reach {
+        events.push(r#"{"type": "reach", "actor": 1}"#.to_owned());
+    }
+    events.push(r#"{"type": "dahai", "actor": 1, "pai": "F", "tsumogiri": true}"#.to_owned());
+    if reach {
+        events.push(r#"{"type": "reach_accepted", "actor": 1}"#.to_owned());
+    }
+    events.extend([
+        r#"{"type": "tsumo", "actor": 2, "pai": "?"}"#.to_owned(),
+        r#"{"type": "dahai", "actor": 2, "pai": "F", "tsumogiri": true}"#.to_owned(),
+        r#"{"type": "tsumo", "actor": 3, "pai": "?"}"#.to_owned(),
+        r#"{"type": "dahai", "actor": 3, "pai": "F", "tsumogiri": true}"#.to_owned(),
+        r#"{"type": "tsumo", "actor": 0, "pai": "P"}"#.to_owned(),
+        r#"{"type": "dahai", "actor": 0, "pai": "P", "tsumogiri": true}"#.to_owned(),
+        r#"{"type": "ryukyoku"}"#.to_owned(),
+        r#"{"type": "end_kyoku"}"#.to_owned(),
+    ]);
+    events
+}
+
+fn parse(events: &[String]) -> Vec<Event> {
+    events.iter().map(|e| json::from_str(e).unwrap()).collect()
+}
+
+#[test]
+fn test_plan_skips_kyokus_without_threat() {
+    let calm = kyoku(false);
+    let threatened = kyoku(true);
+    let mut events = vec![r#"{
+        "type": "start_game", "kyoku_first": 0, "aka_flag": true,
+        "names": ["A", "B", "C", "D"]
+    }"#
+    .to_owned()];
+    events.extend(calm.iter().cloned());
+    events.extend(threatened.iter().cloned());
+    events.push(r#"{"type": "end_game"}"#.to_owned());
+    let events = parse(&events);
+
+    let skipped = defense::plan(&events, 0).unwrap();
+    assert_eq!(skipped.len(), events.len());
+
+    // every event of the calm kyoku, decisions of actor 0 included, is left
+    // out, while the threatened kyoku is sent whole for its context
+    let calm_range = 1..1 + calm.len();
+    let threatened_range = calm_range.end..calm_range.end + threatened.len();
+    assert!(skipped[calm_range].iter().all(|&s| s));
+    assert!(skipped[threatened_range].iter().all(|&s| !s));
+    assert!(!skipped[0]);
+    assert!(!skipped[events.len() - 1]);
+}
+
+#[test]
+fn test_plan_keeps_kyokus_threatened_for_other_targets() {
+    // the reach of actor 1 is no threat to actor 1 itself
+    let events = parse(&kyoku(true));
+    let skipped = defense::plan(&events, 1).unwrap();
+    assert!(skipped.iter().all(|&s| s));
+}
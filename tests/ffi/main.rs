@@ -0,0 +1,145 @@
+//! Builds ffi/akochan_ffi.cpp around a stub of akochan's main and drives it
+//! through `InProcess`. Needs a C++ compiler as `c++`, or `$CXX`.
+#![cfg(feature = "ffi")]
+
+#[path = "../../src/ffi.rs"]
+mod ffi;
+
+use ffi::InProcess;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+use std::time::Duration;
+
+/// Builds the library once for all the tests.
+fn build_stub_library() -> PathBuf {
+    static BUILD: Once = Once::new();
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let lib_path = out_dir.join(format!(
+        "{}akochan_ffi_stub{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX,
+    ));
+
+    BUILD.call_once(|| {
+        let compiler = env::var("CXX").unwrap_or_else(|_| "c++".to_owned());
+        let status = Command::new(compiler)
+            .args([
+                "-std=c++11",
+                "-shared",
+                "-fPIC",
+                "-pthread",
+                "-Dmain=akochan_main",
+                "-include",
+            ])
+            .arg(root.join("ffi/akochan_ffi_io.h"))
+            .arg("-o")
+            .arg(&lib_path)
+            .arg(root.join("ffi/akochan_ffi.cpp"))
+            .arg(root.join("tests/ffi/stub_akochan.cpp"))
+            .status()
+            .expect("failed to run the C++ compiler");
+        assert!(status.success(), "failed to build the stub library");
+    });
+
+    lib_path
+}
+
+/// A directory with a tactics config for the stub to find.
+fn akochan_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("akochan_ffi_dir");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("tactics.json"), "{}").unwrap();
+    dir
+}
+
+fn tsumo(actor: u8, pai: &str) -> String {
+    format!(r#"{{"type":"tsumo","actor":{},"pai":"{}"}}"#, actor, pai)
+}
+
+fn response(pai: &str) -> String {
+    format!(
+        r#"[{{"moves":[{{"type":"dahai","pai":"{}"}}],"review":{{}}}}]"#,
+        pai
+    )
+}
+
+#[test]
+fn test_in_process_stub() {
+    let lib_path = build_stub_library();
+    let akochan_dir = akochan_dir();
+    let tactics_config = Path::new("tactics.json");
+    let cwd = env::current_dir().unwrap();
+
+    let mut akochan = InProcess::load(&lib_path, &akochan_dir, tactics_config, 2).unwrap();
+    // found the tactics config without moving the process
+    assert_eq!(env::current_dir().unwrap(), cwd);
+
+    akochan.feed(&tsumo(1, "?"), None).unwrap();
+    akochan
+        .feed(
+            r#"{"type":"dahai","actor":1,"pai":"E","tsumogiri":true}"#,
+            None,
+        )
+        .unwrap();
+    assert_eq!(akochan.pop_response(), None);
+
+    akochan.feed(&tsumo(2, "5mr"), None).unwrap();
+    akochan.feed(&tsumo(2, "N"), None).unwrap();
+    assert_eq!(akochan.pop_response(), Some(response("5mr")));
+    assert_eq!(akochan.pop_response(), Some(response("N")));
+    assert_eq!(akochan.pop_response(), None);
+
+    akochan.close();
+    assert!(akochan.feed(&tsumo(2, "N"), None).is_err());
+
+    // a missing akochan_dir fails
+    let missing = akochan_dir.join("missing");
+    assert!(InProcess::load(&lib_path, &missing, tactics_config, 2).is_err());
+}
+
+#[test]
+fn test_in_process_stub_instances() {
+    let lib_path = build_stub_library();
+    let akochan_dir = akochan_dir();
+    let tactics_config = Path::new("tactics.json");
+
+    let mut instances: Vec<_> = (0..4)
+        .map(|actor| InProcess::load(&lib_path, &akochan_dir, tactics_config, actor).unwrap())
+        .collect();
+    for (actor, akochan) in instances.iter_mut().enumerate() {
+        for pai in ["1m", "2p", "3s", "E"] {
+            akochan.feed(&tsumo(actor as u8, pai), None).unwrap();
+        }
+    }
+    // each answers its own events only
+    for akochan in &mut instances {
+        for pai in ["1m", "2p", "3s", "E"] {
+            assert_eq!(akochan.pop_response(), Some(response(pai)));
+        }
+        assert_eq!(akochan.pop_response(), None);
+    }
+
+    // the others keep working when one is freed
+    instances.remove(0);
+    instances[0].feed(&tsumo(1, "S"), None).unwrap();
+    assert_eq!(instances[0].pop_response(), Some(response("S")));
+}
+
+#[test]
+fn test_in_process_stub_timeout() {
+    let lib_path = build_stub_library();
+    let akochan_dir = akochan_dir();
+    let tactics_config = Path::new("tactics.json");
+
+    let mut akochan = InProcess::load(&lib_path, &akochan_dir, tactics_config, 0).unwrap();
+    let timeout = Some(Duration::from_millis(100));
+    let err = akochan.feed(&tsumo(0, "?"), timeout).unwrap_err();
+    assert!(err.to_string().contains("did not respond"), "{}", err);
+    // the busy instance can still be dropped
+    drop(akochan);
+}
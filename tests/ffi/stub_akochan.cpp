@@ -0,0 +1,35 @@
+// Stands in for akochan's main in pipe_detailed mode: answers the tsumo of the
+// target actor with a line naming the pai, and reads nothing else of the
+// events. Like akochan, it fails unless the tactics config can be opened
+// relative to the working directory.
+
+#include <chrono>
+#include <fstream>
+#include <iostream>
+#include <string>
+#include <thread>
+
+int main(int argc, char *argv[]) {
+    if (argc != 4 || std::string(argv[1]) != "pipe_detailed" ||
+        !std::ifstream(argv[2])) {
+        return 1;
+    }
+    const std::string tsumo = std::string("\"type\":\"tsumo\",\"actor\":") + argv[3];
+
+    std::string line;
+    while (std::getline(std::cin, line)) {
+        if (line.find(tsumo) == std::string::npos) {
+            continue;
+        }
+        const std::string key = "\"pai\":\"";
+        size_t start = line.find(key) + key.size();
+        std::string pai = line.substr(start, line.find('"', start) - start);
+        if (pai == "?") {
+            // too slow, for the timeout
+            std::this_thread::sleep_for(std::chrono::seconds(1));
+        }
+        std::cout << "[{\"moves\":[{\"type\":\"dahai\",\"pai\":\"" << pai
+                  << "\"}],\"review\":{}}]" << std::endl;
+    }
+    return 0;
+}
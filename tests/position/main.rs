@@ -0,0 +1,94 @@
+//! Parses hands in the notation of `position`.
+
+use akochan_reviewer::position::{parse_hand, parse_pais, MeldKind};
+use convlog::Pai;
+
+#[test]
+fn test_closed_pais() {
+    let hand = parse_hand("40m123p 77z").unwrap();
+    assert_eq!(hand.pais, parse_pais("4m 0m 1p 2p 3p 7z 7z").unwrap());
+    assert!(hand.melds.is_empty());
+    assert_eq!(hand.seat_wind, None);
+    assert!(hand.dora_markers.is_empty());
+}
+
+#[test]
+fn test_melds() {
+    let hand =
+        parse_hand("11z [312m chi] [777z pon toimen] [4444p ankan] [9999s kan shimocha]").unwrap();
+    assert_eq!(hand.pais, parse_pais("11z").unwrap());
+
+    let melds: Vec<_> = hand
+        .melds
+        .iter()
+        .map(|m| (m.kind, m.pais.clone(), m.from))
+        .collect();
+    assert_eq!(
+        melds,
+        [
+            (MeldKind::Chi, parse_pais("312m").unwrap(), 3),
+            (MeldKind::Pon, parse_pais("777z").unwrap(), 2),
+            (MeldKind::Ankan, parse_pais("4444p").unwrap(), 3),
+            (MeldKind::Daiminkan, parse_pais("9999s").unwrap(), 1),
+        ],
+    );
+
+    // written right after the closed pais, and with the added pai first
+    let hand = parse_hand("123m[5055p kakan kamicha]").unwrap();
+    assert_eq!(hand.pais, parse_pais("123m").unwrap());
+    assert_eq!(hand.melds[0].kind, MeldKind::Kakan);
+    assert_eq!(hand.melds[0].pais, parse_pais("5055p").unwrap());
+    assert_eq!(hand.melds[0].from, 3);
+}
+
+#[test]
+fn test_tsumo_goes_last() {
+    let hand = parse_hand("+6p 345m678p [777z pon] 11z").unwrap();
+    assert_eq!(hand.pais, parse_pais("345m678p11z6p").unwrap());
+
+    let hand = parse_hand("345m +0s").unwrap();
+    assert_eq!(hand.pais.last(), Some(&Pai::AkaSou5));
+}
+
+#[test]
+fn test_seat_and_dora() {
+    let hand = parse_hand("123m seat=S dora=1z0m").unwrap();
+    assert_eq!(hand.seat_wind, Some(Pai::South));
+    assert_eq!(hand.dora_markers, [Pai::East, Pai::AkaMan5]);
+
+    // in the short notation too, and dora= adds up
+    let hand = parse_hand("dora=9s seat=4z 123m dora=7z").unwrap();
+    assert_eq!(hand.seat_wind, Some(Pai::North));
+    assert_eq!(hand.dora_markers, [Pai::Sou9, Pai::Chun]);
+    assert_eq!(hand.pais, parse_pais("123m").unwrap());
+}
+
+#[test]
+fn test_invalid_hands() {
+    for s in [
+        "123x",
+        "123",
+        "8z",
+        "123m [777z pon",
+        "123m [77z pon]",
+        "123m [7777z pon]",
+        "123m [777z]",
+        "123m [777z peng]",
+        "123m [777z pon left]",
+        "123m [777z pon toimen now]",
+        "123m [135m chi]",
+        "123m [789p1s chi]",
+        "123m [123z chi]",
+        "123m [312m chi toimen]",
+        "123m [776z pon]",
+        "123m [777z kan]",
+        "123m +",
+        "123m +12m",
+        "123m seat=P",
+        "123m seat=5z",
+        "123m seat=11z",
+        "123m dora=1x",
+    ] {
+        assert!(parse_hand(s).is_err(), "{:?} should be rejected", s);
+    }
+}
@@ -0,0 +1,47 @@
+//! Checks what the review events carry, as sent by `--stream` and written by
+//! `--out-format ndjson`.
+
+use akochan_reviewer::review::ReviewEvent;
+use serde_json::{self as json, json};
+
+#[test]
+fn test_done_event() {
+    let done = ReviewEvent::Done {
+        total_reviewed: 40,
+        total_tolerated: 3,
+        total_equivalent: 5,
+        total_problems: 7,
+        score: 0.5,
+    };
+    assert_eq!(
+        json::to_value(&done).unwrap(),
+        json!({
+            "type": "done",
+            "total_reviewed": 40,
+            "total_tolerated": 3,
+            "total_equivalent": 5,
+            "total_problems": 7,
+            "score": 0.5,
+        }),
+    );
+}
+
+#[test]
+fn test_progress_event() {
+    let progress = ReviewEvent::Progress {
+        kyoku: 4,
+        honba: 1,
+        junme: 9,
+        progress: 50.,
+    };
+    assert_eq!(
+        json::to_value(&progress).unwrap(),
+        json!({
+            "type": "progress",
+            "kyoku": 4,
+            "honba": 1,
+            "junme": 9,
+            "progress": 50.0,
+        }),
+    );
+}
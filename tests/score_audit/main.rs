@@ -0,0 +1,77 @@
+//! Recalculates the deltas of real logs, which must match the recorded ones.
+
+#[path = "../../convlog/tests/testdata/mod.rs"]
+mod testdata;
+
+use akochan_reviewer::score_audit::ScoreAuditor;
+use convlog::mjai::Event;
+use convlog::{tenhou, tenhou_to_mjai};
+use testdata::{TestCase, TESTDATA};
+
+fn testdata(description: &str) -> Vec<Event> {
+    let TestCase { data, .. } = TESTDATA
+        .iter()
+        .find(|t| t.description == description)
+        .unwrap();
+    tenhou_to_mjai(&tenhou::Log::from_json_str(data).unwrap()).unwrap()
+}
+
+fn audit(description: &str) {
+    let events = testdata(description);
+    let mut auditor = ScoreAuditor::new(true);
+    for (i, event) in events.iter().enumerate() {
+        let mismatch = auditor.update(event).unwrap();
+        assert!(
+            mismatch.is_none(),
+            "{}: {:?} at event {}: {:?}",
+            description,
+            mismatch,
+            i,
+            event,
+        );
+    }
+}
+
+#[test]
+fn test_double_ron() {
+    audit("double_ron");
+}
+
+#[test]
+fn test_four_reach() {
+    audit("four_reach");
+}
+
+#[test]
+fn test_ryukyoku() {
+    audit("ryukyoku");
+}
+
+#[test]
+fn test_ranked_game() {
+    audit("ranked_game");
+}
+
+#[test]
+fn test_corrupted_deltas() {
+    let mut events = testdata("double_ron");
+    for event in &mut events {
+        if let Event::Hora {
+            actor,
+            target,
+            deltas: Some(deltas),
+            ..
+        } = event
+        {
+            deltas[*actor as usize] += 1000;
+            deltas[*target as usize] -= 1000;
+        }
+    }
+
+    let mut auditor = ScoreAuditor::new(true);
+    let mismatches = events
+        .iter()
+        .filter(|e| auditor.update(e).unwrap().is_some())
+        .count();
+    assert_eq!(mismatches, 2);
+}
@@ -0,0 +1,114 @@
+//! Scores hands and hora deltas against the usual tables.
+
+use akochan_reviewer::position::parse_pais;
+use akochan_reviewer::scoring::{
+    calculate, dora_from_marker, hora_deltas, Agari, HoraContext, Yaku, YakuHan,
+};
+use convlog::Pai;
+
+/// A closed hand of a ko in the south seat of an east round, with `tehai`
+/// including `win_pai`.
+fn agari(tehai: &str, win_pai: &str, is_tsumo: bool, dora_markers: &str, riichi: bool) -> Agari {
+    let situational = if riichi {
+        vec![YakuHan {
+            yaku: Yaku::Riichi,
+            han: 1,
+        }]
+    } else {
+        vec![]
+    };
+    let ctx = HoraContext {
+        win_pai: parse_pais(win_pai).unwrap()[0],
+        is_tsumo,
+        is_oya: false,
+        bakaze: Pai::East,
+        jikaze: Pai::South,
+        dora_markers: parse_pais(dora_markers).unwrap(),
+        ura_markers: vec![],
+        situational,
+        kuitan: true,
+    };
+    calculate(&parse_pais(tehai).unwrap(), &[], &ctx, &[]).unwrap()
+}
+
+fn has_yaku(agari: &Agari, yaku: Yaku) -> bool {
+    agari.yakus.iter().any(|y| y.yaku == yaku)
+}
+
+#[test]
+fn test_pinfu_tsumo_is_20_fu() {
+    let agari = agari("234m567p234678s88p", "8s", true, "1z", false);
+    assert!(has_yaku(&agari, Yaku::Pinfu));
+    assert!(has_yaku(&agari, Yaku::MenzenTsumo));
+    assert_eq!((agari.han, agari.fu), (3, 20));
+    // 700 from each ko and 1300 from the oya
+    assert_eq!(agari.points, 2700);
+}
+
+#[test]
+fn test_chiitoitsu_is_25_fu() {
+    let agari = agari("1133m5577p99s1122z", "9s", false, "1z", false);
+    assert!(has_yaku(&agari, Yaku::Chiitoitsu));
+    // the dora marker E makes S the dora
+    assert_eq!((agari.han, agari.fu), (4, 25));
+    assert_eq!(agari.points, 6400);
+}
+
+#[test]
+fn test_kazoe_yakuman() {
+    // riichi, chinitsu, ryanpeikou, pinfu and two dora
+    let agari = agari("11223344556677m", "7m", false, "6m", true);
+    assert!(has_yaku(&agari, Yaku::Chinitsu));
+    assert!(has_yaku(&agari, Yaku::Ryanpeikou));
+    assert!(agari.han >= 13);
+    assert_eq!(agari.yakuman, 0);
+    assert_eq!(agari.points, 32000);
+}
+
+#[test]
+fn test_hora_deltas() {
+    // (points, actor, target, oya, honba, kyotaku), deltas
+    let table = [
+        // ron, with 300 per honba from the target
+        ((8000, 1, 2, 0, 2, 1), [0, 9600, -8600, 0]),
+        // ko tsumo, with 100 per honba from each
+        ((2700, 1, 1, 0, 1, 0), [-1400, 3000, -800, -800]),
+        ((8000, 1, 1, 0, 3, 1), [-4300, 9900, -2300, -2300]),
+        // oya tsumo
+        ((6000, 0, 0, 0, 0, 2), [8000, -2000, -2000, -2000]),
+        ((12000, 2, 2, 2, 1, 0), [-4100, -4100, 12300, -4100]),
+    ];
+    for &((points, actor, target, oya, honba, kyotaku), deltas) in &table {
+        assert_eq!(
+            hora_deltas(points, actor, target, oya, honba, kyotaku),
+            deltas,
+            "{} points by {} from {} with oya {}, {} honba and {} kyotaku",
+            points,
+            actor,
+            target,
+            oya,
+            honba,
+            kyotaku,
+        );
+    }
+}
+
+#[test]
+fn test_dora_from_marker_wraps_around() {
+    let table = [
+        ("9m", "1m"),
+        ("9p", "1p"),
+        ("9s", "1s"),
+        ("0m", "6m"),
+        ("3s", "4s"),
+        ("4z", "1z"),
+        ("1z", "2z"),
+        ("7z", "5z"),
+        ("5z", "6z"),
+    ];
+    for &(marker, dora) in &table {
+        let marker = parse_pais(marker).unwrap()[0];
+        let dora = parse_pais(dora).unwrap()[0];
+        assert_eq!(dora_from_marker(marker), Some(dora), "marker {:?}", marker);
+    }
+}
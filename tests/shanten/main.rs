@@ -0,0 +1,120 @@
+//! Cross-checks the shanten calculation with `oracle` over random hands.
+
+mod oracle;
+
+use akochan_reviewer::shanten::{
+    chiitoi_shanten, get_shanten, kokushi_shanten, normal_shanten, Tiles,
+};
+
+/// Hands checked for each kind of wall and number of fuuros.
+const HANDS: usize = 50;
+
+/// Draws `len` pais from a wall of the kinds in `kinds`, four of each.
+fn random_hand(rng: &mut fastrand::Rng, kinds: &[usize], len: usize) -> Tiles {
+    let mut wall: Vec<_> = kinds.iter().flat_map(|&i| [i; 4]).collect();
+    rng.shuffle(&mut wall);
+    let mut tiles = [0; 34];
+    for &i in &wall[..len] {
+        tiles[i] += 1;
+    }
+    tiles
+}
+
+fn walls() -> Vec<(&'static str, Vec<usize>)> {
+    vec![
+        ("all", (0..34).collect()),
+        ("chinitsu", (0..9).collect()),
+        ("honitsu", (0..9).chain(27..34).collect()),
+        (
+            "yaochuu",
+            vec![0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33],
+        ),
+    ]
+}
+
+#[test]
+fn test_normal_shanten() {
+    let mut rng = fastrand::Rng::with_seed(1089);
+    for (name, kinds) in walls() {
+        for fuuro_count in 0..=4 {
+            for extra in [1, 2] {
+                let len = (4 - fuuro_count as usize) * 3 + extra;
+                for _ in 0..HANDS {
+                    let tiles = random_hand(&mut rng, &kinds, len);
+                    assert_eq!(
+                        normal_shanten(&tiles, fuuro_count),
+                        oracle::normal(&tiles, fuuro_count),
+                        "wall {}, {} fuuros: {:?}",
+                        name,
+                        fuuro_count,
+                        tiles,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_get_shanten() {
+    let mut rng = fastrand::Rng::with_seed(1089);
+    for (name, kinds) in walls() {
+        for len in [13, 14] {
+            for _ in 0..HANDS {
+                let tiles = random_hand(&mut rng, &kinds, len);
+                let chiitoi = oracle::chiitoi(&tiles);
+                let kokushi = oracle::kokushi(&tiles);
+                assert_eq!(
+                    chiitoi_shanten(&tiles),
+                    chiitoi,
+                    "wall {}: {:?}",
+                    name,
+                    tiles
+                );
+                assert_eq!(
+                    kokushi_shanten(&tiles),
+                    kokushi,
+                    "wall {}: {:?}",
+                    name,
+                    tiles
+                );
+
+                let expected = oracle::normal(&tiles, 0).min(chiitoi).min(kokushi);
+                assert_eq!(
+                    get_shanten(&tiles, 0),
+                    expected,
+                    "wall {}: {:?}",
+                    name,
+                    tiles
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_known_hands() {
+    fn tiles(indices: &[usize]) -> Tiles {
+        let mut tiles = [0; 34];
+        for &i in indices {
+            tiles[i] += 1;
+        }
+        tiles
+    }
+
+    // 123m456p789s11122z, agari
+    let agari = tiles(&[0, 1, 2, 12, 13, 14, 24, 25, 26, 27, 27, 27, 28, 28]);
+    assert_eq!(get_shanten(&agari, 0), -1);
+    // 1111m waiting on the fifth 1m is not tenpai: 1111m234p567s888z
+    let no_fifth = tiles(&[0, 0, 0, 0, 10, 11, 12, 22, 23, 24, 33, 33, 33]);
+    assert_eq!(get_shanten(&no_fifth, 0), 1);
+    // 4 of a kind is only one pair of chiitoitsu: 1111m11p11s1122z3z
+    let four = tiles(&[0, 0, 0, 0, 9, 9, 18, 18, 27, 27, 28, 28, 29]);
+    assert_eq!(chiitoi_shanten(&four), 2);
+    // kokushi tenpai on 13 sides
+    let kokushi = tiles(&[0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33]);
+    assert_eq!(get_shanten(&kokushi, 0), 0);
+    // only a jantou is left after 4 fuuros
+    assert_eq!(get_shanten(&tiles(&[5, 5]), 4), -1);
+    assert_eq!(get_shanten(&tiles(&[5]), 4), 0);
+}
@@ -0,0 +1,112 @@
+//! Shanten straight from its definition, slow but hard to get wrong.
+//!
+//! The shanten of a hand is one less than the least number of pais it lacks
+//! for some complete hand, where a complete hand is any 14 pais, or fewer
+//! with fuuros, of the required form and at most 4 of a kind.
+
+use akochan_reviewer::shanten::Tiles;
+
+/// Every mentsu as the indices of its pais, koutsu first.
+fn mentsus() -> Vec<[usize; 3]> {
+    let koutsus = (0..34).map(|i| [i; 3]);
+    let shuntsus = (0..27).filter(|i| i % 9 <= 6).map(|i| [i, i + 1, i + 2]);
+    koutsus.chain(shuntsus).collect()
+}
+
+/// Pais of `target` missing from `tiles`.
+fn missing(tiles: &Tiles, target: &Tiles) -> u8 {
+    tiles
+        .iter()
+        .zip(target)
+        .map(|(&have, &want)| want.saturating_sub(have))
+        .sum()
+}
+
+/// Tries every complete hand of `mentsu_left` more mentsu and a jantou on top
+/// of `target`, keeping the least missing pais in `best`.
+fn search(
+    tiles: &Tiles,
+    mentsus: &[[usize; 3]],
+    start: usize,
+    mentsu_left: u8,
+    target: &mut Tiles,
+    best: &mut u8,
+) {
+    // adding pais never makes fewer of them missing
+    if missing(tiles, target) >= *best {
+        return;
+    }
+
+    if mentsu_left == 0 {
+        for i in 0..34 {
+            if target[i] <= 2 {
+                target[i] += 2;
+                *best = (*best).min(missing(tiles, target));
+                target[i] -= 2;
+            }
+        }
+        return;
+    }
+
+    for (m, mentsu) in mentsus.iter().enumerate().skip(start) {
+        for &i in mentsu {
+            target[i] += 1;
+        }
+        if mentsu.iter().all(|&i| target[i] <= 4) {
+            search(tiles, mentsus, m, mentsu_left - 1, target, best);
+        }
+        for &i in mentsu {
+            target[i] -= 1;
+        }
+    }
+}
+
+pub fn normal(tiles: &Tiles, fuuro_count: u8) -> i8 {
+    let mut best = u8::MAX;
+    search(
+        tiles,
+        &mentsus(),
+        0,
+        4 - fuuro_count,
+        &mut [0; 34],
+        &mut best,
+    );
+    best as i8 - 1
+}
+
+/// Tries every 7 distinct pairs, remembering the best choice from each kind
+/// on.
+pub fn chiitoi(tiles: &Tiles) -> i8 {
+    // least[i][k]: least missing pais for k pairs of kinds i and after
+    let mut least = [[u8::MAX; 8]; 35];
+    for row in &mut least {
+        row[0] = 0;
+    }
+    for i in (0..34).rev() {
+        for k in 1..=7 {
+            let skip = least[i + 1][k];
+            let take = least[i + 1][k - 1].saturating_add(2u8.saturating_sub(tiles[i]));
+            least[i][k] = skip.min(take);
+        }
+    }
+    least[0][7] as i8 - 1
+}
+
+/// Tries every yaochuu pai as the jantou.
+pub fn kokushi(tiles: &Tiles) -> i8 {
+    const YAOCHUU: [usize; 13] = [0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33];
+
+    YAOCHUU
+        .iter()
+        .map(|&jantou| {
+            let mut target = [0; 34];
+            for &i in &YAOCHUU {
+                target[i] = 1;
+            }
+            target[jantou] = 2;
+            missing(tiles, &target)
+        })
+        .min()
+        .unwrap() as i8
+        - 1
+}
@@ -0,0 +1,125 @@
+//! Replays hand-written kyokus through `State` and checks what it tracks.
+
+use akochan_reviewer::position::{parse_hand, parse_pais, Position};
+use akochan_reviewer::state::{Furiten, State};
+use convlog::mjai::Event;
+use convlog::Pai;
+use serde_json as json;
+
+/// Actor 0 waits on 1m and 4m from the haipai, the others only have pais
+/// actor 0 does not need.
+const START_KYOKU: &str = r#"{
+    "type": "start_kyoku", "bakaze": "E", "dora_marker": "C", "kyoku": 1,
+    "honba": 0, "kyotaku": 0, "oya": 0, "scores": [25000, 25000, 25000, 25000],
+    "tehais": [
+        ["1m", "2m", "3m", "2m", "3m", "4p", "5p", "6p", "7s", "8s", "9s", "E", "E"],
+        ["5m", "6m", "7m", "8m", "9m", "1p", "2p", "3p", "7p", "8p", "9p", "S", "S"],
+        ["5m", "6m", "7m", "8m", "9m", "1p", "2p", "3p", "7p", "8p", "9p", "W", "W"],
+        ["5m", "6m", "7m", "8m", "9m", "1p", "2p", "3p", "7p", "8p", "9p", "N", "N"]
+    ]
+}"#;
+
+fn replay(state: &mut State, events: &[&str]) {
+    for event in events {
+        let event: Event = json::from_str(event).unwrap();
+        state.update(&event).unwrap();
+    }
+}
+
+/// The events up to the tsumo of actor 0, the oya, written as a hand for
+/// `position` and the discards of every actor.
+fn position(hand: &str, discards: [&str; 4]) -> Vec<Event> {
+    let hand = parse_hand(hand).unwrap();
+    let position = Position {
+        hand: hand.pais,
+        melds: hand.melds,
+        dora_markers: hand.dora_markers,
+        bakaze: Pai::East,
+        kyoku: 1,
+        honba: 0,
+        kyotaku: 0,
+        target_actor: 0,
+        scores: [25000; 4],
+        discards: discards.map(|d| parse_pais(d).unwrap()),
+    };
+    position.to_events().unwrap()
+}
+
+/// Tsumo and tsumogiri of `pai` by each of the others.
+fn others_turns(state: &mut State, pai: &str) {
+    for actor in 1..4 {
+        let tsumo = format!(r#"{{"type": "tsumo", "actor": {}, "pai": "?"}}"#, actor);
+        let dahai = format!(
+            r#"{{"type": "dahai", "actor": {}, "pai": "{}", "tsumogiri": true}}"#,
+            actor, pai,
+        );
+        replay(state, &[&tsumo, &dahai]);
+    }
+}
+
+#[test]
+fn test_furiten_is_unknown_before_dahai() {
+    // actor 0 has discarded 4m and waits on 1m and 4m again
+    let events = position("123m23m456p789s11z +5z dora=7z", ["4m", "6z", "6z", "6z"]);
+    let (tsumo, before) = events.split_last().unwrap();
+    let mut state = State::new(0);
+    for event in before {
+        state.update(event).unwrap();
+    }
+    assert_eq!(state.furiten(), Furiten::Permanent);
+
+    state.update(tsumo).unwrap();
+    // the waits before the tsumo no longer tell, the dahai decides
+    assert_eq!(state.furiten(), Furiten::Unknown);
+    assert_eq!(state.furiten_after_dahai(Pai::Haku), Some(true));
+    assert_eq!(state.furiten_after_dahai(Pai::Man1), None);
+}
+
+#[test]
+fn test_fuuro_from_position() {
+    let events = position(
+        "23m456p789s11z [777z pon toimen] +1m dora=1p",
+        ["9m9p", "1s2s", "3s", "4s"],
+    );
+    let mut state = State::new(0);
+    for event in &events {
+        state.update(event).unwrap();
+    }
+    assert_eq!(state.fuuro_count(0), 1);
+    assert_eq!(state.fuuro_count(2), 0);
+    // waiting on 1m and 4m, with the 1m just discarded
+    assert_eq!(state.furiten_after_dahai(Pai::Man1), Some(true));
+}
+
+#[test]
+fn test_passing_tsumo_agari_after_reach() {
+    let mut state = State::new(0);
+    replay(
+        &mut state,
+        &[
+            START_KYOKU,
+            r#"{"type": "tsumo", "actor": 0, "pai": "P"}"#,
+            r#"{"type": "reach", "actor": 0}"#,
+            r#"{"type": "dahai", "actor": 0, "pai": "P", "tsumogiri": true}"#,
+            r#"{"type": "reach_accepted", "actor": 0, "deltas": [-1000, 0, 0, 0], "scores": [24000, 25000, 25000, 25000]}"#,
+        ],
+    );
+    others_turns(&mut state, "F");
+    replay(
+        &mut state,
+        &[r#"{"type": "tsumo", "actor": 0, "pai": "1m"}"#],
+    );
+    assert_eq!(state.furiten(), Furiten::Unknown);
+
+    replay(
+        &mut state,
+        &[r#"{"type": "dahai", "actor": 0, "pai": "1m", "tsumogiri": true}"#],
+    );
+    others_turns(&mut state, "C");
+    replay(
+        &mut state,
+        &[r#"{"type": "tsumo", "actor": 0, "pai": "F"}"#],
+    );
+    // known before the dahai, as it lasts until the end of the kyoku
+    assert_eq!(state.furiten(), Furiten::Permanent);
+}